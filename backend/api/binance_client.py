@@ -0,0 +1,209 @@
+"""
+Binance USDⓈ-M futures client with the same interface as GateFuturesClient.
+Contracts keep the Gate.io names ("BTC_USDT") and are mapped to Binance symbols
+("BTCUSDT"); results are returned in the shape the Gate.io client uses.
+"""
+import hashlib
+import hmac
+import logging
+import time
+from typing import Dict, List, Optional, Any
+from urllib.parse import urlencode
+
+import requests
+
+from backend.config.settings import Config
+
+logger = logging.getLogger(__name__)
+
+# 与前端 exchange::binance::TESTNET_BASE_URL 一致
+TESTNET_HOST = "https://testnet.binancefuture.com"
+RECV_WINDOW_MS = 5000
+REQUEST_TIMEOUT = 10
+# 主流永续合约(BTC/ETH/LTC)的数量步长为0.001
+QUANTITY_DECIMALS = 3
+
+
+class BinanceAPIError(Exception):
+    """Custom exception for Binance API errors."""
+    pass
+
+
+def to_binance_symbol(contract: str) -> str:
+    """BTC_USDT -> BTCUSDT"""
+    return contract.replace("_", "")
+
+
+def from_binance_symbol(symbol: str) -> str:
+    """BTCUSDT -> BTC_USDT"""
+    if symbol.endswith("USDT") and len(symbol) > 4:
+        return f"{symbol[:-4]}_USDT"
+    return symbol
+
+
+class BinanceFuturesClient:
+    """
+    币安U本位合约客户端，接口与 GateFuturesClient 相同
+    """
+    def __init__(self):
+        config_loader = Config()
+        if not config_loader.is_configured():
+            raise BinanceAPIError("API key and secret are not configured in ~/.portfolio_rebalancer.json")
+        self.api_key = config_loader.api_key
+        self.api_secret = config_loader.api_secret
+        self.host = TESTNET_HOST
+        self.session = requests.Session()
+        self.session.headers.update({"X-MBX-APIKEY": self.api_key})
+        logger.info("BinanceFuturesClient initialized successfully for host: %s", self.host)
+
+    def _request(self, method: str, path: str, params: Optional[Dict[str, Any]] = None, signed: bool = False):
+        params = dict(params or {})
+        if signed:
+            params["recvWindow"] = RECV_WINDOW_MS
+            params["timestamp"] = int(time.time() * 1000)
+            query = urlencode(params)
+            signature = hmac.new(self.api_secret.encode(), query.encode(), hashlib.sha256).hexdigest()
+            url = f"{self.host}{path}?{query}&signature={signature}"
+        else:
+            url = f"{self.host}{path}"
+            if params:
+                url = f"{url}?{urlencode(params)}"
+        try:
+            response = self.session.request(method, url, timeout=REQUEST_TIMEOUT)
+        except requests.RequestException as e:
+            raise BinanceAPIError(f"Request to {path} failed: {e}") from e
+        body = response.json()
+        if not response.ok:
+            # 错误格式: {"code": -2015, "msg": "..."}
+            raise BinanceAPIError(f"API Error in {path}: {body.get('code')} - {body.get('msg')}")
+        return body
+
+    def get_futures_account(self) -> Dict[str, Any]:
+        account = self._request("GET", "/fapi/v2/account", signed=True)
+        return {
+            "total": account.get("totalWalletBalance", "0"),
+            "available": account.get("availableBalance", "0"),
+        }
+
+    def get_futures_positions(self) -> List[Dict[str, Any]]:
+        positions = self._request("GET", "/fapi/v2/positionRisk", signed=True)
+        return [
+            {
+                "contract": from_binance_symbol(position["symbol"]),
+                "size": float(position.get("positionAmt", 0)),
+                "entry_price": float(position.get("entryPrice", 0)),
+                "mark_price": float(position.get("markPrice", 0)),
+                "leverage": position.get("leverage"),
+            }
+            for position in positions
+            if float(position.get("positionAmt", 0)) != 0
+        ]
+
+    def get_futures_position(self, contract: str) -> Dict[str, Any]:
+        for position in self.get_futures_positions():
+            if position["contract"] == contract:
+                return position
+        return {"contract": contract, "size": 0}
+
+    def get_futures_price(self, contract: str) -> float:
+        """标记价格，失败时返回0.0（与Gate.io客户端一致）"""
+        try:
+            index = self._request("GET", "/fapi/v1/premiumIndex", {"symbol": to_binance_symbol(contract)})
+            return float(index["markPrice"])
+        except (BinanceAPIError, KeyError, ValueError) as e:
+            logger.error("Failed to get price for %s: %s", contract, e)
+            return 0.0
+
+    def get_futures_closes(self, contract: str, days: int = 30) -> List[float]:
+        try:
+            candles = self._request(
+                "GET", "/fapi/v1/klines",
+                {"symbol": to_binance_symbol(contract), "interval": "1d", "limit": days},
+            )
+            # [open_time, open, high, low, close, ...]
+            return [float(candle[4]) for candle in candles]
+        except (BinanceAPIError, IndexError, ValueError) as e:
+            logger.error("Failed to get candlesticks for %s: %s", contract, e)
+            return []
+
+    def get_funding_rates(self) -> Dict[str, float]:
+        try:
+            indices = self._request("GET", "/fapi/v1/premiumIndex")
+            return {
+                from_binance_symbol(index["symbol"]): float(index["lastFundingRate"])
+                for index in indices
+                if index.get("symbol", "").endswith("USDT")
+            }
+        except (BinanceAPIError, KeyError, ValueError) as e:
+            logger.error("Failed to get funding rates: %s", e)
+            return {}
+
+    def set_leverage(self, contract: str, leverage: int) -> bool:
+        if leverage < 1:
+            logger.error("Leverage must be at least 1.")
+            return False
+        try:
+            self._request("POST", "/fapi/v1/leverage",
+                          {"symbol": to_binance_symbol(contract), "leverage": leverage}, signed=True)
+            logger.info("Successfully set leverage for %s to %sx", contract, leverage)
+            return True
+        except BinanceAPIError as e:
+            logger.error("%s", e)
+            return False
+
+    def set_margin_mode(self, contract: str, mode: str) -> bool:
+        margin_type = "CROSSED" if mode == "cross" else "ISOLATED"
+        try:
+            self._request("POST", "/fapi/v1/marginType",
+                          {"symbol": to_binance_symbol(contract), "marginType": margin_type}, signed=True)
+            return True
+        except BinanceAPIError as e:
+            # -4046: 保证金模式未改变
+            if "-4046" in str(e):
+                return True
+            logger.error("%s", e)
+            return False
+
+    def create_futures_order(self,
+                             contract: str,
+                             size: float,
+                             price: Optional[float] = None,
+                             reduce_only: bool = False) -> Optional[Dict[str, Any]]:
+        """
+        下单；size为币的数量，正数买入，负数卖出。返回Gate.io格式的订单字典
+        """
+        # 向零取整到数量步长
+        step = 10 ** QUANTITY_DECIMALS
+        quantity = int(abs(size) * step) / step
+        if quantity == 0:
+            logger.warning("Order size for %s is below the lot step: %s", contract, size)
+            return None
+        params = {
+            "symbol": to_binance_symbol(contract),
+            "side": "BUY" if size > 0 else "SELL",
+            "quantity": f"{quantity:.{QUANTITY_DECIMALS}f}",
+            "newOrderRespType": "RESULT",
+        }
+        if price is not None:
+            params.update({"type": "LIMIT", "price": str(price), "timeInForce": "GTC"})
+        else:
+            params["type"] = "MARKET"
+        if reduce_only:
+            params["reduceOnly"] = "true"
+        try:
+            order = self._request("POST", "/fapi/v1/order", params, signed=True)
+        except BinanceAPIError as e:
+            logger.error("create_futures_order(%s, size=%s): %s", contract, size, e)
+            return None
+        executed = float(order.get("executedQty", 0))
+        status = order.get("status")
+        logger.info("Successfully created order for %s: ID %s, Size %s, Status %s",
+                    contract, order.get("orderId"), executed, status)
+        return {
+            "id": order.get("orderId"),
+            "contract": contract,
+            "size": executed if size > 0 else -executed,
+            "fill_price": order.get("avgPrice"),
+            "status": "open" if status in ("NEW", "PARTIALLY_FILLED") else "finished",
+            "finish_as": "cancelled" if status in ("CANCELED", "EXPIRED") else "filled",
+        }
@@ -149,6 +149,41 @@ class GateFuturesClient:
             logger.error("Error converting price to float for %s: %s", contract, e)
             return 0.0
 
+    def get_futures_closes(self, contract: str, days: int = 30) -> List[float]:
+        """
+        Get daily closing prices for a futures contract, oldest first.
+
+        Args:
+            contract: Contract name (e.g., "BTC_USDT")
+            days: Number of daily candles to fetch
+
+        Returns:
+            List of closing prices. Returns an empty list if fetching fails.
+        """
+        try:
+            candles = self.futures_api.list_futures_candlesticks(
+                settle=SETTLE_CURRENCY, contract=contract, interval="1d", limit=days
+            )
+            return [float(candle.c) for candle in candles]
+        except (ApiException, ValueError) as e:
+            logger.error("Failed to get candlesticks for %s: %s", contract, e)
+            return []
+
+    def get_funding_rates(self) -> Dict[str, float]:
+        """
+        Get the current 8-hour funding rate of every USDT-settled contract.
+
+        Returns:
+            Dict mapping contract name to rate (e.g. 0.0001 for 0.01%); positive
+            rates are paid by longs. Returns an empty dict if fetching fails.
+        """
+        try:
+            contracts = self.futures_api.list_futures_contracts(settle=SETTLE_CURRENCY)
+            return {contract.name: float(contract.funding_rate) for contract in contracts}
+        except (ApiException, ValueError, TypeError) as e:
+            logger.error("Failed to get funding rates: %s", e)
+            return {}
+
     def set_leverage(self, contract: str, leverage: int) -> bool:
         """
         Set leverage for a specific contract and ensure cross margin mode.
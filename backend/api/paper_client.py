@@ -0,0 +1,111 @@
+"""
+Paper trading client: real testnet prices, simulated account and orders.
+"""
+import itertools
+import logging
+from typing import Dict, List, Optional, Any
+
+from gate_api import ApiClient, Configuration, FuturesApi
+
+from backend.api.gate_client import GateFuturesClient, TESTNET_HOST
+
+logger = logging.getLogger(__name__)
+
+# 与前端 PAPER_INITIAL_USDT 一致
+PAPER_INITIAL_USDT = 10000.0
+
+class PaperFuturesClient(GateFuturesClient):
+    """
+    与 GateFuturesClient 接口相同，但账户、持仓和订单都是虚拟的：
+    行情（价格、K线）仍来自测试网公开接口，不需要API密钥
+    """
+    def __init__(self, initial_usdt=PAPER_INITIAL_USDT):
+        # 不调用父类构造函数：公开行情接口无需凭据
+        self.host = TESTNET_HOST
+        self.configuration = Configuration(host=self.host)
+        self.api_client = ApiClient(self.configuration)
+        self.futures_api = FuturesApi(self.api_client)
+
+        # 已实现的账户余额（不含未实现盈亏）
+        self.balance = initial_usdt
+        # contract -> {"size": 合约数量(多为正，空为负), "entry_price": 开仓均价}
+        self.positions: Dict[str, Dict[str, float]] = {}
+        self._order_ids = itertools.count(1)
+        logger.info("PaperFuturesClient initialized with %.2f virtual USDT", initial_usdt)
+
+    def _unrealized_pnl(self) -> float:
+        pnl = 0.0
+        for contract, position in self.positions.items():
+            mark_price = self.get_futures_price(contract)
+            if mark_price > 0:
+                pnl += position["size"] * (mark_price - position["entry_price"])
+        return pnl
+
+    def get_futures_account(self) -> Dict[str, Any]:
+        total = self.balance + self._unrealized_pnl()
+        return {"total": str(total), "available": str(total)}
+
+    def get_futures_positions(self) -> List[Dict[str, Any]]:
+        return [
+            {
+                "contract": contract,
+                "size": position["size"],
+                "entry_price": position["entry_price"],
+                "mark_price": self.get_futures_price(contract),
+            }
+            for contract, position in self.positions.items()
+            if position["size"] != 0
+        ]
+
+    def get_futures_position(self, contract: str) -> Dict[str, Any]:
+        position = self.positions.get(contract, {"size": 0, "entry_price": 0.0})
+        return {"contract": contract, **position}
+
+    def set_leverage(self, contract: str, leverage: int) -> bool:
+        return True
+
+    def set_margin_mode(self, contract: str, mode: str) -> bool:
+        return True
+
+    def create_futures_order(self,
+                             contract: str,
+                             size: float,
+                             price: Optional[float] = None,
+                             reduce_only: bool = False) -> Optional[Dict[str, Any]]:
+        """
+        以当前价格（限价单则以指定价格）立即成交，只更新虚拟账户
+        """
+        if size == 0:
+            logger.warning("Attempted to create order with size 0 for %s", contract)
+            return None
+        fill_price = price if price is not None else self.get_futures_price(contract)
+        if fill_price <= 0:
+            logger.error("No price for %s, paper order rejected", contract)
+            return None
+
+        position = self.positions.setdefault(contract, {"size": 0.0, "entry_price": 0.0})
+        old_size = position["size"]
+        new_size = old_size + size
+        if old_size == 0 or (old_size > 0) == (size > 0):
+            # 加仓：更新开仓均价
+            position["entry_price"] = (
+                old_size * position["entry_price"] + size * fill_price
+            ) / new_size
+        else:
+            # 减仓或反向：平掉的部分计入已实现盈亏
+            closed = min(abs(size), abs(old_size))
+            direction = 1 if old_size > 0 else -1
+            self.balance += closed * direction * (fill_price - position["entry_price"])
+            if abs(size) > abs(old_size):
+                position["entry_price"] = fill_price
+        position["size"] = new_size
+
+        order_id = f"paper-{next(self._order_ids)}"
+        logger.info("Paper order %s: %s %s @ %s", order_id, contract, size, fill_price)
+        return {
+            "id": order_id,
+            "contract": contract,
+            "size": size,
+            "fill_price": fill_price,
+            "status": "finished",
+        }
@@ -2,9 +2,11 @@
 Configuration settings for the portfolio rebalancer.
 """
 import os
+from datetime import datetime, timedelta
 from typing import Optional
 import json
 import logging
+import math
 
 # 配置日志
 logging.basicConfig(
@@ -21,6 +23,9 @@ DEFAULT_PORTFOLIO = {
     "USDT": 50.0
 }
 
+# 未配置杠杆的合约使用的杠杆，与前端的 DEFAULT_LEVERAGE 一致
+DEFAULT_LEVERAGE = 3.0
+
 # Rebalancing threshold (in percentage)
 REBALANCE_THRESHOLD = 5.0
 
@@ -32,44 +37,110 @@ CHECK_INTERVAL = 60 * 5  # 5 minutes
 
 class PortfolioAllocation:
     """Portfolio allocation model."""
-    def __init__(self, BTC_USDT=20.0, ETH_USDT=15.0, LTC_USDT=5.0):
+    def __init__(self, assets=None, leverages=None):
         """
         初始化投资组合配置
         
         Args:
-            BTC_USDT: BTC_USDT的分配百分比 (默认: 20.0%)
-            ETH_USDT: ETH_USDT的分配百分比 (默认: 15.0%)
-            LTC_USDT: LTC_USDT的分配百分比 (默认: 5.0%)
+            assets: 合约到分配百分比的映射，如 {"BTC_USDT": 20.0}
+                    (默认: BTC_USDT 20%, ETH_USDT 15%, LTC_USDT 5%)
+            leverages: 合约到杠杆的映射，未列出的合约使用 DEFAULT_LEVERAGE
         """
-        self.BTC_USDT = BTC_USDT
-        self.ETH_USDT = ETH_USDT
-        self.LTC_USDT = LTC_USDT
+        if assets is None:
+            assets = {"BTC_USDT": 20.0, "ETH_USDT": 15.0, "LTC_USDT": 5.0}
         # USDT比例通过计算得出，不再作为配置项
+        self.assets = {
+            symbol: float(pct) for symbol, pct in assets.items() if symbol != "USDT"
+        }
+        self.leverages = {symbol: float(leverage) for symbol, leverage in (leverages or {}).items()}
         self._validate()
     
+    @classmethod
+    def from_config(cls, allocation):
+        """从配置文件读取：新版前端为列表 [{"symbol": ..., "target_pct": ..., "leverage": ...}]，旧版为字典"""
+        if isinstance(allocation, list):
+            leverages = {a["symbol"]: a["leverage"] for a in allocation if "leverage" in a}
+            return cls({a["symbol"]: a["target_pct"] for a in allocation}, leverages)
+        return cls(allocation)
+    
     def _validate(self):
         """验证配置的有效性"""
-        total = self.BTC_USDT + self.ETH_USDT + self.LTC_USDT
-        if total > 100.0:
+        for symbol, pct in self.assets.items():
+            if not math.isfinite(pct) or pct < 0.0:
+                raise ValueError(f"{symbol} 的分配比例无效: {pct}")
+        for symbol, leverage in self.leverages.items():
+            # 保证金 = 仓位价值 / 杠杆
+            if not math.isfinite(leverage) or leverage < 1.0:
+                raise ValueError(f"{symbol} 的杠杆无效: {leverage}")
+        total = sum(self.assets.values())
+        # 前端按权重算出的目标可能有浮点误差
+        if total > 100.0 + 1e-6:
             raise ValueError(f"资产配置总和不能超过100%，当前总和: {total}%")
     
+    @property
+    def symbols(self):
+        """配置中的合约，按配置顺序"""
+        return list(self.assets)
+    
     @property
     def USDT(self):
         """计算USDT的分配比例"""
-        return max(0.0, 100.0 - self.BTC_USDT - self.ETH_USDT - self.LTC_USDT)
+        return max(0.0, 100.0 - sum(self.assets.values()))
+    
+    def leverage_for(self, symbol):
+        """合约开仓使用的杠杆"""
+        return self.leverages.get(symbol, DEFAULT_LEVERAGE)
     
     def as_dict(self):
         """转换为字典"""
-        return {
-            "BTC_USDT": self.BTC_USDT,
-            "ETH_USDT": self.ETH_USDT,
-            "LTC_USDT": self.LTC_USDT,
-            "USDT": self.USDT
-        }
+        return {**self.assets, "USDT": self.USDT}
         
     def __repr__(self):
         """字符串表示"""
-        return f"PortfolioAllocation(BTC_USDT={self.BTC_USDT}, ETH_USDT={self.ETH_USDT}, LTC_USDT={self.LTC_USDT}, USDT={self.USDT})"
+        fields = ", ".join(f"{symbol}={pct}" for symbol, pct in self.as_dict().items())
+        return f"PortfolioAllocation({fields})"
+
+WEEKDAYS = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+
+class RebalanceSchedule:
+    """
+    定时再平衡设置，对应前端的 RebalanceSchedule
+    （{"type": "threshold_only" | "daily" | "weekly" | "interval", ...}）
+    """
+    def __init__(self, data=None):
+        data = data or {}
+        self.type = data.get("type", "threshold_only")
+        self.time = None
+        self.weekday = None
+        self.hours = None
+        if self.type in ("daily", "weekly"):
+            # chrono 以 "HH:MM:SS" 格式序列化 NaiveTime
+            self.time = datetime.strptime(data["time"][:8], "%H:%M:%S").time()
+        if self.type == "weekly":
+            self.weekday = WEEKDAYS.index(data["weekday"][:3].capitalize())
+        if self.type == "interval":
+            self.hours = int(data["hours"])
+
+    def last_due(self, now: datetime) -> Optional[datetime]:
+        """最近一次（不晚于now）应执行定时再平衡的时刻；interval 和 threshold_only 返回 None"""
+        if self.type == "daily":
+            due = datetime.combine(now.date(), self.time)
+            return due if due <= now else due - timedelta(days=1)
+        if self.type == "weekly":
+            days_back = (now.weekday() - self.weekday) % 7
+            due = datetime.combine(now.date() - timedelta(days=days_back), self.time)
+            return due if due <= now else due - timedelta(days=7)
+        return None
+
+    def is_due(self, last_run: datetime, now: datetime) -> bool:
+        """自上次运行(last_run)以来是否到了定时再平衡的时间"""
+        if self.type == "interval":
+            return now - last_run >= timedelta(hours=self.hours)
+        due = self.last_due(now)
+        return due is not None and due > last_run
+
+    def __repr__(self):
+        return f"RebalanceSchedule(type={self.type}, weekday={self.weekday}, time={self.time}, hours={self.hours})"
 
 class Config:
     """
@@ -87,53 +158,78 @@ class Config:
         self.api_secret = ""
         self.portfolio_allocation = PortfolioAllocation()
         self.rebalance_threshold = 5.0  # 默认再平衡阈值 5%
+        self.per_asset_threshold = None  # 按资产覆盖的阈值 {"LTC_USDT": 8.0}
         self.min_usdt_inflow = 50.0  # 默认最小USDT流入 50
+        self.min_trade_usdt = 5.0  # 小于该名义金额的交易会被交易所拒绝，跳过
+        self.paper_trading = False  # 模拟交易：虚拟账户，不下真实订单
+        self.exchange = "gateio"  # 交易所: "gateio" 或 "binance"
+        self.schedule = RebalanceSchedule()
         self.load_config()
+        # 市值加权、风险平价和凯利仓位的目标由前端计算后传入，重新加载配置时保留
+        targets = os.environ.get("KIN_TARGET_ALLOCATION")
+        self.resolved_allocation = PortfolioAllocation.from_config(json.loads(targets)) if targets else None
     
     def load_config(self):
         """加载配置文件"""
-        if os.path.exists(self.config_file):
+        # 加密的配置文件由前端解密后通过环境变量传入
+        config_json = os.environ.get("KIN_CONFIG_JSON")
+        if config_json or os.path.exists(self.config_file):
             try:
-                with open(self.config_file, 'r') as f:
-                    config_data = json.load(f)
+                if config_json:
+                    config_data = json.loads(config_json)
+                elif self.config_file.endswith(".toml"):
+                    import tomllib  # Python 3.11+
+                    with open(self.config_file, 'rb') as f:
+                        config_data = tomllib.load(f)
+                else:
+                    with open(self.config_file, 'r') as f:
+                        config_data = json.load(f)
                 
                 # 获取API配置
-                self.api_key = config_data.get("api_key", "")
-                self.api_secret = config_data.get("api_secret", "")
+                # 前端使用系统钥匙串时，凭据通过环境变量传入，配置文件中为空
+                self.api_key = os.environ.get("KIN_API_KEY") or config_data.get("api_key", "")
+                self.api_secret = os.environ.get("KIN_API_SECRET") or config_data.get("api_secret", "")
                 
                 # 获取投资组合配置
-                allocation = config_data.get("portfolio_allocation", {})
-                btc_pct = allocation.get("BTC_USDT", 20.0)
-                eth_pct = allocation.get("ETH_USDT", 15.0)
-                ltc_pct = allocation.get("LTC_USDT", 5.0)
-                
-                self.portfolio_allocation = PortfolioAllocation(
-                    BTC_USDT=btc_pct,
-                    ETH_USDT=eth_pct,
-                    LTC_USDT=ltc_pct
-                )
+                if "portfolio_allocation" in config_data:
+                    self.portfolio_allocation = PortfolioAllocation.from_config(
+                        config_data["portfolio_allocation"]
+                    )
                 
                 # 获取其他设置
                 self.rebalance_threshold = config_data.get("rebalance_threshold", 5.0)
+                self.per_asset_threshold = config_data.get("per_asset_threshold")
                 self.min_usdt_inflow = config_data.get("min_usdt_inflow", 50.0)
+                self.min_trade_usdt = config_data.get("min_trade_usdt", 5.0)
+                self.paper_trading = bool(config_data.get("paper_trading", False))
+                self.exchange = config_data.get("exchange", "gateio")
+                self.schedule = RebalanceSchedule(config_data.get("schedule"))
                 
             except Exception as e:
                 logger.error("加载配置文件失败: %s", e)
                 # 使用默认配置
         else:
             logger.warning("配置文件 %s 不存在，使用默认配置", self.config_file)
+        # 前端启动时明确告知是否为模拟交易，优先于配置文件
+        paper_env = os.environ.get("KIN_PAPER_TRADING")
+        if paper_env is not None:
+            self.paper_trading = paper_env == "1"
+        self.exchange = os.environ.get("KIN_EXCHANGE") or self.exchange
     
     def save_config(self):
         """保存配置到文件"""
         config_data = {
             "api_key": self.api_key,
             "api_secret": self.api_secret,
-            "portfolio_allocation": {
-                "BTC_USDT": self.portfolio_allocation.BTC_USDT,
-                "ETH_USDT": self.portfolio_allocation.ETH_USDT,
-                "LTC_USDT": self.portfolio_allocation.LTC_USDT,
-                # USDT比例不再保存，通过计算得出
-            },
+            # 与前端相同的列表格式；USDT比例不保存，通过计算得出
+            "portfolio_allocation": [
+                {
+                    "symbol": symbol,
+                    "target_pct": pct,
+                    "leverage": self.portfolio_allocation.leverage_for(symbol),
+                }
+                for symbol, pct in self.portfolio_allocation.assets.items()
+            ],
             "rebalance_threshold": self.rebalance_threshold,
             "min_usdt_inflow": self.min_usdt_inflow
         }
@@ -147,6 +243,23 @@ class Config:
             logger.error("保存配置失败: %s", e)
             return False
     
+    def set_resolved_allocation(self, targets):
+        """前端按分配模式和仓位管理重新计算的目标，格式同配置中的 portfolio_allocation"""
+        self.resolved_allocation = PortfolioAllocation.from_config(targets)
+    
+    @property
+    def target_allocation(self):
+        """再平衡的目标：前端算出的目标，未提供时为配置中的比例"""
+        return self.resolved_allocation or self.portfolio_allocation
+    
+    def leverage_for(self, asset):
+        """合约的杠杆，取自配置中的 portfolio_allocation"""
+        return self.portfolio_allocation.leverage_for(asset)
+    
+    def threshold_for(self, asset):
+        """资产的再平衡阈值(%)，未单独设置时使用全局阈值"""
+        return (self.per_asset_threshold or {}).get(asset, self.rebalance_threshold)
+    
     def is_configured(self):
         """检查API是否已配置"""
         return bool(self.api_key and self.api_secret)
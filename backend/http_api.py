@@ -0,0 +1,146 @@
+"""
+Local HTTP API polled by the Rust frontend (frontend/src/backend_client.rs).
+
+    GET  /status         {"status": "Running", "paper_trading": false}
+    GET  /snapshot       {"allocations": {"BTC_USDT": 24.8, ...}, "total_value_usdt": 1000.0,
+                          "funding_rates": {"BTC_USDT": 0.0001, ...}}
+    POST /rebalance      {"trades": [...], "skipped": []}
+    POST /reload-config  {}
+
+POST routes trade or change state, so they need the token the frontend passed
+in $KIN_HTTP_TOKEN, sent back as the X-Kin-Token header. Requests from a browser
+(anything with an Origin header) are refused outright, so web pages cannot
+reach the API through cross-site requests.
+"""
+import hmac
+import json
+import os
+import threading
+import traceback
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+
+# Port chosen by the frontend; the server only listens on localhost
+HTTP_PORT_ENV = "KIN_HTTP_PORT"
+# Random per frontend session; without it the POST routes stay closed
+HTTP_TOKEN_ENV = "KIN_HTTP_TOKEN"
+TOKEN_HEADER = "X-Kin-Token"
+
+
+class BackendState:
+    """
+    What the HTTP handlers work on. `lock` is held by the main loop during a
+    rebalancing cycle so a manual rebalance never trades at the same time.
+    """
+
+    def __init__(self, configs, portfolio_manager, rebalancer):
+        self.lock = threading.Lock()
+        self.status = "Running"
+        # 前端检测到回撤超限时暂停，只报告快照不交易
+        self.paused = False
+        # 各组件各自持有Config实例，重新加载时都要刷新
+        self.configs = configs
+        self.portfolio_manager = portfolio_manager
+        self.rebalancer = rebalancer
+
+    def set_paused(self, paused):
+        self.paused = paused
+        self.status = "Paused" if paused else "Running"
+
+    def status_json(self):
+        return {"status": self.status, "paper_trading": self.configs[0].paper_trading}
+
+    def snapshot_json(self):
+        with self.lock:
+            summary = self.portfolio_manager.get_portfolio_summary()
+        return {
+            "allocations": {asset: pct * 100 for asset, pct in summary["current_percentages"].items()},
+            "total_value_usdt": summary["total_assets"],
+            "funding_rates": summary["funding_rates"],
+        }
+
+    def rebalance(self):
+        with self.lock:
+            return self.rebalancer.rebalance_now()
+
+    def reload_config(self):
+        with self.lock:
+            for config in self.configs:
+                config.load_config()
+        print("Config reloaded on request from the frontend.")
+        return {}
+
+
+def _make_handler(state, token):
+    routes = {
+        ("GET", "/status"): state.status_json,
+        ("GET", "/snapshot"): state.snapshot_json,
+        ("POST", "/rebalance"): state.rebalance,
+        ("POST", "/reload-config"): state.reload_config,
+    }
+
+    class Handler(BaseHTTPRequestHandler):
+        def _handle(self, method):
+            if self.headers.get("Origin") is not None:
+                self._reply(403, {"error": "browser requests are not allowed"})
+                return
+            route = routes.get((method, self.path))
+            if route is None:
+                self._reply(404, {"error": f"no route for {method} {self.path}"})
+                return
+            if method != "GET" and not self._authorized():
+                self._reply(401, {"error": f"missing or wrong {TOKEN_HEADER} header"})
+                return
+            try:
+                self._reply(200, route())
+            except Exception as e:
+                traceback.print_exc()
+                self._reply(500, {"error": str(e)})
+
+        def _authorized(self):
+            sent = self.headers.get(TOKEN_HEADER, "")
+            return bool(token) and hmac.compare_digest(sent.encode("utf-8"), token.encode("utf-8"))
+
+        def _reply(self, code, body):
+            data = json.dumps(body).encode("utf-8")
+            self.send_response(code)
+            self.send_header("Content-Type", "application/json")
+            self.send_header("Content-Length", str(len(data)))
+            self.end_headers()
+            self.wfile.write(data)
+
+        def do_GET(self):
+            self._handle("GET")
+
+        def do_POST(self):
+            self._handle("POST")
+
+        def log_message(self, format, *args):
+            # 前端每隔几秒轮询一次，不把访问记录写进日志
+            pass
+
+    return Handler
+
+
+def start_http_server(state, port=None, token=None):
+    """
+    Serve `state` on localhost at `port`, or at $KIN_HTTP_PORT, on a daemon thread.
+    POST routes require `token`, or $KIN_HTTP_TOKEN.
+
+    Returns:
+        ThreadingHTTPServer or None: None when no port is set or it is taken
+    """
+    port = port or os.environ.get(HTTP_PORT_ENV)
+    if not port:
+        return None
+    token = token or os.environ.get(HTTP_TOKEN_ENV, "")
+    if not token:
+        print(f"No {HTTP_TOKEN_ENV} set; the HTTP API only answers GET requests.")
+    try:
+        server = ThreadingHTTPServer(("127.0.0.1", int(port)), _make_handler(state, token))
+    except (OSError, ValueError) as e:
+        print(f"Cannot serve the HTTP API on port {port} ({e}).")
+        return None
+    server.daemon_threads = True
+    threading.Thread(target=server.serve_forever, daemon=True).start()
+    print(f"HTTP API listening on http://127.0.0.1:{port}")
+    return server
@@ -0,0 +1,230 @@
+"""
+Newline-delimited JSON messages read by the Rust frontend.
+
+Messages go to the frontend's socket (a named pipe on Windows) once it has
+connected, and to stdout before that or when no socket path was given.
+Command lines the frontend writes to the socket are queued for the main loop.
+"""
+import json
+import os
+import queue
+import socket
+import sys
+import threading
+
+# Socket path chosen by the frontend
+SOCKET_PATH_ENV = "KIN_IPC_PATH"
+
+_PIPE_BUFFER_SIZE = 65536
+
+_server = None
+
+
+class _PipeConnection:
+    """Server end of one Windows named pipe instance, with the socket methods IpcServer uses."""
+
+    def __init__(self, handle):
+        self._handle = handle
+
+    def sendall(self, data):
+        import _winapi
+        ov, _ = _winapi.WriteFile(self._handle, data, overlapped=True)
+        _, err = ov.GetOverlappedResult(True)
+        if err:
+            raise OSError(err, "WriteFile failed")
+
+    def recv(self, size):
+        import _winapi
+        # 重叠I/O，读线程等待时其他线程仍可写入同一个管道
+        try:
+            ov, _ = _winapi.ReadFile(self._handle, size, overlapped=True)
+            _, err = ov.GetOverlappedResult(True)
+        except OSError:
+            return b""
+        return b"" if err else ov.getbuffer()
+
+    def close(self):
+        import _winapi
+        _winapi.CloseHandle(self._handle)
+
+
+class IpcServer:
+    """
+    Accepts frontend connections at `path`. Messages are written to every
+    connected client; commands read from any client land in `commands`.
+    """
+
+    def __init__(self, path):
+        self.path = path
+        self.commands = queue.Queue()
+        self._clients = []
+        self._lock = threading.Lock()
+        self._listener = None
+        self._closed = False
+
+    def start(self):
+        if sys.platform == "win32":
+            accept = self._accept_pipes
+        else:
+            if os.path.exists(self.path):
+                os.unlink(self.path)  # left behind by a backend that crashed
+            self._listener = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+            self._listener.bind(self.path)
+            self._listener.listen()
+            accept = self._accept_unix
+        threading.Thread(target=accept, daemon=True).start()
+
+    def _accept_unix(self):
+        while not self._closed:
+            try:
+                conn, _ = self._listener.accept()
+            except OSError:
+                break  # listener closed
+            self._add(conn)
+
+    def _accept_pipes(self):
+        import _winapi
+        while not self._closed:
+            handle = _winapi.CreateNamedPipe(
+                self.path,
+                _winapi.PIPE_ACCESS_DUPLEX | _winapi.FILE_FLAG_OVERLAPPED,
+                _winapi.PIPE_TYPE_BYTE | _winapi.PIPE_READMODE_BYTE | _winapi.PIPE_WAIT,
+                _winapi.PIPE_UNLIMITED_INSTANCES,
+                _PIPE_BUFFER_SIZE,
+                _PIPE_BUFFER_SIZE,
+                _winapi.NMPWAIT_WAIT_FOREVER,
+                _winapi.NULL,
+            )
+            try:
+                ov = _winapi.ConnectNamedPipe(handle, overlapped=True)
+                ov.GetOverlappedResult(True)
+            except OSError:
+                _winapi.CloseHandle(handle)
+                continue
+            self._add(_PipeConnection(handle))
+
+    def _add(self, conn):
+        with self._lock:
+            self._clients.append(conn)
+        threading.Thread(target=self._read_commands, args=(conn,), daemon=True).start()
+
+    def _remove(self, conn):
+        with self._lock:
+            if conn not in self._clients:
+                return
+            self._clients.remove(conn)
+        try:
+            conn.close()
+        except OSError:
+            pass
+
+    def _read_commands(self, conn):
+        buffer = b""
+        while True:
+            try:
+                data = conn.recv(4096)
+            except OSError:
+                data = b""
+            if not data:
+                break
+            buffer += data
+            while b"\n" in buffer:
+                line, buffer = buffer.split(b"\n", 1)
+                try:
+                    command = json.loads(line)
+                except ValueError:
+                    continue
+                if isinstance(command, dict):
+                    self.commands.put(command)
+        self._remove(conn)
+
+    def broadcast(self, line):
+        """
+        Write `line` to every client.
+
+        Returns:
+            bool: Whether at least one client received it
+        """
+        with self._lock:
+            clients = list(self._clients)
+        delivered = False
+        for conn in clients:
+            try:
+                conn.sendall(line.encode("utf-8"))
+                delivered = True
+            except OSError:
+                self._remove(conn)
+        return delivered
+
+    def close(self):
+        self._closed = True
+        with self._lock:
+            clients = list(self._clients)
+        for conn in clients:
+            self._remove(conn)
+        if self._listener is not None:
+            self._listener.close()
+            if os.path.exists(self.path):
+                os.unlink(self.path)
+
+
+def start_server(path=None):
+    """
+    Listen for the frontend at `path`, or at $KIN_IPC_PATH.
+
+    Returns:
+        IpcServer or None: None when no path is set or it cannot be opened,
+            in which case messages keep going to stdout
+    """
+    global _server
+    path = path or os.environ.get(SOCKET_PATH_ENV)
+    if not path:
+        return None
+    server = IpcServer(path)
+    try:
+        server.start()
+    except OSError as e:
+        print(f"Cannot listen on {path} ({e}); writing messages to stdout only.")
+        return None
+    _server = server
+    return server
+
+
+def stop_server():
+    global _server
+    if _server is not None:
+        _server.close()
+        _server = None
+
+
+def next_command(timeout):
+    """
+    Wait up to `timeout` seconds for a frontend command.
+
+    Returns:
+        dict or None: The command, e.g. {"type": "stop"}, or None on timeout
+    """
+    if _server is None:
+        threading.Event().wait(timeout)
+        return None
+    try:
+        return _server.commands.get(timeout=timeout)
+    except queue.Empty:
+        return None
+
+
+def emit(message_type, **fields):
+    """
+    Write one protocol message as a single JSON line.
+
+    Args:
+        message_type: One of "status", "portfolio_snapshot", "trade_executed", "order_update",
+            "rebalance_progress", "price_history", "error"
+        **fields: Message payload
+    """
+    fields["type"] = message_type
+    line = json.dumps(fields) + "\n"
+    if _server is not None and _server.broadcast(line):
+        return
+    sys.stdout.write(line)
+    sys.stdout.flush()
@@ -7,10 +7,16 @@ import time
 import traceback
 import argparse
 import os  # Add os import for environment variables
+import signal
+from datetime import datetime
+from backend.api.binance_client import BinanceFuturesClient
 from backend.api.gate_client import GateFuturesClient # Updated client name
+from backend.api.paper_client import PaperFuturesClient
 from backend.portfolio_manager import PortfolioManager # Corrected import path
 from backend.services.rebalancer import Rebalancer
 from backend.config.settings import Config
+from backend.ipc import emit, next_command, start_server, stop_server
+from backend.http_api import BackendState, start_http_server
 
 # Default check interval in seconds
 CHECK_INTERVAL = 60 * 5  # 5 minutes
@@ -23,14 +29,21 @@ def initialize_api_and_components(config_path=None):
         config_path: Path to configuration file
         
     Returns:
-        tuple: (api_client, portfolio_manager, rebalancer)
+        tuple: (config, api_client, portfolio_manager, rebalancer)
     """
     # Initialize config
     config = Config(config_path)
     
-    print("Initializing Gate.io Futures API client...")
-    # The new client loads config internally via backend.config.settings.Config
-    api_client = GateFuturesClient() 
+    if config.paper_trading:
+        print("Paper trading: orders are simulated against a virtual account.")
+        api_client = PaperFuturesClient()
+    elif config.exchange == "binance":
+        print("Initializing Binance Futures API client...")
+        api_client = BinanceFuturesClient()
+    else:
+        print("Initializing Gate.io Futures API client...")
+        # The new client loads config internally via backend.config.settings.Config
+        api_client = GateFuturesClient()
     
     print("Initializing Portfolio Manager...")
     portfolio_manager = PortfolioManager(api_client)
@@ -38,12 +51,48 @@ def initialize_api_and_components(config_path=None):
     print("Initializing Rebalancer...")
     rebalancer = Rebalancer(api_client, portfolio_manager)
     
-    return api_client, portfolio_manager, rebalancer
+    return config, api_client, portfolio_manager, rebalancer
+
+def _handle_sigterm(signum, frame):
+    # 前端停止时发送SIGTERM，按Ctrl+C的流程退出
+    raise KeyboardInterrupt
+
+def wait_for_next_check(state):
+    """
+    Sleep until the next check, answering frontend commands meanwhile.
+
+    Raises:
+        KeyboardInterrupt: When the frontend sends a stop command
+    """
+    deadline = time.monotonic() + CHECK_INTERVAL
+    while True:
+        remaining = deadline - time.monotonic()
+        if remaining <= 0:
+            return
+        command = next_command(remaining)
+        if command is None:
+            return
+        if command.get("type") == "stop":
+            raise KeyboardInterrupt
+        if command.get("type") in ("pause_rebalancing", "resume_rebalancing"):
+            state.set_paused(command["type"] == "pause_rebalancing")
+            print(f"Rebalancing {'paused' if state.paused else 'resumed'} by the frontend.")
+        if command.get("type") == "set_targets":
+            try:
+                for config in state.configs:
+                    config.set_resolved_allocation(command["targets"])
+                print(f"Targets updated by the frontend: {state.configs[0].target_allocation}")
+            except (KeyError, TypeError, ValueError) as e:
+                print(f"Ignored invalid targets from the frontend: {e}")
+        if command.get("type") in ("status", "pause_rebalancing", "resume_rebalancing"):
+            emit("status", status=state.status, paper_trading=state.configs[0].paper_trading)
 
 def main():
     """
     Main function to run the rebalancing bot.
     """
+    signal.signal(signal.SIGTERM, _handle_sigterm)
+
     # Get config from environment or parse args
     config_path = os.environ.get('PORTFOLIO_CONFIG')
     
@@ -55,47 +104,86 @@ def main():
         config_path = args.config
     
     print(f"Starting Portfolio Rebalancing Bot with config: {config_path}")
+    start_server()
     
     # Initialize components
-    api_client, portfolio_manager, rebalancer = initialize_api_and_components(config_path)
+    config, api_client, portfolio_manager, rebalancer = initialize_api_and_components(config_path)
+    print(f"Rebalance schedule: {config.schedule}")
+    state = BackendState([config, portfolio_manager.config, rebalancer.config], portfolio_manager, rebalancer)
+    http_server = start_http_server(state)
+    last_scheduled_run = datetime.now()
+    emit("status", status="Running", paper_trading=config.paper_trading)
+    last_history_date = None
     
     try:
         # Main loop
         while True:
             print(f"\nChecking portfolio at {time.strftime('%Y-%m-%d %H:%M:%S')}")
             
+            # 持有锁期间前端触发的手动再平衡会等待本轮结束
+            state.lock.acquire()
             try:
-                # Check for threshold-based rebalancing
-                print("Checking for threshold-based rebalancing...")
-                threshold_rebalanced = rebalancer.threshold_rebalance()
+                # Daily closes only change once a day
+                now = datetime.now()
+                if last_history_date != now.date():
+                    portfolio_manager.emit_price_history()
+                    last_history_date = now.date()
                 
-                if threshold_rebalanced:
-                    print("Threshold-based rebalancing performed!")
+                if state.paused:
+                    # Snapshots keep the frontend's drawdown check current
+                    print("Rebalancing paused after a drawdown; reporting the portfolio only.")
+                    portfolio_manager.get_portfolio_summary()
                 else:
-                    print("No threshold-based rebalancing needed.")
+                    # Scheduled rebalancing ignores the threshold
+                    if config.schedule.is_due(last_scheduled_run, now):
+                        print("Scheduled rebalancing is due...")
+                        last_scheduled_run = now
+                        if rebalancer.threshold_rebalance(force=True):
+                            print("Scheduled rebalancing performed!")
+                        else:
+                            print("No trades needed for scheduled rebalancing.")
                 
-                # Check for cash-flow-based rebalancing
-                print("Checking for cash-flow-based rebalancing...")
-                cash_flow_rebalanced = rebalancer.cash_flow_rebalance()
+                    # Check for threshold-based rebalancing
+                    print("Checking for threshold-based rebalancing...")
+                    threshold_rebalanced = rebalancer.threshold_rebalance()
                 
-                if cash_flow_rebalanced:
-                    print("Cash-flow-based rebalancing performed!")
-                else:
-                    print("No cash-flow-based rebalancing needed.")
+                    if threshold_rebalanced:
+                        print("Threshold-based rebalancing performed!")
+                    else:
+                        print("No threshold-based rebalancing needed.")
+                
+                    # Check for cash-flow-based rebalancing
+                    print("Checking for cash-flow-based rebalancing...")
+                    cash_flow_rebalanced = rebalancer.cash_flow_rebalance()
+                
+                    if cash_flow_rebalanced:
+                        print("Cash-flow-based rebalancing performed!")
+                    else:
+                        print("No cash-flow-based rebalancing needed.")
                 
             except Exception as e:
                 print(f"Error during rebalancing cycle: {e}")
                 traceback.print_exc()
+                emit("error", message=f"Error during rebalancing cycle: {e}")
+            finally:
+                state.lock.release()
             
             # Sleep until next check
             print(f"Sleeping for {CHECK_INTERVAL//60} minutes...")
-            time.sleep(CHECK_INTERVAL)
+            wait_for_next_check(state)
             
     except KeyboardInterrupt:
         print("\nBot stopped by user.")
+        state.status = "Stopped"
+        emit("status", status="Stopped")
     except Exception as e:
         print(f"Unexpected error: {e}")
         traceback.print_exc()
+        emit("error", message=f"Unexpected error: {e}")
+    finally:
+        stop_server()
+        if http_server is not None:
+            http_server.shutdown()
 
 if __name__ == "__main__":
     main()
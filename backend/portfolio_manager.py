@@ -3,6 +3,7 @@ Portfolio Manager for handling portfolio-related operations.
 """
 from typing import Dict, Tuple
 from backend.config.settings import Config
+from backend.ipc import emit
 
 class PortfolioManager:
     """
@@ -18,8 +19,26 @@ class PortfolioManager:
         """
         self.api_client = api_client
         self.config = Config()
-        self.supported_assets = ["BTC_USDT", "ETH_USDT", "LTC_USDT", "USDT"]
     
+    @property
+    def supported_assets(self):
+        """目标中的合约加上USDT；配置重新加载后随之变化"""
+        return self.config.target_allocation.symbols + ["USDT"]
+    
+    def emit_price_history(self, days=30):
+        """
+        上报各合约的日收盘价序列，前端据此计算资产间的相关性
+        """
+        contracts = [asset for asset in self.supported_assets if asset != "USDT"]
+        prices = [self.api_client.get_futures_closes(contract, days) for contract in contracts]
+        # 只保留都有数据的最近若干天，保证序列等长
+        length = min((len(series) for series in prices), default=0)
+        emit(
+            "price_history",
+            symbols=contracts,
+            prices=[series[len(series) - length:] for series in prices],
+        )
+
     def get_current_portfolio(self) -> Dict[str, float]:
         """
         获取当前投资组合，基于全仓模式下的保证金使用情况
@@ -51,7 +70,7 @@ class PortfolioManager:
                 try:
                     size = float(position.get("size", "0"))
                     mark_price = float(position.get("mark_price", "0"))
-                    LEVERAGE = 3  # 默认3倍杠杆
+                    leverage = self.config.leverage_for(contract)
                     
                     if mark_price <= 0:
                         print(f"Warning: Invalid mark price ({mark_price}) for {contract}. Skipping.")
@@ -59,10 +78,10 @@ class PortfolioManager:
                     
                     # 计算使用的保证金
                     position_value = abs(size) * mark_price
-                    margin_used = position_value / LEVERAGE
+                    margin_used = position_value / leverage
                     used_margin += margin_used
                     
-                    print(f"Position {contract}: Size={size}, Price={mark_price}, Leverage={LEVERAGE}, Value={position_value:.2f}, Margin={margin_used:.2f}")
+                    print(f"Position {contract}: Size={size}, Price={mark_price}, Leverage={leverage}, Value={position_value:.2f}, Margin={margin_used:.2f}")
                     
                     portfolio[contract] = margin_used
                     
@@ -88,18 +107,11 @@ class PortfolioManager:
         Returns:
             Dict mapping asset to target percentage (0.0-1.0)
         """
-        # 读取配置文件中的配置比例，不使用USDT的比例（将自动计算）
-        targets = {
-            "BTC_USDT": self.config.portfolio_allocation.BTC_USDT / 100.0,
-            "ETH_USDT": self.config.portfolio_allocation.ETH_USDT / 100.0,
-            "LTC_USDT": self.config.portfolio_allocation.LTC_USDT / 100.0,
+        # 各合约的目标比例，USDT为剩余部分
+        return {
+            asset: pct / 100.0
+            for asset, pct in self.config.target_allocation.as_dict().items()
         }
-        
-        # 计算剩余百分比给USDT
-        crypto_total = sum(targets.values())
-        targets["USDT"] = max(0.0, 1.0 - crypto_total)
-        
-        return targets
     
     def get_market_prices(self) -> Dict[str, float]:
         """
@@ -157,6 +169,18 @@ class PortfolioManager:
             dev_pct = deviations.get(asset, 0) * 100
             print(f"{asset:<10} {current_value:>15.2f} {current_pct:>9.2f}% {target_pct:>9.2f}% {dev_pct:>9.2f}%")
         print(f"总资产: {sum(current_portfolio.values()):.2f} USDT\n")
+
+        # 资金费率每8小时结算一次，只上报持有的合约
+        funding_rates = {
+            asset: rate for asset, rate in self.api_client.get_funding_rates().items()
+            if asset in current_portfolio
+        }
+        emit(
+            "portfolio_snapshot",
+            allocations={asset: pct * 100 for asset, pct in current_percentages.items()},
+            total_value_usdt=sum(current_portfolio.values()),
+            funding_rates=funding_rates,
+        )
         
         return {
             "current_portfolio": current_portfolio,
@@ -164,5 +188,6 @@ class PortfolioManager:
             "current_percentages": current_percentages,
             "deviations": deviations,
             "total_assets": sum(current_portfolio.values()),
+            "funding_rates": funding_rates,
             "used_margin": sum(current_portfolio.values()) - current_portfolio["USDT"]
         }
\ No newline at end of file
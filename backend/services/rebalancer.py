@@ -5,7 +5,9 @@ Rebalancer module implementing different rebalancing strategies.
 
 from typing import Dict, List
 from backend.config.settings import Config
+from backend.ipc import emit
 import time
+from datetime import datetime, timezone
 
 class Rebalancer:
     """
@@ -23,7 +25,6 @@ class Rebalancer:
         self.api_client = api_client
         self.portfolio_manager = portfolio_manager
         self.config = Config()
-        self.leverage = 3  # 固定使用3倍杠杆
     
     def _calculate_rebalance_amounts(self, portfolio_data: Dict) -> Dict[str, float]:
         """
@@ -90,10 +91,16 @@ class Rebalancer:
                 print(f"无效的市场价格: {contract}: {market_price}")
                 continue
             
-            # 计算合约数量（固定杠杆下，合约价值 = 保证金 * 杠杆）
+            # 计算合约数量（合约价值 = 保证金 * 该合约的杠杆）
             # 调整金额为目标保证金与当前保证金的差值
             # 合约数量 = (保证金差值 * 杠杆) / 市场价格
-            size = (amount_diff * self.leverage) / market_price
+            leverage = self.config.leverage_for(contract)
+            size = (amount_diff * leverage) / market_price
+            
+            notional = abs(amount_diff * leverage)
+            if notional < self.config.min_trade_usdt:
+                print(f"跳过 {contract}: 名义金额 {notional:.2f} USDT 低于最小交易额 (below_min_trade)")
+                continue
             
             trades.append({
                 'contract': contract,
@@ -103,12 +110,13 @@ class Rebalancer:
         
         return trades
     
-    def _execute_trades(self, trades):
+    def _execute_trades(self, trades, portfolio_data=None):
         """
         执行交易列表（全仓模式）
         
         Args:
             trades (list): 包含'contract', 'size', 'market_price'的交易字典列表
+            portfolio_data (dict): 投资组合汇总，用于上报交易前后的比例
             
         Returns:
             list: 已执行的交易详情列表
@@ -125,29 +133,28 @@ class Rebalancer:
             market_price = trade['market_price']
             side = "买入" if size > 0 else "卖出"
             value = abs(size) * market_price
-            margin = value / self.leverage
+            margin = value / self.config.leverage_for(contract)
             print(f"{contract:<10} {side:<6} {abs(size):<10.4f} {value:<15.2f} {margin:<15.2f}")
         print("")
         
-        for trade in trades:
+        # 跳过零大小的交易和USDT "交易"
+        trades = [t for t in trades if abs(t['size']) >= 0.00001 and t['contract'] != "USDT"]
+        
+        for completed, trade in enumerate(trades):
             contract = trade['contract']
             size = trade['size']
             market_price = trade['market_price']
             
-            # 跳过零大小的交易
-            if abs(size) < 0.00001:
-                continue
-            
-            # 跳过USDT "交易"
-            if contract == "USDT":
-                continue
+            # 前端状态栏的进度条
+            emit("rebalance_progress", total_trades=len(trades), completed_trades=completed, current_asset=contract)
                 
             # 确定买卖方向
             side = "buy" if size > 0 else "sell"
 
-            # 设置杠杆为固定值（Gate.io API会自动使用全仓模式）
-            if not self.api_client.set_leverage(contract, self.leverage):
-                print(f"无法设置 {contract} 为{self.leverage}倍杠杆。跳过交易。")
+            # 设置为配置中的杠杆（Gate.io API会自动使用全仓模式）
+            leverage = self.config.leverage_for(contract)
+            if not self.api_client.set_leverage(contract, int(leverage)):
+                print(f"无法设置 {contract} 为{leverage}倍杠杆。跳过交易。")
                 continue
 
             # 执行市价单
@@ -158,6 +165,8 @@ class Rebalancer:
                 reduce_only=False
             )
 
+            self._emit_order_update(contract, side, size, market_price, order_result)
+
             # 检查结果
             if order_result and order_result.get('status') != 'open':
                 executed_size = float(order_result.get('size', size))
@@ -171,16 +180,58 @@ class Rebalancer:
                 }
                 executed_trades.append(executed_trade)
                 print(f"已执行 {side} {abs(executed_size)} {contract} @ {executed_trade['price']} (订单ID: {order_result.get('id')})")
+                summary = portfolio_data or {}
+                emit(
+                    "trade_executed",
+                    asset=contract,
+                    side=side,
+                    from_pct=summary.get("current_percentages", {}).get(contract, 0.0) * 100,
+                    to_pct=summary.get("target_allocations", {}).get(contract, 0.0) * 100,
+                    usdt_traded=executed_trade['amount'] * executed_trade['price'],
+                    order_id=str(order_result.get('id')),
+                    portfolio_value_usdt=sum(summary.get("current_portfolio", {}).values()),
+                    quantity=executed_trade['amount'],
+                    price=executed_trade['price'],
+                )
             else:
                 print(f"执行 {contract} {side} 订单失败，大小: {size}")
         
+        if trades:
+            emit("rebalance_progress", total_trades=len(trades), completed_trades=len(trades), current_asset=None)
         return executed_trades
     
-    def threshold_rebalance(self):
+    def _emit_order_update(self, contract, side, size, market_price, order_result):
+        """
+        上报下单结果，前端的订单页按订单ID记录
+        """
+        if not order_result:
+            status = "failed"
+        elif order_result.get('status') == 'open':
+            status = "open"
+        elif order_result.get('finish_as') == 'cancelled':
+            status = "cancelled"
+        else:
+            status = "filled"
+        order_id = order_result.get('id') if order_result else None
+        emit(
+            "order_update",
+            id=str(order_id) if order_id is not None else f"failed-{contract}-{int(time.time())}",
+            symbol=contract,
+            side=side,
+            qty=abs(float(order_result.get('size', size))) if order_result else abs(size),
+            price=float(order_result.get('fill_price') or market_price) if order_result else market_price,
+            status=status,
+            created_at=datetime.now(timezone.utc).isoformat(),
+        )
+
+    def threshold_rebalance(self, force=False):
         """
         执行基于阈值的再平衡策略：
         当资产的实际配置偏离目标配置超过设定阈值时触发再平衡
         
+        Args:
+            force: 定时再平衡时为True，忽略阈值直接调整到目标配置
+        
         Returns:
             bool: 如果执行了再平衡则返回True，否则返回False
         """
@@ -188,9 +239,6 @@ class Rebalancer:
         portfolio_data = self.portfolio_manager.get_portfolio_summary()
         deviations = portfolio_data["deviations"]
         
-        # 阈值百分比转换为小数
-        threshold = self.config.rebalance_threshold / 100.0
-        
         # 分析偏差并决定是否需要再平衡
         print("\n== 阈值再平衡分析 ==")
         print(f"{'资产':<10} {'偏差':<10} {'阈值':<10} {'需要再平衡':<10}")
@@ -199,13 +247,13 @@ class Rebalancer:
         needs_rebalance = False
         for asset, dev in deviations.items():
             dev_pct = dev * 100
-            threshold_pct = self.config.rebalance_threshold
-            needs_rebal = abs(dev) > threshold
+            threshold_pct = self.config.threshold_for(asset)
+            needs_rebal = abs(dev) > threshold_pct / 100.0
             if needs_rebal:
                 needs_rebalance = True
             print(f"{asset:<10} {dev_pct:>9.2f}% {threshold_pct:>9.2f}% {'是' if needs_rebal else '否':^10}")
         
-        if not needs_rebalance:
+        if not needs_rebalance and not force:
             print("\n没有资产超过再平衡阈值，跳过再平衡")
             return False
         
@@ -221,12 +269,38 @@ class Rebalancer:
             print("没有需要执行的交易")
             return False
             
-        executed_trades = self._execute_trades(trades)
+        executed_trades = self._execute_trades(trades, portfolio_data)
         
         # 显示再平衡后的组合
                 
         return len(executed_trades) > 0
     
+    def rebalance_now(self):
+        """
+        立即调整到目标配置（忽略阈值），由前端通过HTTP API手动触发
+        
+        Returns:
+            dict: 已执行的交易，格式与前端的RebalancePlan一致
+        """
+        portfolio_data = self.portfolio_manager.get_portfolio_summary()
+        rebalance_amounts = self._calculate_rebalance_amounts(portfolio_data)
+        trades = self._calculate_trades(rebalance_amounts)
+        
+        print("\n执行手动再平衡...")
+        executed_trades = self._execute_trades(trades, portfolio_data) if trades else []
+        return {
+            "trades": [
+                {
+                    "symbol": trade['contract'],
+                    "side": trade['side'],
+                    "notional_usdt": trade['amount'] * trade['price'],
+                    "reason": "manual",
+                }
+                for trade in executed_trades
+            ],
+            "skipped": [],
+        }
+    
     def cash_flow_rebalance(self):
         """
         执行基于现金流入的再平衡策略：
@@ -262,7 +336,7 @@ class Rebalancer:
             return False
             
         print("\n执行基于现金流的再平衡...")
-        executed_trades = self._execute_trades(trades)
+        executed_trades = self._execute_trades(trades, portfolio_data)
         
         # 显示再平衡后的组合
                 
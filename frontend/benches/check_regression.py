@@ -0,0 +1,28 @@
+"""Fails when a criterion benchmark got slower than the saved baseline.
+
+Usage: check_regression.py <criterion dir> <max relative slowdown, e.g. 0.20>
+
+Reads the change/estimates.json criterion writes for every benchmark run with
+`--baseline`, and compares the mean change against the limit.
+"""
+import json
+import sys
+from pathlib import Path
+
+
+def main():
+    root, limit = Path(sys.argv[1]), float(sys.argv[2])
+    regressions = []
+    for estimates in sorted(root.glob("**/change/estimates.json")):
+        name = estimates.parent.parent.relative_to(root)
+        change = json.loads(estimates.read_text())["mean"]["point_estimate"]
+        print(f"{name}: {change:+.1%}")
+        if change > limit:
+            regressions.append(name)
+    if regressions:
+        print(f"Slower by more than {limit:.0%}: " + ", ".join(map(str, regressions)))
+        sys.exit(1)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,156 @@
+use chrono::{Days, NaiveDate};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::io::Write;
+use std::path::PathBuf;
+
+use kin_portfolio_rebalancer_gui::backtest::{run_backtest, BacktestConfig};
+use kin_portfolio_rebalancer_gui::config::{
+    AssetAllocation, Config, MarketData, PortfolioAllocation, USDT,
+};
+use kin_portfolio_rebalancer_gui::exchange::Balances;
+use kin_portfolio_rebalancer_gui::metrics::{compute_correlation, portfolio_health_score};
+use kin_portfolio_rebalancer_gui::planner::compute_plan;
+
+// 固定种子的线性同余生成器，保证每次运行的数据相同
+struct Lcg(u64);
+
+impl Lcg {
+    // Uniform in 0.0..1.0
+    fn next(&mut self) -> f64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn symbols(n: usize) -> Vec<String> {
+    (0..n).map(|i| format!("COIN{}_USDT", i)).collect()
+}
+
+// Random walks starting at 100, one close per day
+fn price_series(assets: usize, days: usize) -> Vec<Vec<f64>> {
+    let mut rng = Lcg(42);
+    (0..assets)
+        .map(|_| {
+            let mut price = 100.0;
+            (0..days)
+                .map(|_| {
+                    price *= 1.0 + (rng.next() - 0.5) * 0.08;
+                    price
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// `n` equal targets taking 90% together, and balances far enough off to trade
+fn portfolio(n: usize) -> (Config, Balances, HashMap<String, f64>) {
+    let symbols = symbols(n);
+    let config = Config {
+        portfolio_allocation: PortfolioAllocation {
+            assets: symbols
+                .iter()
+                .map(|s| AssetAllocation::new(s.clone(), 90.0 / n as f64))
+                .collect(),
+        },
+        ..Config::default()
+    };
+    let mut rng = Lcg(7);
+    let mut balances: Balances = symbols
+        .iter()
+        .map(|s| (s.clone(), 100.0 + rng.next() * 2_000.0))
+        .collect();
+    balances.insert(USDT.to_string(), 1_000.0);
+    let prices = symbols
+        .iter()
+        .map(|s| (s.clone(), 1.0 + rng.next() * 50_000.0))
+        .collect();
+    (config, balances, prices)
+}
+
+fn bench_compute_plan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compute_plan");
+    for n in [3, 10, 50] {
+        let (config, balances, prices) = portfolio(n);
+        let market_data = MarketData::default();
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, _| {
+            b.iter(|| compute_plan(black_box(&balances), &config, &prices, &market_data))
+        });
+    }
+    group.finish();
+}
+
+fn bench_health_score(c: &mut Criterion) {
+    let mut group = c.benchmark_group("portfolio_health_score");
+    for n in [3, 10, 50] {
+        let (config, balances, _) = portfolio(n);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, _| {
+            b.iter(|| {
+                portfolio_health_score(
+                    black_box(&balances),
+                    &config.portfolio_allocation,
+                    |symbol| config.threshold_for(symbol),
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_correlation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compute_correlation");
+    for days in [30, 90, 180] {
+        let prices = price_series(10, days);
+        group.bench_with_input(BenchmarkId::from_parameter(days), &days, |b, _| {
+            b.iter(|| compute_correlation(black_box(&prices)))
+        });
+    }
+    group.finish();
+}
+
+// Daily closes of BTC, ETH and LTC in the layout `run_backtest` reads
+fn write_prices_csv(start: NaiveDate, days: usize) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("kin_bench_prices_{}d.csv", days));
+    let mut file = std::fs::File::create(&path).unwrap();
+    writeln!(file, "date,BTC_USDT,ETH_USDT,LTC_USDT").unwrap();
+    let series = price_series(3, days);
+    for (day, ((btc, eth), ltc)) in series[0].iter().zip(&series[1]).zip(&series[2]).enumerate() {
+        let date = start + Days::new(day as u64);
+        writeln!(file, "{},{:.4},{:.4},{:.4}", date, btc, eth, ltc).unwrap();
+    }
+    path
+}
+
+fn bench_backtest(c: &mut Criterion) {
+    let mut group = c.benchmark_group("run_backtest");
+    group.sample_size(20);
+    let start = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+    let config = Config::default();
+    for years in [1, 5] {
+        let days = years * 365;
+        let bt = BacktestConfig {
+            prices_csv: write_prices_csv(start, days),
+            start_date: start,
+            end_date: start + Days::new(days as u64),
+            initial_usdt: 10_000.0,
+        };
+        group.bench_with_input(BenchmarkId::new("years", years), &years, |b, _| {
+            b.iter(|| run_backtest(&config, black_box(&bt)).unwrap())
+        });
+        let _ = std::fs::remove_file(&bt.prices_csv);
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_compute_plan,
+    bench_health_score,
+    bench_correlation,
+    bench_backtest
+);
+criterion_main!(benches);
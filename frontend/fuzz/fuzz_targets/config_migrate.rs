@@ -0,0 +1,79 @@
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use serde_json::{Map, Number, Value};
+
+use kin_portfolio_rebalancer_gui::config::Config;
+
+// serde_json::Value cannot derive Arbitrary itself
+#[derive(Debug, Arbitrary)]
+enum FuzzValue {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<FuzzValue>),
+    Object(Vec<(String, FuzzValue)>),
+    // Known keys make it past the first checks far more often than random ones
+    Known(KnownKey, Box<FuzzValue>),
+}
+
+#[derive(Debug, Arbitrary)]
+enum KnownKey {
+    Version,
+    PortfolioAllocation,
+    Btc,
+    TargetPct,
+    RebalanceThreshold,
+    PerAssetThreshold,
+}
+
+impl KnownKey {
+    fn name(&self) -> &'static str {
+        match self {
+            KnownKey::Version => "version",
+            KnownKey::PortfolioAllocation => "portfolio_allocation",
+            KnownKey::Btc => "BTC_USDT",
+            KnownKey::TargetPct => "target_pct",
+            KnownKey::RebalanceThreshold => "rebalance_threshold",
+            KnownKey::PerAssetThreshold => "per_asset_threshold",
+        }
+    }
+}
+
+impl From<FuzzValue> for Value {
+    fn from(value: FuzzValue) -> Self {
+        match value {
+            FuzzValue::Null => Value::Null,
+            FuzzValue::Bool(b) => Value::Bool(b),
+            FuzzValue::Integer(i) => Value::Number(i.into()),
+            // JSON has no NaN or infinity
+            FuzzValue::Float(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+            FuzzValue::String(s) => Value::String(s),
+            FuzzValue::Array(items) => Value::Array(items.into_iter().map(Value::from).collect()),
+            FuzzValue::Object(entries) => Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, Value::from(value)))
+                    .collect(),
+            ),
+            FuzzValue::Known(key, value) => {
+                let mut object = Map::new();
+                object.insert(key.name().to_string(), Value::from(*value));
+                Value::Object(object)
+            }
+        }
+    }
+}
+
+fuzz_target!(|value: FuzzValue| {
+    if let Ok(config) = Config::migrate(Value::from(value)) {
+        let in_range = |pct: f64| (0.0..=200.0).contains(&pct);
+        for asset in &config.portfolio_allocation.assets {
+            assert!(in_range(asset.target_pct), "{:?}", asset);
+        }
+        assert!(in_range(config.rebalance_threshold));
+    }
+});
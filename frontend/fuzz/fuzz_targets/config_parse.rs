@@ -0,0 +1,33 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use kin_portfolio_rebalancer_gui::config::{Config, ConfigFormat};
+
+// Config text as read from disk: parsed, migrated and range-checked
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(config) = Config::from_text(text, ConfigFormat::Json) {
+        assert_valid(&config, text.len());
+    }
+});
+
+// Whatever loads must have its percentages in range, and strings without a
+// default cannot be longer than the input they came from
+fn assert_valid(config: &Config, input_len: usize) {
+    let in_range = |pct: f64| (0.0..=200.0).contains(&pct);
+    for asset in &config.portfolio_allocation.assets {
+        assert!(in_range(asset.target_pct), "{:?}", asset);
+    }
+    assert!(in_range(config.rebalance_threshold));
+    assert!(in_range(config.margin_alert_threshold_pct));
+    assert!(config.max_drawdown_halt_pct.is_none_or(in_range));
+    for (symbol, pct) in config.per_asset_threshold.iter().flatten() {
+        assert!(in_range(*pct), "{}: {}", symbol, pct);
+    }
+    assert!(config.api_key.len() <= input_len);
+    assert!(config.api_secret.len() <= input_len);
+    assert!(config.notes.len() <= input_len);
+}
@@ -1,357 +1,4340 @@
-use anyhow::{anyhow, Result};
-use eframe::egui::{self, Align, Button, Color32, Grid, Layout, RichText, TextEdit, Vec2};
-use std::fs;
-use std::io::Write; // 仅保留用于保存配置的Write
-use std::path::PathBuf;
-use std::process::{Child, Command};
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, Timelike, Utc, Weekday};
+use eframe::egui::{
+    self, Align, Button, Color32, Grid, Layout, Pos2, RichText, Sense, Slider, Stroke, TextEdit,
+    Vec2,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+use zeroize::{Zeroize, Zeroizing};
 
-use crate::config::{Config, PortfolioAllocation};
+use crate::audit::{self, AuditAction, AuditEntry, AuditLog};
+use crate::backend::{self, BackendEndpoint};
+use crate::backend_client::{BackendClient, BackendStatus};
+use crate::backtest::{self, BacktestConfig, BacktestResult, MonteCarloSummary, FAN_PERCENTILES};
+use crate::config::{
+    self, import_from_csv, validate_api_key, validate_api_secret, AllocationMode, AssetAllocation,
+    Config, ConfigBackup, ConfigFormat, MarketData, PortfolioAllocation, PositionSizing,
+    ProfileManager, RiskParityConfig, DEFAULT_LEVERAGE, DEFAULT_PROFILE, EXPOSURE_RISK_LIMIT,
+    KELLY_CONCENTRATION_LIMIT_PCT, MAX_DISPLAY_PRECISION, MAX_NOTES_CHARS, MAX_UI_SCALE,
+    MIN_UI_SCALE, USDT,
+};
+use crate::currency::{self, DisplayCurrency};
+use crate::db::Database;
+use crate::error::{FieldId, KinError};
+use crate::exchange::mock::{MockExchange, TestScenario, PAPER_INITIAL_USDT};
+use crate::exchange::{
+    self, ApiError, Balances, Exchange, ExchangeChoice, OrderResult, OrderSide, Position,
+};
+use crate::health::{AppState, HealthServer, SharedState};
+use crate::history::{
+    self, HistoryColumn, Order, OrderStatus, RebalanceEvent, TimeRange, ValueHistory,
+};
+use crate::ipc::{self, BackendSocket};
+use crate::keybindings::{format_shortcut, KeyAction};
+use crate::locale::{format_number, Locale};
+use crate::logging;
+use crate::market_cap;
+use crate::metrics;
+use crate::planner::{self, RebalancePlan};
+use crate::portfolio::{self, PortfolioManager};
+use crate::presets::{self, AllocationPreset, PortfolioAllocationPatch, SharedAllocation};
+use crate::protocol::{self, BackendCommand, BackendMessage};
+use crate::risk;
+use crate::schedule::{RebalanceSchedule, ScheduleKind};
+use crate::slippage::{SlippageKind, SlippageModel};
+use crate::symbols::SymbolSuggestions;
+use crate::tax::{self, CostBasisMethod, RealizedGain, TaxLot};
+use crate::theme::{self, Theme};
+use crate::tray::{Tray, TrayCommand};
+use crate::update::{self, Release, UpdateChecker};
+use crate::watcher::ConfigWatcher;
+use crate::webhook::{self, WebhookEvent, WebhookPayload};
+use crate::widgets::gauge::{AllocationGauge, MarginGauge};
+use crate::widgets::heatmap::CorrelationHeatmap;
+use crate::widgets::pie_chart::{slice_color, AllocationPieChart, USDT_COLOR};
+use crate::window_state::{WindowState, DEFAULT_SIDEBAR_RATIO};
 
 pub struct RebalancerApp {
-    config: Config,
-    api_key: String,
-    api_secret: String,
+    profiles: ProfileManager,
+    // Typed into the API settings; zeroed on drop and once saved
+    api_key: Zeroizing<String>,
+    api_secret: Zeroizing<String>,
+    // Format problems in the fields above, updated as the user types
+    api_key_error: Option<String>,
+    api_secret_error: Option<String>,
+    keyring_available: bool,
+    keyring_api_key: String, // 钥匙串中已保存的Key，仅用于显示
     config_path: PathBuf,
+    profiles_path: PathBuf,
+    // Set when the config was opened with --passphrase; every save is encrypted
+    passphrase: Option<String>,
+    config_watcher: Option<ConfigWatcher>,
+    // Config written to disk by another program, waiting for Reload or Ignore
+    external_config: Option<Config>,
+    confirm_reload: bool,
+    // Listed when the Restore Backup menu opens; None again after each change to the file
+    config_backups: Option<Vec<ConfigBackup>>,
     backend_process: Option<Child>, // Keep handle to manage the process
+    backend_receiver: Option<Receiver<BackendMessage>>,
+    // Feeds socket messages into the same channel as the backend's stdout
+    backend_sender: Option<Sender<BackendMessage>>,
+    backend_socket: Option<Arc<BackendSocket>>,
+    // Pending connection attempt, running on a background thread
+    socket_connect: Option<Receiver<Result<BackendSocket, KinError>>>,
+    next_socket_attempt: Option<Instant>,
+    // Socket and HTTP port of the selected portfolio's backend
+    backend_endpoint: BackendEndpoint,
+    backend_client: BackendClient,
+    status_sender: Sender<Result<BackendStatus, KinError>>,
+    status_receiver: Receiver<Result<BackendStatus, KinError>>,
+    status_poll_pending: bool,
+    next_status_poll: Option<Instant>,
+    manual_rebalance_sender: Sender<Result<RebalancePlan, KinError>>,
+    manual_rebalance_receiver: Receiver<Result<RebalancePlan, KinError>>,
+    manual_rebalance_loading: bool,
+    symbol_suggestions: SymbolSuggestions,
+    // Contract list being fetched from Gate.io on the first launch
+    symbols_receiver: Option<Receiver<Vec<String>>>,
     status: String,
     is_running: bool,
+    // Automatic restarts after the backend exits on its own; reset by a manual start
+    restart_count: u32,
+    next_restart_at: Option<Instant>,
     error_message: Option<String>,
 
-    // Removed backend output state:
-    // backend_output_receiver: Option<Receiver<String>>,
-    // portfolio_summary_output: Vec<String>,
+    // Latest actual allocation reported for the portfolio, in percent per symbol
+    live_allocations: Option<HashMap<String, f64>>,
+    // Highest portfolio value reported this session; rebalancing is paused once
+    // the value falls `max_drawdown_halt_pct` below it
+    peak_value_usdt: f64,
+    halted_due_to_drawdown: bool,
+    // 8-hour funding rate per contract, from the latest snapshot that had them
+    funding_rates: HashMap<String, f64>,
+    // Set while the margin ratio is below the alert threshold, so it notifies once
+    margin_alert_active: bool,
+    // Daily return correlations of `correlation_symbols`, from the backend's price history
+    correlation_symbols: Vec<String>,
+    correlation_matrix: Option<Vec<Vec<f64>>>,
+    // Daily closes of `correlation_symbols`, kept for the Monte Carlo simulation
+    price_history: Vec<Vec<f64>>,
+    // Set while a simulation runs on its own thread
+    simulation_receiver: Option<Receiver<MonteCarloSummary>>,
+    simulation: Option<MonteCarloSummary>,
+
+    // Balances queried directly from the exchange, without the Python backend
+    runtime: Option<Runtime>,
+    // Connected lazily from the saved credentials; dropped when they change
+    exchange: Option<Arc<dyn Exchange>>,
+    balance_sender: Sender<BalanceResult>,
+    balance_receiver: Receiver<BalanceResult>,
+    live_balances: Option<HashMap<String, f64>>,
+    balances_loading: bool,
+    // Open positions, fetched along with the balances
+    positions_sender: Sender<Result<Vec<Position>, ApiError>>,
+    positions_receiver: Receiver<Result<Vec<Position>, ApiError>>,
+    positions: Vec<Position>,
+    // Periodic refresh of the balances and prices every `poll_interval_seconds`
+    poll_sender: Sender<PollResult>,
+    poll_receiver: Receiver<PollResult>,
+    last_poll: Option<Instant>,
+    last_updated: Option<Instant>,
+    // CoinGecko market caps per contract for market-cap weighting, refreshed with each
+    // poll, and volatilities for risk parity from the closes stored in the database
+    market_cap_sender: Sender<Result<HashMap<String, f64>, KinError>>,
+    market_cap_receiver: Receiver<Result<HashMap<String, f64>, KinError>>,
+    market_data: MarketData,
+    // Status bar dot; None until the first ping has come back
+    connectivity: Option<ConnectivityStatus>,
+    connectivity_checking: bool,
+    connectivity_sender: Sender<Result<u64, ApiError>>,
+    connectivity_receiver: Receiver<Result<u64, ApiError>>,
+    // Status area progress bar; None while no rebalance is executing
+    rebalance_progress: Option<RebalanceProgress>,
+    // Theme last applied to the context; None before the first frame
+    current_theme: Option<Theme>,
+    // Navigation sidebar width over window width, saved with the window state
+    sidebar_ratio: f32,
+    update_sender: Sender<Result<Option<Release>, KinError>>,
+    update_receiver: Receiver<Result<Option<Release>, KinError>>,
+    // Banner for a newer release; None when up to date or dismissed
+    available_update: Option<Release>,
+    // Portfolio total from the latest balances or backend snapshot, and the price
+    // of the display currency's contract when it is not USDT
+    total_value_usdt: Option<f64>,
+    display_prices: HashMap<String, f64>,
+    // Snapshot totals for the Performance chart, and the span it shows
+    value_history: ValueHistory,
+    value_chart_range: TimeRange,
+    // Dry run: balances and prices fetched for the plan, then the plan awaiting confirmation
+    dry_run_sender: Sender<DryRunResult>,
+    dry_run_receiver: Receiver<DryRunResult>,
+    dry_run_loading: bool,
+    dry_run: Option<(RebalancePlan, HashMap<String, f64>)>,
+    // "Simulate Changes" window; closing it discards the proposed allocation
+    what_if: Option<WhatIf>,
+    order_sender: Sender<Result<OrderResult, ApiError>>,
+    order_receiver: Receiver<Result<OrderResult, ApiError>>,
+
+    // Trades executed by the backend, persisted in the database next to the config file
+    db: Option<Database>,
+    event_history: Vec<RebalanceEvent>,
+    // Orders placed by the backend and by dry runs, oldest first, also kept in the database
+    orders: Vec<Order>,
+    orders_page: usize,
+    orders_search: String,
+    // Open tax lots and every gain realized so far, also kept in the database
+    tax_lots: Vec<TaxLot>,
+    realized_gains: Vec<RealizedGain>,
+    // When each asset last raised a desktop notification
+    last_notified: HashMap<String, Instant>,
+    // Result of the "Test Webhook" button: the HTTP status or the error message
+    webhook_test_sender: Sender<Result<u16, String>>,
+    webhook_test_receiver: Receiver<Result<u16, String>>,
+    webhook_test_result: Option<Result<u16, String>>,
+    webhook_testing: bool,
+    history_sort_column: HistoryColumn,
+    history_sort_ascending: bool,
+    // History shows only the trades of assets with this tag
+    filter_by_tag: Option<String>,
 
     // Portfolio allocation editor
     portfolio_editor: PortfolioAllocationEditor,
+    // Previously saved allocations of the active profile, most recent last
+    history_stack: Vec<PortfolioAllocation>,
+    redo_stack: Vec<PortfolioAllocation>,
+    // Fields that failed the last validation, outlined in red
+    field_errors: Vec<(FieldId, String)>,
+    // Editor changes not yet written to disk; auto-saved after AUTOSAVE_DELAY of idle time
+    dirty: bool,
+    // The last edit was to the strategy notes, which wait NOTES_AUTOSAVE_DELAY instead
+    notes_edited: bool,
+    last_edit_instant: Option<Instant>,
+    last_saved_instant: Option<Instant>,
+    // "Copied ✓" shows on the Copy as JSON button for COPIED_BADGE_DURATION
+    allocation_copied_at: Option<Instant>,
+    // A clipboard paste waiting for confirmation, with the changes it would make
+    pending_paste: Option<(PortfolioAllocationPatch, Vec<String>)>,
+    // STOP was clicked; the backend keeps running until the user confirms
+    pending_stop_confirm: bool,
+    // START was clicked with unsaved editor changes the backend would not see
+    pending_start_confirm: bool,
 
     // UI state
-    show_portfolio_editor: bool,
-    show_api_settings: bool,
+    active_tab: Tab,
+    backtest_csv: Option<PathBuf>,
+    backtest_start: String,
+    backtest_end: String,
+    backtest_initial_usdt: String,
+    backtest_result: Option<BacktestResult>,
+    // Outer position and inner size of the window, saved on exit
+    window_geometry: Option<(Pos2, Vec2)>,
+    log_lines: VecDeque<String>,
+    new_profile_name: String,
+    // The selected portfolio is the one `config_path` points at
+    portfolios: PortfolioManager,
+    // Some while the name field of a new portfolio is open in the sidebar
+    new_portfolio_name: Option<String>,
+
+    // System tray, where supported; its menu clicks arrive on `tray_commands`
+    tray: Option<Tray>,
+    tray_commands: Option<Receiver<TrayCommand>>,
+    // Set by the tray's Quit so the close is not turned into minimize-to-tray
+    quit_requested: bool,
+    // Set from the SIGINT/SIGTERM handlers installed by main
+    shutdown_signal: Option<Arc<AtomicBool>>,
+    // Append-only record of the user's state-changing actions
+    audit_log: AuditLog,
+    // Read by the /health and /metrics server, refreshed every frame
+    health_state: SharedState,
+    // Serves until dropped
+    _health_server: Option<HealthServer>,
+    // `--mock-exchange`: balances, prices and orders come from this canned account
+    mock_scenario: Option<TestScenario>,
+
+    // First-run setup wizard, shown instead of the tabs until it is finished or skipped
+    show_wizard: bool,
+    wizard_step: WizardStep,
+    wizard_start_now: bool,
+    // Action waiting for its new shortcut to be pressed in the Key Bindings section
+    rebinding: Option<KeyAction>,
+}
+
+type BalanceResult = Result<(Balances, Option<f64>), ApiError>;
+type DryRunResult = (
+    SnapshotPurpose,
+    Result<(Balances, HashMap<String, f64>), ApiError>,
+);
+// Snapshot message, the balances it was computed from and the display currency's price
+type PollResult = Result<(BackendMessage, Balances, Option<f64>), ApiError>;
+
+const MAX_LOG_LINES: usize = 500;
+// Older trades stay in the database but are not loaded into the History tab
+const MAX_HISTORY_EVENTS: usize = 10_000;
+const AUTOSAVE_DELAY: Duration = Duration::from_secs(2);
+// Notes are typed in longer bursts than numbers
+const NOTES_AUTOSAVE_DELAY: Duration = Duration::from_secs(3);
+const SAVED_BADGE_DURATION: Duration = Duration::from_secs(1);
+const COPIED_BADGE_DURATION: Duration = Duration::from_secs(2);
+const MAX_UNDO_STEPS: usize = 50;
+const MAX_RESTARTS: u32 = 5;
+// Delay before the first automatic restart, doubled for every further attempt
+const RESTART_BACKOFF: Duration = Duration::from_secs(2);
+// Wait before connecting again to a backend that never opened its socket
+const SOCKET_RETRY: Duration = Duration::from_secs(30);
+const STATUS_POLL_INTERVAL: Duration = Duration::from_secs(5);
+// At most one trade notification per asset in this window
+const NOTIFICATION_COOLDOWN: Duration = Duration::from_secs(30);
+// Drift beyond this share of the rebalance threshold is shown as approaching it
+const DRIFT_WARNING_RATIO: f64 = 0.75;
+const MONTE_CARLO_SIMS: usize = 1_000;
+const MONTE_CARLO_DAYS: usize = 180;
+const CONNECTIVITY_INTERVAL: Duration = Duration::from_secs(30);
+// Pings up to these round trips show green and yellow, slower ones red
+const PING_GOOD_MS: u64 = 200;
+const PING_FAIR_MS: u64 = 1000;
+// Narrower windows (in points) collapse the sidebar to icons
+const COMPACT_WINDOW_WIDTH: f32 = 400.0;
+
+/// Outcome of the last exchange ping; `exchange_ping_ms` is None when it failed.
+struct ConnectivityStatus {
+    exchange_ping_ms: Option<u64>,
+    last_checked: Instant,
+}
+
+/// Orders placed so far in the rebalance the backend is executing.
+struct RebalanceProgress {
+    total_trades: usize,
+    completed_trades: usize,
+    current_asset: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Tab {
+    #[default]
+    PortfolioConfig,
+    ApiSettings,
+    Log,
+    History,
+    Orders,
+    Performance,
+    Backtest,
+}
+
+impl Tab {
+    pub const ALL: [Tab; 7] = [
+        Tab::ApiSettings,
+        Tab::PortfolioConfig,
+        Tab::Log,
+        Tab::History,
+        Tab::Orders,
+        Tab::Performance,
+        Tab::Backtest,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Tab::PortfolioConfig => "Portfolio Config",
+            Tab::ApiSettings => "API Settings",
+            Tab::Log => "Backend Log",
+            Tab::History => "History",
+            Tab::Orders => "Orders",
+            Tab::Performance => "Performance",
+            Tab::Backtest => "Backtest",
+        }
+    }
+
+    // Shown instead of the label in the collapsed sidebar
+    fn icon(self) -> &'static str {
+        match self {
+            Tab::PortfolioConfig => "▶",
+            Tab::ApiSettings => "⚙",
+            Tab::Log => "📋",
+            Tab::History => "🕘",
+            Tab::Orders => "☰",
+            Tab::Performance => "📈",
+            Tab::Backtest => "⏪",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum WizardStep {
+    #[default]
+    ApiKeys,
+    Allocations,
+    Review,
+}
+
+impl WizardStep {
+    const ALL: [WizardStep; 3] = [
+        WizardStep::ApiKeys,
+        WizardStep::Allocations,
+        WizardStep::Review,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            WizardStep::ApiKeys => "Enter API Keys",
+            WizardStep::Allocations => "Configure Portfolio Allocations",
+            WizardStep::Review => "Review & Start",
+        }
+    }
+
+    fn number(self) -> usize {
+        Self::ALL.iter().position(|s| *s == self).unwrap_or(0) + 1
+    }
+
+    fn next(self) -> Self {
+        Self::ALL.get(self.number()).copied().unwrap_or(self)
+    }
+
+    fn back(self) -> Self {
+        Self::ALL[self.number().saturating_sub(2)]
+    }
 }
 
+/// The portfolio tab's fields as typed, turned into config values on save.
 #[derive(Default)]
-struct PortfolioAllocationEditor {
-    BTC_USDT_allocation: String,
-    ETH_USDT_allocation: String,
-    LTC_USDT_allocation: String,
-    USDT_allocation: String, // 保留为只读显示项
+pub struct PortfolioAllocationEditor {
+    assets: Vec<AssetEditorRow>,
+    new_asset_symbol: String,
+    usdt_allocation: String, // 保留为只读显示项
+    use_sliders: bool,
     rebalance_threshold: String,
+    // Keyed by symbol so the values survive the rows being rebuilt; empty uses the global one
+    override_thresholds: bool,
+    asset_thresholds: HashMap<String, String>,
     min_usdt_inflow: String,
+    min_trade_usdt: String,
+    // In percent, while the config holds fractions
+    taker_fee_pct: String,
+    maker_fee_pct: String,
+    allocation_mode: AllocationMode,
+    position_sizing: PositionSizing,
+    cost_basis_method: CostBasisMethod,
+    slippage_model: SlippageModel,
+    show_slippage_settings: bool,
+    schedule: RebalanceSchedule,
+    poll_interval_seconds: u64,
+    max_drawdown_halt_pct: Option<f64>,
+    // Decimal places of the percentages the editor formats itself
+    display_precision: u8,
+    // Problems found while typing, shown under each field until it is fixed
+    live_errors: Vec<(FieldId, String)>,
+    // Symbols in the order the grid shows them; saved as `Config::display_order`
+    drag_order: Vec<String>,
+    notes: String,
+}
+
+// Editor fields parsed into config values
+struct EditorValues {
+    allocation: PortfolioAllocation,
+    threshold: f64,
+    per_asset_threshold: Option<HashMap<String, f64>>,
+    min_inflow: f64,
+    min_trade: f64,
+    taker_fee_rate: f64,
+    maker_fee_rate: f64,
+}
+
+// What a fetched balance and price snapshot is for
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SnapshotPurpose {
+    DryRun,
+    WhatIf,
+}
+
+// A what-if copy of the editor's allocation, planned against one snapshot
+struct WhatIf {
+    proposed: PortfolioAllocation,
+    // None while the balances and prices are being fetched
+    snapshot: Option<(Balances, HashMap<String, f64>)>,
+    plan: RebalancePlan,
+}
+
+struct AssetEditorRow {
+    symbol: String,
+    allocation: String,
+    // Not editable in the grid; kept so saving does not reset it
+    leverage: f64,
+    // Comma-separated, as typed
+    tags: String,
+}
+
+impl Default for AssetEditorRow {
+    fn default() -> Self {
+        Self {
+            symbol: String::new(),
+            allocation: String::new(),
+            leverage: DEFAULT_LEVERAGE,
+            tags: String::new(),
+        }
+    }
+}
+
+impl PortfolioAllocationEditor {
+    pub fn from_config(config: &Config) -> Self {
+        let mut editor = Self {
+            rebalance_threshold: config.rebalance_threshold.to_string(),
+            override_thresholds: config.per_asset_threshold.is_some(),
+            asset_thresholds: config
+                .per_asset_threshold
+                .iter()
+                .flatten()
+                .map(|(symbol, threshold)| (symbol.clone(), threshold.to_string()))
+                .collect(),
+            min_usdt_inflow: config.min_usdt_inflow.to_string(),
+            min_trade_usdt: config.min_trade_usdt.to_string(),
+            taker_fee_pct: fee_pct_text(config.taker_fee_rate),
+            maker_fee_pct: fee_pct_text(config.maker_fee_rate),
+            allocation_mode: config.allocation_mode,
+            position_sizing: config.position_sizing,
+            cost_basis_method: config.cost_basis_method,
+            slippage_model: config.slippage_model,
+            schedule: config.schedule,
+            poll_interval_seconds: config.poll_interval_seconds,
+            max_drawdown_halt_pct: config.max_drawdown_halt_pct,
+            display_precision: config.display_precision,
+            notes: config.notes.clone(),
+            ..Default::default()
+        };
+        editor.load_allocation(&config.portfolio_allocation);
+        editor.drag_order = config.display_order.clone();
+        editor.sync_drag_order();
+        editor
+    }
+
+    /// Types `text` into the allocation of `symbol`, adding a row when it has none.
+    pub fn set_allocation(&mut self, symbol: &str, text: &str) {
+        match self.assets.iter_mut().find(|row| row.symbol == symbol) {
+            Some(row) => row.allocation = text.to_string(),
+            None => {
+                self.assets.push(AssetEditorRow {
+                    symbol: symbol.to_string(),
+                    allocation: text.to_string(),
+                    ..Default::default()
+                });
+                self.sync_drag_order();
+            }
+        }
+    }
+
+    pub fn set_rebalance_threshold(&mut self, text: &str) {
+        self.rebalance_threshold = text.to_string();
+    }
+
+    pub fn set_min_usdt_inflow(&mut self, text: &str) {
+        self.min_usdt_inflow = text.to_string();
+    }
+
+    /// Validates every field and writes the values into `config`. On failure
+    /// `config` is left as it was and all problems come back at once as
+    /// `KinError::ValidationErrors`.
+    pub fn update_config(&self, config: &mut Config) -> Result<(), KinError> {
+        let values = self.validate().map_err(KinError::ValidationErrors)?;
+        config.portfolio_allocation = values.allocation;
+        config.rebalance_threshold = values.threshold;
+        config.per_asset_threshold = values.per_asset_threshold;
+        config.min_usdt_inflow = values.min_inflow;
+        config.min_trade_usdt = values.min_trade;
+        config.taker_fee_rate = values.taker_fee_rate;
+        config.maker_fee_rate = values.maker_fee_rate;
+        config.allocation_mode = self.allocation_mode;
+        config.position_sizing = self.position_sizing;
+        config.cost_basis_method = self.cost_basis_method;
+        config.slippage_model = self.slippage_model;
+        config.schedule = self.schedule;
+        config.poll_interval_seconds = self.poll_interval_seconds;
+        config.max_drawdown_halt_pct = self.max_drawdown_halt_pct;
+        config.display_precision = self.display_precision;
+        config.display_order = self.drag_order.clone();
+        config.notes = self.notes.clone();
+        Ok(())
+    }
+
+    // Drops removed assets from the drag order and appends added ones
+    fn sync_drag_order(&mut self) {
+        let symbols: Vec<String> = self.assets.iter().map(|row| row.symbol.clone()).collect();
+        self.drag_order = config::display_order(&self.drag_order, &symbols);
+    }
+
+    // Indices into `assets` in display order; expects `sync_drag_order` to have run
+    fn ordered_rows(&self) -> Vec<usize> {
+        let mut rows: Vec<usize> = Vec::with_capacity(self.assets.len());
+        for symbol in &self.drag_order {
+            if let Some(index) = (0..self.assets.len())
+                .find(|i| self.assets[*i].symbol == *symbol && !rows.contains(i))
+            {
+                rows.push(index);
+            }
+        }
+        rows
+    }
+
+    // Checks every field and reports all problems at once instead of stopping at the first
+    fn validate(&self) -> Result<EditorValues, Vec<(FieldId, String)>> {
+        let mut errors = Vec::new();
+        let mut assets: Vec<AssetAllocation> = Vec::new();
+        for (i, row) in self.assets.iter().enumerate() {
+            let field = FieldId::Allocation(i);
+            let symbol = row.symbol.trim().to_uppercase();
+            if symbol.is_empty() {
+                errors.push((
+                    field,
+                    format!("Row {}: asset symbol cannot be empty.", i + 1),
+                ));
+                continue;
+            }
+            if assets.iter().any(|a| a.symbol == symbol) {
+                errors.push((field, format!("{} is listed more than once.", symbol)));
+                continue;
+            }
+            match validate_field(field, &row.allocation) {
+                Some(error) => errors.push((field, format!("{} allocation: {}", symbol, error))),
+                None => assets.push(AssetAllocation {
+                    leverage: row.leverage,
+                    tags: config::parse_tags(&row.tags),
+                    ..AssetAllocation::new(symbol, row.allocation.trim().parse().unwrap_or(0.0))
+                }),
+            }
+        }
+
+        let allocation = PortfolioAllocation { assets };
+        if allocation.check_total().is_err() {
+            errors.push((
+                FieldId::AllocationTotal,
+                format!(
+                    "Sum of asset allocations ({}%) cannot exceed 100%.",
+                    self.format_pct(allocation.crypto_total())
+                ),
+            ));
+        }
+
+        let threshold = parse_setting(
+            &self.rebalance_threshold,
+            FieldId::RebalanceThreshold,
+            "Rebalance threshold",
+            &mut errors,
+        );
+        let per_asset_threshold = if self.override_thresholds {
+            let mut thresholds = HashMap::new();
+            for (i, row) in self.assets.iter().enumerate() {
+                let Some(text) = self
+                    .asset_thresholds
+                    .get(&row.symbol)
+                    .filter(|text| !text.trim().is_empty())
+                else {
+                    continue;
+                };
+                let value = parse_setting(
+                    text,
+                    FieldId::AssetThreshold(i),
+                    &format!("{} threshold", row.symbol),
+                    &mut errors,
+                );
+                thresholds.insert(row.symbol.clone(), value);
+            }
+            Some(thresholds)
+        } else {
+            None
+        };
+        let min_inflow = parse_setting(
+            &self.min_usdt_inflow,
+            FieldId::MinUsdtInflow,
+            "Minimum USDT inflow",
+            &mut errors,
+        );
+        let min_trade = parse_setting(
+            &self.min_trade_usdt,
+            FieldId::MinTradeUsdt,
+            "Minimum trade size",
+            &mut errors,
+        );
+        let taker_fee_pct = parse_setting(
+            &self.taker_fee_pct,
+            FieldId::TakerFeeRate,
+            "Taker fee",
+            &mut errors,
+        );
+        let maker_fee_pct = parse_setting(
+            &self.maker_fee_pct,
+            FieldId::MakerFeeRate,
+            "Maker fee",
+            &mut errors,
+        );
+        if self.notes.chars().count() > MAX_NOTES_CHARS {
+            errors.push((
+                FieldId::Notes,
+                format!(
+                    "Strategy notes cannot exceed {} characters.",
+                    MAX_NOTES_CHARS
+                ),
+            ));
+        }
+
+        if errors.is_empty() {
+            Ok(EditorValues {
+                allocation,
+                threshold,
+                per_asset_threshold,
+                min_inflow,
+                min_trade,
+                taker_fee_rate: taker_fee_pct / 100.0,
+                maker_fee_rate: maker_fee_pct / 100.0,
+            })
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn load_preset(&mut self, preset: &AllocationPreset) {
+        self.load_allocation(&preset.allocation);
+        self.rebalance_threshold = preset.rebalance_threshold.to_string();
+        self.min_usdt_inflow = preset.min_usdt_inflow.to_string();
+    }
+
+    // Replaces the asset rows; the rebalancing settings fields are left as they are
+    fn load_allocation(&mut self, allocation: &PortfolioAllocation) {
+        self.assets = allocation
+            .assets
+            .iter()
+            .map(|a| AssetEditorRow {
+                symbol: a.symbol.clone(),
+                allocation: a.target_pct.to_string(),
+                leverage: a.leverage,
+                tags: a.tags.join(", "),
+            })
+            .collect();
+        self.usdt_allocation = self.format_pct(allocation.usdt());
+    }
+
+    // "BTC from 25% → 30%" for each value the patch would change
+    fn patch_changes(&self, patch: &PortfolioAllocationPatch) -> Vec<String> {
+        let mut changes = Vec::new();
+        for (symbol, pct) in &patch.assets {
+            let current = self
+                .assets
+                .iter()
+                .find(|row| &row.symbol == symbol)
+                .map_or("0", |row| row.allocation.trim());
+            if current.parse::<f64>().ok() != Some(*pct) {
+                let asset = symbol.strip_suffix("_USDT").unwrap_or(symbol);
+                changes.push(format!("{} from {}% → {}%", asset, current, pct));
+            }
+        }
+        let settings = [
+            (
+                "the rebalance threshold",
+                &self.rebalance_threshold,
+                patch.rebalance_threshold,
+                "%",
+            ),
+            (
+                "the minimum USDT inflow",
+                &self.min_usdt_inflow,
+                patch.min_usdt_inflow,
+                " USDT",
+            ),
+        ];
+        for (name, current, value, unit) in settings {
+            let Some(value) = value else { continue };
+            if current.trim().parse::<f64>().ok() != Some(value) {
+                changes.push(format!(
+                    "{} from {}{} → {}{}",
+                    name,
+                    current.trim(),
+                    unit,
+                    value,
+                    unit
+                ));
+            }
+        }
+        changes
+    }
+
+    // Unlike load_allocation, rows the patch does not mention are kept
+    fn apply_patch(&mut self, patch: &PortfolioAllocationPatch) {
+        for (symbol, pct) in &patch.assets {
+            match self.assets.iter_mut().find(|row| &row.symbol == symbol) {
+                Some(row) => row.allocation = pct.to_string(),
+                None => self.assets.push(AssetEditorRow {
+                    symbol: symbol.clone(),
+                    allocation: pct.to_string(),
+                    ..AssetEditorRow::default()
+                }),
+            }
+        }
+        if let Some(threshold) = patch.rebalance_threshold {
+            self.rebalance_threshold = threshold.to_string();
+        }
+        if let Some(min_inflow) = patch.min_usdt_inflow {
+            self.min_usdt_inflow = min_inflow.to_string();
+        }
+        self.usdt_allocation = self.get_usdt_display();
+    }
+
+    // The rows as typed; rows that do not parse count as 0
+    fn custom_allocation(&self) -> PortfolioAllocation {
+        PortfolioAllocation {
+            assets: self
+                .assets
+                .iter()
+                .map(|row| AssetAllocation {
+                    symbol: row.symbol.clone(),
+                    target_pct: row.allocation.parse::<f64>().unwrap_or(0.0),
+                    leverage: row.leverage,
+                    tags: config::parse_tags(&row.tags),
+                })
+                .collect(),
+        }
+    }
+
+    // Calculate USDT allocation based on other allocations
+    // Sum of the asset rows; rows that do not parse count as 0
+    fn crypto_total(&self) -> f64 {
+        self.assets
+            .iter()
+            .map(|row| row.allocation.parse::<f64>().unwrap_or(0.0))
+            .sum()
+    }
+
+    // Rounded to `display_precision` so 100 - 3 × 33.3 is 0.1, not 0.10000000000000853
+    fn calculate_usdt(&self) -> f64 {
+        let crypto_total = self.crypto_total();
+
+        if crypto_total > 100.0 {
+            0.0
+        } else {
+            let factor = 10f64.powi(i32::from(self.display_precision));
+            ((100.0 - crypto_total) * factor).round().max(0.0) / factor // Ensure it's not negative due to float issues
+        }
+    }
+
+    /// USDT remainder as shown in the editor, with `display_precision` decimals.
+    pub fn get_usdt_display(&self) -> String {
+        self.format_pct(self.calculate_usdt())
+    }
+
+    fn format_pct(&self, value: f64) -> String {
+        format!("{:.prec$}", value, prec = self.display_precision as usize)
+    }
+
+    // Append the symbol typed into the "Add Asset" field as a new 0% row
+    fn add_asset(&mut self) -> Result<(), KinError> {
+        let symbol = self.new_asset_symbol.trim().to_uppercase();
+        if symbol.is_empty() {
+            return Err(KinError::invalid_allocation(
+                "new asset",
+                "symbol cannot be empty",
+            ));
+        }
+        if symbol == USDT {
+            return Err(KinError::invalid_allocation(
+                USDT,
+                "calculated automatically and cannot be added",
+            ));
+        }
+        if self.assets.iter().any(|row| row.symbol == symbol) {
+            return Err(KinError::invalid_allocation(
+                &symbol,
+                "already in the portfolio",
+            ));
+        }
+        self.assets.push(AssetEditorRow {
+            symbol,
+            allocation: "0".to_string(),
+            ..Default::default()
+        });
+        self.new_asset_symbol.clear();
+        Ok(())
+    }
 }
 
-impl PortfolioAllocationEditor {
-    // Calculate USDT allocation based on other allocations
-    fn calculate_usdt(&self) -> f64 {
-        let btc = self.BTC_USDT_allocation.parse::<f64>().unwrap_or(0.0);
-        let eth = self.ETH_USDT_allocation.parse::<f64>().unwrap_or(0.0);
-        let ltc = self.LTC_USDT_allocation.parse::<f64>().unwrap_or(0.0);
+impl RebalancerApp {
+    /// `config_path` is the file the backend reads, with the profiles kept beside it
+    /// (see `ProfileManager::path_for`). With a `passphrase` the config and
+    /// profiles files are read and written encrypted. `window_state` restores the
+    /// open tab and the sidebar width of the last session.
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        config_path: PathBuf,
+        window_state: Option<WindowState>,
+        passphrase: Option<String>,
+    ) -> Self {
+        let profiles_path = ProfileManager::path_for(&config_path);
+        let profiles = load_profiles(&config_path, passphrase.as_deref());
+        let portfolios = PortfolioManager::load(
+            &config_path,
+            profiles.active().clone(),
+            &PortfolioManager::default_dir(),
+            passphrase.as_deref(),
+        );
+
+        let portfolio_editor = PortfolioAllocationEditor::from_config(profiles.active());
+
+        let config_watcher = ConfigWatcher::new(&config_path, cc.egui_ctx.clone())
+            .map_err(|e| log::warn!("{}; external edits will not be detected.", e))
+            .ok();
+
+        let db = open_history_db(&config_path);
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .map_err(|e| log::warn!("Failed to start async runtime: {}", e))
+            .ok();
+        let symbols_path = SymbolSuggestions::default_path();
+        let symbol_suggestions = SymbolSuggestions::load(&symbols_path);
+        let symbols_receiver = match &runtime {
+            Some(runtime) if !symbols_path.exists() && !cfg!(feature = "mock-exchange") => {
+                let (sender, receiver) = mpsc::channel();
+                let ctx = cc.egui_ctx.clone();
+                runtime.spawn(async move {
+                    let client =
+                        exchange::gateio::GateioClient::testnet(String::new(), String::new());
+                    match client.list_contracts().await {
+                        Ok(symbols) => {
+                            let _ = sender.send(symbols);
+                            ctx.request_repaint();
+                        }
+                        Err(e) => log::warn!("Failed to fetch the contract list: {}", e),
+                    }
+                });
+                Some(receiver)
+            }
+            _ => None,
+        };
+        let (balance_sender, balance_receiver) = mpsc::channel();
+        let (dry_run_sender, dry_run_receiver) = mpsc::channel();
+        let (poll_sender, poll_receiver) = mpsc::channel();
+        let (market_cap_sender, market_cap_receiver) = mpsc::channel();
+        let (update_sender, update_receiver) = mpsc::channel();
+        let (connectivity_sender, connectivity_receiver) = mpsc::channel();
+        let (positions_sender, positions_receiver) = mpsc::channel();
+        let (status_sender, status_receiver) = mpsc::channel();
+        let (manual_rebalance_sender, manual_rebalance_receiver) = mpsc::channel();
+        let (order_sender, order_receiver) = mpsc::channel();
+        let (webhook_test_sender, webhook_test_receiver) = mpsc::channel();
+
+        let today = Utc::now().date_naive();
+        let mut app = Self {
+            profiles,
+            api_key: Zeroizing::default(),
+            api_secret: Zeroizing::default(),
+            api_key_error: None,
+            api_secret_error: None,
+            keyring_available: false,
+            keyring_api_key: String::new(),
+            config_path,
+            profiles_path,
+            passphrase,
+            config_watcher,
+            external_config: None,
+            confirm_reload: false,
+            config_backups: None,
+            backend_process: None,
+            backend_receiver: None,
+            backend_sender: None,
+            backend_socket: None,
+            socket_connect: None,
+            next_socket_attempt: None,
+            backend_endpoint: BackendEndpoint::default(),
+            backend_client: BackendClient::new(&BackendEndpoint::default().base_url())
+                .with_token(backend::api_token()),
+            status_sender,
+            status_receiver,
+            status_poll_pending: false,
+            next_status_poll: None,
+            manual_rebalance_sender,
+            manual_rebalance_receiver,
+            manual_rebalance_loading: false,
+            symbol_suggestions,
+            symbols_receiver,
+            status: "Stopped".to_string(),
+            is_running: false,
+            restart_count: 0,
+            next_restart_at: None,
+            error_message: None,
+            live_allocations: None,
+            peak_value_usdt: 0.0,
+            halted_due_to_drawdown: false,
+            funding_rates: HashMap::new(),
+            margin_alert_active: false,
+            correlation_symbols: Vec::new(),
+            correlation_matrix: None,
+            price_history: Vec::new(),
+            simulation_receiver: None,
+            simulation: None,
+            runtime,
+            exchange: None,
+            balance_sender,
+            balance_receiver,
+            live_balances: None,
+            balances_loading: false,
+            positions_sender,
+            positions_receiver,
+            positions: Vec::new(),
+            poll_sender,
+            poll_receiver,
+            last_poll: None,
+            last_updated: None,
+            market_cap_sender,
+            market_cap_receiver,
+            market_data: MarketData::default(),
+            connectivity: None,
+            connectivity_checking: false,
+            connectivity_sender,
+            connectivity_receiver,
+            rebalance_progress: None,
+            current_theme: None,
+            sidebar_ratio: window_state.map_or(DEFAULT_SIDEBAR_RATIO, |s| s.sidebar_ratio),
+            update_sender,
+            update_receiver,
+            available_update: None,
+            total_value_usdt: None,
+            display_prices: HashMap::new(),
+            value_history: ValueHistory::default(),
+            value_chart_range: TimeRange::default(),
+            dry_run_sender,
+            dry_run_receiver,
+            dry_run_loading: false,
+            dry_run: None,
+            what_if: None,
+            order_sender,
+            order_receiver,
+            db,
+            event_history: Vec::new(),
+            orders: Vec::new(),
+            orders_page: 0,
+            tax_lots: Vec::new(),
+            realized_gains: Vec::new(),
+            orders_search: String::new(),
+            last_notified: HashMap::new(),
+            webhook_test_sender,
+            webhook_test_receiver,
+            webhook_test_result: None,
+            webhook_testing: false,
+            history_sort_column: HistoryColumn::Timestamp,
+            history_sort_ascending: false,
+            filter_by_tag: None,
+            portfolio_editor,
+            history_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            field_errors: Vec::new(),
+            dirty: false,
+            notes_edited: false,
+            last_edit_instant: None,
+            last_saved_instant: None,
+            allocation_copied_at: None,
+            pending_paste: None,
+            pending_stop_confirm: false,
+            pending_start_confirm: false,
+            active_tab: window_state.map(|s| s.active_tab).unwrap_or_default(),
+            backtest_csv: None,
+            backtest_start: (today - Days::new(365)).to_string(),
+            backtest_end: today.to_string(),
+            backtest_initial_usdt: "10000".to_string(),
+            backtest_result: None,
+            window_geometry: None,
+            log_lines: VecDeque::with_capacity(MAX_LOG_LINES),
+            new_profile_name: String::new(),
+            portfolios,
+            new_portfolio_name: None,
+            tray: None,
+            tray_commands: None,
+            quit_requested: false,
+            shutdown_signal: None,
+            audit_log: AuditLog::new(&AuditLog::default_path()),
+            health_state: SharedState::default(),
+            _health_server: None,
+            mock_scenario: None,
+            show_wizard: false,
+            wizard_step: WizardStep::default(),
+            wizard_start_now: true,
+            rebinding: None,
+        };
+        app.load_history();
+        app.show_wizard = app.profiles.active().first_run;
+        app.init_keyring();
+        app.refresh_volatilities();
+        app.check_for_updates(&cc.egui_ctx);
+        app.start_health_server();
+        app
+    }
+
+    // Port 0 in the config turns it off; a port in use only costs a warning
+    fn start_health_server(&mut self) {
+        let port = self.profiles.active().health_port;
+        if port == 0 {
+            return;
+        }
+        match HealthServer::start(port, Arc::clone(&self.health_state)) {
+            Ok(server) => self._health_server = Some(server),
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+
+    // Copies what /health and /metrics report for the selected portfolio
+    fn publish_health_state(&self) {
+        let config = self.profiles.active();
+        let target = config.target_allocation(&self.market_data);
+        let state = AppState {
+            is_running: self.is_running,
+            health_score: self.live_balances.as_ref().map(|balances| {
+                metrics::portfolio_health_score(balances, &target, |symbol| {
+                    config.threshold_for(symbol)
+                })
+            }),
+            drift: self
+                .live_allocations
+                .as_ref()
+                .map(|live| {
+                    target
+                        .assets
+                        .iter()
+                        .map(|a| {
+                            (
+                                a.symbol.clone(),
+                                live.get(&a.symbol).copied().unwrap_or(0.0) - a.target_pct,
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+        if let Ok(mut shared) = self.health_state.write() {
+            if *shared != state {
+                *shared = state;
+            }
+        }
+    }
+
+    // Events, orders and tax lots from the history database
+    fn load_history(&mut self) {
+        let Some(db) = &self.db else {
+            self.event_history.clear();
+            self.orders.clear();
+            self.tax_lots.clear();
+            self.realized_gains.clear();
+            return;
+        };
+        self.event_history = db.load_events(MAX_HISTORY_EVENTS).unwrap_or_else(|e| {
+            log::warn!("Failed to load history: {}", e);
+            Vec::new()
+        });
+        self.orders = db.load_orders(MAX_HISTORY_EVENTS).unwrap_or_else(|e| {
+            log::warn!("Failed to load orders: {}", e);
+            Vec::new()
+        });
+        (self.tax_lots, self.realized_gains) = db
+            .load_tax_lots()
+            .and_then(|lots| Ok((lots, db.load_realized_gains()?)))
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to load tax lots: {}", e);
+                (Vec::new(), Vec::new())
+            });
+    }
+
+    pub fn with_tray(mut self, tray: Tray, commands: Receiver<TrayCommand>) -> Self {
+        self.tray = Some(tray);
+        self.tray_commands = Some(commands);
+        self
+    }
+
+    pub fn with_shutdown_signal(mut self, signal: Arc<AtomicBool>) -> Self {
+        self.shutdown_signal = Some(signal);
+        self
+    }
+
+    /// Writes the audit log to `path` instead of `AuditLog::default_path()`.
+    pub fn with_audit_log(mut self, path: &Path) -> Self {
+        self.audit_log = AuditLog::new(path);
+        self
+    }
+
+    /// Looks for the additional portfolios in `dir` instead of
+    /// `PortfolioManager::default_dir()`.
+    pub fn with_portfolio_dir(mut self, dir: &Path) -> Self {
+        self.portfolios = PortfolioManager::load(
+            &self.config_path,
+            self.profiles.active().clone(),
+            dir,
+            self.passphrase.as_deref(),
+        );
+        self
+    }
+
+    /// Replaces the exchange the UI queries; the backend still trades on the testnet.
+    pub fn with_mock_exchange(mut self, scenario: TestScenario) -> Self {
+        log::info!("Using the mock exchange ({}).", scenario);
+        self.mock_scenario = Some(scenario);
+        self.exchange = None;
+        self
+    }
+
+    // The backend is stopped right away: on_exit may not run if the process is killed next
+    fn handle_shutdown_signal(&mut self, ctx: &egui::Context) {
+        if !self
+            .shutdown_signal
+            .as_ref()
+            .is_some_and(|signal| signal.swap(false, Ordering::SeqCst))
+        {
+            return;
+        }
+        log::info!("Shutdown signal received. Stopping backends...");
+        self.stop_all_portfolios();
+        self.quit_requested = true;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+
+    fn process_tray_commands(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.tray_commands else {
+            return;
+        };
+        let commands: Vec<TrayCommand> = receiver.try_iter().collect();
+        for command in commands {
+            match command {
+                TrayCommand::ShowWindow => {} // already shown by the tray's event handler
+                TrayCommand::StartRebalancer => {
+                    if !self.is_running {
+                        self.start_backend_manually(ctx);
+                    }
+                }
+                TrayCommand::StopRebalancer => {
+                    if self.is_running {
+                        self.stop_backend_manually();
+                    }
+                }
+                TrayCommand::Quit => {
+                    self.quit_requested = true;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            }
+        }
+        if let Some(tray) = &mut self.tray {
+            tray.set_status(self.is_running, &self.status);
+        }
+    }
+
+    // on_exit runs only after the window is gone, so the close is intercepted here
+    fn handle_close_request(&mut self, ctx: &egui::Context) {
+        let minimize =
+            self.tray.is_some() && self.profiles.active().minimize_to_tray && !self.quit_requested;
+        if minimize && ctx.input(|i| i.viewport().close_requested()) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+        }
+    }
+
+    // Probe the system keychain, moving credentials older versions kept in a
+    // single shared entry to the one of the active profile.
+    fn init_keyring(&mut self) {
+        match Config::migrate_legacy_secrets(&self.keyring_account()) {
+            Ok(true) => log::info!("Moved API credentials to the keychain entry of this profile."),
+            Ok(false) => {}
+            Err(e) => {
+                log::warn!("System keychain unavailable ({}), using config file.", e);
+                self.keyring_available = false;
+                return;
+            }
+        }
+        self.keyring_available = true;
+        self.refresh_keyring_key();
+    }
+
+    // Keychain account of the selected portfolio, active profile and exchange
+    fn keyring_account(&self) -> String {
+        config::keyring_account(
+            &self.portfolios.selected_portfolio().name,
+            self.profiles.active_name(),
+            self.profiles.active().exchange,
+        )
+    }
+
+    // Reads the key of the current keychain account for display, which also tells
+    // `poll_portfolio` whether credentials are stored. Credentials left in the
+    // plaintext files by older versions are moved into the keychain first.
+    fn refresh_keyring_key(&mut self) {
+        // The client holds the credentials of the previous account
+        self.exchange = None;
+        if !self.keyring_available {
+            return;
+        }
+        let result = self.move_plaintext_secrets().and_then(|()| {
+            match Config::load_secrets(&self.keyring_account()) {
+                Ok((key, _)) => Ok(key),
+                Err(KinError::Keyring(keyring::Error::NoEntry)) => Ok(String::new()),
+                Err(e) => Err(e),
+            }
+        });
+        match result {
+            Ok(key) => self.keyring_api_key = key,
+            Err(e) => {
+                log::warn!("System keychain unavailable ({}), using config file.", e);
+                self.keyring_available = false;
+            }
+        }
+    }
+
+    // Plaintext credentials of every profile go to that profile's keychain
+    // account, unless it has its own already; the file keeps neither
+    fn move_plaintext_secrets(&mut self) -> Result<(), KinError> {
+        let portfolio = self.portfolios.selected_portfolio().name.clone();
+        let mut moved = false;
+        for (name, config) in self.profiles.iter_mut() {
+            if config.api_key.is_empty() || config.api_secret.is_empty() {
+                continue;
+            }
+            let account = config::keyring_account(&portfolio, name, config.exchange);
+            match Config::load_secrets(&account) {
+                Ok(_) => {}
+                Err(KinError::Keyring(keyring::Error::NoEntry)) => {
+                    Config::store_secrets(&account, &config.api_key, &config.api_secret)?
+                }
+                Err(e) => return Err(e),
+            }
+            config.api_key.zeroize();
+            config.api_secret.zeroize();
+            moved = true;
+        }
+        if moved {
+            if let Err(e) = self.save_config() {
+                log::warn!("Failed to remove plaintext credentials: {}", e);
+            }
+            log::info!("Moved API credentials into the system keychain.");
+        }
+        Ok(())
+    }
+
+    // New profiles and portfolios start with the credentials of the one they copy
+    fn copy_keyring_secrets(&self, from: &str) {
+        if !self.keyring_available {
+            return;
+        }
+        if let Ok((key, secret)) = Config::load_secrets(from) {
+            if let Err(e) = Config::store_secrets(&self.keyring_account(), &key, &secret) {
+                log::warn!("Failed to copy API credentials: {}", e);
+            }
+        }
+    }
+
+    fn configured_api_key(&self) -> &str {
+        if self.keyring_available {
+            &self.keyring_api_key
+        } else {
+            &self.profiles.active().api_key
+        }
+    }
+
+    // Credentials handed to the backend process through its environment; empty
+    // when none are stored
+    fn api_credentials(&self) -> Result<(String, Zeroizing<String>), KinError> {
+        if !self.keyring_available {
+            let config = self.profiles.active();
+            return Ok((config.api_key.clone(), config.api_secret.clone()));
+        }
+        match Config::load_secrets(&self.keyring_account()) {
+            Err(KinError::Keyring(keyring::Error::NoEntry)) => Ok(Default::default()),
+            result => result,
+        }
+    }
+
+    // The backend only reads `config_path`, so the active profile is always mirrored there
+    fn save_config(&self) -> Result<(), KinError> {
+        let passphrase = self.passphrase.as_deref();
+        self.profiles
+            .active()
+            .save_with_passphrase(&self.config_path, passphrase)?;
+        self.profiles
+            .save_with_passphrase(&self.profiles_path, passphrase)?;
+        // 运行中的后端重新读取配置，否则要重启才生效
+        if self.is_running {
+            let client = self.backend_client.clone();
+            thread::spawn(move || {
+                if let Err(e) = client.reload_config() {
+                    log::warn!("Backend did not reload the config: {}", e);
+                }
+            });
+            self.send_backend_targets();
+        }
+        Ok(())
+    }
+
+    fn start_backend(&mut self, ctx: &egui::Context) -> Result<(), KinError> {
+        if let Err(e) = self.update_config_from_editor() {
+            self.error_message = Some(format!("Failed to save config before start: {}", e));
+            return Err(e);
+        }
+        self.error_message = None; // Clear previous config errors
+
+        // 确保已保存配置
+        if let Err(e) = self.save_config() {
+            self.error_message = Some(format!("Failed to save config: {}", e));
+            return Err(e);
+        }
+
+        // 凭据通过环境变量传递给后端，配置文件中只保留空占位符
+        let (api_key, api_secret) = match self.api_credentials() {
+            Ok(credentials) => credentials,
+            Err(e) => {
+                self.error_message = Some(format!("Failed to read API credentials: {}", e));
+                return Err(e);
+            }
+        };
+
+        let config_json = match &self.passphrase {
+            Some(_) => Some(self.profiles.active().to_text(ConfigFormat::Json)?),
+            None => None,
+        };
+
+        match backend::spawn(
+            &self.config_path,
+            &api_key,
+            &api_secret,
+            config_json.as_deref(),
+            self.profiles.active(),
+            &self.profiles.active().target_allocation(&self.market_data),
+            &self.backend_endpoint,
+        ) {
+            Ok(mut child) => {
+                let (sender, receiver) = mpsc::channel();
+                if let Some(stdout) = child.stdout.take() {
+                    protocol::spawn_reader(stdout, sender.clone(), ctx.clone());
+                }
+                if let Some(stderr) = child.stderr.take() {
+                    protocol::spawn_reader(stderr, sender.clone(), ctx.clone());
+                }
+                log::info!("Backend started (pid {}).", child.id());
+                self.backend_process = Some(child);
+                self.backend_receiver = Some(receiver);
+                self.backend_sender = Some(sender);
+                self.connect_backend_socket(ctx);
+                self.status = "Running".to_string();
+                self.is_running = true;
+                self.error_message = None;
+                Ok(())
+            }
+            Err(e) => {
+                self.status = "Error".to_string();
+                self.is_running = false;
+                self.error_message = Some(e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    fn start_backend_manually(&mut self, ctx: &egui::Context) {
+        self.restart_count = 0;
+        self.next_restart_at = None;
+        self.status = "Starting".to_string();
+        if self.start_backend(ctx).is_ok() {
+            // errors are shown by start_backend
+            let config = self.profiles.active();
+            let details = format!(
+                "portfolio \"{}\", profile \"{}\", {}{}",
+                self.portfolios.selected_portfolio().name,
+                self.profiles.active_name(),
+                config.exchange.label(),
+                if config.paper_trading {
+                    ", paper trading"
+                } else {
+                    ""
+                }
+            );
+            self.audit(AuditAction::StartBackend, details);
+        }
+    }
+
+    fn stop_backend_manually(&mut self) {
+        self.stop_backend();
+        let details = format!(
+            "portfolio \"{}\", profile \"{}\"",
+            self.portfolios.selected_portfolio().name,
+            self.profiles.active_name()
+        );
+        self.audit(AuditAction::StopBackend, details);
+    }
+
+    // A failed write is logged; the action itself has already happened
+    fn audit(&self, action: AuditAction, details: String) {
+        if let Err(e) = self.audit_log.append(&AuditEntry::new(action, details)) {
+            log::warn!("Failed to write the audit log: {}", e);
+        }
+    }
+
+    fn open_audit_log(&mut self) {
+        let path = self.audit_log.path();
+        if !path.exists() {
+            self.error_message = Some("Nothing has been written to the audit log yet.".to_string());
+            return;
+        }
+        if let Err(e) = open::that(path) {
+            self.error_message = Some(format!("Failed to open {}: {}", path.display(), e));
+        }
+    }
+
+    // Called when the backend died or an automatic restart failed to launch it
+    fn schedule_restart(&mut self) {
+        if self.restart_count >= MAX_RESTARTS {
+            self.next_restart_at = None;
+            return;
+        }
+        let delay = RESTART_BACKOFF * 2u32.pow(self.restart_count);
+        self.restart_count += 1;
+        self.next_restart_at = Some(Instant::now() + delay);
+        self.push_log_line(format!(
+            "Backend exited; restarting in {}s (attempt {}/{}).",
+            delay.as_secs(),
+            self.restart_count,
+            MAX_RESTARTS
+        ));
+    }
+
+    fn run_pending_restart(&mut self, ctx: &egui::Context) {
+        let Some(at) = self.next_restart_at else {
+            return;
+        };
+        let now = Instant::now();
+        if now < at {
+            // Once a second for the countdown shown above the controls
+            ctx.request_repaint_after((at - now).min(Duration::from_secs(1)));
+            return;
+        }
+        self.next_restart_at = None;
+        self.status = "Starting".to_string();
+        if self.start_backend(ctx).is_err() {
+            self.schedule_restart();
+        }
+    }
+
+    fn stop_backend(&mut self) {
+        self.next_restart_at = None;
+        if let Some(mut child) = self.backend_process.take() {
+            // 先通过socket请求退出，没有连接或超时再发信号
+            let exited = self
+                .backend_socket
+                .as_ref()
+                .filter(|socket| socket.send(BackendCommand::Stop).is_ok())
+                .and_then(|_| backend::wait_for_exit(&mut child, backend::SHUTDOWN_TIMEOUT).ok())
+                .flatten()
+                .is_some();
+            if !exited {
+                if let Err(e) = backend::shutdown(&mut child) {
+                    log::error!("Failed to stop backend process: {}", e);
+                }
+            }
+        }
+        self.disconnect_backend_socket();
+        self.backend_receiver = None;
+        self.status = "Stopped".to_string();
+        self.is_running = false;
+        log::info!("Backend stopped.");
+    }
+
+    fn send_backend_command(&self, cmd: BackendCommand) {
+        match &self.backend_socket {
+            Some(socket) => {
+                if let Err(e) = socket.send(cmd.clone()) {
+                    log::warn!("Failed to send {:?} to the backend: {}", cmd, e);
+                }
+            }
+            None => log::warn!("Not connected to the backend; {:?} not sent.", cmd),
+        }
+    }
+
+    // The backend cannot weight by market cap or volatility itself, so it gets the
+    // resolved targets whenever they may have changed
+    fn send_backend_targets(&self) {
+        if self.backend_socket.is_none() {
+            return;
+        }
+        let targets = self.profiles.active().target_allocation(&self.market_data);
+        self.send_backend_command(BackendCommand::SetTargets { targets });
+    }
+
+    fn check_drawdown(&mut self, value_usdt: f64) {
+        self.peak_value_usdt = self.peak_value_usdt.max(value_usdt);
+        let Some(limit) = self.profiles.active().max_drawdown_halt_pct else {
+            return;
+        };
+        let drawdown = metrics::drawdown_pct(self.peak_value_usdt, value_usdt);
+        if self.halted_due_to_drawdown || drawdown <= limit {
+            return;
+        }
+        self.halted_due_to_drawdown = true;
+        log::warn!(
+            "Portfolio is {:.1}% below its peak of {:.2} USDT; pausing rebalancing.",
+            drawdown,
+            self.peak_value_usdt
+        );
+        // 未连接时在重新连接后补发
+        self.send_backend_command(BackendCommand::PauseRebalancing);
+    }
+
+    fn resume_after_drawdown(&mut self) {
+        self.halted_due_to_drawdown = false;
+        // The peak starts over from here, or the next snapshot would halt again
+        if let Some(total) = self.total_value_usdt {
+            self.peak_value_usdt = total;
+        }
+        log::info!("Rebalancing resumed after a drawdown halt.");
+        self.send_backend_command(BackendCommand::ResumeRebalancing);
+    }
+
+    // Simulates the current targets with the volatility of the backend's price history
+    fn start_simulation(&mut self, ctx: &egui::Context) {
+        let returns = metrics::portfolio_returns(
+            &self.correlation_symbols,
+            &self.price_history,
+            &self.profiles.active().target_allocation(&self.market_data),
+        );
+        let seed = Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64;
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let paths =
+                backtest::monte_carlo_simulate(&returns, MONTE_CARLO_SIMS, MONTE_CARLO_DAYS, seed);
+            let _ = sender.send(backtest::summarize_simulation(&paths));
+            ctx.request_repaint();
+        });
+        self.simulation_receiver = Some(receiver);
+    }
+
+    fn process_simulation_result(&mut self) {
+        if let Some(receiver) = &self.simulation_receiver {
+            match receiver.try_recv() {
+                Ok(summary) => {
+                    self.simulation = Some(summary);
+                    self.simulation_receiver = None;
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.simulation_receiver = None,
+            }
+        }
+    }
+
+    // 后端启动需要时间，在后台线程里等待socket出现，避免阻塞界面
+    fn connect_backend_socket(&mut self, ctx: &egui::Context) {
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        let path = self.backend_endpoint.ipc_path.clone();
+        thread::spawn(move || {
+            let result = BackendSocket::connect_with_timeout(&path, ipc::CONNECT_TIMEOUT);
+            let _ = sender.send(result);
+            ctx.request_repaint();
+        });
+        self.socket_connect = Some(receiver);
+        self.next_socket_attempt = None;
+    }
+
+    fn disconnect_backend_socket(&mut self) {
+        self.backend_socket = None;
+        self.socket_connect = None;
+        self.next_socket_attempt = None;
+        self.backend_sender = None;
+    }
+
+    // Finishes connection attempts and reconnects while the backend runs
+    fn process_backend_socket(&mut self, ctx: &egui::Context) {
+        if !self.is_running {
+            return;
+        }
+        if let Some(receiver) = &self.socket_connect {
+            match receiver.try_recv() {
+                Ok(Ok(socket)) => {
+                    let socket = Arc::new(socket);
+                    if let Some(sender) = &self.backend_sender {
+                        ipc::spawn_reader(socket.clone(), sender.clone(), ctx.clone());
+                    }
+                    log::info!(
+                        "Connected to backend at {}.",
+                        self.backend_endpoint.ipc_path
+                    );
+                    // 重连后让后端重新报告状态
+                    let _ = socket.send(BackendCommand::Status);
+                    if self.halted_due_to_drawdown {
+                        let _ = socket.send(BackendCommand::PauseRebalancing);
+                    }
+                    self.backend_socket = Some(socket);
+                    self.socket_connect = None;
+                    self.send_backend_targets();
+                }
+                Ok(Err(e)) => {
+                    log::warn!("{}; retrying in {}s.", e, SOCKET_RETRY.as_secs());
+                    self.socket_connect = None;
+                    self.next_socket_attempt = Some(Instant::now() + SOCKET_RETRY);
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.socket_connect = None,
+            }
+            return;
+        }
+        if self
+            .backend_socket
+            .as_ref()
+            .is_some_and(|socket| !socket.is_connected())
+        {
+            log::warn!("Lost connection to the backend, reconnecting.");
+            self.backend_socket = None;
+            self.connect_backend_socket(ctx);
+            return;
+        }
+        if let Some(at) = self.next_socket_attempt {
+            let now = Instant::now();
+            if now >= at {
+                self.connect_backend_socket(ctx);
+            } else {
+                ctx.request_repaint_after(at - now);
+            }
+        }
+    }
+
+    fn connect_exchange(&mut self) -> Option<Arc<dyn Exchange>> {
+        if self.runtime.is_none() {
+            self.error_message = Some("Async runtime unavailable.".to_string());
+            return None;
+        }
+        if let Some(scenario) = self.mock_scenario {
+            return Some(
+                self.exchange
+                    .get_or_insert_with(|| Arc::new(MockExchange::with_scenario(scenario)))
+                    .clone(),
+            );
+        }
+        // The client keeps the credentials, so the keychain is only read to build it
+        if let Some(exchange) = &self.exchange {
+            return Some(exchange.clone());
+        }
+        let (api_key, api_secret) = match self.api_credentials() {
+            Ok(credentials) => credentials,
+            Err(e) => {
+                self.error_message = Some(format!("Failed to read API credentials: {}", e));
+                return None;
+            }
+        };
+        let paper_trading = self.profiles.active().paper_trading;
+        let choice = self.profiles.active().exchange;
+        let retry = self.profiles.active().retry.clone();
+        if !paper_trading && (api_key.is_empty() || api_secret.is_empty()) {
+            self.error_message =
+                Some("Save API settings before querying the exchange.".to_string());
+            return None;
+        }
+        Some(
+            self.exchange
+                .get_or_insert_with(|| {
+                    exchange::connect(choice, &api_key, &api_secret, paper_trading, &retry)
+                })
+                .clone(),
+        )
+    }
+
+    fn fetch_balances(&mut self, ctx: &egui::Context) {
+        let (Some(exchange), Some(runtime)) = (self.connect_exchange(), &self.runtime) else {
+            return;
+        };
+        let display_contract = self.display_currency().contract();
+        let sender = self.balance_sender.clone();
+        let positions_sender = self.positions_sender.clone();
+        let ctx = ctx.clone();
+        self.balances_loading = true;
+        runtime.spawn(async move {
+            let result = async {
+                let balances = exchange.get_balances().await?;
+                let display_price = match display_contract {
+                    Some(contract) => exchange
+                        .get_prices(&[contract])
+                        .await?
+                        .get(contract)
+                        .copied(),
+                    None => None,
+                };
+                Ok((balances, display_price))
+            }
+            .await;
+            let _ = sender.send(result);
+            let _ = positions_sender.send(exchange.get_positions().await);
+            ctx.request_repaint();
+        });
+    }
+
+    fn refresh_market_caps(&self, ctx: &egui::Context) {
+        let Some(runtime) = &self.runtime else {
+            return;
+        };
+        let contracts: Vec<String> = self
+            .profiles
+            .active()
+            .portfolio_allocation
+            .assets
+            .iter()
+            .map(|a| a.symbol.clone())
+            .collect();
+        let sender = self.market_cap_sender.clone();
+        let ctx = ctx.clone();
+        runtime.spawn(async move {
+            let _ = sender.send(market_cap::fetch_market_caps(&contracts).await);
+            ctx.request_repaint();
+        });
+    }
+
+    // Pings the exchange every `CONNECTIVITY_INTERVAL`; the endpoints are public,
+    // so this works before credentials are saved
+    fn check_connectivity(&mut self, ctx: &egui::Context) {
+        if self.connectivity_checking {
+            return;
+        }
+        if let Some(remaining) = self
+            .connectivity
+            .as_ref()
+            .and_then(|status| CONNECTIVITY_INTERVAL.checked_sub(status.last_checked.elapsed()))
+        {
+            ctx.request_repaint_after(remaining);
+            return;
+        }
+        let Some(runtime) = &self.runtime else {
+            return;
+        };
+        let config = self.profiles.active();
+        let mock = self
+            .mock_scenario
+            .map(|scenario| Arc::new(MockExchange::with_scenario(scenario)) as Arc<dyn Exchange>);
+        let exchange = self.exchange.clone().or(mock).unwrap_or_else(|| {
+            exchange::connect(config.exchange, "", "", config.paper_trading, &config.retry)
+        });
+        self.connectivity_checking = true;
+        let sender = self.connectivity_sender.clone();
+        let ctx = ctx.clone();
+        runtime.spawn(async move {
+            let started = Instant::now();
+            let result = exchange
+                .ping()
+                .await
+                .map(|()| started.elapsed().as_millis() as u64);
+            let _ = sender.send(result);
+            ctx.request_repaint();
+        });
+    }
+
+    // Failures are logged once when the exchange becomes unreachable, not every 30s
+    fn process_connectivity_results(&mut self) {
+        while let Ok(result) = self.connectivity_receiver.try_recv() {
+            self.connectivity_checking = false;
+            let was_reachable = self
+                .connectivity
+                .as_ref()
+                .is_none_or(|status| status.exchange_ping_ms.is_some());
+            let exchange_ping_ms = match result {
+                Ok(ms) => {
+                    if !was_reachable {
+                        self.push_log_line(format!("Exchange reachable again ({} ms).", ms));
+                    }
+                    Some(ms)
+                }
+                Err(e) => {
+                    if was_reachable {
+                        log::warn!("Exchange unreachable: {}", e);
+                        self.push_log_line(format!("WARN: Exchange unreachable: {}", e));
+                    }
+                    None
+                }
+            };
+            self.connectivity = Some(ConnectivityStatus {
+                exchange_ping_ms,
+                last_checked: Instant::now(),
+            });
+        }
+    }
+
+    fn process_market_cap_results(&mut self) {
+        while let Ok(result) = self.market_cap_receiver.try_recv() {
+            match result {
+                Ok(market_caps) => {
+                    self.market_data.market_caps = market_caps;
+                    self.send_backend_targets();
+                }
+                // The last known caps stay in use
+                Err(e) => log::warn!("{}", e),
+            }
+        }
+    }
+
+    // GitHub Releases once per start, skipped within a day of the last successful check
+    fn check_for_updates(&self, ctx: &egui::Context) {
+        let config = self.profiles.active();
+        if !config.check_for_updates || !update::is_due(config.last_update_check, Utc::now()) {
+            return;
+        }
+        let Some(runtime) = &self.runtime else {
+            return;
+        };
+        let sender = self.update_sender.clone();
+        let ctx = ctx.clone();
+        runtime.spawn(async move {
+            let _ = sender.send(UpdateChecker::new().check().await);
+            ctx.request_repaint();
+        });
+    }
+
+    fn process_update_results(&mut self) {
+        while let Ok(result) = self.update_receiver.try_recv() {
+            match result {
+                Ok(release) => {
+                    if let Some(release) = &release {
+                        log::info!("Update available: v{} ({})", release.version, release.url);
+                    }
+                    self.available_update = release;
+                    self.profiles.active_mut().last_update_check = Some(Utc::now());
+                    if let Err(e) = self.save_config() {
+                        log::warn!("Failed to save the update check time: {}", e);
+                    }
+                }
+                // Tried again on the next start
+                Err(e) => log::warn!("{}", e),
+            }
+        }
+    }
+
+    // Volatility of every edited or active asset over the risk parity lookback, from
+    // the daily closes stored with each price history
+    fn refresh_volatilities(&mut self) {
+        let Some(db) = &self.db else {
+            return;
+        };
+        let lookback_days = match self.portfolio_editor.allocation_mode {
+            AllocationMode::RiskParity(risk_parity) => risk_parity.lookback_days,
+            _ => RiskParityConfig::DEFAULT.lookback_days,
+        };
+        let since = Utc::now().date_naive() - Days::new(u64::from(lookback_days));
+        let symbols = self
+            .portfolio_editor
+            .custom_allocation()
+            .assets
+            .into_iter()
+            .chain(self.profiles.active().portfolio_allocation.assets.clone())
+            .map(|a| a.symbol);
+        let mut volatilities = HashMap::new();
+        for symbol in symbols {
+            match db.load_daily_closes(&symbol, since) {
+                Ok(closes) => {
+                    if let Some(volatility) = metrics::annualised_volatility(&closes) {
+                        volatilities.insert(symbol, volatility);
+                    }
+                }
+                Err(e) => log::warn!("Failed to load closes of {}: {}", symbol, e),
+            }
+        }
+        self.market_data.volatilities = volatilities;
+        self.send_backend_targets();
+    }
+
+    // The backend sends the latest closes, the last one today's; they are kept so
+    // the volatility lookback can reach further back than one message
+    fn store_daily_closes(&self, symbols: &[String], prices: &[Vec<f64>]) {
+        let Some(db) = &self.db else {
+            return;
+        };
+        let today = Utc::now().date_naive();
+        for (symbol, series) in symbols.iter().zip(prices) {
+            let closes: Vec<(NaiveDate, f64)> = series
+                .iter()
+                .rev()
+                .enumerate()
+                .map(|(days_ago, close)| (today - Days::new(days_ago as u64), *close))
+                .collect();
+            if let Err(e) = db.upsert_daily_closes(symbol, &closes) {
+                log::warn!("Failed to save closes of {}: {}", symbol, e);
+            }
+        }
+    }
+
+    // Refreshes balances and prices every `poll_interval_seconds`; skipped quietly
+    // until credentials are saved, unlike the buttons that query the exchange
+    fn poll_portfolio(&mut self, ctx: &egui::Context) {
+        let interval = Duration::from_secs(self.profiles.active().poll_interval_seconds.max(1));
+        if let Some(remaining) = self
+            .last_poll
+            .and_then(|at| interval.checked_sub(at.elapsed()))
+        {
+            ctx.request_repaint_after(remaining);
+            return;
+        }
+        // The key shown in the settings mirrors the keychain, so no query runs every frame
+        if self.runtime.is_none()
+            || (!self.profiles.active().paper_trading && self.configured_api_key().is_empty())
+        {
+            return;
+        }
+        let (Some(exchange), Some(runtime)) = (self.connect_exchange(), &self.runtime) else {
+            return;
+        };
+        self.last_poll = Some(Instant::now());
+        if self.profiles.active().allocation_mode == AllocationMode::MarketCapWeight {
+            self.refresh_market_caps(ctx);
+        }
+        let display_contract = self.display_currency().contract();
+        let sender = self.poll_sender.clone();
+        let positions_sender = self.positions_sender.clone();
+        let ctx = ctx.clone();
+        runtime.spawn(async move {
+            let result = async {
+                let balances = exchange.get_balances().await?;
+                let display_price = match display_contract {
+                    Some(contract) => exchange
+                        .get_prices(&[contract])
+                        .await?
+                        .get(contract)
+                        .copied(),
+                    None => None,
+                };
+                // Funding is informational; a failed query leaves the last known rates shown
+                let funding_rates = exchange.get_funding_rates().await.unwrap_or_default();
+                let total_value_usdt: f64 = balances.values().sum();
+                let allocations = balances
+                    .iter()
+                    .filter(|_| total_value_usdt > 0.0)
+                    .map(|(symbol, value)| (symbol.clone(), value / total_value_usdt * 100.0))
+                    .collect();
+                let snapshot = BackendMessage::PortfolioSnapshot {
+                    allocations,
+                    total_value_usdt,
+                    funding_rates,
+                };
+                Ok((snapshot, balances, display_price))
+            }
+            .await;
+            let _ = sender.send(result);
+            let _ = positions_sender.send(exchange.get_positions().await);
+            ctx.request_repaint();
+        });
+    }
+
+    fn process_poll_results(&mut self) {
+        while let Ok(result) = self.poll_receiver.try_recv() {
+            match result {
+                Ok((snapshot, balances, display_price)) => {
+                    if let (Some(contract), Some(price)) =
+                        (self.display_currency().contract(), display_price)
+                    {
+                        self.display_prices.insert(contract.to_string(), price);
+                    }
+                    // A running backend sends its own snapshots
+                    if !self.is_running {
+                        self.handle_backend_message(snapshot);
+                    }
+                    self.live_balances = Some(balances);
+                    self.last_updated = Some(Instant::now());
+                }
+                // 定时刷新失败不弹窗，下一个周期再试
+                Err(e) => log::warn!("Periodic balance refresh failed: {}", e),
+            }
+        }
+    }
+
+    fn process_position_results(&mut self) {
+        while let Ok(result) = self.positions_receiver.try_recv() {
+            match result {
+                Ok(positions) => self.positions = positions,
+                Err(e) => log::warn!("Failed to fetch open positions: {}", e),
+            }
+        }
+    }
+
+    fn display_currency(&self) -> DisplayCurrency {
+        self.profiles.active().display_currency
+    }
+
+    fn locale(&self) -> Locale {
+        self.profiles.active().locale
+    }
+
+    // Only touches the context when the configured theme changed
+    fn apply_theme(&mut self, ctx: &egui::Context) {
+        let theme = self.profiles.active().theme;
+        if self.current_theme == Some(theme) {
+            return;
+        }
+        self.current_theme = Some(theme);
+        match theme {
+            Theme::Dark => ctx.set_theme(egui::Theme::Dark),
+            Theme::Light => ctx.set_theme(egui::Theme::Light),
+            Theme::System => {
+                // egui follows the theme winit reports and falls back to this one
+                let fallback = match theme::desktop_prefers_dark() {
+                    Some(false) => egui::Theme::Light,
+                    _ => egui::Theme::Dark,
+                };
+                ctx.options_mut(|options| options.fallback_theme = fallback);
+                ctx.set_theme(egui::ThemePreference::System);
+            }
+        }
+    }
+
+    // Viewport sizes are converted with the new zoom, so keeping the size in points
+    // grows or shrinks the window in proportion to the scale
+    fn set_ui_scale(&mut self, ctx: &egui::Context, scale: f32) {
+        let scale = scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+        ctx.set_zoom_factor(scale);
+        if let Some(inner) = ctx.input(|i| i.viewport().inner_rect) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(inner.size()));
+        }
+        ctx.request_repaint();
+        self.profiles.active_mut().ui_scale = scale;
+        if let Err(e) = self.save_config() {
+            self.error_message = Some(format!("Failed to save config: {}", e));
+        }
+    }
+
+    // Tab list on the left, resizable by its border; icons only in narrow windows
+    fn show_navigation(&mut self, ctx: &egui::Context) {
+        let window_width = ctx.screen_rect().width();
+        let compact = window_width < COMPACT_WINDOW_WIDTH;
+        let panel = if compact {
+            egui::SidePanel::left("navigation_compact")
+                .resizable(false)
+                .exact_width(36.0)
+        } else {
+            egui::SidePanel::left("navigation")
+                .resizable(true)
+                .default_width(window_width * self.sidebar_ratio)
+                .width_range(80.0..=window_width * 0.5)
+        };
+        let response = panel.show(ctx, |ui| {
+            ui.add_space(10.0);
+            if !compact {
+                self.show_portfolio_list(ui, ctx);
+                ui.separator();
+            }
+            for tab in Tab::ALL {
+                let label = if compact {
+                    ui.selectable_label(
+                        self.active_tab == tab,
+                        RichText::new(tab.icon()).monospace(),
+                    )
+                    .on_hover_text(tab.label())
+                } else {
+                    ui.selectable_label(self.active_tab == tab, tab.label())
+                };
+                if label.clicked() {
+                    self.active_tab = tab;
+                }
+            }
+        });
+        if !compact {
+            self.sidebar_ratio = response.response.rect.width() / window_width;
+        }
+    }
+
+    // Portfolios above the tabs, each with a dot for its backend; "+" adds one
+    fn show_portfolio_list(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.horizontal(|ui| {
+            ui.strong("Portfolios");
+            if ui
+                .small_button("+")
+                .on_hover_text("Add a portfolio, starting from a copy of the selected one")
+                .clicked()
+            {
+                self.new_portfolio_name = Some(String::new());
+            }
+        });
+        let selected = self.portfolios.selected();
+        let mut clicked = None;
+        for (i, portfolio) in self.portfolios.portfolios.iter().enumerate() {
+            let (running, status, exchange) = if i == selected {
+                (
+                    self.is_running,
+                    self.status.clone(),
+                    self.profiles.active().exchange,
+                )
+            } else {
+                (
+                    portfolio.process.is_some(),
+                    portfolio.status.label(),
+                    portfolio.config.exchange,
+                )
+            };
+            let color = if running {
+                Color32::GREEN
+            } else {
+                Color32::GRAY
+            };
+            ui.horizontal(|ui| {
+                ui.colored_label(color, "●");
+                if portfolio.locked {
+                    let name = format!("🔒 {}", portfolio.name);
+                    ui.add_enabled(false, egui::SelectableLabel::new(false, name))
+                        .on_disabled_hover_text(
+                            "Encrypted with another passphrase; start the app with it to open",
+                        );
+                    return;
+                }
+                let label = ui
+                    .selectable_label(i == selected, &portfolio.name)
+                    .on_hover_text(format!("{} · {}", exchange.label(), status));
+                if label.clicked() {
+                    clicked = Some(i);
+                }
+            });
+        }
+
+        let mut add = None;
+        if let Some(name) = &mut self.new_portfolio_name {
+            let mut cancel = false;
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(name)
+                        .hint_text("Name")
+                        .desired_width(90.0),
+                );
+                let entered =
+                    response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.small_button("Add").clicked() || entered {
+                    add = Some(name.clone());
+                }
+                cancel = ui.small_button("✖").clicked();
+            });
+            if cancel {
+                self.new_portfolio_name = None;
+            }
+        }
+        if let Some(name) = add {
+            match self.add_portfolio(&name, ctx) {
+                Ok(()) => self.new_portfolio_name = None,
+                Err(e) => self.error_message = Some(e.to_string()),
+            }
+        }
+        if let Some(i) = clicked {
+            self.select_portfolio(i, ctx);
+        }
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        self.profiles.active_mut().theme = theme;
+        if let Err(e) = self.save_config() {
+            self.error_message = Some(format!("Failed to save config: {}", e));
+        }
+    }
+
+    fn set_display_currency(&mut self, currency: DisplayCurrency, ctx: &egui::Context) {
+        self.profiles.active_mut().display_currency = currency;
+        self.display_prices.clear();
+        if let Err(e) = self.save_config() {
+            self.error_message = Some(format!("Failed to save config: {}", e));
+        }
+        if currency.contract().is_some() && !self.configured_api_key().is_empty() {
+            self.fetch_balances(ctx);
+        }
+    }
+
+    /// A USDT amount in the display currency, and its USDT value as a tooltip
+    /// when it was converted.
+    fn amount_text(&self, value_usdt: f64) -> (String, Option<String>) {
+        let currency = self.display_currency();
+        let text =
+            currency::format_amount(value_usdt, currency, &self.display_prices, self.locale());
+        let converted =
+            currency != DisplayCurrency::Usdt && currency.price(&self.display_prices).is_some();
+        (
+            text,
+            converted.then(|| format!("{} USDT", format_number(value_usdt, 2, self.locale()))),
+        )
+    }
+
+    // Fetches fresh balances and prices; the plan is computed once they arrive
+    fn start_dry_run(&mut self, ctx: &egui::Context) {
+        let targets: Vec<String> = self
+            .profiles
+            .active()
+            .portfolio_allocation
+            .assets
+            .iter()
+            .map(|a| a.symbol.clone())
+            .collect();
+        self.dry_run_loading = self.fetch_snapshot(ctx, targets, SnapshotPurpose::DryRun);
+    }
+
+    // Opens the what-if window on the editor's rows as typed, unsaved ones included
+    fn start_what_if(&mut self, ctx: &egui::Context) {
+        let proposed = self.portfolio_editor.custom_allocation();
+        let targets = proposed.assets.iter().map(|a| a.symbol.clone()).collect();
+        if self.fetch_snapshot(ctx, targets, SnapshotPurpose::WhatIf) {
+            self.what_if = Some(WhatIf {
+                proposed,
+                snapshot: None,
+                plan: RebalancePlan::default(),
+            });
+        }
+    }
+
+    // Plans the proposed allocation with the active profile's other settings
+    fn update_what_if_plan(&mut self) {
+        let Some(what_if) = &mut self.what_if else {
+            return;
+        };
+        let Some((balances, prices)) = &what_if.snapshot else {
+            return;
+        };
+        let config = Config {
+            portfolio_allocation: what_if.proposed.clone(),
+            ..self.profiles.active().clone()
+        };
+        what_if.plan = planner::compute_plan(balances, &config, prices, &self.market_data);
+    }
+
+    // Balances plus prices of `targets`, held contracts and the display currency;
+    // false when no exchange is configured
+    fn fetch_snapshot(
+        &mut self,
+        ctx: &egui::Context,
+        targets: Vec<String>,
+        purpose: SnapshotPurpose,
+    ) -> bool {
+        let (Some(exchange), Some(runtime)) = (self.connect_exchange(), &self.runtime) else {
+            return false;
+        };
+        let display_contract = self.display_currency().contract();
+        let sender = self.dry_run_sender.clone();
+        let ctx = ctx.clone();
+        runtime.spawn(async move {
+            let result = async {
+                let balances = exchange.get_balances().await?;
+                let mut symbols: Vec<&str> = targets.iter().map(String::as_str).collect();
+                symbols.extend(display_contract.filter(|c| !targets.iter().any(|t| t == c)));
+                symbols.extend(
+                    balances
+                        .keys()
+                        .map(String::as_str)
+                        .filter(|s| *s != USDT && !targets.iter().any(|t| t == s)),
+                );
+                let prices = exchange.get_prices(&symbols).await?;
+                Ok((balances, prices))
+            }
+            .await;
+            let _ = sender.send((purpose, result));
+            ctx.request_repaint();
+        });
+        true
+    }
+
+    fn process_dry_run_results(&mut self) {
+        while let Ok((purpose, result)) = self.dry_run_receiver.try_recv() {
+            if purpose == SnapshotPurpose::DryRun {
+                self.dry_run_loading = false;
+            }
+            let (balances, prices) = match result {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    if purpose == SnapshotPurpose::WhatIf {
+                        self.what_if = None;
+                    }
+                    self.error_message = Some(e.to_string());
+                    continue;
+                }
+            };
+            if let Some(contract) = self.display_currency().contract() {
+                if let Some(price) = prices.get(contract) {
+                    self.display_prices.insert(contract.to_string(), *price);
+                }
+            }
+            self.total_value_usdt = Some(balances.values().sum());
+            self.live_balances = Some(balances.clone());
+            match purpose {
+                SnapshotPurpose::DryRun => {
+                    let config = self.profiles.active();
+                    let plan = planner::compute_plan(&balances, config, &prices, &self.market_data);
+                    self.dry_run = Some((plan, prices));
+                }
+                // Dropped if the window was closed in the meantime
+                SnapshotPurpose::WhatIf => {
+                    if let Some(what_if) = &mut self.what_if {
+                        what_if.snapshot = Some((balances, prices));
+                        self.update_what_if_plan();
+                    }
+                }
+            }
+        }
+    }
+
+    // Copies the proposed weights into the editor, left unsaved like a preset
+    fn apply_what_if(&mut self) {
+        let Some(what_if) = self.what_if.take() else {
+            return;
+        };
+        let patch = PortfolioAllocationPatch {
+            assets: what_if
+                .proposed
+                .assets
+                .into_iter()
+                .map(|a| (a.symbol, a.target_pct))
+                .collect(),
+            ..PortfolioAllocationPatch::default()
+        };
+        self.portfolio_editor.apply_patch(&patch);
+        self.hold_unsaved();
+    }
+
+    // Places the previewed orders one by one; results are reported to the log
+    fn execute_dry_run(&mut self, ctx: &egui::Context) {
+        if self.is_running {
+            self.error_message =
+                Some("Stop the rebalancer before executing trades manually.".to_string());
+            return;
+        }
+        let Some((plan, prices)) = self.dry_run.take() else {
+            return;
+        };
+        let (Some(exchange), Some(runtime)) = (self.connect_exchange(), &self.runtime) else {
+            return;
+        };
+        let sender = self.order_sender.clone();
+        let ctx = ctx.clone();
+        runtime.spawn(async move {
+            for trade in plan.trades {
+                let qty = trade.quantity(prices[&trade.symbol]);
+                let _ = sender.send(exchange.place_order(&trade.symbol, trade.side, qty).await);
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    fn process_order_results(&mut self) {
+        while let Ok(result) = self.order_receiver.try_recv() {
+            match result {
+                Ok(order) => {
+                    self.push_log_line(format!(
+                        "Dry run executed: {} {} {} @ {} (order {})",
+                        order.side,
+                        order.filled_qty,
+                        order.symbol,
+                        order.fill_price,
+                        order.order_id
+                    ));
+                    self.record_fill(
+                        &order.symbol,
+                        order.side,
+                        order.filled_qty,
+                        order.fill_price,
+                    );
+                    self.record_order(Order {
+                        id: order.order_id,
+                        symbol: order.symbol,
+                        side: order.side,
+                        qty: order.filled_qty,
+                        price: order.fill_price,
+                        status: OrderStatus::Filled,
+                        created_at: Utc::now(),
+                    });
+                }
+                Err(e) => {
+                    self.push_log_line(format!("Dry run order failed: {}", e));
+                    self.error_message = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    // While the backend runs its snapshots stay the source of the live allocation
+    fn process_balance_results(&mut self) {
+        while let Ok(result) = self.balance_receiver.try_recv() {
+            self.balances_loading = false;
+            match result {
+                Ok((balances, display_price)) => {
+                    // Balances are margin already quoted in USDT, so they add up directly
+                    let total: f64 = balances.values().sum();
+                    self.total_value_usdt = Some(total);
+                    if let (Some(contract), Some(price)) =
+                        (self.display_currency().contract(), display_price)
+                    {
+                        self.display_prices.insert(contract.to_string(), price);
+                    }
+                    if !self.is_running && total > 0.0 {
+                        self.live_allocations = Some(
+                            balances
+                                .iter()
+                                .map(|(symbol, value)| (symbol.clone(), value / total * 100.0))
+                                .collect(),
+                        );
+                    }
+                    self.live_balances = Some(balances);
+                }
+                Err(e) => self.error_message = Some(e.to_string()),
+            }
+        }
+    }
+
+    // Exit of the backend process, on the status poll tick
+    fn check_backend_exit(&mut self, ctx: &egui::Context) {
+        let mut process_exited = false;
+        let mut exit_status_str = String::new();
+
+        if let Some(child) = &mut self.backend_process {
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    // Process has exited
+                    log::warn!(
+                        "Backend process exited unexpectedly with status: {}",
+                        status
+                    );
+                    exit_status_str = format!("Stopped (Exited: {})", status);
+                    process_exited = true;
+                }
+                Ok(None) => { /* Process still running, do nothing */ }
+                Err(e) => {
+                    // Error trying to check status
+                    log::error!("Error checking backend process status: {}", e);
+                    exit_status_str = format!("Error ({})", e);
+                    process_exited = true; // Treat error as if it exited
+                }
+            }
+        } else {
+            // Should not happen if is_running is true, but handle defensively
+            log::warn!("Inconsistent state: is_running=true but backend_process is None.");
+            exit_status_str = "Error (Inconsistent)".to_string();
+            process_exited = true;
+        }
+
+        if process_exited {
+            self.fire_webhook(
+                WebhookEvent::BackendStopped,
+                None,
+                serde_json::json!({ "status": exit_status_str }),
+            );
+            self.is_running = false;
+            self.backend_process = None; // Clear the handle
+            self.disconnect_backend_socket();
+            self.status = exit_status_str;
+            self.schedule_restart();
+            // Optionally add to error_message:
+            // self.error_message = Some("Backend process stopped unexpectedly.".to_string());
+            ctx.request_repaint(); // Request repaint to show updated status
+        }
+    }
+
+    // 每隔 STATUS_POLL_INTERVAL 检查进程并向后端的 HTTP API 查询状态
+    fn poll_backend_status(&mut self, ctx: &egui::Context) {
+        if !self.is_running {
+            self.next_status_poll = None;
+            return;
+        }
+        let now = Instant::now();
+        if let Some(at) = self.next_status_poll.filter(|at| now < *at) {
+            ctx.request_repaint_after(at - now);
+            return;
+        }
+        self.next_status_poll = Some(now + STATUS_POLL_INTERVAL);
+        ctx.request_repaint_after(STATUS_POLL_INTERVAL);
+        self.check_backend_exit(ctx);
+        if !self.is_running || self.status_poll_pending {
+            return;
+        }
+        self.status_poll_pending = true;
+        let client = self.backend_client.clone();
+        let sender = self.status_sender.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let _ = sender.send(client.get_status());
+            ctx.request_repaint();
+        });
+    }
+
+    fn process_status_results(&mut self) {
+        while let Ok(result) = self.status_receiver.try_recv() {
+            self.status_poll_pending = false;
+            match result {
+                // A reply from a backend that was stopped meanwhile is stale
+                Ok(status) if self.is_running => self.status = status.status,
+                Ok(_) => {}
+                // The API comes up a moment after the process; the exit check covers a dead backend
+                Err(e) => log::debug!("Backend status poll failed: {}", e),
+            }
+        }
+    }
+
+    fn trigger_manual_rebalance(&mut self, ctx: &egui::Context) {
+        self.manual_rebalance_loading = true;
+        let client = self.backend_client.clone();
+        let sender = self.manual_rebalance_sender.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let _ = sender.send(client.trigger_rebalance());
+            ctx.request_repaint();
+        });
+    }
+
+    fn process_manual_rebalance_results(&mut self) {
+        while let Ok(result) = self.manual_rebalance_receiver.try_recv() {
+            self.manual_rebalance_loading = false;
+            self.error_message = Some(match result {
+                Ok(plan) if plan.is_empty() => "Rebalance Now: no trades were needed.".to_string(),
+                Ok(plan) => format!("Rebalance Now: executed {} trade(s).", plan.trades.len()),
+                Err(e) => format!("Rebalance Now failed: {}", e),
+            });
+        }
+    }
+
+    // Drain everything the reader threads have forwarded since the last frame
+    fn process_backend_messages(&mut self) {
+        let Some(receiver) = &self.backend_receiver else {
+            return;
+        };
+        let mut messages = Vec::new();
+        loop {
+            match receiver.try_recv() {
+                Ok(message) => messages.push(message),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.backend_receiver = None;
+                    break;
+                }
+            }
+        }
+        for message in messages {
+            self.handle_backend_message(message);
+        }
+    }
+
+    fn record_event(&mut self, event: RebalanceEvent) {
+        if let Some(db) = &self.db {
+            if let Err(e) = db.insert_event(&event) {
+                log::warn!("Failed to save history: {}", e);
+            }
+        }
+        self.event_history.push(event);
+    }
+
+    fn record_fill(&mut self, asset: &str, side: OrderSide, quantity: f64, price: f64) {
+        let gains = tax::record_fill(
+            &mut self.tax_lots,
+            asset,
+            side,
+            quantity,
+            price,
+            Utc::now(),
+            self.profiles.active().cost_basis_method,
+        );
+        if let Some(db) = &self.db {
+            let saved = db
+                .save_tax_lots(&self.tax_lots)
+                .and_then(|()| gains.iter().try_for_each(|g| db.insert_realized_gain(g)));
+            if let Err(e) = saved {
+                log::warn!("Failed to save tax lots: {}", e);
+            }
+        }
+        self.realized_gains.extend(gains);
+    }
+
+    // A known ID is a status update and replaces the earlier row
+    fn record_order(&mut self, order: Order) {
+        if let Some(db) = &self.db {
+            if let Err(e) = db.upsert_order(&order) {
+                log::warn!("Failed to save order: {}", e);
+            }
+        }
+        match self.orders.iter_mut().find(|o| o.id == order.id) {
+            Some(existing) => *existing = order,
+            None => self.orders.push(order),
+        }
+    }
+
+    fn notify_trade(&mut self, asset: &str, side: &str, usdt_traded: f64) {
+        if !self.profiles.active().notifications_enabled {
+            return;
+        }
+        let now = Instant::now();
+        if self
+            .last_notified
+            .get(asset)
+            .is_some_and(|last| now.duration_since(*last) < NOTIFICATION_COOLDOWN)
+        {
+            return;
+        }
+        self.last_notified.insert(asset.to_string(), now);
+        show_notification(format!("{}: {} {:.2} USDT", asset, side, usdt_traded));
+    }
+
+    // Notifies once when the ratio falls below the alert threshold, again only
+    // after it has recovered
+    fn check_margin_ratio(&mut self) {
+        let config = self.profiles.active();
+        let Some(ratio) = self.margin_ratio() else {
+            self.margin_alert_active = false;
+            return;
+        };
+        if ratio >= config.margin_alert_threshold_pct {
+            self.margin_alert_active = false;
+            return;
+        }
+        if self.margin_alert_active {
+            return;
+        }
+        self.margin_alert_active = true;
+        log::warn!("Margin ratio critical: {:.1}%", ratio);
+        if config.notifications_enabled {
+            show_notification(format!(
+                "Margin ratio critical: {}%",
+                format_number(ratio, 1, config.locale)
+            ));
+        }
+    }
+
+    // Grey until checked, then green, yellow or red by latency; click for the details
+    fn connectivity_dot(&self, ui: &mut egui::Ui) {
+        let exchange = self.profiles.active().exchange.label();
+        let (color, text) = match &self.connectivity {
+            None => (Color32::GRAY, format!("{} not checked yet", exchange)),
+            Some(status) => {
+                let checked = format!("checked {}s ago", status.last_checked.elapsed().as_secs());
+                match status.exchange_ping_ms {
+                    Some(ms) => {
+                        let color = if ms < PING_GOOD_MS {
+                            Color32::GREEN
+                        } else if ms <= PING_FAIR_MS {
+                            Color32::YELLOW
+                        } else {
+                            Color32::RED
+                        };
+                        (color, format!("{} ping: {} ms ({})", exchange, ms, checked))
+                    }
+                    None => (
+                        Color32::RED,
+                        format!("{} unreachable ({})", exchange, checked),
+                    ),
+                }
+            }
+        };
+        let (rect, response) = ui.allocate_exact_size(Vec2::splat(12.0), Sense::click());
+        ui.painter().circle_filled(rect.center(), 5.0, color);
+        let popup_id = response.id.with("connectivity");
+        if response.clicked() {
+            ui.memory_mut(|m| m.toggle_popup(popup_id));
+        }
+        let response = response.on_hover_text(&text);
+        egui::popup_below_widget(
+            ui,
+            popup_id,
+            &response,
+            egui::PopupCloseBehavior::CloseOnClickOutside,
+            |ui| {
+                ui.set_min_width(180.0);
+                ui.label(text);
+            },
+        );
+    }
+
+    fn margin_ratio(&self) -> Option<f64> {
+        let allocations = self.live_allocations.as_ref()?;
+        metrics::margin_ratio_pct(allocations, &self.profiles.active().portfolio_allocation)
+    }
+
+    // Delivery can take several retries, so it never runs on the UI thread
+    fn fire_webhook(&self, event: WebhookEvent, asset: Option<String>, details: serde_json::Value) {
+        let config = self.profiles.active();
+        let Some(url) = config.webhook_url.clone() else {
+            return;
+        };
+        if !config.webhook_events.contains(&event) {
+            return;
+        }
+        let payload = WebhookPayload::new(event, asset, details);
+        thread::spawn(move || {
+            if let Err(e) = webhook::send_webhook(&url, &payload) {
+                log::warn!("Failed to send {} webhook: {}", payload.event_type, e);
+            }
+        });
+    }
+
+    fn test_webhook(&mut self, ctx: &egui::Context) {
+        let Some(url) = self.profiles.active().webhook_url.clone() else {
+            return;
+        };
+        let sender = self.webhook_test_sender.clone();
+        let ctx = ctx.clone();
+        self.webhook_testing = true;
+        self.webhook_test_result = None;
+        thread::spawn(move || {
+            let result =
+                webhook::send_webhook(&url, &WebhookPayload::test()).map_err(|e| e.to_string());
+            let _ = sender.send(result);
+            ctx.request_repaint();
+        });
+    }
+
+    fn process_symbol_list(&mut self) {
+        let Some(receiver) = &self.symbols_receiver else {
+            return;
+        };
+        let Ok(mut symbols) = receiver.try_recv() else {
+            return;
+        };
+        self.symbols_receiver = None;
+        if symbols.is_empty() {
+            return;
+        }
+        symbols.sort_unstable();
+        self.symbol_suggestions = SymbolSuggestions { symbols };
+        let path = SymbolSuggestions::default_path();
+        match self.symbol_suggestions.save(&path) {
+            Ok(()) => log::info!(
+                "Cached {} contract names in {:?}.",
+                self.symbol_suggestions.symbols.len(),
+                path
+            ),
+            Err(e) => log::warn!("Failed to cache the contract list ({:?}): {}", path, e),
+        }
+    }
+
+    fn process_webhook_test_results(&mut self) {
+        while let Ok(result) = self.webhook_test_receiver.try_recv() {
+            self.webhook_testing = false;
+            self.webhook_test_result = Some(result);
+        }
+    }
+
+    // Clicking the active column flips the direction; a new column starts ascending
+    fn toggle_history_sort(&mut self, column: HistoryColumn) {
+        if self.history_sort_column == column {
+            self.history_sort_ascending = !self.history_sort_ascending;
+        } else {
+            self.history_sort_column = column;
+            self.history_sort_ascending = true;
+        }
+    }
+
+    // The app's own warnings, e.g. exchange rate-limit waits, join the backend's lines
+    fn process_log_warnings(&mut self) {
+        for line in logging::take_warnings() {
+            self.push_log_line(line);
+        }
+    }
+
+    fn push_log_line(&mut self, line: String) {
+        if self.log_lines.len() == MAX_LOG_LINES {
+            self.log_lines.pop_front();
+        }
+        self.log_lines.push_back(line);
+    }
+
+    fn handle_backend_message(&mut self, message: BackendMessage) {
+        match message {
+            BackendMessage::Status {
+                status,
+                paper_trading,
+            } => {
+                if status == "Running" && paper_trading != self.profiles.active().paper_trading {
+                    self.error_message = Some(format!(
+                        "Backend started in {} mode, but the config asks for {}.",
+                        if paper_trading {
+                            "paper trading"
+                        } else {
+                            "live"
+                        },
+                        if paper_trading {
+                            "live"
+                        } else {
+                            "paper trading"
+                        }
+                    ));
+                }
+                self.status = status;
+            }
+            BackendMessage::PortfolioSnapshot {
+                allocations,
+                total_value_usdt,
+                funding_rates,
+            } => {
+                self.live_allocations = Some(allocations);
+                // Keep the last known rates when a snapshot comes without them
+                if !funding_rates.is_empty() {
+                    self.funding_rates = funding_rates;
+                }
+                self.total_value_usdt = Some(total_value_usdt);
+                self.value_history.push(Utc::now(), total_value_usdt);
+                self.check_drawdown(total_value_usdt);
+                self.check_margin_ratio();
+            }
+            BackendMessage::RebalanceProgress {
+                total_trades,
+                completed_trades,
+                current_asset,
+            } => {
+                self.rebalance_progress =
+                    (completed_trades < total_trades).then_some(RebalanceProgress {
+                        total_trades,
+                        completed_trades,
+                        current_asset,
+                    });
+            }
+            BackendMessage::PriceHistory { symbols, prices } => {
+                self.store_daily_closes(&symbols, &prices);
+                self.refresh_volatilities();
+                self.correlation_matrix = Some(metrics::compute_correlation(&prices));
+                self.correlation_symbols = symbols;
+                self.price_history = prices;
+            }
+            BackendMessage::TradeExecuted {
+                asset,
+                side,
+                from_pct,
+                to_pct,
+                usdt_traded,
+                order_id,
+                portfolio_value_usdt,
+                quantity,
+                price,
+            } => {
+                // Older backends do not report the fill, so no lot can be booked
+                if quantity > 0.0 && price > 0.0 {
+                    let side = if side == "sell" {
+                        OrderSide::Sell
+                    } else {
+                        OrderSide::Buy
+                    };
+                    self.record_fill(&asset, side, quantity, price);
+                }
+                log::info!(
+                    "Trade executed: {} {} {:.2} USDT (order {})",
+                    side,
+                    asset,
+                    usdt_traded,
+                    order_id
+                );
+                self.notify_trade(&asset, &side, usdt_traded);
+                self.fire_webhook(
+                    WebhookEvent::TradeExecuted,
+                    Some(asset.clone()),
+                    serde_json::json!({
+                        "side": side,
+                        "from_pct": from_pct,
+                        "to_pct": to_pct,
+                        "usdt_traded": usdt_traded,
+                        "order_id": order_id,
+                        "portfolio_value_usdt": portfolio_value_usdt,
+                    }),
+                );
+                self.record_event(RebalanceEvent {
+                    timestamp: Utc::now(),
+                    asset,
+                    from_pct,
+                    to_pct,
+                    usdt_traded,
+                    order_id,
+                    portfolio_value_usdt,
+                    fee_usdt: usdt_traded * self.profiles.active().taker_fee_rate,
+                });
+            }
+            BackendMessage::OrderUpdate(order) => self.record_order(order),
+            BackendMessage::Error { message } => {
+                self.push_log_line(format!("ERROR: {}", message));
+                self.error_message = Some(format!("Backend error: {}", message));
+                self.fire_webhook(
+                    WebhookEvent::BackendError,
+                    None,
+                    serde_json::json!({ "message": message }),
+                );
+            }
+            BackendMessage::Log { line } => self.push_log_line(line),
+        }
+    }
+
+    fn update_config_from_editor(&mut self) -> Result<(), KinError> {
+        let previous_config = self.profiles.active().clone();
+        let previous = previous_config.portfolio_allocation.clone();
+        if let Err(e) = self
+            .portfolio_editor
+            .update_config(self.profiles.active_mut())
+        {
+            if let KinError::ValidationErrors(errors) = &e {
+                self.field_errors = errors.clone();
+            }
+            return Err(e);
+        }
+        self.field_errors.clear();
+
+        let allocation = &self.profiles.active().portfolio_allocation;
+        // Saves that change nothing, e.g. before each start, are left out of the audit log
+        let changes = (*self.profiles.active() != previous_config)
+            .then(|| audit::allocation_changes(&previous, allocation));
+        // USDT allocation is calculated automatically
+        self.portfolio_editor.usdt_allocation = self.portfolio_editor.format_pct(allocation.usdt()); // Update display value
+        if *allocation != previous {
+            push_capped(&mut self.history_stack, previous);
+            self.redo_stack.clear();
+        }
+
+        self.save_config()?;
+        if let Some(changes) = changes {
+            let details = format!("profile \"{}\": {}", self.profiles.active_name(), changes);
+            self.audit(AuditAction::SavePortfolioConfig, details);
+        }
+        self.refresh_volatilities();
+        self.dirty = false;
+        self.notes_edited = false;
+        self.last_edit_instant = None;
+        self.last_saved_instant = Some(Instant::now());
+        log::info!("Configuration saved successfully.");
+        Ok(())
+    }
+
+    // Our own saves also trigger the watcher; they are filtered out by comparing
+    // the file with the active profile.
+    fn process_config_changes(&mut self) {
+        let Some(watcher) = &self.config_watcher else {
+            return;
+        };
+        if !watcher.changed() {
+            return;
+        }
+        self.config_backups = None;
+        match Config::load_with_passphrase(&self.config_path, self.passphrase.as_deref()) {
+            // Every save stamps a new saved_at, which is not a change of settings
+            Ok(config)
+                if Config {
+                    saved_at: self.profiles.active().saved_at,
+                    ..config.clone()
+                } == *self.profiles.active() =>
+            {
+                self.external_config = None
+            }
+            Ok(config) => {
+                self.external_config = Some(config);
+                self.confirm_reload = false;
+            }
+            // Possibly caught halfway through a write; the next event retries
+            Err(e) => log::warn!("Config file changed but could not be read: {}", e),
+        }
+    }
+
+    // Whether reloading `config` would throw away edits not yet saved
+    fn unsaved_edits_differ(&self, config: &Config) -> bool {
+        if !self.dirty {
+            return false;
+        }
+        match self.portfolio_editor.validate() {
+            Ok(values) => {
+                values.allocation != config.portfolio_allocation
+                    || values.threshold != config.rebalance_threshold
+                    || values.per_asset_threshold != config.per_asset_threshold
+                    || values.min_inflow != config.min_usdt_inflow
+                    || values.min_trade != config.min_trade_usdt
+                    || values.taker_fee_rate != config.taker_fee_rate
+                    || values.maker_fee_rate != config.maker_fee_rate
+                    || self.portfolio_editor.allocation_mode != config.allocation_mode
+                    || self.portfolio_editor.position_sizing != config.position_sizing
+                    || self.portfolio_editor.cost_basis_method != config.cost_basis_method
+                    || self.portfolio_editor.slippage_model != config.slippage_model
+                    || self.portfolio_editor.schedule != config.schedule
+                    || self.portfolio_editor.poll_interval_seconds != config.poll_interval_seconds
+                    || self.portfolio_editor.max_drawdown_halt_pct != config.max_drawdown_halt_pct
+                    || self.portfolio_editor.display_precision != config.display_precision
+                    || self.portfolio_editor.notes != config.notes
+                    || self.portfolio_editor.drag_order
+                        != config::display_order(
+                            &config.display_order,
+                            &self.portfolio_editor.drag_order,
+                        )
+            }
+            Err(_) => true,
+        }
+    }
+
+    fn reload_external_config(&mut self) -> Result<(), KinError> {
+        let Some(config) = self.external_config.take() else {
+            return Ok(());
+        };
+        self.confirm_reload = false;
+        *self.profiles.active_mut() = config;
+        self.discard_editor_changes();
+        self.display_prices.clear();
+        self.exchange = None;
+        self.profiles
+            .save_with_passphrase(&self.profiles_path, self.passphrase.as_deref())?;
+        log::info!("Reloaded config from {:?}.", self.config_path);
+        Ok(())
+    }
+
+    // The restored file is loaded like an outside edit, so the editor and profiles follow it
+    fn restore_backup(&mut self, n: u8) -> Result<(), KinError> {
+        Config::restore_backup(&self.config_path, n, self.profiles.active().backup_count)?;
+        self.config_backups = None;
+        self.external_config = Some(Config::load_with_passphrase(
+            &self.config_path,
+            self.passphrase.as_deref(),
+        )?);
+        self.reload_external_config()?;
+        log::info!("Restored config backup {}.", n);
+        Ok(())
+    }
+
+    fn mark_dirty(&mut self) {
+        self.dirty = true;
+        self.notes_edited = false;
+        self.last_edit_instant = Some(Instant::now());
+    }
+
+    // Loaded values are left unsaved for the user to review; auto-save only
+    // resumes after their next edit
+    fn hold_unsaved(&mut self) {
+        self.dirty = true;
+        self.last_edit_instant = None;
+    }
+
+    fn apply_preset(&mut self, preset: &AllocationPreset) {
+        self.portfolio_editor.load_preset(preset);
+        self.hold_unsaved();
+    }
+
+    // The editor's values, for sharing on forums; invalid fields are reported instead
+    fn copy_allocation_json(&mut self) -> Result<(), KinError> {
+        let values = self
+            .portfolio_editor
+            .validate()
+            .map_err(KinError::ValidationErrors)?;
+        let json = SharedAllocation {
+            portfolio_allocation: values.allocation,
+            rebalance_threshold: Some(values.threshold),
+            min_usdt_inflow: Some(values.min_inflow),
+        }
+        .to_json()?;
+        arboard::Clipboard::new()?.set_text(json)?;
+        self.allocation_copied_at = Some(Instant::now());
+        Ok(())
+    }
+
+    // Only parses the clipboard; the editor changes once the preview is confirmed
+    fn paste_allocation_json(&mut self) -> Result<(), KinError> {
+        let text = arboard::Clipboard::new()?.get_text()?;
+        let patch = PortfolioAllocationPatch::from_json(&text)?;
+        let changes = self.portfolio_editor.patch_changes(&patch);
+        if changes.is_empty() {
+            return Err(KinError::invalid_allocation(
+                "Clipboard",
+                "the pasted allocation matches the editor",
+            ));
+        }
+        self.pending_paste = Some((patch, changes));
+        Ok(())
+    }
+
+    fn confirm_paste(&mut self) {
+        if let Some((patch, _)) = self.pending_paste.take() {
+            self.portfolio_editor.apply_patch(&patch);
+            self.hold_unsaved();
+        }
+    }
+
+    fn show_setup_wizard(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("Welcome to KIN Portfolio Rebalancer");
+                ui.label(format!(
+                    "Step {} of {}: {}",
+                    self.wizard_step.number(),
+                    WizardStep::ALL.len(),
+                    self.wizard_step.title()
+                ));
+            });
+            ui.add_space(10.0);
+            ui.separator();
+            ui.add_space(10.0);
+
+            let can_continue = match self.wizard_step {
+                WizardStep::ApiKeys => self.wizard_api_keys_step(ui),
+                WizardStep::Allocations => self.wizard_allocations_step(ui),
+                WizardStep::Review => self.wizard_review_step(ui),
+            };
+
+            if let Some(error) = &self.error_message {
+                ui.add_space(5.0);
+                ui.colored_label(Color32::RED, error);
+            }
+            ui.add_space(10.0);
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(self.wizard_step != WizardStep::ApiKeys, Button::new("Back"))
+                    .clicked()
+                {
+                    self.wizard_step = self.wizard_step.back();
+                    self.error_message = None;
+                }
+                if self.wizard_step == WizardStep::Review {
+                    if ui
+                        .add_enabled(can_continue, Button::new("Finish"))
+                        .clicked()
+                    {
+                        if let Err(e) = self.finish_setup_wizard(ctx) {
+                            self.error_message = Some(e.to_string());
+                        }
+                    }
+                } else if ui.add_enabled(can_continue, Button::new("Next")).clicked() {
+                    self.wizard_step = self.wizard_step.next();
+                    self.error_message = None;
+                }
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    if ui
+                        .button("Skip Setup")
+                        .on_hover_text("Use the tabs to configure everything later")
+                        .clicked()
+                    {
+                        self.close_setup_wizard();
+                    }
+                });
+            });
+        });
+    }
+
+    // Returns whether the entered keys allow moving on
+    fn wizard_api_keys_step(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut choice = self.profiles.active().exchange;
+        ui.horizontal(|ui| {
+            ui.label("Exchange:");
+            for option in ExchangeChoice::ALL {
+                ui.radio_value(&mut choice, option, option.label());
+            }
+        });
+        if choice != self.profiles.active().exchange {
+            self.set_exchange(choice);
+        }
+        ui.label(format!(
+            "Create an API key with futures trading permission on the {} TestNet.",
+            choice.label()
+        ));
+        ui.add_space(10.0);
+        Grid::new("wizard_api_grid")
+            .num_columns(2)
+            .spacing([10.0, 4.0])
+            .show(ui, |ui| {
+                ui.label(RichText::new("API Key:").strong());
+                if ui
+                    .add(TextEdit::singleline(&mut *self.api_key).desired_width(300.0))
+                    .changed()
+                {
+                    self.api_key_error = credential_error(
+                        &self.api_key,
+                        self.profiles.active().exchange,
+                        validate_api_key,
+                    );
+                }
+                ui.end_row();
+                ui.label(RichText::new("API Secret:").strong());
+                if ui
+                    .add(
+                        TextEdit::singleline(&mut *self.api_secret)
+                            .password(true)
+                            .desired_width(300.0),
+                    )
+                    .changed()
+                {
+                    self.api_secret_error = credential_error(
+                        &self.api_secret,
+                        self.profiles.active().exchange,
+                        validate_api_secret,
+                    );
+                }
+                ui.end_row();
+            });
+        for error in [&self.api_key_error, &self.api_secret_error]
+            .into_iter()
+            .flatten()
+        {
+            ui.colored_label(Color32::RED, error);
+        }
+        ui.add_space(5.0);
+        let mut paper_trading = self.profiles.active().paper_trading;
+        if ui
+            .checkbox(&mut paper_trading, "Paper trading")
+            .on_hover_text(format!(
+                "Simulate orders against a virtual {:.0} USDT account; no API keys needed",
+                PAPER_INITIAL_USDT
+            ))
+            .changed()
+        {
+            self.profiles.active_mut().paper_trading = paper_trading;
+            self.exchange = None;
+        }
+
+        let entered = !self.api_key.trim().is_empty()
+            && !self.api_secret.trim().is_empty()
+            && self.api_key_error.is_none()
+            && self.api_secret_error.is_none();
+        entered || paper_trading || !self.configured_api_key().is_empty()
+    }
+
+    // Returns whether the allocation is valid
+    fn wizard_allocations_step(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.label("Choose a preset or enter a target percentage per asset. USDT takes the rest.");
+        let mut selected_preset = None;
+        egui::ComboBox::from_id_salt("wizard_preset")
+            .selected_text("Load Preset")
+            .show_ui(ui, |ui| {
+                for preset in presets::all() {
+                    if ui.selectable_label(false, &preset.name).clicked() {
+                        selected_preset = Some(preset);
+                    }
+                }
+            });
+        if let Some(preset) = selected_preset {
+            self.apply_preset(&preset);
+        }
+        ui.add_space(10.0);
+
+        let locale = self.locale();
+        let editor = &mut self.portfolio_editor;
+        Grid::new("wizard_allocation_grid")
+            .num_columns(3)
+            .spacing([10.0, 4.0])
+            .striped(true)
+            .show(ui, |ui| {
+                for (index, row) in editor.assets.iter_mut().enumerate() {
+                    ui.label(format!("{}:", row.symbol));
+                    validated_text_edit(
+                        ui,
+                        &mut row.allocation,
+                        FieldId::Allocation(index),
+                        false,
+                        &mut editor.live_errors,
+                        "",
+                        60.0,
+                    );
+                    ui.label("%");
+                    ui.end_row();
+                }
+                ui.label("USDT:");
+                ui.strong(format!("{}%", editor.get_usdt_display()));
+                ui.end_row();
+                ui.label("Threshold Deviation:");
+                validated_text_edit(
+                    ui,
+                    &mut editor.rebalance_threshold,
+                    FieldId::RebalanceThreshold,
+                    false,
+                    &mut editor.live_errors,
+                    "",
+                    60.0,
+                );
+                ui.label("%");
+                ui.end_row();
+            });
+        allocation_total_gauge(ui, editor.crypto_total(), editor.display_precision, locale);
+        ui.add_space(5.0);
+        ui.weak("More settings, such as per-asset thresholds and schedules, are in the Portfolio Config tab.");
+        editor.validate().is_ok()
+    }
 
-        let crypto_total = btc + eth + ltc;
-        let usdt = if crypto_total > 100.0 {
-            0.0
+    fn wizard_review_step(&mut self, ui: &mut egui::Ui) -> bool {
+        let paper_trading = self.profiles.active().paper_trading;
+        let key = self.api_key.trim();
+        let key = if key.is_empty() {
+            self.configured_api_key()
         } else {
-            (100.0 - crypto_total).max(0.0) // Ensure it's not negative due to float issues
+            key
         };
-        usdt
+        Grid::new("wizard_review_grid")
+            .num_columns(2)
+            .spacing([20.0, 4.0])
+            .show(ui, |ui| {
+                ui.label("Mode:");
+                ui.strong(if paper_trading {
+                    "Paper trading"
+                } else {
+                    "TestNet"
+                });
+                ui.end_row();
+                ui.label("API Key:");
+                ui.label(match key.len() {
+                    0 => "Not set".to_string(),
+                    n if n > 6 => format!("...{}", &key[n - 6..]),
+                    _ => "******".to_string(),
+                });
+                ui.end_row();
+                for row in &self.portfolio_editor.assets {
+                    ui.label(format!("{}:", row.symbol));
+                    ui.label(format!("{}%", row.allocation.trim()));
+                    ui.end_row();
+                }
+                ui.label("USDT:");
+                ui.label(format!("{}%", self.portfolio_editor.get_usdt_display()));
+                ui.end_row();
+                ui.label("Threshold Deviation:");
+                ui.label(format!(
+                    "{}%",
+                    self.portfolio_editor.rebalance_threshold.trim()
+                ));
+                ui.end_row();
+            });
+        ui.add_space(10.0);
+        ui.checkbox(&mut self.wizard_start_now, "Start the rebalancer now");
+        true
     }
 
-    // Get USDT allocation as a string for display
-    fn get_usdt_display(&self) -> String {
-        format!("{:.1}", self.calculate_usdt())
+    // Keys left empty are only acceptable in paper trading or when saved before
+    fn finish_setup_wizard(&mut self, ctx: &egui::Context) -> Result<(), KinError> {
+        if !self.api_key.trim().is_empty() || !self.api_secret.trim().is_empty() {
+            self.update_api_settings()?;
+        }
+        self.update_config_from_editor()?;
+        self.close_setup_wizard();
+        if self.wizard_start_now {
+            self.start_backend_manually(ctx);
+        }
+        Ok(())
     }
-}
 
-impl RebalancerApp {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let mut style = (*cc.egui_ctx.style()).clone();
-        style.visuals = egui::Visuals::dark();
-        cc.egui_ctx.set_style(style);
-
-        let config_path = Self::get_config_path();
-        let config = Self::load_config(&config_path).unwrap_or_else(|e| {
-            println!(
-                "Failed to load config ({:?}): {}, using default.",
-                config_path, e
-            );
-            Config::default()
+    fn close_setup_wizard(&mut self) {
+        self.profiles.active_mut().first_run = false;
+        if let Err(e) = self.save_config() {
+            self.error_message = Some(format!("Failed to save config: {}", e));
+        }
+        self.show_wizard = false;
+    }
+
+    fn export_config_csv(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("portfolio_config.csv")
+            .save_file()
+        else {
+            return;
+        };
+        self.error_message = Some(match self.profiles.active().export_to_csv(&path) {
+            Ok(()) => format!("Config exported to {}", path.display()),
+            Err(e) => format!("Failed to export config: {}", e),
         });
+    }
 
-        let portfolio_editor = PortfolioAllocationEditor {
-            BTC_USDT_allocation: config.portfolio_allocation.BTC_USDT.to_string(),
-            ETH_USDT_allocation: config.portfolio_allocation.ETH_USDT.to_string(),
-            LTC_USDT_allocation: config.portfolio_allocation.LTC_USDT.to_string(),
-            USDT_allocation: format!("{:.1}", config.portfolio_allocation.USDT),
-            rebalance_threshold: config.rebalance_threshold.to_string(),
-            min_usdt_inflow: config.min_usdt_inflow.to_string(),
+    fn export_history_csv(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("rebalance_history.csv")
+            .save_file()
+        else {
+            return;
         };
+        self.error_message = Some(
+            match RebalanceEvent::export_all_to_csv(&self.event_history, &path) {
+                Ok(()) => format!("History exported to {}", path.display()),
+                Err(e) => format!("Failed to export history: {}", e),
+            },
+        );
+    }
 
-        Self {
-            config,
-            api_key: String::new(),
-            api_secret: String::new(),
-            config_path,
-            backend_process: None,
-            status: "Stopped".to_string(),
-            is_running: false,
-            error_message: None,
-            // Removed backend output state initialization
-            // backend_output_receiver: None,
-            // portfolio_summary_output: Vec::new(),
-            portfolio_editor,
-            show_portfolio_editor: true,
-            show_api_settings: false,
-        }
+    // Only the samples of the range the chart shows
+    fn export_value_history_csv(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("portfolio_value.csv")
+            .save_file()
+        else {
+            return;
+        };
+        let indices = self
+            .value_history
+            .indices(self.value_chart_range, Utc::now());
+        self.error_message = Some(match self.value_history.export_to_csv(indices, &path) {
+            Ok(()) => format!("Chart data exported to {}", path.display()),
+            Err(e) => format!("Failed to export chart data: {}", e),
+        });
     }
 
-    fn get_config_path() -> PathBuf {
-        dirs::home_dir()
-            .unwrap_or_default()
-            .join(".portfolio_rebalancer.json")
+    fn export_tax_report(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("tax_report.csv")
+            .save_file()
+        else {
+            return;
+        };
+        self.error_message = Some(
+            match tax::export_gains_to_csv(&self.realized_gains, &path) {
+                Ok(()) => format!("Tax report exported to {}", path.display()),
+                Err(e) => format!("Failed to export tax report: {}", e),
+            },
+        );
     }
 
-    fn load_config(path: &PathBuf) -> Result<Config> {
-        if path.exists() {
-            let config_str = fs::read_to_string(path)?;
-            serde_json::from_str(&config_str).map_err(|e| anyhow!("Failed to parse config: {}", e))
-        } else {
-            Err(anyhow!("Config file not found at {:?}", path))
+    fn import_csv(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .pick_file()
+        else {
+            return;
+        };
+        match import_from_csv(&path) {
+            Ok(allocation) => {
+                self.portfolio_editor.load_allocation(&allocation);
+                self.hold_unsaved();
+                self.error_message = None;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Failed to import {}: {}", path.display(), e));
+            }
         }
     }
 
-    fn save_config(&self) -> Result<()> {
-        let config_json = serde_json::to_string_pretty(&self.config)?;
-        let mut file = fs::File::create(&self.config_path)?;
-        file.write_all(config_json.as_bytes())?;
+    fn discard_editor_changes(&mut self) {
+        self.portfolio_editor = PortfolioAllocationEditor::from_config(self.profiles.active());
+        self.dirty = false;
+        self.last_edit_instant = None;
+        self.history_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    fn undo_allocation(&mut self) -> Result<(), KinError> {
+        let Some(previous) = self.history_stack.pop() else {
+            return Ok(());
+        };
+        let current = self.restore_allocation(previous)?;
+        push_capped(&mut self.redo_stack, current);
+        Ok(())
+    }
+
+    fn redo_allocation(&mut self) -> Result<(), KinError> {
+        let Some(next) = self.redo_stack.pop() else {
+            return Ok(());
+        };
+        let current = self.restore_allocation(next)?;
+        push_capped(&mut self.history_stack, current);
         Ok(())
     }
 
-    fn start_backend(&mut self) -> Result<()> {
+    // Saves `allocation` as the active one, syncs the editor rows and returns the replaced allocation
+    fn restore_allocation(
+        &mut self,
+        allocation: PortfolioAllocation,
+    ) -> Result<PortfolioAllocation, KinError> {
+        self.portfolio_editor.load_allocation(&allocation);
+        let previous = std::mem::replace(
+            &mut self.profiles.active_mut().portfolio_allocation,
+            allocation,
+        );
+        self.save_config()?;
+        self.last_saved_instant = Some(Instant::now());
+        Ok(previous)
+    }
+
+    // Errors are left for the user to spot via the "Unsaved" badge; retrying
+    // waits for the next edit instead of hammering the disk every frame.
+    fn autosave(&mut self, ctx: &egui::Context) {
+        if !self.dirty {
+            return;
+        }
+        let Some(last_edit) = self.last_edit_instant else {
+            return;
+        };
+        let delay = if self.notes_edited {
+            NOTES_AUTOSAVE_DELAY
+        } else {
+            AUTOSAVE_DELAY
+        };
+        let idle = last_edit.elapsed();
+        if idle < delay {
+            ctx.request_repaint_after(delay - idle);
+            return;
+        }
         if let Err(e) = self.update_config_from_editor() {
-            self.error_message = Some(format!("Failed to save config before start: {}", e));
-            return Err(e);
+            log::warn!("Auto-save skipped: {}", e);
+            self.last_edit_instant = None;
         }
-        self.error_message = None; // Clear previous config errors
+    }
 
-        // 确保已保存配置
-        if let Err(e) = self.save_config() {
-            self.error_message = Some(format!("Failed to save config: {}", e));
-            return Err(e);
+    fn save_portfolio_config(&mut self) {
+        match self.update_config_from_editor() {
+            Ok(_) => self.error_message = Some("Portfolio config saved.".to_string()), // Use error field briefly
+            Err(KinError::ValidationErrors(errors)) => {
+                let mut lines: Vec<String> = errors.iter().map(|(_, msg)| msg.clone()).collect();
+                if errors
+                    .iter()
+                    .any(|(f, _)| matches!(f, FieldId::Allocation(_) | FieldId::AllocationTotal))
+                {
+                    lines.push(
+                        "Allocations are percentages of the total portfolio; USDT takes the rest."
+                            .to_string(),
+                    );
+                }
+                self.error_message = Some(lines.join("\n"));
+            }
+            Err(e) => self.error_message = Some(e.to_string()),
         }
+    }
 
-        // 在Windows上使用PowerShell启动后端
-        if cfg!(windows) {
-            let mut cmd = Command::new("powershell");
-            cmd.arg("-NoExit"); // 保持窗口打开
-            cmd.arg("-Command");
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if self.rebinding.is_some() {
+            self.capture_rebinding(ctx);
+            return;
+        }
+        let bindings = self.profiles.active().bindings;
+        for action in KeyAction::ALL {
+            if ctx.input_mut(|i| i.consume_shortcut(&bindings.get(action))) {
+                self.run_key_action(action, ctx);
+            }
+        }
+    }
 
-            // 构建Python命令
-            let python_cmd = format!(
-                "cd ..; python -m backend.main --config \"{}\"",
-                self.config_path.display()
-            );
+    fn run_key_action(&mut self, action: KeyAction, ctx: &egui::Context) {
+        match action {
+            KeyAction::StartStop if self.is_running => self.stop_backend_manually(),
+            KeyAction::StartStop => {
+                if self.backend_process.is_none() {
+                    self.start_backend_manually(ctx);
+                }
+            }
+            KeyAction::SaveConfig => self.save_portfolio_config(),
+            KeyAction::DryRun => {
+                if !self.dry_run_loading {
+                    self.start_dry_run(ctx);
+                }
+            }
+            KeyAction::SwitchToPortfolio => self.active_tab = Tab::PortfolioConfig,
+            KeyAction::SwitchToLog => self.active_tab = Tab::Log,
+            KeyAction::SwitchToHistory => self.active_tab = Tab::History,
+        }
+    }
+
+    // Takes the next key press as the new shortcut; Escape cancels. A modifier is
+    // required so bindings never swallow plain typing.
+    fn capture_rebinding(&mut self, ctx: &egui::Context) {
+        let Some(action) = self.rebinding else {
+            return;
+        };
+        let pressed = ctx.input_mut(|i| {
+            let pressed = i.events.iter().find_map(|event| match event {
+                egui::Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => Some((*key, *modifiers)),
+                _ => None,
+            });
+            if pressed.is_some() {
+                i.events
+                    .retain(|event| !matches!(event, egui::Event::Key { .. }));
+            }
+            pressed
+        });
+        let Some((key, pressed_modifiers)) = pressed else {
+            return;
+        };
+        if key == egui::Key::Escape {
+            self.rebinding = None;
+            return;
+        }
+        if !pressed_modifiers.command && !pressed_modifiers.alt {
+            return;
+        }
+        let mut modifiers = egui::Modifiers::NONE;
+        for (held, modifier) in [
+            (pressed_modifiers.command, egui::Modifiers::COMMAND),
+            (pressed_modifiers.alt, egui::Modifiers::ALT),
+            (pressed_modifiers.shift, egui::Modifiers::SHIFT),
+        ] {
+            if held {
+                modifiers |= modifier;
+            }
+        }
+        let shortcut = egui::KeyboardShortcut::new(modifiers, key);
+        self.rebinding = None;
+        let bindings = &mut self.profiles.active_mut().bindings;
+        if let Some(other) = bindings.conflict(action, shortcut) {
+            self.error_message = Some(format!(
+                "{} is already bound to \"{}\".",
+                format_shortcut(&shortcut),
+                other.label()
+            ));
+            return;
+        }
+        bindings.set(action, shortcut);
+        if let Err(e) = self.save_config() {
+            self.error_message = Some(format!("Failed to save config: {}", e));
+        }
+    }
 
-            cmd.arg(&python_cmd);
+    // Keys of one exchange do not work on the other, so the entered ones are
+    // checked again and balances come from the new account
+    fn set_exchange(&mut self, choice: ExchangeChoice) {
+        self.profiles.active_mut().exchange = choice;
+        self.exchange = None;
+        self.connectivity = None;
+        self.live_balances = None;
+        self.total_value_usdt = None;
+        self.value_history = ValueHistory::default();
+        self.api_key_error = credential_error(&self.api_key, choice, validate_api_key);
+        self.api_secret_error = credential_error(&self.api_secret, choice, validate_api_secret);
+        self.refresh_keyring_key();
+    }
 
-            // 启动进程
-            match cmd.spawn() {
-                Ok(_) => {
-                    // 不保存子进程的句柄，因为它在独立窗口中运行
-                    self.status = "Running (External)".to_string();
-                    self.is_running = true;
-                    self.error_message = None;
-                    println!("Backend started in external PowerShell window.");
-                    Ok(())
-                }
+    fn update_api_settings(&mut self) -> Result<(), KinError> {
+        if self.api_key.trim().is_empty() || self.api_secret.trim().is_empty() {
+            return Err(KinError::ApiAuth(
+                "API key and secret cannot be empty.".to_string(),
+            ));
+        }
+        let (key, secret) = (self.api_key.trim(), self.api_secret.trim());
+        let exchange = self.profiles.active().exchange;
+        validate_api_key(key, exchange).map_err(KinError::ApiAuth)?;
+        validate_api_secret(secret, exchange).map_err(KinError::ApiAuth)?;
+        if self.keyring_available {
+            match Config::store_secrets(&self.keyring_account(), key, secret) {
+                Ok(()) => self.keyring_api_key = key.to_string(),
                 Err(e) => {
-                    self.status = "Error".to_string();
-                    self.is_running = false;
-                    self.error_message = Some(format!("Failed to start backend: {}", e));
-                    Err(anyhow!("Failed to start backend: {}", e))
+                    log::warn!("System keychain unavailable ({}), using config file.", e);
+                    self.keyring_available = false;
                 }
             }
-        } else if cfg!(target_os = "linux") || cfg!(target_os = "macos") {
-            // 在Linux/macOS上使用终端启动后端
-            let terminal_cmd = if cfg!(target_os = "macos") {
-                "open -a Terminal"
+        }
+        if !self.keyring_available {
+            let config = self.profiles.active_mut();
+            config.api_key = key.to_string();
+            config.api_secret = Zeroizing::new(secret.to_string());
+            self.save_config()?;
+        }
+        self.api_key.zeroize();
+        self.api_secret.zeroize();
+        self.exchange = None;
+        let details = format!(
+            "{} key and secret stored in the {}",
+            self.profiles.active().exchange.label(),
+            if self.keyring_available {
+                "system keychain"
             } else {
-                "x-terminal-emulator" // Linux通用终端启动器
-            };
+                "config file"
+            }
+        );
+        self.audit(AuditAction::UpdateApiKey, details);
+        log::info!("API settings saved successfully.");
+        Ok(())
+    }
 
-            let mut cmd = Command::new(terminal_cmd);
+    // Pending editor edits are saved to the profile being left first; invalid
+    // ones keep it active so the user can fix them
+    fn switch_profile(&mut self, name: &str) -> Result<(), KinError> {
+        self.save_pending_edits()?;
+        let previous = self.profiles.active_name().to_string();
+        self.profiles.switch(name)?;
+        self.discard_editor_changes();
+        self.refresh_keyring_key();
+        // The new profile may use another display currency
+        self.display_prices.clear();
+        self.save_config()?;
+        self.audit(
+            AuditAction::ProfileSwitch,
+            format!("\"{}\" → \"{}\"", previous, name),
+        );
+        log::info!("Switched to profile \"{}\".", name);
+        Ok(())
+    }
 
-            // 构建要在终端中运行的命令
-            let python_cmd = format!(
-                "cd \"$(dirname \"$(dirname \"$0\")\")\" && python -m backend.main --config \"{}\"",
-                self.config_path.display()
-            );
+    fn save_pending_edits(&mut self) -> Result<(), KinError> {
+        if self.dirty {
+            self.update_config_from_editor()?;
+        }
+        Ok(())
+    }
 
-            if cfg!(target_os = "macos") {
-                cmd.arg("-e");
-                cmd.arg(&python_cmd);
-            } else {
-                cmd.arg("-e");
-                cmd.arg(&format!("bash -c '{}'", python_cmd));
-            }
+    // New profiles start as a copy of the active one, pending edits included
+    fn add_profile(&mut self) -> Result<(), KinError> {
+        let name = self.new_profile_name.trim().to_string();
+        if name.is_empty() {
+            return Err(KinError::Profile(
+                "Profile name cannot be empty.".to_string(),
+            ));
+        }
+        if self.profiles.get(&name).is_some() {
+            return Err(KinError::Profile(format!(
+                "Profile \"{}\" already exists.",
+                name
+            )));
+        }
+        self.save_pending_edits()?;
+        let account = self.keyring_account();
+        self.profiles
+            .add(name.clone(), self.profiles.active().clone());
+        self.new_profile_name.clear();
+        self.switch_profile(&name)?;
+        self.copy_keyring_secrets(&account);
+        self.refresh_keyring_key();
+        Ok(())
+    }
 
-            // 启动进程
-            match cmd.spawn() {
-                Ok(_) => {
-                    // 不保存子进程的句柄
-                    self.status = "Running (External)".to_string();
-                    self.is_running = true;
-                    self.error_message = None;
-                    println!("Backend started in external terminal window.");
-                    Ok(())
-                }
-                Err(e) => {
-                    self.status = "Error".to_string();
-                    self.is_running = false;
-                    self.error_message = Some(format!("Failed to start backend: {}", e));
-                    Err(anyhow!("Failed to start backend: {}", e))
+    fn delete_active_profile(&mut self) -> Result<(), KinError> {
+        let name = self.profiles.active_name().to_string();
+        self.profiles.delete(&name)?;
+        self.discard_editor_changes();
+        self.refresh_keyring_key();
+        self.save_config()?;
+        log::info!("Deleted profile \"{}\".", name);
+        Ok(())
+    }
+
+    // The backend of the portfolio left behind keeps running detached. The app is
+    // re-pointed at the other portfolio's files and takes over its backend if it
+    // runs; pending editor edits are discarded as with profiles.
+    fn select_portfolio(&mut self, index: usize, ctx: &egui::Context) {
+        if index == self.portfolios.selected()
+            || self
+                .portfolios
+                .portfolios
+                .get(index)
+                .is_none_or(|p| p.locked)
+        {
+            return;
+        }
+        let process = self.backend_process.take();
+        let output = self.backend_sender.take().zip(self.backend_receiver.take());
+        self.disconnect_backend_socket();
+        let current = self.portfolios.selected_portfolio_mut();
+        current.config = self.profiles.active().clone();
+        current.status = if process.is_some() {
+            portfolio::BackendStatus::Running
+        } else {
+            portfolio::BackendStatus::Stopped
+        };
+        current.process = process;
+        current.output = output;
+
+        self.portfolios.select(index);
+        let selected = self.portfolios.selected_portfolio_mut();
+        let (process, output) = (selected.process.take(), selected.output.take());
+        let status = std::mem::take(&mut selected.status);
+        let name = selected.name.clone();
+        self.config_path = selected.config_path.clone();
+        self.profiles_path = ProfileManager::path_for(&self.config_path);
+        self.profiles = load_profiles(&self.config_path, self.passphrase.as_deref());
+        self.config_watcher = ConfigWatcher::new(&self.config_path, ctx.clone())
+            .map_err(|e| log::warn!("{}; external edits will not be detected.", e))
+            .ok();
+        self.external_config = None;
+        self.config_backups = None;
+        self.db = open_history_db(&self.config_path);
+        self.load_history();
+        self.backend_endpoint = self.portfolios.endpoint(index);
+        self.backend_client = BackendClient::new(&self.backend_endpoint.base_url())
+            .with_token(&self.backend_endpoint.http_token);
+
+        // Balances and plans of the other portfolio do not apply here
+        self.exchange = None;
+        self.connectivity = None;
+        self.live_balances = None;
+        self.live_allocations = None;
+        self.total_value_usdt = None;
+        self.value_history = ValueHistory::default();
+        self.positions.clear();
+        self.funding_rates.clear();
+        self.display_prices.clear();
+        self.dry_run = None;
+        self.what_if = None;
+        self.peak_value_usdt = 0.0;
+        self.halted_due_to_drawdown = false;
+        self.restart_count = 0;
+        self.next_restart_at = None;
+        self.error_message = None;
+        self.discard_editor_changes();
+        self.refresh_keyring_key();
+        self.refresh_volatilities();
+
+        match process {
+            Some(child) => {
+                if let Some((sender, receiver)) = output {
+                    self.backend_sender = Some(sender);
+                    self.backend_receiver = Some(receiver);
                 }
+                self.backend_process = Some(child);
+                self.is_running = true;
+                self.status = "Running".to_string();
+                self.connect_backend_socket(ctx);
+            }
+            None => {
+                self.is_running = false;
+                self.status = status.label();
             }
-        } else {
-            Err(anyhow!("Unsupported operating system"))
         }
+        log::info!("Switched to portfolio \"{}\".", name);
     }
 
-    fn stop_backend(&mut self) {
-        // 由于后端现在运行在独立窗口中，我们只需更新状态
-        self.status = "Stopped (Close Terminal to Stop Backend)".to_string();
-        self.is_running = false;
-        self.backend_process = None;
-        println!("To completely stop the backend, close the terminal window.");
+    // New portfolios start as a copy of the selected one, with the backend stopped
+    fn add_portfolio(&mut self, name: &str, ctx: &egui::Context) -> Result<(), KinError> {
+        let account = self.keyring_account();
+        let index = self.portfolios.add(name, self.profiles.active().clone())?;
+        self.select_portfolio(index, ctx);
+        self.copy_keyring_secrets(&account);
+        self.refresh_keyring_key();
+        Ok(())
     }
 
-    fn update_config_from_editor(&mut self) -> Result<()> {
-        let btc = self
-            .portfolio_editor
-            .BTC_USDT_allocation
-            .parse::<f64>()
-            .map_err(|_| anyhow!("Invalid BTC allocation"))?;
-        let eth = self
-            .portfolio_editor
-            .ETH_USDT_allocation
-            .parse::<f64>()
-            .map_err(|_| anyhow!("Invalid ETH allocation"))?;
-        let ltc = self
-            .portfolio_editor
-            .LTC_USDT_allocation
-            .parse::<f64>()
-            .map_err(|_| anyhow!("Invalid LTC allocation"))?;
-
-        if btc < 0.0 || eth < 0.0 || ltc < 0.0 {
-            return Err(anyhow!("Allocations cannot be negative."));
+    // Log lines and exits of the backends running for other portfolios
+    fn process_detached_portfolios(&mut self) {
+        let selected = self.portfolios.selected();
+        let mut lines = Vec::new();
+        for (i, portfolio) in self.portfolios.portfolios.iter_mut().enumerate() {
+            if i == selected || portfolio.process.is_none() {
+                continue;
+            }
+            for line in portfolio.poll_detached() {
+                lines.push(format!("[{}] {}", portfolio.name, line));
+            }
+            if portfolio.process.is_none() {
+                log::warn!(
+                    "Backend of portfolio \"{}\" {}.",
+                    portfolio.name,
+                    portfolio.status.label().to_lowercase()
+                );
+            }
         }
-        let crypto_total = btc + eth + ltc;
-        if crypto_total > 100.0 {
-            return Err(anyhow!(
-                "Sum of BTC, ETH, LTC allocations ({:.1}%) cannot exceed 100%.",
-                crypto_total
-            ));
+        for line in lines {
+            self.push_log_line(line);
         }
+    }
 
-        // USDT allocation is calculated automatically
-        let usdt = (100.0 - crypto_total).max(0.0);
-
-        let threshold = self
-            .portfolio_editor
-            .rebalance_threshold
-            .parse::<f64>()
-            .map_err(|_| anyhow!("Invalid rebalance threshold"))?;
-        if threshold < 0.0 {
-            return Err(anyhow!("Rebalance threshold cannot be negative."));
+    // Every backend is stopped, in the order the portfolios are listed
+    fn stop_all_portfolios(&mut self) {
+        let selected = self.portfolios.selected();
+        for i in 0..self.portfolios.portfolios.len() {
+            if i == selected {
+                if self.backend_process.is_some() {
+                    self.stop_backend();
+                }
+            } else {
+                self.portfolios.portfolios[i].stop();
+            }
         }
+    }
 
-        let min_inflow = self
-            .portfolio_editor
-            .min_usdt_inflow
-            .parse::<f64>()
-            .map_err(|_| anyhow!("Invalid minimum USDT inflow"))?;
-        if min_inflow < 0.0 {
-            return Err(anyhow!("Minimum USDT inflow cannot be negative."));
+    fn target_slices(&self) -> Vec<(String, f64, Color32)> {
+        let allocation = &self.profiles.active().portfolio_allocation;
+        let mut slices: Vec<_> = allocation
+            .assets
+            .iter()
+            .enumerate()
+            .map(|(i, a)| (a.symbol.clone(), a.target_pct, slice_color(i)))
+            .collect();
+        slices.push((USDT.to_string(), allocation.usdt(), USDT_COLOR));
+        slices
+    }
+
+    // Saved targets per tag, as in `PortfolioAllocation::by_tag`
+    fn tag_slices(&self) -> Vec<(String, f64, Color32)> {
+        let allocation = &self.profiles.active().portfolio_allocation;
+        let mut slices: Vec<_> = allocation
+            .by_tag()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (tag, pct))| (tag, pct, slice_color(i)))
+            .collect();
+        slices.push((USDT.to_string(), allocation.usdt(), USDT_COLOR));
+        slices
+    }
+
+    // Colours line up with `target_slices`; held assets without a target are appended
+    fn live_slices(&self) -> Option<Vec<(String, f64, Color32)>> {
+        let live = self.live_allocations.as_ref()?;
+        let assets = &self.profiles.active().portfolio_allocation.assets;
+        let mut slices: Vec<_> = assets
+            .iter()
+            .enumerate()
+            .map(|(i, a)| {
+                let pct = live.get(&a.symbol).copied().unwrap_or(0.0);
+                (a.symbol.clone(), pct, slice_color(i))
+            })
+            .collect();
+        let mut untracked: Vec<_> = live
+            .iter()
+            .filter(|(symbol, _)| *symbol != USDT && !assets.iter().any(|a| &a.symbol == *symbol))
+            .collect();
+        untracked.sort_by(|a, b| a.0.cmp(b.0));
+        for (offset, (symbol, pct)) in untracked.into_iter().enumerate() {
+            slices.push((symbol.clone(), *pct, slice_color(assets.len() + offset)));
         }
+        let usdt = live.get(USDT).copied().unwrap_or(0.0);
+        slices.push((USDT.to_string(), usdt, USDT_COLOR));
+        Some(slices)
+    }
 
-        self.config.portfolio_allocation = PortfolioAllocation {
-            BTC_USDT: btc,
-            ETH_USDT: eth,
-            LTC_USDT: ltc,
-            USDT: usdt,
+    // The JSON file is left in place; the TOML file is preferred from now on
+    // Runs on the saved config of the active profile; a year of daily closes takes milliseconds
+    fn run_backtest(&mut self) -> Result<(), KinError> {
+        let Some(prices_csv) = self.backtest_csv.clone() else {
+            return Err(KinError::Backtest("choose a price CSV first".to_string()));
+        };
+        let parse_date = |text: &str| {
+            NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").map_err(|_| {
+                KinError::Backtest(format!("\"{}\" is not a YYYY-MM-DD date", text.trim()))
+            })
+        };
+        let bt = BacktestConfig {
+            prices_csv,
+            start_date: parse_date(&self.backtest_start)?,
+            end_date: parse_date(&self.backtest_end)?,
+            initial_usdt: self
+                .backtest_initial_usdt
+                .trim()
+                .parse()
+                .map_err(|_| KinError::Backtest("initial USDT is not a number".to_string()))?,
         };
-        self.config.rebalance_threshold = threshold;
-        self.config.min_usdt_inflow = min_inflow;
-        self.portfolio_editor.USDT_allocation = format!("{:.1}", usdt); // Update display value
+        self.backtest_result = Some(backtest::run_backtest(self.profiles.active(), &bt)?);
+        Ok(())
+    }
 
+    fn convert_config_to_toml(&mut self) -> Result<(), KinError> {
+        self.config_path = self
+            .config_path
+            .with_extension(ConfigFormat::Toml.extension());
+        self.profiles.active_mut().format = ConfigFormat::Toml;
         self.save_config()?;
-        println!("Configuration saved successfully.");
+        log::info!("Config converted to TOML: {:?}", self.config_path);
         Ok(())
     }
+}
 
-    fn update_api_settings(&mut self) -> Result<()> {
-        if self.api_key.trim().is_empty() || self.api_secret.trim().is_empty() {
-            return Err(anyhow!("API key and secret cannot be empty."));
+// Non-negative number, or an entry in `errors` (the returned 0.0 is then unused)
+fn parse_setting(
+    text: &str,
+    field: FieldId,
+    label: &str,
+    errors: &mut Vec<(FieldId, String)>,
+) -> f64 {
+    match validate_field(field, text) {
+        Some(error) => {
+            errors.push((field, format!("{}: {}", label, error)));
+            0.0
         }
-        // TODO: Add encryption here if needed before saving
-        self.config.api_key = self.api_key.trim().to_string();
-        self.config.api_secret = self.api_secret.trim().to_string();
-        self.save_config()?;
-        self.api_key.clear();
-        self.api_secret.clear();
-        println!("API settings saved successfully.");
-        Ok(())
+        None => text.trim().parse().unwrap_or(0.0),
+    }
+}
+
+fn open_history_db(config_path: &Path) -> Option<Database> {
+    Database::open_for_config(config_path)
+        .map_err(|e| {
+            log::warn!(
+                "Failed to open history database ({:?}): {}",
+                Database::path_for(config_path),
+                e
+            )
+        })
+        .ok()
+}
+
+// The profiles beside `config_path`, or a single profile holding the config
+// file (or the defaults) when there are none yet
+fn load_profiles(config_path: &Path, passphrase: Option<&str>) -> ProfileManager {
+    let profiles_path = ProfileManager::path_for(config_path);
+    ProfileManager::load_with_passphrase(&profiles_path, passphrase).unwrap_or_else(|e| {
+        log::warn!(
+            "Failed to load profiles ({:?}): {}, starting from config file.",
+            profiles_path,
+            e
+        );
+        let config = Config::load_with_passphrase(config_path, passphrase).unwrap_or_else(|e| {
+            log::warn!(
+                "Failed to load config ({:?}): {}, using default.",
+                config_path,
+                e
+            );
+            Config::default()
+        });
+        ProfileManager::new(DEFAULT_PROFILE.to_string(), config)
+    })
+}
+
+/// Checks a single editor field as it is typed; None when the value is acceptable.
+/// Cross-field rules such as the allocation total are left to `validate`.
+fn validate_field(field: FieldId, value: &str) -> Option<String> {
+    let value = value.trim();
+    // An empty per-asset threshold falls back to the global one
+    if value.is_empty() && matches!(field, FieldId::AssetThreshold(_)) {
+        return None;
+    }
+    let Ok(number) = value.parse::<f64>() else {
+        return Some("Not a number.".to_string());
+    };
+    // `parse` also accepts "nan" and "inf", which serde_json cannot write back
+    if !number.is_finite() {
+        return Some("Must be a finite number.".to_string());
+    }
+    if number < 0.0 {
+        return Some("Cannot be negative.".to_string());
+    }
+    match field {
+        FieldId::Allocation(_)
+        | FieldId::RebalanceThreshold
+        | FieldId::AssetThreshold(_)
+        | FieldId::TakerFeeRate
+        | FieldId::MakerFeeRate
+            if number > 100.0 =>
+        {
+            Some("Cannot exceed 100%.".to_string())
+        }
+        _ => None,
+    }
+}
+
+// Fee rate fraction as the percentage shown in the editor, without float noise
+fn fee_pct_text(rate: f64) -> String {
+    let pct = format!("{:.6}", rate * 100.0);
+    pct.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn field_error(errors: &[(FieldId, String)], field: FieldId) -> Option<&String> {
+    errors.iter().find(|(f, _)| *f == field).map(|(_, e)| e)
+}
+
+fn set_field_error(errors: &mut Vec<(FieldId, String)>, field: FieldId, error: Option<String>) {
+    errors.retain(|(f, _)| *f != field);
+    if let Some(error) = error {
+        errors.push((field, error));
+    }
+}
+
+/// Text field validated with `validate_field` on every change, outlined in red
+/// with the problem underneath while it is invalid. `flagged` marks a field the
+/// last save rejected. Returns whether the text changed.
+fn validated_text_edit(
+    ui: &mut egui::Ui,
+    text: &mut String,
+    field: FieldId,
+    flagged: bool,
+    live_errors: &mut Vec<(FieldId, String)>,
+    hint: &str,
+    width: f32,
+) -> bool {
+    ui.vertical(|ui| {
+        let invalid = flagged || field_error(live_errors, field).is_some();
+        let changed = field_frame(invalid)
+            .show(ui, |ui| {
+                ui.add(
+                    TextEdit::singleline(text)
+                        .hint_text(hint)
+                        .desired_width(width),
+                )
+            })
+            .inner
+            .changed();
+        if changed {
+            set_field_error(live_errors, field, validate_field(field, text));
+        }
+        if let Some(error) = field_error(live_errors, field) {
+            ui.label(RichText::new(error).small().color(Color32::RED));
+        }
+        changed
+    })
+    .inner
+}
+
+// "≈ 0.000100 BTC" next to a USDT field of the editor
+fn converted_hint(
+    ui: &mut egui::Ui,
+    value_usdt: &str,
+    currency: DisplayCurrency,
+    prices: &HashMap<String, f64>,
+    locale: Locale,
+) {
+    if currency == DisplayCurrency::Usdt || currency.price(prices).is_none() {
+        return;
+    }
+    if let Ok(value) = value_usdt.trim().parse::<f64>() {
+        ui.weak(format!(
+            "≈ {}",
+            currency::format_amount(value, currency, prices, locale)
+        ));
+    }
+}
+
+// An empty field is not flagged until the user tries to save it
+fn credential_error(
+    value: &str,
+    exchange: ExchangeChoice,
+    validate: fn(&str, ExchangeChoice) -> Result<(), String>,
+) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        validate(value, exchange).err()
+    }
+}
+
+// Outline for an editor field; red while it is invalid
+fn field_frame(invalid: bool) -> egui::Frame {
+    let stroke = if invalid {
+        Stroke::new(1.0, Color32::RED)
+    } else {
+        Stroke::NONE
+    };
+    egui::Frame::NONE.inner_margin(1.0).stroke(stroke)
+}
+
+// "Total: X% / 100%" bar of the asset rows; USDT takes whatever they leave
+fn allocation_total_gauge(ui: &mut egui::Ui, total: f64, precision: u8, locale: Locale) {
+    let text = format!(
+        "Total: {}% / 100%",
+        format_number(total, precision as usize, locale)
+    );
+    AllocationGauge::new(total, text).show(ui).on_hover_text(format!(
+        "Sum of the asset targets. USDT is the remainder, 100% minus this sum ({}%), \
+         so the assets can total at most 100%. Green from 95 to 100%, yellow within 5 points of that, red otherwise.",
+        format_number((100.0 - total).max(0.0), precision as usize, locale)
+    ));
+}
+
+// Centred yes/cancel window; Some(true) once confirmed, Some(false) on Cancel
+fn confirm_window(ctx: &egui::Context, title: &str, message: &str, confirm: &str) -> Option<bool> {
+    let mut action = None;
+    egui::Window::new(title)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label(message);
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                if ui.button(confirm).clicked() {
+                    action = Some(true);
+                }
+                if ui.button("Cancel").clicked() {
+                    action = Some(false);
+                }
+            });
+        });
+    action
+}
+
+fn push_capped(stack: &mut Vec<PortfolioAllocation>, allocation: PortfolioAllocation) {
+    if stack.len() == MAX_UNDO_STEPS {
+        stack.remove(0);
+    }
+    stack.push(allocation);
+}
+
+// `live - target` in percentage points, coloured against the rebalance threshold
+fn drift_text(drift: Option<f64>, threshold: f64) -> RichText {
+    let Some(drift) = drift else {
+        return RichText::new("—").weak();
+    };
+    let color = if drift.abs() > threshold {
+        Color32::RED
+    } else if drift.abs() >= threshold * DRIFT_WARNING_RATIO {
+        Color32::YELLOW
+    } else {
+        Color32::GREEN
+    };
+    RichText::new(format!("{:+.1}%", drift)).color(color)
+}
+
+// 8h funding rate in percent; positive rates are paid by the long positions held here
+fn funding_text(rate: Option<f64>) -> RichText {
+    match rate {
+        Some(rate) => {
+            let color = if rate > 0.0 {
+                Color32::RED
+            } else {
+                Color32::GREEN
+            };
+            RichText::new(format!("{:+.4}%", rate * 100.0)).color(color)
+        }
+        None => RichText::new("—").weak(),
     }
 }
 
+// show() talks to the notification service synchronously; keep it off the UI thread
+fn show_notification(body: String) {
+    thread::spawn(move || {
+        if let Err(e) = notify_rust::Notification::new()
+            .summary("KIN Rebalancer")
+            .body(&body)
+            .show()
+        {
+            log::warn!("Failed to show notification: {}", e);
+        }
+    });
+}
+
 // --- eframe::App Implementation ---
 impl eframe::App for RebalancerApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // --- Check if backend process exited unexpectedly ---
-        if self.is_running {
-            let mut process_exited = false;
-            let mut exit_status_str = String::new();
-
-            if let Some(child) = &mut self.backend_process {
-                match child.try_wait() {
-                    Ok(Some(status)) => {
-                        // Process has exited
-                        println!(
-                            "Backend process exited unexpectedly with status: {}",
-                            status
-                        );
-                        exit_status_str = format!("Stopped (Exited: {})", status);
-                        process_exited = true;
-                    }
-                    Ok(None) => { /* Process still running, do nothing */ }
-                    Err(e) => {
-                        // Error trying to check status
-                        eprintln!("Error checking backend process status: {}", e);
-                        exit_status_str = format!("Error ({})", e);
-                        process_exited = true; // Treat error as if it exited
-                    }
-                }
-            } else {
-                // Should not happen if is_running is true, but handle defensively
-                println!("Inconsistent state: is_running=true but backend_process is None.");
-                exit_status_str = "Error (Inconsistent)".to_string();
-                process_exited = true;
-            }
+        self.handle_shutdown_signal(ctx);
+        // --- Process backend output from channel ---
+        self.process_backend_messages();
+        self.process_detached_portfolios();
+        self.process_log_warnings();
+        self.process_backend_socket(ctx);
+        self.process_status_results();
+        self.process_manual_rebalance_results();
+        self.process_balance_results();
+        self.process_poll_results();
+        self.process_market_cap_results();
+        self.process_update_results();
+        self.process_connectivity_results();
+        self.process_simulation_result();
+        self.process_position_results();
+        self.process_webhook_test_results();
+        self.process_symbol_list();
+        self.process_dry_run_results();
+        self.process_order_results();
+        self.process_tray_commands(ctx);
+        self.process_config_changes();
+        self.run_pending_restart(ctx);
+        self.handle_close_request(ctx);
+        self.autosave(ctx);
+        self.poll_backend_status(ctx);
+        self.poll_portfolio(ctx);
+        self.check_connectivity(ctx);
+        self.publish_health_state();
+        self.apply_theme(ctx);
 
-            if process_exited {
-                self.is_running = false;
-                self.backend_process = None; // Clear the handle
-                self.status = exit_status_str;
-                // Optionally add to error_message:
-                // self.error_message = Some("Backend process stopped unexpectedly.".to_string());
-                ctx.request_repaint(); // Request repaint to show updated status
+        // Ctrl+Z / Ctrl+Y (Cmd on macOS); focused text fields keep their own undo
+        if !ctx.wants_keyboard_input() {
+            let (undo, redo) = ctx.input(|i| {
+                (
+                    i.modifiers.command && i.key_pressed(egui::Key::Z),
+                    i.modifiers.command && i.key_pressed(egui::Key::Y),
+                )
+            });
+            let result = if undo {
+                self.undo_allocation()
+            } else if redo {
+                self.redo_allocation()
+            } else {
+                Ok(())
+            };
+            if let Err(e) = result {
+                self.error_message = Some(e.to_string());
             }
         }
+        ctx.input(|i| {
+            let viewport = i.viewport();
+            if viewport.minimized == Some(true) {
+                return;
+            }
+            if let (Some(outer), Some(inner)) = (viewport.outer_rect, viewport.inner_rect) {
+                self.window_geometry = Some((outer.min, inner.size()));
+            }
+        });
 
-        // --- Removed: Processing backend output from channel ---
+        if self.show_wizard {
+            self.show_setup_wizard(ctx);
+            return;
+        }
+        self.handle_shortcuts(ctx);
 
         // --- UI Definition ---
+        self.show_navigation(ctx);
+        let locale = self.locale();
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.vertical_centered(|ui| {
                 ui.heading("KIN Portfolio Rebalancer (TestNet Version)");
+                // 醒目标识，避免误以为是真实交易
+                if self.profiles.active().paper_trading {
+                    let badge = RichText::new(" PAPER TRADING ").strong().color(Color32::BLACK).background_color(Color32::from_rgb(255, 140, 0));
+                    ui.label(badge).on_hover_text("Orders are simulated; no real money is used.");
+                }
             });
             ui.add_space(15.0);
 
@@ -360,15 +4343,138 @@ impl eframe::App for RebalancerApp {
                 ui.label("Status:");
                 let status_color = match self.status.as_str() {
                     "Running" => Color32::GREEN,
-                    "Starting" => Color32::YELLOW,
+                    "Starting" | "Paused" => Color32::YELLOW,
                     s if s.starts_with("Error") => Color32::RED,
                     s if s.starts_with("Stopped") => Color32::GRAY,
                     _ => Color32::LIGHT_GRAY,
                 };
                 ui.colored_label(status_color, &self.status);
+                self.connectivity_dot(ui);
+                ui.separator();
+                ui.label("Portfolio Value:");
+                match self.total_value_usdt {
+                    Some(total) => {
+                        let (text, usdt) = self.amount_text(total);
+                        let response = ui.strong(text);
+                        if let Some(usdt) = usdt { response.on_hover_text(usdt); }
+                    }
+                    None => { ui.weak("—"); }
+                }
+                if let Some(balances) = &self.live_balances {
+                    let config = self.profiles.active();
+                    let target = config.target_allocation(&self.market_data);
+                    let score = metrics::portfolio_health_score(balances, &target, |symbol| config.threshold_for(symbol));
+                    let score_color = if score >= metrics::HEALTH_GOOD {
+                        Color32::GREEN
+                    } else if score >= metrics::HEALTH_FAIR {
+                        Color32::YELLOW
+                    } else {
+                        Color32::RED
+                    };
+                    ui.separator();
+                    ui.label("Health:");
+                    ui.label(RichText::new(score.to_string()).size(22.0).strong().color(score_color)).on_hover_text(format!(
+                        "100 minus the percentage points each asset drifts beyond its rebalance threshold ({}% unless overridden), clamped to 0–100.\nGreen from {}, yellow from {}, red below.",
+                        format_number(config.rebalance_threshold, 1, locale), metrics::HEALTH_GOOD, metrics::HEALTH_FAIR
+                    ));
+                    let can_rebalance = score < metrics::HEALTH_FAIR && self.is_running && !self.manual_rebalance_loading;
+                    if ui.add_enabled(can_rebalance, Button::new("Rebalance Now").small())
+                        .on_hover_text("Rebalance right away; available while the backend runs and the score is red")
+                        .on_disabled_hover_text(format!("Available while the backend runs and the score is below {}", metrics::HEALTH_FAIR))
+                        .clicked()
+                    {
+                        self.trigger_manual_rebalance(ctx);
+                    }
+                }
+                if let Some(ratio) = self.margin_ratio() {
+                    let alert = self.profiles.active().margin_alert_threshold_pct;
+                    let text = RichText::new(format!("{}%", format_number(ratio, 1, locale)));
+                    ui.separator();
+                    ui.label("Margin:");
+                    ui.label(if ratio < alert { text.color(Color32::RED).strong() } else { text })
+                        .on_hover_text(format!("Free margin as a share of the total position value; alert below {}%", format_number(alert, 1, locale)));
+                }
+                if let Some(at) = self.last_updated {
+                    ui.separator();
+                    ui.weak(format!("Last updated {}s ago", at.elapsed().as_secs()));
+                    ctx.request_repaint_after(Duration::from_secs(1));
+                }
             });
+            if !self.is_running {
+                self.rebalance_progress = None;
+            }
+            if let Some(progress) = &self.rebalance_progress {
+                ui.add(egui::ProgressBar::new(progress.completed_trades as f32 / progress.total_trades as f32)
+                    .text(format!("Rebalancing: {}/{} trades", progress.completed_trades, progress.total_trades)));
+                if let Some(asset) = &progress.current_asset {
+                    ui.weak(format!("Placing order for {}…", asset));
+                }
+            }
             ui.add_space(5.0);
 
+            let mut dismiss_update = false;
+            if let Some(release) = &self.available_update {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(format!("Update available: v{} →", release.version)).strong());
+                    ui.hyperlink_to("Download", &release.url);
+                    if ui.small_button("✖").on_hover_text("Dismiss until the next check").clicked() {
+                        dismiss_update = true;
+                    }
+                });
+                ui.add_space(5.0);
+            }
+            if dismiss_update {
+                self.available_update = None;
+            }
+
+            if self.halted_due_to_drawdown {
+                let drawdown = metrics::drawdown_pct(self.peak_value_usdt, self.total_value_usdt.unwrap_or(self.peak_value_usdt));
+                ui.horizontal(|ui| {
+                    ui.colored_label(Color32::RED, RichText::new(format!("DRAWDOWN HALT: portfolio down {}% from peak. Click to resume.", format_number(drawdown, 1, locale))).strong());
+                    if ui.button("Resume Rebalancing").on_hover_text("Resume trading and measure drawdowns from the current value").clicked() {
+                        self.resume_after_drawdown();
+                    }
+                });
+                ui.add_space(5.0);
+            }
+
+            // 自动重启次数用尽后常驻提示，直到用户重置
+            if self.restart_count >= MAX_RESTARTS && !self.is_running {
+                ui.horizontal(|ui| {
+                    ui.colored_label(Color32::RED, format!("Backend crashed {} times in a row; automatic restart stopped.", MAX_RESTARTS));
+                    if ui.button("Reset Restart Counter").clicked() {
+                        self.restart_count = 0;
+                    }
+                });
+                ui.add_space(5.0);
+            } else if let Some(at) = self.next_restart_at {
+                ui.colored_label(Color32::YELLOW, format!("Restarting backend in {}s (attempt {}/{})", at.saturating_duration_since(Instant::now()).as_secs() + 1, self.restart_count, MAX_RESTARTS));
+                ui.add_space(5.0);
+            }
+
+            if let Some(config) = &self.external_config {
+                let overwrites_edits = self.unsaved_edits_differ(config);
+                ui.horizontal(|ui| {
+                    ui.colored_label(Color32::YELLOW, "Config file changed on disk");
+                    if self.confirm_reload {
+                        ui.colored_label(Color32::RED, "You have unsaved changes; reloading will overwrite them.");
+                    }
+                    let label = if self.confirm_reload { "Reload Anyway" } else { "Reload" };
+                    if ui.button(label).clicked() {
+                        if overwrites_edits && !self.confirm_reload {
+                            self.confirm_reload = true;
+                        } else if let Err(e) = self.reload_external_config() {
+                            self.error_message = Some(format!("Failed to reload config: {}", e));
+                        }
+                    }
+                    if ui.button("Ignore").clicked() {
+                        self.external_config = None;
+                        self.confirm_reload = false;
+                    }
+                });
+                ui.add_space(5.0);
+            }
+
             // Error Message Display
             if let Some(error) = &self.error_message {
                 ui.colored_label(Color32::RED, error);
@@ -383,25 +4489,62 @@ impl eframe::App for RebalancerApp {
                 if !self.is_running {
                     let start_button = ui.add_enabled(self.backend_process.is_none(), Button::new("START Rebalancer"));
                     if start_button.clicked() {
-                        self.status = "Starting".to_string();
-                        match self.start_backend() {
-                            Ok(_) => { /* Status updated in start_backend */ }
-                            Err(_) => { /* Status updated in start_backend */ }
+                        if self.dirty {
+                            self.pending_start_confirm = true;
+                        } else {
+                            self.start_backend_manually(ctx); // Status updated in start_backend
                         }
                     }
-                } else {
-                    if ui.button("STOP Rebalancer").clicked() {
-                        self.stop_backend(); // Status updated in stop_backend
-                    }
+                } else if ui.button("STOP Rebalancer").clicked() {
+                    self.pending_stop_confirm = true;
+                }
+                if ui.add_enabled(!self.dry_run_loading, Button::new("Dry Run")).on_hover_text("Preview the trades a rebalance would place now").clicked() {
+                    self.start_dry_run(ctx);
                 }
+                if self.dry_run_loading { ui.spinner(); }
+                if ui.add_enabled(self.is_running && !self.manual_rebalance_loading, Button::new("Rebalance Now")).on_hover_text("Ask the running backend to rebalance to the target right away, ignoring the threshold").clicked() {
+                    self.trigger_manual_rebalance(ctx);
+                }
+                if self.manual_rebalance_loading { ui.spinner(); }
                 ui.separator();
-                if ui.selectable_label(self.show_api_settings, "API Settings").clicked() {
-                    self.show_api_settings = true;
-                    self.show_portfolio_editor = false;
+                let (icon, other) = match ctx.theme() {
+                    egui::Theme::Dark => ("☀", Theme::Light),
+                    egui::Theme::Light => ("🌙", Theme::Dark),
+                };
+                if ui.button(icon).on_hover_text(format!("Switch to the {} theme", other.label().to_lowercase())).clicked() {
+                    self.set_theme(other);
+                }
+            });
+            ui.add_space(5.0);
+
+            // Profile switcher
+            ui.horizontal(|ui| {
+                ui.label("Profile:");
+                let mut selected_profile = None;
+                egui::ComboBox::from_id_salt("profile_selector")
+                    .selected_text(self.profiles.active_name())
+                    .show_ui(ui, |ui| {
+                        for name in self.profiles.names() {
+                            if ui.selectable_label(name == self.profiles.active_name(), name).clicked() {
+                                selected_profile = Some(name.to_string());
+                            }
+                        }
+                    });
+                if let Some(name) = selected_profile.filter(|n| n != self.profiles.active_name()) {
+                    if let Err(e) = self.switch_profile(&name) {
+                        self.error_message = Some(e.to_string());
+                    }
+                }
+                ui.add(TextEdit::singleline(&mut self.new_profile_name).hint_text("New profile").desired_width(90.0));
+                if ui.button("Add").clicked() {
+                    if let Err(e) = self.add_profile() {
+                        self.error_message = Some(e.to_string());
+                    }
                 }
-                if ui.selectable_label(self.show_portfolio_editor, "Portfolio Config").clicked() {
-                    self.show_portfolio_editor = true;
-                    self.show_api_settings = false;
+                if ui.button("Delete").on_hover_text("Delete the active profile").clicked() {
+                    if let Err(e) = self.delete_active_profile() {
+                        self.error_message = Some(e.to_string());
+                    }
                 }
             });
             ui.add_space(10.0);
@@ -409,101 +4552,1428 @@ impl eframe::App for RebalancerApp {
             ui.add_space(10.0);
 
             // Conditional UI Sections (Portfolio Editor / API Settings)
-            if self.show_portfolio_editor {
+            if self.active_tab == Tab::PortfolioConfig {
                 ui.group(|ui| {
                      ui.heading("Portfolio Allocation (投资组合配置)");
                      ui.label("Target percentages for 3x leveraged pairs and USDT.");
+                     ui.horizontal(|ui| {
+                         ui.checkbox(&mut self.portfolio_editor.use_sliders, "Use sliders");
+                         ui.label("Decimals:").on_hover_text("Decimal places allocation percentages are shown with");
+                         if ui.add(egui::DragValue::new(&mut self.portfolio_editor.display_precision).range(0..=MAX_DISPLAY_PRECISION)).changed() {
+                             self.portfolio_editor.usdt_allocation = self.portfolio_editor.get_usdt_display();
+                             self.mark_dirty();
+                         }
+                         let mut selected_preset = None;
+                         egui::ComboBox::from_id_salt("preset_selector")
+                             .selected_text("Load Preset")
+                             .show_ui(ui, |ui| {
+                                 for preset in presets::all() {
+                                     let hover = format!("USDT {:.0}%, threshold {}%", preset.allocation.usdt(), preset.rebalance_threshold);
+                                     if ui.selectable_label(false, &preset.name).on_hover_text(hover).clicked() {
+                                         selected_preset = Some(preset);
+                                     }
+                                 }
+                             });
+                         if let Some(preset) = selected_preset { self.apply_preset(&preset); }
+                         let mut mode = self.portfolio_editor.allocation_mode;
+                         egui::ComboBox::from_id_salt("allocation_mode").selected_text(mode.label()).show_ui(ui, |ui| {
+                             for option in AllocationMode::ALL {
+                                 if ui.selectable_label(mode.same_kind(option), option.label()).clicked() && !mode.same_kind(option) { mode = option; }
+                             }
+                         });
+                         if let AllocationMode::RiskParity(risk_parity) = &mut mode {
+                             ui.label("Lookback:").on_hover_text("Days of daily closes each asset's volatility is estimated from.");
+                             ui.add(egui::DragValue::new(&mut risk_parity.lookback_days).range(2..=365).suffix(" d"));
+                             ui.label("Target vol:").on_hover_text("Annualised portfolio volatility; the assets hold less than 100% when they would exceed it.");
+                             let mut target_vol_pct = risk_parity.target_volatility * 100.0;
+                             if ui.add(egui::DragValue::new(&mut target_vol_pct).range(0.0..=500.0).speed(0.5).suffix("%")).changed() {
+                                 risk_parity.target_volatility = target_vol_pct / 100.0;
+                             }
+                         }
+                         if mode != self.portfolio_editor.allocation_mode {
+                             let lookback_changed = !matches!((mode, self.portfolio_editor.allocation_mode),
+                                 (AllocationMode::RiskParity(new), AllocationMode::RiskParity(old)) if new.lookback_days == old.lookback_days);
+                             self.portfolio_editor.allocation_mode = mode;
+                             self.mark_dirty();
+                             if mode == AllocationMode::MarketCapWeight && self.market_data.market_caps.is_empty() {
+                                 self.refresh_market_caps(ctx);
+                             }
+                             if lookback_changed { self.refresh_volatilities(); }
+                         }
+                     });
                      ui.add_space(10.0);
                      let text_edit_width = 60.0;
-                     Grid::new("allocation_grid").num_columns(3).spacing([10.0, 4.0]).striped(true).show(ui, |ui| {
-                         ui.label("BTC_USDT (3x Long):");
-                         ui.add(TextEdit::singleline(&mut self.portfolio_editor.BTC_USDT_allocation).desired_width(text_edit_width)); ui.label("%"); ui.end_row();
-                         ui.label("ETH_USDT (3x Long):");
-                         ui.add(TextEdit::singleline(&mut self.portfolio_editor.ETH_USDT_allocation).desired_width(text_edit_width)); ui.label("%"); ui.end_row();
-                         ui.label("LTC_USDT (3x Long):");
-                         ui.add(TextEdit::singleline(&mut self.portfolio_editor.LTC_USDT_allocation).desired_width(text_edit_width)); ui.label("%"); ui.end_row();
-                         ui.label("USDT (剩余):");
-                         let usdt_display = self.portfolio_editor.get_usdt_display();
-                         ui.label(RichText::new(format!("{}%", usdt_display)).strong()); ui.label(""); ui.end_row();
+                     let use_sliders = self.portfolio_editor.use_sliders;
+                     let precision = self.portfolio_editor.display_precision;
+                     let mut edited = false;
+                     let active_config = self.profiles.active();
+                     let threshold = active_config.rebalance_threshold;
+                     // Drift is measured against the targets the backend trades toward
+                     let active_target = active_config.target_allocation(&self.market_data);
+                     let live = self.live_allocations.as_ref();
+                     let drift_tooltip = format!("Live allocation minus target. The backend rebalances once an asset drifts more than its threshold ({}% unless overridden) from its target.", format_number(threshold, 1, locale));
+                     let field_errors = &self.field_errors;
+                     let has_error = |field: FieldId| field_errors.iter().any(|(f, _)| *f == field);
+                     let total_invalid = has_error(FieldId::AllocationTotal);
+                     let mut row_removed = false;
+                     // Fields edited this frame; errors the last save reported for them are dropped
+                     let mut changed_fields = Vec::new();
+                     let exposure_tooltip = "Target × leverage: the position size as a share of the portfolio value.";
+                     let funding_tooltip = "Current 8-hour funding rate. Long positions pay it when positive (red) and receive it when negative (green).";
+                     let tags_tooltip = "Comma-separated groups, e.g. Layer1, DeFi; used by the By Tag chart on the Performance tab and the History filter";
+                     let funding_rates = &self.funding_rates;
+                     let mut total_exposure = 0.0;
+                     self.portfolio_editor.sync_drag_order();
+                     let rows = self.portfolio_editor.ordered_rows();
+                     // (dragged symbol, symbol it was dropped on)
+                     let mut dropped: Option<(String, String)> = None;
+                     let custom_mode = self.portfolio_editor.allocation_mode == AllocationMode::Custom;
+                     let mode_tooltip = match self.portfolio_editor.allocation_mode {
+                         AllocationMode::EqualWeight => "Equal-weight mode splits 100% evenly between the assets; switch to Custom to edit the targets.",
+                         AllocationMode::RiskParity(_) => "Risk parity weights the assets by inverse volatility, capped at the target volatility; switch to Custom to edit the targets.",
+                         _ => "Market-cap mode weights the assets by their CoinGecko market cap and keeps the USDT share set here; switch to Custom to edit the targets.",
+                     };
+                     ui.add_enabled_ui(custom_mode, |ui| {
+                         Grid::new("allocation_grid").num_columns(9).spacing([10.0, 4.0]).striped(true).show(ui, |ui| {
+                             ui.label(""); ui.label(""); ui.label(""); ui.label("");
+                             ui.weak("Effective Exposure").on_hover_text(exposure_tooltip);
+                             ui.weak("Drift").on_hover_text(&drift_tooltip);
+                             ui.weak("Funding Rate").on_hover_text(funding_tooltip);
+                             ui.weak("Tags").on_hover_text(tags_tooltip); ui.label(""); ui.end_row();
+                             let mut remove_index = None;
+                             // 滑块共享剩余额度：每个滑块的上限为 100 - 其他资产之和
+                             let mut crypto_total: f64 = self.portfolio_editor.assets.iter().map(|row| row.allocation.parse::<f64>().unwrap_or(0.0)).sum();
+                             let live_errors = &mut self.portfolio_editor.live_errors;
+                             for index in rows {
+                                 let row = &mut self.portfolio_editor.assets[index];
+                                 let handle_id = egui::Id::new(("allocation_drag", &row.symbol));
+                                 ui.dnd_drag_source(handle_id, row.symbol.clone(), |ui| ui.label("⠿"))
+                                     .response.on_hover_cursor(egui::CursorIcon::Grab).on_hover_text("Drag onto another asset to swap them");
+                                 let label = ui.label(format!("{} ({}x Long):", row.symbol, row.leverage));
+                                 if label.dnd_hover_payload::<String>().is_some_and(|dragged| *dragged != row.symbol) {
+                                     ui.painter().rect_stroke(label.rect.expand(2.0), 2.0, Stroke::new(1.0, ui.visuals().selection.stroke.color), egui::StrokeKind::Outside);
+                                 }
+                                 if let Some(dragged) = label.dnd_release_payload::<String>() {
+                                     dropped = Some((dragged.as_ref().clone(), row.symbol.clone()));
+                                 }
+                                 let field = FieldId::Allocation(index);
+                                 let flagged = total_invalid || has_error(field);
+                                 let changed = if use_sliders {
+                                     field_frame(flagged).show(ui, |ui| {
+                                         let mut value = row.allocation.parse::<f64>().unwrap_or(0.0);
+                                         let max = (100.0 - (crypto_total - value)).max(0.0);
+                                         let changed = ui.add(Slider::new(&mut value, 0.0..=max).step_by(10f64.powi(-i32::from(precision))).max_decimals(precision as usize)).changed();
+                                         if changed {
+                                             crypto_total += value - row.allocation.parse::<f64>().unwrap_or(0.0);
+                                             row.allocation = format!("{:.prec$}", value, prec = precision as usize);
+                                             set_field_error(live_errors, field, None);
+                                         }
+                                         changed
+                                     }).inner
+                                 } else {
+                                     validated_text_edit(ui, &mut row.allocation, field, flagged, live_errors, "", text_edit_width)
+                                 };
+                                 if changed {
+                                     edited = true;
+                                     changed_fields.extend([field, FieldId::AllocationTotal]);
+                                 }
+                                 ui.label("%");
+                                 let target = row.allocation.parse::<f64>().unwrap_or(0.0);
+                                 total_exposure += target * row.leverage;
+                                 ui.label(format!("{}%", format_number(target * row.leverage, 1, locale))).on_hover_text(exposure_tooltip);
+                                 let drift = live.map(|l| l.get(&row.symbol).copied().unwrap_or(0.0) - active_target.get(&row.symbol).unwrap_or(target));
+                                 ui.label(drift_text(drift, active_config.threshold_for(&row.symbol))).on_hover_text(&drift_tooltip);
+                                 ui.label(funding_text(funding_rates.get(&row.symbol).copied())).on_hover_text(funding_tooltip);
+                                 if ui.add(TextEdit::singleline(&mut row.tags).desired_width(110.0).hint_text("Layer1, DeFi")).on_hover_text(tags_tooltip).changed() {
+                                     edited = true;
+                                 }
+                                 if ui.small_button("✖").on_hover_text("Remove asset").clicked() { remove_index = Some(index); }
+                                 ui.end_row();
+                             }
+                             if let Some(index) = remove_index {
+                                 self.portfolio_editor.assets.remove(index);
+                                 row_removed = true;
+                                 edited = true;
+                             }
+                             ui.label(""); ui.label("USDT (剩余):");
+                             let usdt_display = self.portfolio_editor.get_usdt_display();
+                             ui.label(RichText::new(format!("{}%", usdt_display)).strong()); ui.label(""); ui.label("");
+                             let drift = live.map(|l| l.get(USDT).copied().unwrap_or(0.0) - active_target.usdt());
+                             ui.label(drift_text(drift, active_config.threshold_for(USDT))).on_hover_text(&drift_tooltip); ui.end_row();
+                         });
+                     }).response.on_disabled_hover_text(mode_tooltip);
+                     if !custom_mode {
+                         let weights = self.portfolio_editor.allocation_mode.weights(&self.portfolio_editor.custom_allocation(), &self.market_data);
+                         let custom = self.portfolio_editor.custom_allocation();
+                         let missing_volatility = custom.assets.iter().any(|a| !self.market_data.volatilities.contains_key(&a.symbol));
+                         if self.portfolio_editor.allocation_mode == AllocationMode::MarketCapWeight && self.market_data.market_caps.is_empty() {
+                             ui.weak("Waiting for market caps from CoinGecko; the targets above apply until then.");
+                         } else if matches!(self.portfolio_editor.allocation_mode, AllocationMode::RiskParity(_)) && missing_volatility {
+                             ui.weak("Waiting for price history to estimate volatilities; the targets above apply until then.");
+                         } else {
+                             let summary: Vec<String> = weights.assets.iter().map(|a| format!("{} {}%", a.symbol, format_number(a.target_pct, 1, locale))).collect();
+                             ui.label(format!("Targets in use: {}, USDT {}%", summary.join(", "), format_number(weights.usdt(), 1, locale)));
+                         }
+                     }
+                     if let Some((dragged, target)) = dropped.filter(|(dragged, target)| dragged != target) {
+                         config::swap_symbols(&mut self.portfolio_editor.drag_order, &dragged, &target);
+                         edited = true;
+                     }
+                     // Redrawn with every keystroke, so an over-allocation shows up before saving
+                     allocation_total_gauge(ui, self.portfolio_editor.crypto_total(), self.portfolio_editor.display_precision, locale);
+                     if row_removed {
+                         // row indices have shifted
+                         self.field_errors.clear();
+                         self.portfolio_editor.live_errors.clear();
+                     }
+                     // 只提示，不阻止保存
+                     if total_exposure > 100.0 * EXPOSURE_RISK_LIMIT {
+                         ui.colored_label(Color32::YELLOW, format!("Effective exposure {}% exceeds the {:.0}% risk limit.", format_number(total_exposure, 1, locale), 100.0 * EXPOSURE_RISK_LIMIT));
+                     }
+                     ui.horizontal(|ui| {
+                         ui.label("Position Sizing:");
+                         let sizing = &mut self.portfolio_editor.position_sizing;
+                         let kelly = matches!(sizing, PositionSizing::KellyCriterion { .. });
+                         if ui.radio(!kelly, "Fixed %").clicked() && kelly {
+                             *sizing = PositionSizing::FixedPct;
+                             edited = true;
+                         }
+                         if ui.radio(kelly, "Kelly Criterion").on_hover_text("Invest the Kelly fraction of the portfolio, split between the assets in proportion to the targets above.").clicked() && !kelly {
+                             *sizing = PositionSizing::KellyCriterion { win_rate: 0.55, avg_win_loss_ratio: 1.5, fraction: 0.5 };
+                             edited = true;
+                         }
+                     });
+                     if let PositionSizing::KellyCriterion { win_rate, avg_win_loss_ratio, fraction } = &mut self.portfolio_editor.position_sizing {
+                         ui.horizontal(|ui| {
+                             ui.label("Win rate:");
+                             let mut win_pct = *win_rate * 100.0;
+                             if ui.add(egui::DragValue::new(&mut win_pct).range(0.0..=100.0).speed(0.5).suffix("%")).changed() {
+                                 *win_rate = win_pct / 100.0;
+                                 edited = true;
+                             }
+                             ui.label("Avg win/loss:");
+                             edited |= ui.add(egui::DragValue::new(avg_win_loss_ratio).range(0.0..=100.0).speed(0.05)).changed();
+                             ui.label("Fraction:").on_hover_text("1.0 bets the full Kelly fraction, 0.5 half of it.");
+                             edited |= ui.add(egui::DragValue::new(fraction).range(0.0..=1.0).speed(0.05)).changed();
+                         });
+                         let kelly = metrics::kelly_size(*win_rate, *avg_win_loss_ratio, *fraction);
+                         let weights = self.portfolio_editor.allocation_mode.weights(&self.portfolio_editor.custom_allocation(), &self.market_data);
+                         let targets = metrics::kelly_targets(&weights, kelly);
+                         egui::CollapsingHeader::new("Estimated Kelly Fractions").default_open(true).show(ui, |ui| {
+                             Grid::new("kelly_grid").num_columns(2).spacing([10.0, 4.0]).striped(true).show(ui, |ui| {
+                                 for asset in &targets.assets {
+                                     ui.label(format!("{}:", asset.symbol));
+                                     let text = format!("{}%", format_number(asset.target_pct, 1, locale));
+                                     if asset.target_pct > KELLY_CONCENTRATION_LIMIT_PCT { ui.colored_label(Color32::RED, text); } else { ui.label(text); }
+                                     ui.end_row();
+                                 }
+                                 ui.label("USDT:"); ui.label(format!("{}%", format_number(targets.usdt(), 1, locale))); ui.end_row();
+                             });
+                             if kelly <= 0.0 {
+                                 ui.colored_label(Color32::YELLOW, "No edge at these odds: the Kelly fraction is zero and everything stays in USDT.");
+                             }
+                             if targets.assets.iter().any(|a| a.target_pct > KELLY_CONCENTRATION_LIMIT_PCT) {
+                                 ui.colored_label(Color32::YELLOW, format!("Kelly fractions above {:.0}% concentrate the portfolio in a single asset.", KELLY_CONCENTRATION_LIMIT_PCT));
+                             }
+                         });
+                     }
+                     ui.horizontal(|ui| {
+                         let symbol_edit = ui.add(TextEdit::singleline(&mut self.portfolio_editor.new_asset_symbol).hint_text("e.g. SOL_USDT").desired_width(100.0));
+                         let existing: Vec<&str> = self.portfolio_editor.assets.iter().map(|row| row.symbol.as_str()).collect();
+                         let matches: Vec<String> = self.symbol_suggestions.matching(&self.portfolio_editor.new_asset_symbol, &existing).into_iter().map(str::to_string).collect();
+                         let popup_id = symbol_edit.id.with("symbol_suggestions");
+                         if matches.is_empty() {
+                             if ui.memory(|m| m.is_popup_open(popup_id)) { ui.memory_mut(|m| m.close_popup()); }
+                         } else if symbol_edit.changed() {
+                             ui.memory_mut(|m| m.open_popup(popup_id));
+                         }
+                         let mut picked = None;
+                         egui::popup_below_widget(ui, popup_id, &symbol_edit, egui::PopupCloseBehavior::CloseOnClickOutside, |ui| {
+                             ui.set_min_width(120.0);
+                             for symbol in &matches {
+                                 if ui.selectable_label(false, symbol).clicked() { picked = Some(symbol.clone()); }
+                             }
+                         });
+                         if let Some(symbol) = picked {
+                             self.portfolio_editor.new_asset_symbol = symbol;
+                             ui.memory_mut(|m| m.close_popup());
+                         }
+                         if ui.button("Add Asset").clicked() {
+                             match self.portfolio_editor.add_asset() {
+                                 Ok(()) => edited = true,
+                                 Err(e) => { self.error_message = Some(e.to_string()); }
+                             }
+                         }
+                         if ui.button("Import CSV").on_hover_text("symbol,percentage rows summing to 100%, USDT included").clicked() {
+                             self.import_csv();
+                         }
+                     });
+                     ui.add_space(10.0);
+                     let target_slices = self.target_slices();
+                     let live_slices = self.live_slices();
+                     ui.columns(2, |columns| {
+                         columns[0].label(RichText::new("Target").strong());
+                         AllocationPieChart::new(&target_slices).show(&mut columns[0]);
+                         columns[1].label(RichText::new("Actual").strong());
+                         match &live_slices {
+                             Some(slices) => { AllocationPieChart::new(slices).show(&mut columns[1]); }
+                             None => { columns[1].weak("No live data yet."); }
+                         }
                      });
                      ui.add_space(10.0); ui.separator(); ui.add_space(10.0);
                      ui.heading("Rebalancing Settings (再平衡设置)"); ui.add_space(5.0);
+                     let field_errors = &self.field_errors;
+                     let has_error = |field: FieldId| field_errors.iter().any(|(f, _)| *f == field);
+                     let display_currency = self.display_currency();
+                     let display_prices = &self.display_prices;
                      Grid::new("rebalancing_grid").num_columns(2).spacing([10.0, 4.0]).striped(true).show(ui, |ui| {
+                         let editor = &mut self.portfolio_editor;
                          ui.label("Threshold Deviation (%):");
-                         ui.add(TextEdit::singleline(&mut self.portfolio_editor.rebalance_threshold).desired_width(text_edit_width)); ui.end_row();
+                         let field = FieldId::RebalanceThreshold;
+                         if validated_text_edit(ui, &mut editor.rebalance_threshold, field, has_error(field), &mut editor.live_errors, "", text_edit_width) {
+                             edited = true;
+                             changed_fields.push(field);
+                         }
+                         ui.end_row();
                          ui.label("Min Cash Inflow (USDT):");
-                         ui.add(TextEdit::singleline(&mut self.portfolio_editor.min_usdt_inflow).desired_width(text_edit_width)); ui.end_row();
+                         let field = FieldId::MinUsdtInflow;
+                         ui.horizontal(|ui| {
+                             if validated_text_edit(ui, &mut editor.min_usdt_inflow, field, has_error(field), &mut editor.live_errors, "", text_edit_width) {
+                                 edited = true;
+                                 changed_fields.push(field);
+                             }
+                             converted_hint(ui, &editor.min_usdt_inflow, display_currency, display_prices, locale);
+                         });
+                         ui.end_row();
+                         ui.label("Min Trade Size (USDT):").on_hover_text("Smaller rebalancing trades are skipped; the exchange rejects dust orders.");
+                         let field = FieldId::MinTradeUsdt;
+                         ui.horizontal(|ui| {
+                             if validated_text_edit(ui, &mut editor.min_trade_usdt, field, has_error(field), &mut editor.live_errors, "", text_edit_width) {
+                                 edited = true;
+                                 changed_fields.push(field);
+                             }
+                             converted_hint(ui, &editor.min_trade_usdt, display_currency, display_prices, locale);
+                         });
+                         ui.end_row();
+                         ui.label("Taker Fee (%):").on_hover_text("Charged on market orders, which the rebalancer places; used for the fee estimates.");
+                         let field = FieldId::TakerFeeRate;
+                         if validated_text_edit(ui, &mut editor.taker_fee_pct, field, has_error(field), &mut editor.live_errors, "", text_edit_width) {
+                             edited = true;
+                             changed_fields.push(field);
+                         }
+                         ui.end_row();
+                         ui.label("Maker Fee (%):").on_hover_text("Charged on limit orders that rest on the book.");
+                         let field = FieldId::MakerFeeRate;
+                         if validated_text_edit(ui, &mut editor.maker_fee_pct, field, has_error(field), &mut editor.live_errors, "", text_edit_width) {
+                             edited = true;
+                             changed_fields.push(field);
+                         }
+                         ui.end_row();
+                         ui.label("Slippage Model:").on_hover_text("Expected price impact of the market orders, added to the cost estimates of the dry run.");
+                         ui.horizontal(|ui| {
+                             ui.label(editor.slippage_model.kind().label());
+                             let label = if editor.show_slippage_settings { "Hide" } else { "Configure Slippage Model" };
+                             if ui.button(label).clicked() {
+                                 editor.show_slippage_settings = !editor.show_slippage_settings;
+                             }
+                         });
+                         ui.end_row();
+                         ui.label("Cost Basis:").on_hover_text("Which tax lots a sell consumes first, for the realized gains and the tax report.");
+                         ui.horizontal(|ui| {
+                             for method in [CostBasisMethod::Fifo, CostBasisMethod::Lifo] {
+                                 edited |= ui.radio_value(&mut editor.cost_basis_method, method, method.to_string()).changed();
+                             }
+                         });
+                         ui.end_row();
+                         ui.label("Poll Interval:").on_hover_text("How often the balances and prices shown here are refreshed from the exchange.");
+                         if ui.add(egui::DragValue::new(&mut editor.poll_interval_seconds).range(1..=3600).suffix(" s")).changed() {
+                             edited = true;
+                         }
+                         ui.end_row();
+                         ui.label("Drawdown Halt:").on_hover_text("Pause rebalancing when the portfolio falls this far below its peak, instead of buying into the losses.");
+                         ui.horizontal(|ui| {
+                             let mut enabled = editor.max_drawdown_halt_pct.is_some();
+                             if ui.checkbox(&mut enabled, "").changed() {
+                                 editor.max_drawdown_halt_pct = enabled.then_some(20.0);
+                                 edited = true;
+                             }
+                             if let Some(pct) = &mut editor.max_drawdown_halt_pct {
+                                 edited |= ui.add(egui::DragValue::new(pct).range(1.0..=100.0).speed(0.5).suffix("% below peak")).changed();
+                             }
+                         });
+                         ui.end_row();
                      });
+                     if self.portfolio_editor.show_slippage_settings {
+                         let model = &mut self.portfolio_editor.slippage_model;
+                         ui.group(|ui| {
+                             Grid::new("slippage_grid").num_columns(2).spacing([10.0, 4.0]).show(ui, |ui| {
+                                 ui.label("Model:");
+                                 let mut kind = model.kind();
+                                 egui::ComboBox::from_id_salt("slippage_kind").selected_text(kind.label()).show_ui(ui, |ui| {
+                                     for option in SlippageKind::ALL { ui.selectable_value(&mut kind, option, option.label()); }
+                                 });
+                                 if kind != model.kind() { *model = model.with_kind(kind); edited = true; }
+                                 ui.end_row();
+                                 match model {
+                                     SlippageModel::None => {}
+                                     SlippageModel::LinearBps { bps_per_million_usdt } => {
+                                         ui.label("Impact per 1M USDT:");
+                                         edited |= ui.add(egui::DragValue::new(bps_per_million_usdt).range(0.0..=1000.0).speed(0.5).suffix(" bps")).changed();
+                                         ui.end_row();
+                                     }
+                                     SlippageModel::Sqrt { coeff } => {
+                                         ui.label("Impact of 1M USDT:").on_hover_text("Smaller orders move the price by this times √(notional / 1M).");
+                                         let mut pct = *coeff * 100.0;
+                                         if ui.add(egui::DragValue::new(&mut pct).range(0.0..=10.0).speed(0.01).suffix("%")).changed() {
+                                             *coeff = pct / 100.0;
+                                             edited = true;
+                                         }
+                                         ui.end_row();
+                                     }
+                                 }
+                                 ui.label("Example:");
+                                 ui.weak(format!("{:.2} USDT on a 100,000 USDT order", model.estimate(100_000.0)));
+                                 ui.end_row();
+                             });
+                         });
+                     }
+                     edited |= ui.checkbox(&mut self.portfolio_editor.override_thresholds, "Override global threshold").on_hover_text("Give volatile assets a wider band; empty fields use the global threshold.").changed();
+                     if self.portfolio_editor.override_thresholds {
+                         let editor = &mut self.portfolio_editor;
+                         egui::CollapsingHeader::new("Per-Asset Thresholds").default_open(true).show(ui, |ui| {
+                             Grid::new("asset_threshold_grid").num_columns(3).spacing([10.0, 4.0]).striped(true).show(ui, |ui| {
+                                 for (index, row) in editor.assets.iter().enumerate() {
+                                     ui.label(format!("{}:", row.symbol));
+                                     let field = FieldId::AssetThreshold(index);
+                                     let text = editor.asset_thresholds.entry(row.symbol.clone()).or_default();
+                                     if validated_text_edit(ui, text, field, has_error(field), &mut editor.live_errors, &editor.rebalance_threshold, text_edit_width) {
+                                         edited = true;
+                                         changed_fields.push(field);
+                                     }
+                                     ui.label("%"); ui.end_row();
+                                 }
+                             });
+                         });
+                     }
+                     self.field_errors.retain(|(f, _)| !changed_fields.contains(f));
+                     ui.add_space(10.0);
+                     ui.heading("Schedule (定时再平衡)"); ui.add_space(5.0);
+                     let schedule = &mut self.portfolio_editor.schedule;
+                     ui.horizontal(|ui| {
+                         let mut kind = schedule.kind();
+                         egui::ComboBox::from_id_salt("schedule_kind").selected_text(kind.label()).show_ui(ui, |ui| {
+                             for option in ScheduleKind::ALL { ui.selectable_value(&mut kind, option, option.label()); }
+                         });
+                         if kind != schedule.kind() { *schedule = schedule.with_kind(kind); edited = true; }
+                         if let RebalanceSchedule::Weekly { weekday, .. } = schedule {
+                             egui::ComboBox::from_id_salt("schedule_weekday").selected_text(weekday.to_string()).show_ui(ui, |ui| {
+                                 for day in [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun] {
+                                     edited |= ui.selectable_value(weekday, day, day.to_string()).changed();
+                                 }
+                             });
+                         }
+                         if let Some(time) = schedule.time_mut() {
+                             ui.label("at");
+                             let (mut hour, mut minute) = (time.hour(), time.minute());
+                             let hour_changed = ui.add(egui::DragValue::new(&mut hour).range(0..=23)).changed();
+                             ui.label(":");
+                             let minute_changed = ui.add(egui::DragValue::new(&mut minute).range(0..=59)).changed();
+                             if hour_changed || minute_changed {
+                                 *time = NaiveTime::from_hms_opt(hour, minute, 0).unwrap_or(*time);
+                                 edited = true;
+                             }
+                         }
+                         if let RebalanceSchedule::Interval { hours } = schedule {
+                             ui.label("every");
+                             edited |= ui.add(egui::DragValue::new(hours).range(1..=720).suffix(" h")).changed();
+                         }
+                     }).response.on_hover_text("Scheduled runs rebalance to target even when no asset exceeds the threshold. Times are local to the machine running the backend.");
+                     if edited { self.mark_dirty(); }
                      ui.add_space(15.0);
-                     let save_button = ui.button("Save Portfolio Config");
-                     if save_button.clicked() {
-                         match self.update_config_from_editor() {
-                             Ok(_) => { self.error_message = Some("Portfolio config saved.".to_string()); } // Use error field briefly
-                             Err(e) => { self.error_message = Some(e.to_string()); }
+                     let save_button = ui.horizontal(|ui| {
+                         let save_button = ui.button("Save Portfolio Config");
+                         if ui.add_enabled(!self.history_stack.is_empty(), Button::new("Undo")).on_hover_text("Ctrl+Z").clicked() {
+                             if let Err(e) = self.undo_allocation() { self.error_message = Some(e.to_string()); }
+                         }
+                         if ui.add_enabled(!self.redo_stack.is_empty(), Button::new("Redo")).on_hover_text("Ctrl+Y").clicked() {
+                             if let Err(e) = self.redo_allocation() { self.error_message = Some(e.to_string()); }
+                         }
+                         let copied = self.allocation_copied_at.filter(|t| t.elapsed() < COPIED_BADGE_DURATION);
+                         let copy_label = if copied.is_some() { "Copied ✓" } else { "Copy as JSON" };
+                         if ui.button(copy_label).on_hover_text("Copy the allocation, threshold and minimum inflow to the clipboard").clicked() {
+                             if let Err(e) = self.copy_allocation_json() { self.error_message = Some(e.to_string()); }
+                         }
+                         if let Some(at) = copied {
+                             ctx.request_repaint_after(COPIED_BADGE_DURATION.saturating_sub(at.elapsed()));
                          }
+                         if ui.button("Paste from JSON").on_hover_text("Apply weights from JSON on the clipboard, e.g. {\"BTC_USDT\": 30}; a preview is shown first").clicked() {
+                             if let Err(e) = self.paste_allocation_json() { self.error_message = Some(e.to_string()); }
+                         }
+                         if ui.add_enabled(self.what_if.is_none(), Button::new("Simulate Changes")).on_hover_text("Try other weights against the current balances without saving them").clicked() {
+                             self.start_what_if(ctx);
+                         }
+                         if self.dirty {
+                             ui.colored_label(Color32::YELLOW, "●  Unsaved");
+                         } else if let Some(saved) = self.last_saved_instant.filter(|t| t.elapsed() < SAVED_BADGE_DURATION) {
+                             ui.colored_label(Color32::GREEN, "✓  Saved");
+                             ctx.request_repaint_after(SAVED_BADGE_DURATION.saturating_sub(saved.elapsed()));
+                         }
+                         save_button
+                     }).inner;
+                     if save_button.clicked() {
+                         self.save_portfolio_config();
                      }
                      save_button.on_hover_text("Saves settings to the config file. The backend needs to be restarted (or dynamically reload config) to use new settings.");
+                     ui.add_space(10.0);
+                     let notes_error = field_error(&self.field_errors, FieldId::Notes).cloned();
+                     let notes_changed = egui::CollapsingHeader::new("Strategy Notes").default_open(!self.portfolio_editor.notes.is_empty()).show(ui, |ui| {
+                         let notes = &mut self.portfolio_editor.notes;
+                         // 旧配置里超长的笔记不截断，保存时报错
+                         let char_limit = if notes.chars().count() <= MAX_NOTES_CHARS { MAX_NOTES_CHARS } else { usize::MAX };
+                         let edit = TextEdit::multiline(notes).char_limit(char_limit).desired_rows(4).desired_width(f32::INFINITY).hint_text("Why this allocation? e.g. \"Overweight BTC until the halving\"");
+                         let changed = ui.add(edit).changed();
+                         let count = notes.chars().count();
+                         let count_text = format!("{} / {}", count, MAX_NOTES_CHARS);
+                         if count > MAX_NOTES_CHARS { ui.colored_label(Color32::RED, count_text); } else { ui.weak(count_text); }
+                         if let Some(error) = &notes_error { ui.colored_label(Color32::RED, error); }
+                         changed
+                     }).body_returned.unwrap_or(false);
+                     if notes_changed {
+                         self.field_errors.retain(|(f, _)| *f != FieldId::Notes);
+                         self.mark_dirty();
+                         self.notes_edited = true;
+                     }
                  });
             }
 
-            if self.show_api_settings {
+            if self.active_tab == Tab::ApiSettings {
                  ui.group(|ui| {
-                    ui.heading("Gate.io API Settings (TestNet)");
-                    ui.label("These are stored locally in the config file.");
-                    ui.add_space(10.0);
+                    ui.heading(format!("{} API Settings (TestNet)", self.profiles.active().exchange.label()));
                     ui.horizontal(|ui| {
+                        ui.label("Exchange:");
+                        let mut choice = self.profiles.active().exchange;
+                        ui.add_enabled_ui(!self.is_running, |ui| {
+                            egui::ComboBox::from_id_salt("exchange_choice").selected_text(choice.label()).show_ui(ui, |ui| {
+                                for option in ExchangeChoice::ALL { ui.selectable_value(&mut choice, option, option.label()); }
+                            });
+                        }).response.on_disabled_hover_text("Stop the rebalancer to switch exchanges.");
+                        if choice != self.profiles.active().exchange {
+                            self.set_exchange(choice);
+                            if let Err(e) = self.save_config() {
+                                self.error_message = Some(format!("Failed to save config: {}", e));
+                            }
+                        }
+                    });
+                    if self.keyring_available {
+                        ui.label("These are stored in the system keychain.");
+                    } else {
+                        ui.colored_label(Color32::YELLOW, "System keychain unavailable: these are stored in plaintext in the config file.");
+                    }
+                    ui.add_space(10.0);
+                    let key_changed = ui.horizontal(|ui| {
                         ui.label(RichText::new("API Key:").strong());
                         ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui|{
-                            ui.add_sized(Vec2::new(ui.available_width() * 0.7, 0.0), TextEdit::singleline(&mut self.api_key)); });
-                    });
-                    ui.horizontal(|ui| {
+                            ui.add_sized(Vec2::new(ui.available_width() * 0.7, 0.0), TextEdit::singleline(&mut *self.api_key)).changed() }).inner
+                    }).inner;
+                    if key_changed { self.api_key_error = credential_error(&self.api_key, self.profiles.active().exchange, validate_api_key); }
+                    if let Some(error) = &self.api_key_error { ui.colored_label(Color32::RED, error); }
+                    let secret_changed = ui.horizontal(|ui| {
                         ui.label(RichText::new("API Secret:").strong());
                         ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui|{
-                            let password = TextEdit::singleline(&mut self.api_secret).password(true).desired_width(ui.available_width() * 0.7); ui.add(password); });
-                    });
+                            let password = TextEdit::singleline(&mut *self.api_secret).password(true).desired_width(ui.available_width() * 0.7); ui.add(password).changed() }).inner
+                    }).inner;
+                    if secret_changed { self.api_secret_error = credential_error(&self.api_secret, self.profiles.active().exchange, validate_api_secret); }
+                    if let Some(error) = &self.api_secret_error { ui.colored_label(Color32::RED, error); }
                     ui.add_space(10.0);
                     ui.horizontal(|ui| {
                         ui.label("Configured API Key:");
-                        let display_key = if self.config.api_key.len() > 6 { format!("...{}", &self.config.api_key[self.config.api_key.len() - 6..]) }
-                                          else if self.config.api_key.is_empty() { "Not set".to_string() }
+                        let api_key = self.configured_api_key();
+                        let display_key = if api_key.len() > 6 { format!("...{}", &api_key[api_key.len() - 6..]) }
+                                          else if api_key.is_empty() { "Not set".to_string() }
                                           else { "******".to_string() };
-                        ui.label(display_key).on_hover_text(&self.config.api_key);
+                        ui.label(display_key).on_hover_text(api_key);
                     });
                     ui.add_space(10.0);
                     if ui.button("Save API Settings").clicked() {
                         match self.update_api_settings() {
                             Ok(_) => {
-                                self.show_api_settings = false; self.show_portfolio_editor = true;
+                                self.active_tab = Tab::PortfolioConfig;
                                 self.error_message = Some("API settings saved.".to_string()); // Use error field briefly
                             }
                             Err(e) => { self.error_message = Some(e.to_string()); }
                         }
                     }
+                    ui.add_space(5.0);
+                    let mut minimize_to_tray = self.profiles.active().minimize_to_tray;
+                    let tray_checkbox = ui.add_enabled(self.tray.is_some(), egui::Checkbox::new(&mut minimize_to_tray, "Minimize to tray on close"))
+                        .on_disabled_hover_text("System tray is not available on this platform.");
+                    let mut notifications_enabled = self.profiles.active().notifications_enabled;
+                    let notifications_checkbox = ui.checkbox(&mut notifications_enabled, "Desktop notifications for trades")
+                        .on_hover_text("At most one notification per asset every 30 seconds");
+                    let mut check_for_updates = self.profiles.active().check_for_updates;
+                    let updates_checkbox = ui.checkbox(&mut check_for_updates, "Check for updates on startup")
+                        .on_hover_text(format!("Ask GitHub for a newer release than v{}, at most once a day", update::APP_VERSION));
+                    let mut paper_trading = self.profiles.active().paper_trading;
+                    let paper_checkbox = ui.add_enabled(!self.is_running, egui::Checkbox::new(&mut paper_trading, "Paper trading"))
+                        .on_hover_text(format!("Simulate orders against a virtual {:.0} USDT account; nothing is sent to the exchange", PAPER_INITIAL_USDT))
+                        .on_disabled_hover_text("Stop the rebalancer to switch modes.");
+                    if paper_checkbox.changed() {
+                        // The paper ledger starts over and balances come from the other account
+                        self.exchange = None;
+                        self.live_balances = None;
+                        self.total_value_usdt = None;
+                        self.value_history = ValueHistory::default();
+                    }
+                    if tray_checkbox.changed() || notifications_checkbox.changed() || updates_checkbox.changed() || paper_checkbox.changed() {
+                        let config = self.profiles.active_mut();
+                        config.minimize_to_tray = minimize_to_tray;
+                        config.notifications_enabled = notifications_enabled;
+                        config.check_for_updates = check_for_updates;
+                        config.paper_trading = paper_trading;
+                        if let Err(e) = self.save_config() {
+                            self.error_message = Some(format!("Failed to save config: {}", e));
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Theme:");
+                        let current = self.profiles.active().theme;
+                        let mut selected = None;
+                        egui::ComboBox::from_id_salt("theme")
+                            .selected_text(current.label())
+                            .show_ui(ui, |ui| {
+                                for option in Theme::ALL {
+                                    if ui.selectable_label(current == option, option.label()).clicked() {
+                                        selected = Some(option);
+                                    }
+                                }
+                            });
+                        if let Some(option) = selected.filter(|t| *t != current) {
+                            self.set_theme(option);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("UI Scale:");
+                        let mut scale = self.profiles.active().ui_scale;
+                        let slider = ui.add(egui::Slider::new(&mut scale, MIN_UI_SCALE..=MAX_UI_SCALE).step_by(0.05).fixed_decimals(2).suffix("×"));
+                        // Applied on release: rescaling mid-drag moves the slider away from the pointer
+                        if slider.changed() && !slider.dragged() || slider.drag_stopped() {
+                            self.set_ui_scale(ctx, scale);
+                        } else if slider.changed() {
+                            self.profiles.active_mut().ui_scale = scale;
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Number format:");
+                        let mut selected = None;
+                        egui::ComboBox::from_id_salt("locale")
+                            .selected_text(locale.label())
+                            .show_ui(ui, |ui| {
+                                for option in Locale::ALL {
+                                    if ui.selectable_label(locale == option, option.label()).clicked() {
+                                        selected = Some(option);
+                                    }
+                                }
+                            });
+                        ui.weak(format_number(1234567.89, 2, selected.unwrap_or(locale)));
+                        if let Some(option) = selected.filter(|l| *l != locale) {
+                            self.profiles.active_mut().locale = option;
+                            if let Err(e) = self.save_config() {
+                                self.error_message = Some(format!("Failed to save config: {}", e));
+                            }
+                        }
+                    });
+                    ui.add_space(10.0); ui.separator(); ui.add_space(10.0);
+                    ui.heading("Webhook");
+                    ui.horizontal(|ui| {
+                        ui.label("URL:");
+                        let mut url = self.profiles.active().webhook_url.clone().unwrap_or_default();
+                        let response = ui.add(TextEdit::singleline(&mut url).hint_text("https://hooks.example.com/...").desired_width(ui.available_width() * 0.7));
+                        if response.changed() {
+                            let url = url.trim();
+                            self.profiles.active_mut().webhook_url = (!url.is_empty()).then(|| url.to_string());
+                        }
+                        if response.lost_focus() {
+                            if let Err(e) = self.save_config() {
+                                self.error_message = Some(format!("Failed to save config: {}", e));
+                            }
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let mut events_changed = false;
+                        for event in WebhookEvent::ALL {
+                            let mut enabled = self.profiles.active().webhook_events.contains(&event);
+                            if ui.checkbox(&mut enabled, event.label()).changed() {
+                                let events = &mut self.profiles.active_mut().webhook_events;
+                                events.retain(|e| *e != event);
+                                if enabled { events.push(event); }
+                                events_changed = true;
+                            }
+                        }
+                        if events_changed {
+                            if let Err(e) = self.save_config() {
+                                self.error_message = Some(format!("Failed to save config: {}", e));
+                            }
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let can_test = !self.webhook_testing && self.profiles.active().webhook_url.is_some();
+                        if ui.add_enabled(can_test, Button::new("Test Webhook")).on_hover_text("POST a test payload to the URL").clicked() {
+                            self.test_webhook(ctx);
+                        }
+                        if self.webhook_testing { ui.spinner(); }
+                        match &self.webhook_test_result {
+                            Some(Ok(status)) => { ui.colored_label(Color32::GREEN, format!("OK (HTTP {})", status)); }
+                            Some(Err(e)) => { ui.colored_label(Color32::RED, e); }
+                            None => {}
+                        }
+                    });
+                    ui.add_space(10.0); ui.separator(); ui.add_space(10.0);
+                    ui.heading("Key Bindings");
+                    ui.weak("Click a shortcut, then press the new key combination (Esc cancels).");
+                    let bindings = self.profiles.active().bindings;
+                    Grid::new("key_bindings_grid").num_columns(2).spacing([20.0, 4.0]).striped(true).show(ui, |ui| {
+                        for action in KeyAction::ALL {
+                            ui.label(action.label());
+                            let text = if self.rebinding == Some(action) { "Press keys…".to_string() } else { format_shortcut(&bindings.get(action)) };
+                            if ui.selectable_label(self.rebinding == Some(action), text).clicked() {
+                                self.rebinding = Some(action);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    if ui.small_button("Reset to Defaults").clicked() {
+                        self.profiles.active_mut().bindings = Default::default();
+                        if let Err(e) = self.save_config() {
+                            self.error_message = Some(format!("Failed to save config: {}", e));
+                        }
+                    }
+                    ui.add_space(10.0); ui.separator(); ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        ui.heading("Futures Balances");
+                        if ui.add_enabled(!self.balances_loading, Button::new("Fetch Balances")).on_hover_text("Query the exchange directly; works without starting the rebalancer").clicked() {
+                            self.fetch_balances(ctx);
+                        }
+                        if self.balances_loading { ui.spinner(); }
+                    });
+                    match &self.live_balances {
+                        Some(balances) => {
+                            let mut rows: Vec<_> = balances.iter().collect();
+                            rows.sort_by(|a, b| a.0.cmp(b.0));
+                            Grid::new("balances_grid").num_columns(2).spacing([20.0, 4.0]).striped(true).show(ui, |ui| {
+                                for (symbol, value) in rows {
+                                    ui.label(symbol.as_str());
+                                    let (text, usdt) = self.amount_text(*value);
+                                    let response = ui.label(text);
+                                    if let Some(usdt) = usdt { response.on_hover_text(usdt); }
+                                    ui.end_row();
+                                }
+                            });
+                        }
+                        None => { ui.weak("Not fetched yet."); }
+                    }
+                    if !self.positions.is_empty() {
+                        ui.add_space(10.0);
+                        ui.heading("Open Positions");
+                        let maintenance_margin_rate = self.profiles.active().maintenance_margin_rate;
+                        Grid::new("positions_grid").num_columns(6).spacing([20.0, 4.0]).striped(true).show(ui, |ui| {
+                            for header in ["Contract", "Side", "Size", "Entry Price", "Mark Price", "Liq. Price"] {
+                                ui.weak(header);
+                            }
+                            ui.end_row();
+                            for position in &self.positions {
+                                ui.label(position.symbol.as_str());
+                                ui.label(format!("{} {}x", if position.side == OrderSide::Buy { "Long" } else { "Short" }, position.leverage));
+                                ui.label(format!("{:.4}", position.size));
+                                ui.label(format!("{:.4}", position.entry_price));
+                                ui.label(format!("{:.4}", position.mark_price));
+                                let liq_price = risk::liquidation_price(position.entry_price, position.leverage, position.side, maintenance_margin_rate);
+                                let distance = risk::distance_to_liquidation_pct(position.mark_price, liq_price, position.side);
+                                let text = RichText::new(format!("{:.4}", liq_price));
+                                let text = if distance < risk::LIQUIDATION_WARNING_PCT { text.color(Color32::RED).strong() } else { text };
+                                ui.label(text).on_hover_text(format!("Distance to liquidation: {}%", format_number(distance, 1, locale)));
+                                ui.end_row();
+                            }
+                        });
+                    }
                  });
             }
 
+            if self.active_tab == Tab::Log {
+                ui.group(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.heading("Backend Log (后端日志)");
+                        if ui.button("Clear Log").clicked() {
+                            self.log_lines.clear();
+                        }
+                        if ui.button("View Audit Log").on_hover_text(format!("Open {} in the default text editor", self.audit_log.path().display())).clicked() {
+                            self.open_audit_log();
+                        }
+                    });
+                    ui.add_space(5.0);
+                    egui::ScrollArea::vertical().max_height(300.0).auto_shrink([false, true]).stick_to_bottom(true).show(ui, |ui| {
+                        for line in &self.log_lines {
+                            let text = RichText::new(line).monospace();
+                            let text = if line.contains("ERROR") { text.color(Color32::RED) }
+                                       else if line.contains("WARN") { text.color(Color32::YELLOW) }
+                                       else { text };
+                            ui.label(text);
+                        }
+                    });
+                });
+            }
+
+            if self.active_tab == Tab::Performance {
+                ui.group(|ui| {
+                    ui.heading("Performance (策略表现)");
+                    ui.add_space(5.0);
+                    ui.colored_label(Color32::YELLOW, "Estimates based on the portfolio value at each rebalancing trade only, not a full mark-to-market of positions.");
+                    ui.add_space(10.0);
+                    let metrics = metrics::compute(&self.event_history);
+                    Grid::new("performance_grid").num_columns(2).spacing([20.0, 6.0]).striped(true).show(ui, |ui| {
+                        ui.label("Total Return:"); ui.label(format!("{:+.2}%", metrics.total_return_pct)); ui.end_row();
+                        ui.label("Annualised Return:"); ui.label(format!("{:+.2}%", metrics.annualised_return_pct)); ui.end_row();
+                        ui.label("Sharpe Ratio:"); ui.label(format!("{:.2}", metrics.sharpe_ratio)); ui.end_row();
+                        ui.label("Est. Sharpe:");
+                        let risk_free_rate = self.profiles.active().risk_free_rate_annual;
+                        match metrics::estimate_sharpe(&self.event_history, risk_free_rate) {
+                            Some(sharpe) => { ui.label(format!("{} (annualised)", format_number(sharpe, 2, locale))).on_hover_text(format!("From daily returns interpolated between events, against a {}% risk-free rate", format_number(risk_free_rate * 100.0, 1, locale))); }
+                            None => { ui.weak("—").on_hover_text(format!("Needs at least {} days of rebalancing history", metrics::MIN_SHARPE_DAYS)); }
+                        }
+                        ui.end_row();
+                        ui.label("Max Drawdown:"); ui.label(format!("{:.2}%", metrics.max_drawdown_pct)); ui.end_row();
+                        ui.label("Win Rate:"); ui.label(format!("{}%", format_number(metrics.win_rate_pct, 1, locale))); ui.end_row();
+                        ui.label("Total Fees Paid:");
+                        ui.label(self.amount_text(metrics.total_fees_paid_usdt).0).on_hover_text("Estimated at the taker fee rate for each recorded trade");
+                        ui.end_row();
+                        ui.label("Est. Daily Funding Cost:");
+                        match &self.live_balances {
+                            Some(balances) if !self.funding_rates.is_empty() => {
+                                let funding_cost_daily_usdt = metrics::funding_cost_daily_usdt(balances, &self.profiles.active().portfolio_allocation, &self.funding_rates);
+                                let (text, usdt) = self.amount_text(funding_cost_daily_usdt);
+                                let response = ui.label(text).on_hover_text("Position size × |8h funding rate| × 3 periods, at the current rates.");
+                                if let Some(usdt) = usdt { response.on_hover_text(usdt); }
+                            }
+                            _ => { ui.weak("—"); }
+                        }
+                        ui.end_row();
+                    });
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        ui.strong("Portfolio Value");
+                        for range in TimeRange::ALL {
+                            ui.selectable_value(&mut self.value_chart_range, range, range.label());
+                        }
+                    });
+                    let now = Utc::now();
+                    let indices = self.value_history.indices(self.value_chart_range, now);
+                    if indices.len() < 2 {
+                        ui.weak("The chart fills in from the portfolio snapshots while the rebalancer runs.");
+                    } else {
+                        let timestamps = &self.value_history.timestamps[indices.clone()];
+                        let origin = timestamps[0];
+                        let x = move |t: DateTime<Utc>| (t - origin).num_seconds() as f64;
+                        let points: Vec<[f64; 2]> = timestamps.iter().zip(&self.value_history.values_usdt[indices.clone()]).map(|(t, v)| [x(*t), *v]).collect();
+                        let last = timestamps[timestamps.len() - 1];
+                        let event_lines: Vec<f64> = self.event_history.iter().filter(|e| e.timestamp >= origin && e.timestamp <= last).map(|e| x(e.timestamp)).collect();
+                        let time_at = move |seconds: f64| (origin + chrono::Duration::seconds(seconds as i64)).with_timezone(&chrono::Local);
+                        egui_plot::Plot::new("portfolio_value")
+                            .height(250.0)
+                            .x_axis_formatter(move |mark, _| time_at(mark.value).format("%m-%d %H:%M").to_string())
+                            .label_formatter(move |_, point| format!("{}\n{:.2} USDT", time_at(point.x).format("%Y-%m-%d %H:%M"), point.y))
+                            .show(ui, |plot_ui| {
+                                plot_ui.line(egui_plot::Line::new(points).name("Portfolio value"));
+                                for x in event_lines {
+                                    plot_ui.vline(egui_plot::VLine::new(x).color(Color32::GRAY).style(egui_plot::LineStyle::dotted_dense()));
+                                }
+                            });
+                        ui.weak("Dotted lines mark rebalancing trades.");
+                    }
+                    if ui.add_enabled(!indices.is_empty(), Button::new("Download Chart Data as CSV")).clicked() {
+                        self.export_value_history_csv();
+                    }
+                    if let AllocationMode::RiskParity(risk_parity) = self.profiles.active().allocation_mode {
+                        ui.add_space(10.0);
+                        Grid::new("risk_parity_grid").num_columns(2).spacing([20.0, 6.0]).show(ui, |ui| {
+                            ui.label("Last rebalanced at:");
+                            match self.event_history.last() {
+                                Some(event) => { ui.label(event.timestamp.format("%Y-%m-%d %H:%M:%S").to_string()); }
+                                None => { ui.weak("never"); }
+                            }
+                            ui.end_row();
+                            ui.label("Target vol:");
+                            ui.label(format!("{}%", format_number(risk_parity.target_volatility * 100.0, 1, locale))).on_hover_text(format!("Annualised, from {} days of daily closes", risk_parity.lookback_days));
+                            ui.end_row();
+                        });
+                    }
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        ui.strong("Realized P&L by Year");
+                        if ui.add_enabled(!self.realized_gains.is_empty(), Button::new("Tax Report")).on_hover_text(format!("Export realized gains and losses to CSV; lots are matched {}", self.profiles.active().cost_basis_method)).clicked() {
+                            self.export_tax_report();
+                        }
+                    });
+                    let annual = tax::annual_summary(&self.realized_gains);
+                    if annual.is_empty() {
+                        ui.weak("No sells recorded yet.");
+                    } else {
+                        Grid::new("annual_pnl_grid").num_columns(2).spacing([20.0, 6.0]).striped(true).show(ui, |ui| {
+                            for (year, gain) in annual {
+                                ui.label(format!("{}:", year));
+                                let color = if gain < 0.0 { Color32::RED } else { Color32::GREEN };
+                                ui.colored_label(color, self.amount_text(gain).0);
+                                ui.end_row();
+                            }
+                        });
+                    }
+                    ui.add_space(10.0);
+                    ui.label("Margin Ratio:");
+                    match self.margin_ratio() {
+                        Some(ratio) => { MarginGauge::new(ratio, self.profiles.active().margin_alert_threshold_pct).show(ui); }
+                        None => { ui.weak("No open positions reported yet."); }
+                    }
+                    ui.add_space(10.0); ui.separator(); ui.add_space(10.0);
+                    ui.heading("Composition (资产构成)");
+                    ui.add_space(5.0);
+                    let target_slices = self.target_slices();
+                    let tag_slices = self.tag_slices();
+                    ui.columns(2, |columns| {
+                        columns[0].label(RichText::new("By Asset").strong());
+                        AllocationPieChart::new(&target_slices).show(&mut columns[0]);
+                        columns[1].label(RichText::new("By Tag").strong())
+                            .on_hover_text("Saved targets summed per tag; an asset with several tags is split evenly between them. Tags are set in the Portfolio Config tab.");
+                        AllocationPieChart::new(&tag_slices).show(&mut columns[1]);
+                    });
+                    ui.add_space(10.0); ui.separator(); ui.add_space(10.0);
+                    ui.heading("Monte Carlo Simulation (蒙特卡洛模拟)");
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        let can_simulate = !self.price_history.is_empty() && self.price_history.iter().all(|series| series.len() > 2);
+                        let button = ui.add_enabled(can_simulate && self.simulation_receiver.is_none(), Button::new("Simulate"))
+                            .on_hover_text(format!("{} paths of {} days with the drift and volatility of the current targets over the backend's price history", MONTE_CARLO_SIMS, MONTE_CARLO_DAYS))
+                            .on_disabled_hover_text("Start the rebalancer to load price history.");
+                        if button.clicked() { self.start_simulation(ctx); }
+                        if self.simulation_receiver.is_some() { ui.spinner(); }
+                    });
+                    if let Some(summary) = &self.simulation {
+                        let initial = self.total_value_usdt.filter(|v| *v > 0.0).unwrap_or(1.0);
+                        Grid::new("simulation_grid").num_columns(2).spacing([20.0, 6.0]).striped(true).show(ui, |ui| {
+                            ui.label("Median Final Value:"); ui.label(self.amount_text(summary.median_final * initial).0); ui.end_row();
+                            ui.label("Value at Risk (5th pct.):").on_hover_text("In 95% of the simulated paths the portfolio ends above this value.");
+                            ui.label(self.amount_text(summary.value_at_risk * initial).0); ui.end_row();
+                            ui.label("Chance of Ending Above Today:"); ui.label(format!("{}%", format_number(summary.probability_above_initial * 100.0, 1, locale))); ui.end_row();
+                        });
+                        // One trapezoid per day, since plot polygons are only filled correctly when convex
+                        let band = |lower: usize, upper: usize, color: Color32| -> Vec<egui_plot::Polygon<'static>> {
+                            summary.bands.windows(2).enumerate().map(|(day, w)| {
+                                let x = day as f64;
+                                egui_plot::Polygon::new(vec![[x, w[0][lower] * initial], [x + 1.0, w[1][lower] * initial], [x + 1.0, w[1][upper] * initial], [x, w[0][upper] * initial]])
+                                    .fill_color(color).stroke(Stroke::NONE)
+                            }).collect()
+                        };
+                        let outer = band(0, 4, Color32::from_rgba_unmultiplied(100, 150, 255, 40));
+                        let inner = band(1, 3, Color32::from_rgba_unmultiplied(100, 150, 255, 80));
+                        let median: Vec<[f64; 2]> = summary.bands.iter().enumerate().map(|(day, b)| [day as f64, b[2] * initial]).collect();
+                        egui_plot::Plot::new("monte_carlo_fan")
+                            .height(250.0)
+                            .x_axis_formatter(|mark, _| format!("day {}", mark.value))
+                            .show(ui, |plot_ui| {
+                                for polygon in outer.into_iter().chain(inner) { plot_ui.polygon(polygon); }
+                                plot_ui.line(egui_plot::Line::new(median).name("Median"));
+                            });
+                        ui.weak(format!("Bands: {:.0}th–{:.0}th and {:.0}th–{:.0}th percentiles.", FAN_PERCENTILES[0], FAN_PERCENTILES[4], FAN_PERCENTILES[1], FAN_PERCENTILES[3]));
+                    }
+                    ui.add_space(10.0); ui.separator(); ui.add_space(10.0);
+                    ui.heading("Correlation (相关性)");
+                    ui.add_space(5.0);
+                    match &self.correlation_matrix {
+                        Some(matrix) => {
+                            ui.label("Correlation of daily returns over the last 30 days.");
+                            CorrelationHeatmap::new(&self.correlation_symbols, matrix).show(ui);
+                            ui.add_space(5.0);
+                            // Exposure of each correlated symbol under the targets in use
+                            let target = self.profiles.active().target_allocation(&self.market_data);
+                            let weights: Vec<f64> = self.correlation_symbols.iter()
+                                .map(|symbol| target.assets.iter().find(|a| a.symbol == *symbol).map_or(0.0, |a| a.target_pct * a.leverage))
+                                .collect();
+                            let score = metrics::diversification_score(&weights, matrix);
+                            let (color, verdict) = if score <= metrics::DIVERSIFIED_SCORE {
+                                (Color32::GREEN, "well diversified")
+                            } else if score <= metrics::MODERATE_SCORE {
+                                (Color32::YELLOW, "moderately diversified")
+                            } else {
+                                (Color32::RED, "concentrated")
+                            };
+                            ui.horizontal(|ui| {
+                                ui.label("Diversification Score:");
+                                ui.colored_label(color, format!("{} ({})", format_number(score, 2, locale), verdict)).on_hover_text(format!(
+                                    "Portfolio variance over the average variance of its assets, from the correlations above and the target exposures. {} means the assets move as one; up to {} is well diversified, above {} concentrated.",
+                                    format_number(1.0, 1, locale), format_number(metrics::DIVERSIFIED_SCORE, 1, locale), format_number(metrics::MODERATE_SCORE, 1, locale)
+                                ));
+                            });
+                            if score > metrics::MODERATE_SCORE {
+                                ui.colored_label(Color32::RED, "Your portfolio is highly concentrated; consider adding uncorrelated assets.");
+                            }
+                        }
+                        None => { ui.weak("Start the rebalancer to load price history."); }
+                    }
+                });
+            }
+
+            if self.active_tab == Tab::Backtest {
+                ui.group(|ui| {
+                    ui.heading("Backtest (回测)");
+                    ui.add_space(5.0);
+                    ui.label("Replays daily closes from a CSV (date,BTC_USDT,ETH_USDT,...) through the saved portfolio config. Fees and funding are ignored.");
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("Choose Price CSV").clicked() {
+                            if let Some(path) = rfd::FileDialog::new().add_filter("CSV", &["csv"]).pick_file() {
+                                self.backtest_csv = Some(path);
+                            }
+                        }
+                        match &self.backtest_csv {
+                            Some(path) => { ui.label(path.display().to_string()); }
+                            None => { ui.weak("No file chosen"); }
+                        }
+                    });
+                    Grid::new("backtest_grid").num_columns(2).spacing([10.0, 4.0]).show(ui, |ui| {
+                        ui.label("Start Date:"); ui.add(TextEdit::singleline(&mut self.backtest_start).hint_text("YYYY-MM-DD").desired_width(100.0)); ui.end_row();
+                        ui.label("End Date:"); ui.add(TextEdit::singleline(&mut self.backtest_end).hint_text("YYYY-MM-DD").desired_width(100.0)); ui.end_row();
+                        ui.label("Initial USDT:"); ui.add(TextEdit::singleline(&mut self.backtest_initial_usdt).desired_width(100.0)); ui.end_row();
+                    });
+                    if ui.button("Run").clicked() {
+                        if let Err(e) = self.run_backtest() {
+                            self.error_message = Some(e.to_string());
+                        }
+                    }
+                    if let Some(result) = &self.backtest_result {
+                        ui.add_space(10.0);
+                        let total_return = result.total_return_pct();
+                        let color = if total_return >= 0.0 { Color32::GREEN } else { Color32::RED };
+                        ui.horizontal(|ui| {
+                            ui.label("Total Return:");
+                            ui.colored_label(color, format!("{:+.2}%", total_return));
+                            ui.label(format!("({} days, {} trades)", result.snapshots.len(), result.events.len()));
+                        });
+                        if let Some(first) = result.snapshots.first() {
+                            let start = first.date;
+                            let points: Vec<[f64; 2]> = result.snapshots.iter().map(|s| [(s.date - start).num_days() as f64, s.total_value]).collect();
+                            egui_plot::Plot::new("equity_curve")
+                                .height(250.0)
+                                .x_axis_formatter(move |mark, _| (start + chrono::Duration::days(mark.value as i64)).to_string())
+                                .label_formatter(move |_, point| format!("{}\n{:.2} USDT", start + chrono::Duration::days(point.x.round() as i64), point.y))
+                                .show(ui, |plot_ui| plot_ui.line(egui_plot::Line::new(points).name("Portfolio value")));
+                        }
+                    }
+                });
+            }
+
+            if self.active_tab == Tab::History {
+                ui.group(|ui| {
+                    ui.heading("Rebalancing History (再平衡记录)");
+                    ui.add_space(5.0);
+                    if self.event_history.is_empty() {
+                        ui.label("No rebalancing trades recorded yet.");
+                        return;
+                    }
+                    let tag_names = self.profiles.active().portfolio_allocation.tag_names();
+                    ui.horizontal(|ui| {
+                        if ui.button("Export History CSV").clicked() {
+                            self.export_history_csv();
+                        }
+                        ui.separator();
+                        ui.label("Tag:");
+                        egui::ComboBox::from_id_salt("history_tag_filter")
+                            .selected_text(self.filter_by_tag.as_deref().unwrap_or("All assets"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.filter_by_tag, None, "All assets");
+                                for tag in tag_names {
+                                    let label = tag.clone();
+                                    ui.selectable_value(&mut self.filter_by_tag, Some(tag), label);
+                                }
+                            });
+                    });
+                    ui.add_space(5.0);
+                    // Assets tagged with the filter under the current targets
+                    let symbols: Option<Vec<String>> = self.filter_by_tag.as_ref().map(|tag| {
+                        self.profiles.active().portfolio_allocation.symbols_with_tag(tag).into_iter().map(str::to_string).collect()
+                    });
+                    let mut rows: Vec<&RebalanceEvent> = self.event_history.iter()
+                        .filter(|event| symbols.as_ref().is_none_or(|symbols| symbols.contains(&event.asset)))
+                        .collect();
+                    if rows.is_empty() {
+                        ui.weak("No trades recorded for the assets with this tag.");
+                    }
+                    let column = self.history_sort_column;
+                    rows.sort_by(|a, b| if self.history_sort_ascending { column.compare(a, b) } else { column.compare(b, a) });
+                    let mut clicked_column = None;
+                    egui::ScrollArea::both().max_height(300.0).show(ui, |ui| {
+                        Grid::new("history_grid").num_columns(HistoryColumn::ALL.len()).spacing([12.0, 4.0]).striped(true).show(ui, |ui| {
+                            for column in HistoryColumn::ALL {
+                                let label = if column == self.history_sort_column {
+                                    format!("{} {}", column.label(), if self.history_sort_ascending { "⬆" } else { "⬇" })
+                                } else {
+                                    column.label().to_string()
+                                };
+                                if ui.add(Button::new(RichText::new(label).strong()).frame(false)).clicked() {
+                                    clicked_column = Some(column);
+                                }
+                            }
+                            ui.end_row();
+                            for event in rows {
+                                ui.label(event.timestamp.format("%Y-%m-%d %H:%M:%S").to_string());
+                                ui.label(&event.asset);
+                                ui.label(format_number(event.from_pct, 1, locale));
+                                ui.label(format_number(event.to_pct, 1, locale));
+                                ui.label(format!("{:.2}", event.usdt_traded));
+                                ui.label(RichText::new(&event.order_id).monospace());
+                                ui.end_row();
+                            }
+                        });
+                    });
+                    if let Some(column) = clicked_column {
+                        self.toggle_history_sort(column);
+                    }
+                });
+            }
+
+            if self.active_tab == Tab::Orders {
+                ui.group(|ui| {
+                    ui.heading("Orders (订单)");
+                    ui.add_space(5.0);
+                    if self.orders.is_empty() {
+                        ui.label("No orders placed yet.");
+                        return;
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Search:");
+                        if ui.add(TextEdit::singleline(&mut self.orders_search).hint_text("Symbol or order ID").desired_width(200.0)).changed() {
+                            self.orders_page = 0;
+                        }
+                    });
+                    ui.add_space(5.0);
+                    // Newest first
+                    let rows: Vec<&Order> = self.orders.iter().rev().filter(|o| o.matches(&self.orders_search)).collect();
+                    let pages = history::page_count(rows.len());
+                    self.orders_page = self.orders_page.min(pages - 1);
+                    egui::ScrollArea::both().max_height(300.0).show(ui, |ui| {
+                        Grid::new("orders_grid").num_columns(8).spacing([12.0, 4.0]).striped(true).show(ui, |ui| {
+                            for header in ["Time (UTC)", "Order ID", "Symbol", "Side", "Qty", "Price", "Status", ""] {
+                                ui.strong(header);
+                            }
+                            ui.end_row();
+                            for order in rows.iter().skip(self.orders_page * history::ORDERS_PER_PAGE).take(history::ORDERS_PER_PAGE) {
+                                let first = ui.label(order.created_at.format("%Y-%m-%d %H:%M:%S").to_string());
+                                ui.label(RichText::new(&order.id).monospace());
+                                ui.label(&order.symbol);
+                                ui.label(order.side.to_string());
+                                ui.label(format!("{:.4}", order.qty));
+                                ui.label(format!("{:.4}", order.price));
+                                let last = ui.label(order.status.to_string());
+                                // Only the hovered row shows its copy button
+                                let row_rect = first.rect.union(last.rect);
+                                if ui.rect_contains_pointer(row_rect.with_max_x(row_rect.max.x + 120.0)) {
+                                    if ui.small_button("Copy Order ID").clicked() {
+                                        ui.ctx().copy_text(order.id.clone());
+                                    }
+                                } else {
+                                    ui.label("");
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(self.orders_page > 0, Button::new("◀")).clicked() {
+                            self.orders_page -= 1;
+                        }
+                        ui.label(format!("page {} of {}", self.orders_page + 1, pages));
+                        if ui.add_enabled(self.orders_page + 1 < pages, Button::new("▶")).clicked() {
+                            self.orders_page += 1;
+                        }
+                        ui.weak(format!("{} orders", rows.len()));
+                    });
+                });
+            }
 
             // Add link only when running
             if self.is_running {
                 ui.add_space(10.0);
+                let exchange = self.profiles.active().exchange;
                 ui.hyperlink_to(
-                    "View TestNet Positions on Gate.io",
-                    "https://www.gate.io/en/testnet/futures_trade/USDT/BTC_USDT",
+                    format!("View TestNet Positions on {}", exchange.label()),
+                    exchange.testnet_url(),
                 );
             }
 
+            // Dry run preview
+            let mut dry_run_action = None;
+            let min_trade_usdt = self.profiles.active().min_trade_usdt;
+            if let Some((plan, _)) = &self.dry_run {
+                egui::Window::new("Dry Run")
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ctx, |ui| {
+                        if plan.is_empty() && plan.skipped.is_empty() {
+                            ui.label("All assets are within the rebalance threshold; no trades needed.");
+                        } else {
+                            Grid::new("dry_run_grid").num_columns(6).spacing([15.0, 4.0]).striped(true).show(ui, |ui| {
+                                ui.strong("Asset");
+                                ui.strong("Side");
+                                ui.strong("Notional");
+                                ui.strong("Est. Fee");
+                                ui.strong("Est. Slippage");
+                                ui.strong("Reason");
+                                ui.end_row();
+                                for trade in &plan.trades {
+                                    ui.label(&trade.symbol);
+                                    ui.label(trade.side.to_string());
+                                    let (text, usdt) = self.amount_text(trade.notional_usdt);
+                                    let response = ui.label(text);
+                                    if let Some(usdt) = usdt { response.on_hover_text(usdt); }
+                                    ui.label(self.amount_text(trade.estimated_fee_usdt).0);
+                                    ui.label(self.amount_text(trade.estimated_slippage_usdt).0);
+                                    ui.label(&trade.reason);
+                                    ui.end_row();
+                                }
+                                let skipped_tooltip = format!("Skipped: smaller than the {} USDT minimum trade size.", min_trade_usdt);
+                                for trade in &plan.skipped {
+                                    ui.weak(&trade.symbol).on_hover_text(&skipped_tooltip);
+                                    ui.weak(trade.side.to_string()).on_hover_text(&skipped_tooltip);
+                                    ui.weak(self.amount_text(trade.notional_usdt).0).on_hover_text(&skipped_tooltip);
+                                    ui.weak("—").on_hover_text(&skipped_tooltip);
+                                    ui.weak("—").on_hover_text(&skipped_tooltip);
+                                    ui.weak(&trade.reason).on_hover_text(&skipped_tooltip);
+                                    ui.end_row();
+                                }
+                                ui.strong("Total estimated costs:");
+                                ui.label(""); ui.label("");
+                                ui.strong(self.amount_text(plan.total_fees_usdt()).0);
+                                ui.strong(self.amount_text(plan.total_slippage_usdt()).0);
+                                ui.end_row();
+                            });
+                        }
+                        ui.add_space(10.0);
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(!plan.is_empty(), Button::new("Execute")).clicked() {
+                                dry_run_action = Some(true);
+                            }
+                            if ui.button("Cancel").clicked() {
+                                dry_run_action = Some(false);
+                            }
+                        });
+                    });
+            }
+            match dry_run_action {
+                Some(true) => self.execute_dry_run(ctx),
+                Some(false) => self.dry_run = None,
+                None => {}
+            }
+
+            // Clipboard paste preview
+            let mut paste_action = None;
+            if let Some((_, changes)) = &self.pending_paste {
+                egui::Window::new("Paste Allocation")
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ctx, |ui| {
+                        ui.label(format!("This will change {}. Apply?", changes.join(", ")));
+                        ui.add_space(10.0);
+                        ui.horizontal(|ui| {
+                            if ui.button("Confirm").clicked() {
+                                paste_action = Some(true);
+                            }
+                            if ui.button("Cancel").clicked() {
+                                paste_action = Some(false);
+                            }
+                        });
+                    });
+            }
+            match paste_action {
+                Some(true) => self.confirm_paste(),
+                Some(false) => self.pending_paste = None,
+                None => {}
+            }
+
+            // Stop / start confirmation
+            if self.pending_stop_confirm {
+                match confirm_window(
+                    ctx,
+                    "Stop Rebalancer",
+                    "Are you sure you want to stop the rebalancer? Any in-progress trades may not complete.",
+                    "Yes, Stop",
+                ) {
+                    Some(true) => {
+                        self.pending_stop_confirm = false;
+                        self.stop_backend_manually();
+                    }
+                    Some(false) => self.pending_stop_confirm = false,
+                    None => {}
+                }
+            }
+            if self.pending_start_confirm {
+                match confirm_window(
+                    ctx,
+                    "Start Rebalancer",
+                    "The portfolio config has unsaved changes. The rebalancer will start with the last saved config.",
+                    "Start Anyway",
+                ) {
+                    Some(true) => {
+                        self.pending_start_confirm = false;
+                        self.start_backend_manually(ctx);
+                    }
+                    Some(false) => self.pending_start_confirm = false,
+                    None => {}
+                }
+            }
+
+            // What-if allocation
+            let mut what_if_open = self.what_if.is_some();
+            let mut what_if_changed = false;
+            let mut apply_what_if = false;
+            if let Some(what_if) = &mut self.what_if {
+                let editor = &self.portfolio_editor;
+                let display_currency = self.profiles.active().display_currency;
+                let display_prices = &self.display_prices;
+                let amount = |usdt: f64| currency::format_amount(usdt, display_currency, display_prices, locale);
+                egui::Window::new("Simulate Changes").open(&mut what_if_open).resizable(false).show(ctx, |ui| {
+                    ui.weak("Proposed weights are planned against the current balances; nothing is saved.");
+                    ui.add_space(5.0);
+                    Grid::new("what_if_allocation_grid").num_columns(3).spacing([10.0, 4.0]).show(ui, |ui| {
+                        for asset in &mut what_if.proposed.assets {
+                            ui.label(format!("{}:", asset.symbol));
+                            what_if_changed |= ui.add(Slider::new(&mut asset.target_pct, 0.0..=100.0).step_by(0.5).suffix("%")).changed();
+                            match editor.assets.iter().find(|row| row.symbol == asset.symbol) {
+                                Some(row) => { ui.weak(format!("editor: {}%", row.allocation.trim())); }
+                                None => { ui.label(""); }
+                            }
+                            ui.end_row();
+                        }
+                        ui.label(format!("{}:", USDT));
+                        ui.label(format!("{}%", format_number(what_if.proposed.usdt(), 1, locale)));
+                        ui.end_row();
+                    });
+                    let crypto_total = what_if.proposed.crypto_total();
+                    if crypto_total > 100.0 {
+                        ui.colored_label(Color32::RED, format!("Sum of asset allocations ({}%) cannot exceed 100%.", format_number(crypto_total, 1, locale)));
+                    }
+                    ui.add_space(10.0);
+                    if what_if.snapshot.is_none() {
+                        ui.horizontal(|ui| { ui.spinner(); ui.label("Fetching balances and prices…"); });
+                    } else if what_if.plan.is_empty() {
+                        ui.label("All assets are within the rebalance threshold; no trades needed.");
+                    } else {
+                        Grid::new("what_if_trades_grid").num_columns(4).spacing([15.0, 4.0]).striped(true).show(ui, |ui| {
+                            ui.strong("Asset"); ui.strong("Side"); ui.strong("Notional"); ui.strong("Est. Fee"); ui.end_row();
+                            for trade in &what_if.plan.trades {
+                                ui.label(&trade.symbol);
+                                ui.label(trade.side.to_string());
+                                ui.label(amount(trade.notional_usdt));
+                                ui.label(amount(trade.estimated_fee_usdt));
+                                ui.end_row();
+                            }
+                            ui.strong("Total:"); ui.label(""); ui.label("");
+                            ui.strong(amount(what_if.plan.total_fees_usdt()));
+                            ui.end_row();
+                        });
+                        if !what_if.plan.skipped.is_empty() {
+                            ui.weak(format!("{} trades under the minimum trade size skipped", what_if.plan.skipped.len()));
+                        }
+                    }
+                    ui.add_space(10.0);
+                    apply_what_if = ui.add_enabled(crypto_total <= 100.0, Button::new("Apply These Settings")).on_hover_text("Copy the proposed weights into the portfolio editor").clicked();
+                });
+            }
+            if apply_what_if {
+                self.apply_what_if();
+            } else if !what_if_open {
+                self.what_if = None;
+            } else if what_if_changed {
+                self.update_what_if_plan();
+            }
+
             // Footer
             ui.with_layout(Layout::bottom_up(Align::Center), |ui| {
                 ui.add_space(5.0); ui.separator(); ui.add_space(5.0);
                 ui.label(format!("KIN Portfolio Rebalancer v0.1.0 | Config: {}", self.config_path.display()));
+                ui.horizontal(|ui| {
+                    if ConfigFormat::from_path(&self.config_path) == ConfigFormat::Json && ui.small_button("Convert to TOML").clicked() {
+                        if let Err(e) = self.convert_config_to_toml() {
+                            self.error_message = Some(format!("Failed to convert config: {}", e));
+                        }
+                    }
+                    if ui.small_button("Export Config CSV").clicked() {
+                        self.export_config_csv();
+                    }
+                    let mut restore = None;
+                    egui::ComboBox::from_id_salt("restore_backup")
+                        .selected_text("Restore Backup")
+                        .show_ui(ui, |ui| {
+                            let backup_count = self.profiles.active().backup_count;
+                            let backups = self.config_backups.get_or_insert_with(|| {
+                                Config::list_backups(&self.config_path, backup_count, self.passphrase.as_deref())
+                            });
+                            if backups.is_empty() {
+                                ui.weak("No backups yet");
+                            }
+                            for backup in backups.iter() {
+                                let saved_at = backup.saved_at
+                                    .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+                                    .unwrap_or_else(|| "unknown time".to_string());
+                                if ui.selectable_label(false, format!("#{}  {}", backup.n, saved_at))
+                                    .on_hover_text("The current file becomes backup #1")
+                                    .clicked()
+                                {
+                                    restore = Some(backup.n);
+                                }
+                            }
+                        });
+                    if let Some(n) = restore {
+                        if let Err(e) = self.restore_backup(n) {
+                            self.error_message = Some(format!("Failed to restore backup: {}", e));
+                        }
+                    }
+                    ui.separator();
+                    ui.label("Display currency:");
+                    let mut selected = None;
+                    egui::ComboBox::from_id_salt("display_currency")
+                        .selected_text(self.display_currency().code())
+                        .width(60.0)
+                        .show_ui(ui, |ui| {
+                            for currency in DisplayCurrency::ALL {
+                                if ui.selectable_label(self.display_currency() == currency, currency.code()).clicked() {
+                                    selected = Some(currency);
+                                }
+                            }
+                        });
+                    if let Some(currency) = selected.filter(|c| *c != self.display_currency()) {
+                        self.set_display_currency(currency, ctx);
+                    }
+                });
                 ui.add_space(5.0);
             });
         }); // End CentralPanel
     }
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
-        println!("Exit requested. Stopping backend...");
-        self.stop_backend();
-        println!("Backend stopped. Exiting.");
+        if let Some((position, size)) = self.window_geometry {
+            let state = WindowState {
+                x: position.x,
+                y: position.y,
+                width: size.x,
+                height: size.y,
+                active_tab: self.active_tab,
+                sidebar_ratio: self.sidebar_ratio,
+            };
+            let path = WindowState::default_path();
+            if let Err(e) = state.save(&path) {
+                log::warn!("Failed to save window state ({:?}): {}", path, e);
+            }
+        }
+        log::info!("Exit requested. Stopping backends...");
+        self.stop_all_portfolios();
+        log::info!("Backends stopped. Exiting.");
     }
 }
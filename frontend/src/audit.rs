@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config::PortfolioAllocation;
+use crate::error::KinError;
+
+/// State-changing actions taken by the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    StartBackend,
+    StopBackend,
+    SavePortfolioConfig,
+    UpdateApiKey,
+    ProfileSwitch,
+}
+
+/// One line of the audit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub action: AuditAction,
+    pub details: String,
+}
+
+impl AuditEntry {
+    pub fn new(action: AuditAction, details: impl Into<String>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            action,
+            details: details.into(),
+        }
+    }
+}
+
+/// Append-only JSONL file of `AuditEntry`s; entries are never rewritten.
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".portfolio_rebalancer_audit.log")
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, entry: &AuditEntry) -> Result<(), KinError> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        // One write per entry so concurrent appends do not interleave
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Entries in the order they were written; lines that do not parse are skipped.
+    pub fn load(&self) -> Result<Vec<AuditEntry>, KinError> {
+        let text = fs::read_to_string(&self.path)?;
+        Ok(text
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+/// "BTC_USDT 25% → 30%, SOL_USDT added at 5%, LTC_USDT removed (was 10%)", or
+/// "allocation unchanged".
+pub fn allocation_changes(old: &PortfolioAllocation, new: &PortfolioAllocation) -> String {
+    let mut changes = Vec::new();
+    for asset in &new.assets {
+        match old.get(&asset.symbol) {
+            Some(pct) if pct == asset.target_pct => {}
+            Some(pct) => changes.push(format!("{} {}% → {}%", asset.symbol, pct, asset.target_pct)),
+            None => changes.push(format!("{} added at {}%", asset.symbol, asset.target_pct)),
+        }
+    }
+    for asset in &old.assets {
+        if new.get(&asset.symbol).is_none() {
+            changes.push(format!(
+                "{} removed (was {}%)",
+                asset.symbol, asset.target_pct
+            ));
+        }
+    }
+    if changes.is_empty() {
+        "allocation unchanged".to_string()
+    } else {
+        changes.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AssetAllocation;
+
+    #[test]
+    fn appends_entries_as_jsonl() {
+        let dir = std::env::temp_dir().join(format!("kin-audit-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let log = AuditLog::new(&dir.join("audit.log"));
+        let old = PortfolioAllocation {
+            assets: vec![
+                AssetAllocation::new("BTC_USDT", 25.0),
+                AssetAllocation::new("LTC_USDT", 10.0),
+            ],
+        };
+        let new = PortfolioAllocation {
+            assets: vec![
+                AssetAllocation::new("BTC_USDT", 30.0),
+                AssetAllocation::new("SOL_USDT", 5.0),
+            ],
+        };
+        let details = allocation_changes(&old, &new);
+        assert_eq!(
+            details,
+            "BTC_USDT 25% → 30%, SOL_USDT added at 5%, LTC_USDT removed (was 10%)"
+        );
+        assert_eq!(allocation_changes(&old, &old), "allocation unchanged");
+
+        log.append(&AuditEntry::new(AuditAction::SavePortfolioConfig, details))
+            .unwrap();
+        log.append(&AuditEntry::new(AuditAction::StopBackend, ""))
+            .unwrap();
+
+        let text = fs::read_to_string(log.path()).unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.contains("\"action\":\"save_portfolio_config\""));
+        let entries = log.load().unwrap();
+        let actions: Vec<AuditAction> = entries.iter().map(|e| e.action).collect();
+        assert_eq!(
+            actions,
+            [AuditAction::SavePortfolioConfig, AuditAction::StopBackend]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,185 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+
+use crate::backend_client;
+use crate::config::{Config, PortfolioAllocation};
+use crate::error::KinError;
+use crate::ipc;
+
+// The Python package lives at the repository root, next to `frontend/`
+pub fn backend_dir() -> PathBuf {
+    let cwd = env::current_dir().unwrap_or_default();
+    cwd.ancestors()
+        .find(|dir| dir.join("backend").join("main.py").exists())
+        .map(Path::to_path_buf)
+        .unwrap_or(cwd)
+}
+
+/// Secret the backends of this session require on their mutating HTTP routes;
+/// random per process, handed over as `KIN_HTTP_TOKEN`.
+pub fn api_token() -> &'static str {
+    static TOKEN: OnceLock<String> = OnceLock::new();
+    TOKEN.get_or_init(|| {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        hex::encode(bytes)
+    })
+}
+
+/// Where a backend serves the frontend. Backends running at the same time need
+/// one each.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendEndpoint {
+    pub ipc_path: String,
+    pub http_port: u16,
+    pub http_token: String,
+}
+
+impl Default for BackendEndpoint {
+    fn default() -> Self {
+        BackendEndpoint::for_slot(0)
+    }
+}
+
+impl BackendEndpoint {
+    /// Slot 0 is `ipc::default_path()` on `backend_client::DEFAULT_PORT`; later
+    /// slots count up from there.
+    pub fn for_slot(slot: usize) -> Self {
+        Self {
+            ipc_path: ipc::path_for_slot(slot),
+            http_port: backend_client::DEFAULT_PORT.saturating_add(slot as u16),
+            http_token: api_token().to_string(),
+        }
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.http_port)
+    }
+}
+
+/// Launches `backend.main` with piped stdio. The backend writes one JSON
+/// `BackendMessage` per stdout line until a frontend connects to its socket at
+/// `endpoint.ipc_path`, and serves the HTTP API `BackendClient` talks to on
+/// `endpoint.http_port`, guarded by `endpoint.http_token`. Credentials are passed through the environment
+/// so the config file only holds empty placeholders.
+///
+/// The backend cannot read an encrypted config file, so the decrypted config is
+/// handed over as `config_json` instead.
+///
+/// `config.paper_trading` and `config.exchange` are passed explicitly as well so
+/// the backend switches to simulated execution or the chosen exchange's client
+/// even when an older config file lacks them. `targets` are the weights
+/// `Config::target_allocation` resolved, which the backend cannot compute itself.
+pub fn spawn(
+    config_path: &Path,
+    api_key: &str,
+    api_secret: &str,
+    config_json: Option<&str>,
+    config: &Config,
+    targets: &PortfolioAllocation,
+    endpoint: &BackendEndpoint,
+) -> Result<Child, KinError> {
+    let python = if cfg!(windows) { "python" } else { "python3" };
+    let mut command = Command::new(python);
+    command
+        .args(["-u", "-m", "backend.main", "--config"])
+        .arg(config_path)
+        .current_dir(backend_dir())
+        .env("KIN_API_KEY", api_key)
+        .env("KIN_API_SECRET", api_secret)
+        .env(
+            "KIN_PAPER_TRADING",
+            if config.paper_trading { "1" } else { "0" },
+        )
+        .env("KIN_EXCHANGE", config.exchange.id())
+        .env("KIN_TARGET_ALLOCATION", serde_json::to_string(targets)?)
+        .env("KIN_IPC_PATH", &endpoint.ipc_path)
+        .env("KIN_HTTP_PORT", endpoint.http_port.to_string())
+        .env("KIN_HTTP_TOKEN", &endpoint.http_token);
+    if let Some(config_json) = config_json {
+        command.env("KIN_CONFIG_JSON", config_json);
+    }
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(KinError::BackendLaunch)
+}
+
+/// How long `shutdown` waits for the backend to exit on its own before killing it.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Asks the backend to exit (SIGTERM on Unix, `taskkill` on Windows) so it can
+/// report its final status, and kills it if it is still running after
+/// `SHUTDOWN_TIMEOUT`.
+pub fn shutdown(child: &mut Child) -> Result<ExitStatus, KinError> {
+    if request_exit(child.id()) {
+        if let Some(status) = wait_for_exit(child, SHUTDOWN_TIMEOUT)? {
+            return Ok(status);
+        }
+        log::warn!(
+            "Backend did not exit within {}s, killing it.",
+            SHUTDOWN_TIMEOUT.as_secs()
+        );
+    }
+    child.kill()?;
+    Ok(child.wait()?)
+}
+
+/// Exit status of `child` once it has exited, or None if it still runs after `timeout`.
+pub fn wait_for_exit(child: &mut Child, timeout: Duration) -> Result<Option<ExitStatus>, KinError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+// 通过系统命令发送信号，避免为此引入libc依赖
+#[cfg(unix)]
+fn request_exit(pid: u32) -> bool {
+    Command::new("kill")
+        .args(["-TERM", &pid.to_string()])
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+// Without /F; fails for processes that cannot be closed gracefully
+#[cfg(windows)]
+fn request_exit(pid: u32) -> bool {
+    Command::new("taskkill")
+        .args(["/PID", &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn shutdown_stops_a_running_process() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let started = Instant::now();
+        let status = shutdown(&mut child).unwrap();
+        assert!(!status.success());
+        assert!(started.elapsed() < SHUTDOWN_TIMEOUT);
+    }
+}
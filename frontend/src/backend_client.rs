@@ -0,0 +1,208 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::error::KinError;
+use crate::planner::RebalancePlan;
+
+/// Port the backend serves its HTTP API on; handed to it as `KIN_HTTP_PORT`.
+pub const DEFAULT_PORT: u16 = 47390;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+// A manual rebalance places several orders, so allow it more time than a status poll
+const REBALANCE_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub fn default_base_url() -> String {
+    format!("http://127.0.0.1:{}", DEFAULT_PORT)
+}
+
+/// Answer to `GET /status`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BackendStatus {
+    pub status: String,
+    #[serde(default)]
+    pub paper_trading: bool,
+}
+
+/// Answer to `GET /snapshot`; same fields as `BackendMessage::PortfolioSnapshot`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PortfolioSnapshot {
+    // Actual allocation in percent, keyed by symbol (USDT included)
+    pub allocations: HashMap<String, f64>,
+    pub total_value_usdt: f64,
+    #[serde(default)]
+    pub funding_rates: HashMap<String, f64>,
+}
+
+/// Header carrying `BackendEndpoint::http_token` on mutating requests.
+pub const TOKEN_HEADER: &str = "X-Kin-Token";
+
+/// Client for the HTTP API in `backend/http_api.py`. Requests block, so call
+/// it off the UI thread.
+#[derive(Debug, Clone)]
+pub struct BackendClient {
+    base_url: String,
+    token: String,
+    client: reqwest::blocking::Client,
+}
+
+impl BackendClient {
+    pub fn new(base_url: &str) -> BackendClient {
+        BackendClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: String::new(),
+            client: reqwest::blocking::Client::new(),
+        }
+    }
+
+    /// The token the backend was started with; POST requests are refused without it.
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = token.to_string();
+        self
+    }
+
+    pub fn get_status(&self) -> Result<BackendStatus, KinError> {
+        self.request(self.client.get(self.url("/status")), REQUEST_TIMEOUT)
+    }
+
+    pub fn get_snapshot(&self) -> Result<PortfolioSnapshot, KinError> {
+        self.request(self.client.get(self.url("/snapshot")), REQUEST_TIMEOUT)
+    }
+
+    /// Rebalances to the target right away, ignoring the threshold, and returns
+    /// the trades that were executed.
+    pub fn trigger_rebalance(&self) -> Result<RebalancePlan, KinError> {
+        self.request(self.post("/rebalance"), REBALANCE_TIMEOUT)
+    }
+
+    /// Makes the backend read its config file again, e.g. after the editor saved it.
+    pub fn reload_config(&self) -> Result<(), KinError> {
+        self.request::<Value>(self.post("/reload-config"), REQUEST_TIMEOUT)
+            .map(|_| ())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    fn post(&self, path: &str) -> reqwest::blocking::RequestBuilder {
+        self.client
+            .post(self.url(path))
+            .header(TOKEN_HEADER, &self.token)
+    }
+
+    fn request<T: DeserializeOwned>(
+        &self,
+        request: reqwest::blocking::RequestBuilder,
+        timeout: Duration,
+    ) -> Result<T, KinError> {
+        let response = request
+            .timeout(timeout)
+            .send()
+            .map_err(KinError::BackendApi)?;
+        let status = response.status();
+        if !status.is_success() {
+            // 后端出错时返回 {"error": "..."}
+            let reason = response
+                .json::<Value>()
+                .ok()
+                .and_then(|body| body.get("error")?.as_str().map(str::to_string))
+                .unwrap_or_else(|| status.to_string());
+            return Err(KinError::Backend(format!(
+                "{} (HTTP {})",
+                reason,
+                status.as_u16()
+            )));
+        }
+        response.json().map_err(KinError::BackendApi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::OrderSide;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    // Answers one request with `status_line` and a JSON `body`
+    fn serve_once(status_line: &'static str, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 4096];
+            let _ = stream.read(&mut buf).unwrap();
+            write!(
+                stream,
+                "{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                status_line,
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+        url
+    }
+
+    #[test]
+    fn reads_status_and_plans() {
+        let url = serve_once(
+            "HTTP/1.1 200 OK",
+            r#"{"status": "Running", "paper_trading": true}"#,
+        );
+        assert_eq!(
+            BackendClient::new(&url).get_status().unwrap(),
+            BackendStatus {
+                status: "Running".to_string(),
+                paper_trading: true
+            }
+        );
+
+        let url = serve_once(
+            "HTTP/1.1 200 OK",
+            r#"{"trades": [{"symbol": "BTC_USDT", "side": "buy", "notional_usdt": 30.0, "reason": "manual"}]}"#,
+        );
+        let plan = BackendClient::new(&url).trigger_rebalance().unwrap();
+        assert_eq!(plan.trades.len(), 1);
+        assert_eq!(plan.trades[0].side, OrderSide::Buy);
+        assert!(plan.skipped.is_empty());
+    }
+
+    #[test]
+    fn sends_the_token_on_posts() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let request = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 4096];
+            let n = stream.read(&mut buf).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{{}}"
+            )
+            .unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+        BackendClient::new(&url)
+            .with_token("abc123")
+            .reload_config()
+            .unwrap();
+        assert!(request.join().unwrap().contains("x-kin-token: abc123"));
+    }
+
+    #[test]
+    fn surfaces_the_backend_error_message() {
+        let url = serve_once(
+            "HTTP/1.1 500 Internal Server Error",
+            r#"{"error": "exchange unreachable"}"#,
+        );
+        match BackendClient::new(&url).reload_config() {
+            Err(KinError::Backend(msg)) => assert_eq!(msg, "exchange unreachable (HTTP 500)"),
+            other => panic!("expected a backend error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
+
+use crate::config::{Config, MarketData, DEFAULT_LEVERAGE, USDT};
+use crate::error::KinError;
+use crate::exchange::{Balances, OrderSide};
+use crate::history::RebalanceEvent;
+use crate::planner;
+
+/// Inputs of a backtest besides the strategy itself, which comes from `Config`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestConfig {
+    // `date,BTC_USDT,ETH_USDT,...` with one row of daily closes per date
+    pub prices_csv: PathBuf,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub initial_usdt: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailySnapshot {
+    pub date: NaiveDate,
+    pub total_value: f64,
+    // Position value (margin × leverage) per contract
+    pub asset_values: HashMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BacktestResult {
+    pub snapshots: Vec<DailySnapshot>,
+    pub events: Vec<RebalanceEvent>,
+}
+
+impl BacktestResult {
+    pub fn total_return_pct(&self) -> f64 {
+        match (self.snapshots.first(), self.snapshots.last()) {
+            (Some(first), Some(last)) if first.total_value > 0.0 => {
+                (last.total_value / first.total_value - 1.0) * 100.0
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// Daily closes by date, oldest first; a missing cell leaves the asset out for that day.
+type PriceRows = Vec<(NaiveDate, HashMap<String, f64>)>;
+
+/// Replays the closes in `bt.prices_csv` through the same planner the Dry Run uses.
+/// Rebalancing is checked once a day at the close, trades fill at the close price
+/// without fees, and the account stops trading once its value drops to zero.
+pub fn run_backtest(config: &Config, bt: &BacktestConfig) -> Result<BacktestResult, KinError> {
+    let file = std::fs::File::open(&bt.prices_csv)?;
+    let rows = read_prices(file)?;
+    simulate(config, bt, &rows)
+}
+
+fn read_prices<R: Read>(source: R) -> Result<PriceRows, KinError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(source);
+    let symbols: Vec<String> = reader
+        .headers()?
+        .iter()
+        .skip(1)
+        .map(str::to_uppercase)
+        .collect();
+
+    let mut rows: PriceRows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |p| p.line()) as usize;
+        let csv_error = |reason: String| KinError::CsvImport { line, reason };
+
+        let date = NaiveDate::parse_from_str(&record[0], "%Y-%m-%d")
+            .map_err(|_| csv_error(format!("\"{}\" is not a YYYY-MM-DD date", &record[0])))?;
+        let mut prices = HashMap::new();
+        for (symbol, cell) in symbols.iter().zip(record.iter().skip(1)) {
+            if cell.is_empty() {
+                continue;
+            }
+            match cell.parse::<f64>() {
+                Ok(price) if price > 0.0 => {
+                    prices.insert(symbol.clone(), price);
+                }
+                _ => {
+                    return Err(csv_error(format!(
+                        "{} price \"{}\" is not a positive number",
+                        symbol, cell
+                    )))
+                }
+            }
+        }
+        rows.push((date, prices));
+    }
+    rows.sort_by_key(|(date, _)| *date);
+    Ok(rows)
+}
+
+fn simulate(
+    config: &Config,
+    bt: &BacktestConfig,
+    rows: &PriceRows,
+) -> Result<BacktestResult, KinError> {
+    if bt.start_date > bt.end_date {
+        return Err(KinError::Backtest(
+            "start date is after the end date".to_string(),
+        ));
+    }
+    if bt.initial_usdt <= 0.0 {
+        return Err(KinError::Backtest(
+            "initial USDT must be positive".to_string(),
+        ));
+    }
+    let rows: Vec<_> = rows
+        .iter()
+        .filter(|(date, _)| (bt.start_date..=bt.end_date).contains(date))
+        .collect();
+    let Some((_, first_prices)) = rows.first() else {
+        return Err(KinError::Backtest(format!(
+            "no prices between {} and {}",
+            bt.start_date, bt.end_date
+        )));
+    };
+    if let Some(asset) = config
+        .portfolio_allocation
+        .assets
+        .iter()
+        .find(|a| !first_prices.contains_key(&a.symbol))
+    {
+        return Err(KinError::Backtest(format!(
+            "no {} price on the first day",
+            asset.symbol
+        )));
+    }
+
+    let leverage = |symbol: &str| {
+        config
+            .portfolio_allocation
+            .assets
+            .iter()
+            .find(|a| a.symbol == symbol)
+            .map_or(DEFAULT_LEVERAGE, |a| a.leverage)
+    };
+    let mut value = bt.initial_usdt;
+    // Open position size in contracts (price units)
+    let mut quantities: HashMap<String, f64> = HashMap::new();
+    let mut last_prices: HashMap<String, f64> = HashMap::new();
+    let mut result = BacktestResult::default();
+
+    for (date, prices) in rows {
+        for (symbol, price) in prices {
+            if let Some(previous) = last_prices.insert(symbol.clone(), *price) {
+                value += quantities.get(symbol).copied().unwrap_or(0.0) * (price - previous);
+            }
+        }
+        let notional = |quantities: &HashMap<String, f64>, symbol: &str| {
+            quantities.get(symbol).copied().unwrap_or(0.0) * last_prices[symbol]
+        };
+
+        if value > 0.0 {
+            let mut balances: Balances = quantities
+                .keys()
+                .map(|symbol| {
+                    let margin = notional(&quantities, symbol).abs() / leverage(symbol);
+                    (symbol.clone(), margin)
+                })
+                .collect();
+            let used_margin: f64 = balances.values().sum();
+            balances.insert(USDT.to_string(), value - used_margin);
+
+            // No market cap or volatility history: those modes replay the custom targets
+            let plan =
+                planner::compute_plan(&balances, config, &last_prices, &MarketData::default());
+            let timestamp = Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default());
+            for (index, trade) in plan.trades.iter().enumerate() {
+                let from_pct = notional(&quantities, &trade.symbol) / value * 100.0;
+                let signed = match trade.side {
+                    OrderSide::Buy => trade.notional_usdt,
+                    OrderSide::Sell => -trade.notional_usdt,
+                };
+                *quantities.entry(trade.symbol.clone()).or_default() +=
+                    trade.quantity(last_prices[&trade.symbol]).copysign(signed);
+                result.events.push(RebalanceEvent {
+                    timestamp,
+                    asset: trade.symbol.clone(),
+                    from_pct,
+                    to_pct: notional(&quantities, &trade.symbol) / value * 100.0,
+                    usdt_traded: trade.notional_usdt,
+                    order_id: format!("backtest-{}-{}", date, index + 1),
+                    portfolio_value_usdt: value,
+                    fee_usdt: 0.0,
+                });
+            }
+        } else {
+            // Liquidated: everything is gone and nothing more can be traded
+            value = 0.0;
+            quantities.clear();
+        }
+
+        result.snapshots.push(DailySnapshot {
+            date: *date,
+            total_value: value,
+            asset_values: quantities
+                .keys()
+                .map(|symbol| (symbol.clone(), notional(&quantities, symbol)))
+                .collect(),
+        });
+    }
+    Ok(result)
+}
+
+/// Percentiles of the simulated value shown as the bands of the fan chart.
+pub const FAN_PERCENTILES: [f64; 5] = [5.0, 25.0, 50.0, 75.0, 95.0];
+
+/// Outcome of a Monte Carlo run, relative to a starting value of 1.0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonteCarloSummary {
+    // `FAN_PERCENTILES` of the value on each day, day 0 included
+    pub bands: Vec<[f64; 5]>,
+    pub median_final: f64,
+    // 5th percentile of the final value
+    pub value_at_risk: f64,
+    pub probability_above_initial: f64,
+}
+
+/// `n_sims` paths of `n_days` daily values under geometric Brownian motion with
+/// the mean and volatility of the log of the daily `returns` (simple returns,
+/// e.g. 0.01 for +1%). Every path starts at 1.0, so it has `n_days + 1` values.
+/// The same `seed` gives the same paths.
+pub fn monte_carlo_simulate(
+    returns: &[f64],
+    n_sims: usize,
+    n_days: usize,
+    seed: u64,
+) -> Vec<Vec<f64>> {
+    let log_returns: Vec<f64> = returns
+        .iter()
+        .filter(|r| **r > -1.0)
+        .map(|r| r.ln_1p())
+        .collect();
+    let n = log_returns.len().max(1) as f64;
+    // The mean log return is the GBM drift μ − σ²/2 already
+    let drift = log_returns.iter().sum::<f64>() / n;
+    let volatility = (log_returns.iter().map(|r| (r - drift).powi(2)).sum::<f64>() / n).sqrt();
+
+    let mut rng = SplitMix64(seed);
+    (0..n_sims)
+        .map(|_| {
+            let mut value = 1.0;
+            let mut path = Vec::with_capacity(n_days + 1);
+            path.push(value);
+            for _ in 0..n_days {
+                value *= (drift + volatility * rng.standard_normal()).exp();
+                path.push(value);
+            }
+            path
+        })
+        .collect()
+}
+
+/// Fan chart bands and final-value statistics of `monte_carlo_simulate` paths.
+pub fn summarize_simulation(paths: &[Vec<f64>]) -> MonteCarloSummary {
+    let n_days = paths.iter().map(Vec::len).min().unwrap_or(0);
+    let bands: Vec<[f64; 5]> = (0..n_days)
+        .map(|day| {
+            let mut values: Vec<f64> = paths.iter().map(|path| path[day]).collect();
+            values.sort_by(f64::total_cmp);
+            FAN_PERCENTILES.map(|p| percentile(&values, p))
+        })
+        .collect();
+    let finals: Vec<f64> = paths
+        .iter()
+        .filter_map(|path| path.last().copied())
+        .collect();
+    let above = finals.iter().filter(|v| **v > 1.0).count();
+    let last = bands.last().copied().unwrap_or([1.0; 5]);
+    MonteCarloSummary {
+        median_final: last[2],
+        value_at_risk: last[0],
+        probability_above_initial: above as f64 / finals.len().max(1) as f64,
+        bands,
+    }
+}
+
+// Nearest-rank percentile of sorted, non-empty `values`
+fn percentile(values: &[f64], pct: f64) -> f64 {
+    let rank = (pct / 100.0 * values.len() as f64).ceil() as usize;
+    values[rank.clamp(1, values.len()) - 1]
+}
+
+// Small seeded generator so simulations are reproducible without another dependency
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in (0, 1]
+    fn next_f64(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    // Box–Muller
+    fn standard_normal(&mut self) -> f64 {
+        let (u1, u2) = (self.next_f64(), self.next_f64());
+        (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AssetAllocation, PortfolioAllocation};
+
+    const PRICES: &str = "date,BTC_USDT\n\
+        2025-01-01,100\n\
+        2025-01-02,110\n\
+        2025-01-03,110\n\
+        2025-01-04,200\n";
+
+    fn config(target_pct: f64, leverage: f64) -> Config {
+        Config {
+            portfolio_allocation: PortfolioAllocation {
+                assets: vec![AssetAllocation {
+                    leverage,
+                    ..AssetAllocation::new("BTC_USDT", target_pct)
+                }],
+            },
+            ..Config::default()
+        }
+    }
+
+    fn backtest(start: &str, end: &str) -> BacktestConfig {
+        BacktestConfig {
+            prices_csv: PathBuf::new(),
+            start_date: start.parse().unwrap(),
+            end_date: end.parse().unwrap(),
+            initial_usdt: 1000.0,
+        }
+    }
+
+    fn run(config: &Config, bt: &BacktestConfig) -> Result<BacktestResult, KinError> {
+        simulate(config, bt, &read_prices(PRICES.as_bytes()).unwrap())
+    }
+
+    #[test]
+    fn buys_on_the_first_day_and_rebalances_on_drift() {
+        let result = run(&config(50.0, 1.0), &backtest("2025-01-01", "2025-01-04")).unwrap();
+        let values: Vec<f64> = result.snapshots.iter().map(|s| s.total_value).collect();
+        // 5 BTC bought at 100; at 110 it is 52.4% of 1050, within the 5% threshold
+        assert_eq!(values[..3], [1000.0, 1050.0, 1050.0]);
+        // Day 4: 1050 + 5 × 90, BTC at 1000 of 1500 (66.7%) is rebalanced back to 50%
+        assert!((values[3] - 1500.0).abs() < 1e-9);
+        assert_eq!(result.events.len(), 2);
+        assert_eq!(result.events[0].to_pct, 50.0);
+        assert!((result.events[1].from_pct - 1000.0 / 15.0).abs() < 1e-9);
+        assert!((result.events[1].to_pct - 50.0).abs() < 1e-9);
+        assert!((result.snapshots[3].asset_values["BTC_USDT"] - 750.0).abs() < 1e-9);
+        assert!((result.total_return_pct() - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn respects_the_date_range() {
+        let result = run(&config(50.0, 1.0), &backtest("2025-01-02", "2025-01-03")).unwrap();
+        assert_eq!(result.snapshots.len(), 2);
+        assert_eq!(result.total_return_pct(), 0.0);
+
+        assert!(run(&config(50.0, 1.0), &backtest("2024-01-01", "2024-12-31")).is_err());
+        assert!(run(&config(50.0, 1.0), &backtest("2025-01-04", "2025-01-01")).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_rows_and_unpriced_targets() {
+        let err = read_prices("date,BTC_USDT\n2025-01-01,abc\n".as_bytes()).unwrap_err();
+        assert!(
+            matches!(err, KinError::CsvImport { line: 2, .. }),
+            "{}",
+            err
+        );
+
+        let mut eth = config(50.0, 1.0);
+        eth.portfolio_allocation.assets[0].symbol = "ETH_USDT".to_string();
+        assert!(run(&eth, &backtest("2025-01-01", "2025-01-04")).is_err());
+    }
+
+    #[test]
+    fn monte_carlo_is_reproducible_and_follows_the_drift() {
+        let returns = [0.01, -0.005, 0.02, 0.0, -0.01, 0.015];
+        let paths = monte_carlo_simulate(&returns, 500, 30, 42);
+        assert_eq!(paths.len(), 500);
+        assert!(paths.iter().all(|p| p.len() == 31 && p[0] == 1.0));
+        assert_eq!(paths, monte_carlo_simulate(&returns, 500, 30, 42));
+        assert_ne!(paths, monte_carlo_simulate(&returns, 500, 30, 7));
+
+        let summary = summarize_simulation(&paths);
+        assert_eq!(summary.bands.len(), 31);
+        assert!(summary
+            .bands
+            .iter()
+            .all(|b| b.windows(2).all(|w| w[0] <= w[1])));
+        // Mean log return ≈ 0.49% a day: the median path ends near exp(30 × 0.0049)
+        let drift: f64 = returns.iter().map(|r| r.ln_1p()).sum::<f64>() / 6.0;
+        assert!((summary.median_final / (30.0 * drift).exp() - 1.0).abs() < 0.05);
+        assert!(summary.value_at_risk < summary.median_final);
+        assert!(summary.probability_above_initial > 0.5);
+
+        // Without volatility every path is the same
+        let flat = summarize_simulation(&monte_carlo_simulate(&[0.001; 10], 10, 5, 1));
+        assert!((flat.value_at_risk - 1.001f64.powi(5)).abs() < 1e-12);
+    }
+}
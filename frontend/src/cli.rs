@@ -0,0 +1,514 @@
+use clap::{CommandFactory, Parser, Subcommand};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::backend::{self, BackendEndpoint};
+use crate::config::{
+    self, Config, ConfigFormat, MarketData, ProfileManager, DEFAULT_PROFILE, USDT,
+};
+use crate::crypto;
+use crate::db::Database;
+use crate::error::KinError;
+use crate::exchange::mock::TestScenario;
+use crate::exchange::OrderSide;
+use crate::history::RebalanceEvent;
+use crate::ipc::BackendSocket;
+use crate::portfolio::{PortfolioManager, MAIN_PORTFOLIO};
+use crate::protocol::{BackendCommand, BackendMessage};
+use crate::tax;
+
+/// Headless control of the rebalancer, for servers without a display.
+/// Running without a subcommand opens the GUI.
+#[derive(Debug, Parser)]
+#[command(name = "kin-portfolio-rebalancer", version, about)]
+pub struct Cli {
+    /// Config file to operate on, also for the GUI (defaults to
+    /// ~/.portfolio_rebalancer.toml or .json)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Encrypt the config with a passphrase (prompted for when no value is given).
+    /// Encrypted configs prompt for it automatically.
+    #[arg(
+        long,
+        global = true,
+        value_name = "PASSPHRASE",
+        num_args = 0..=1,
+        default_missing_value = ""
+    )]
+    pub passphrase: Option<String>,
+
+    /// Never open the window, even when no subcommand is given
+    #[arg(long)]
+    pub no_gui: bool,
+
+    /// Print the JSON Schema of the config file and exit
+    #[arg(long)]
+    pub dump_schema: bool,
+
+    /// Query a simulated exchange instead of the testnet from the window:
+    /// all-assets-on-target, btc-overweight (the default), network-error or auth-error
+    #[arg(
+        long,
+        value_name = "SCENARIO",
+        num_args = 0..=1,
+        default_missing_value = "btc-overweight"
+    )]
+    pub mock_exchange: Option<TestScenario>,
+
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// Print the config, with the API secret masked
+    ShowConfig,
+    /// Set the target percentage of an asset; USDT takes whatever is left
+    SetAllocation {
+        #[arg(value_name = "SYMBOL")]
+        symbol: String,
+        #[arg(value_name = "PCT")]
+        pct: f64,
+    },
+    /// Run the backend in the foreground and print its messages until it exits
+    Start,
+    /// Ask the backend running this config to finish its current cycle and exit
+    Stop,
+    /// Ask the backend running this config for its status
+    Status,
+    /// Print the rebalancing trades recorded for this config
+    History,
+}
+
+impl Cli {
+    pub fn wants_gui(&self) -> bool {
+        self.command.is_none() && !self.no_gui && !self.dump_schema
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        Config::get_config_path(self.config.clone())
+    }
+
+    /// The passphrase to read and write the config with, or None to keep it plain.
+    /// Prompts on the terminal for `--passphrase` without a value, and for an
+    /// encrypted config when the flag is missing.
+    pub fn resolve_passphrase(&self) -> Result<Option<String>, KinError> {
+        let prompt = match self.passphrase.as_deref() {
+            Some("") => true,
+            Some(passphrase) => return Ok(Some(passphrase.to_string())),
+            None => fs::read(self.config_path()).is_ok_and(|data| crypto::is_encrypted(&data)),
+        };
+        if !prompt {
+            return Ok(None);
+        }
+        // No terminal to prompt on, e.g. when launched from a desktop shortcut
+        let passphrase = rpassword::prompt_password("Config passphrase: ")
+            .map_err(|_| KinError::PassphraseRequired(self.config_path()))?;
+        if passphrase.is_empty() {
+            return Err(KinError::Encryption(
+                "passphrase cannot be empty".to_string(),
+            ));
+        }
+        Ok(Some(passphrase))
+    }
+}
+
+pub fn run(cli: Cli) -> Result<(), KinError> {
+    if cli.dump_schema {
+        println!("{}", serde_json::to_string_pretty(&Config::json_schema())?);
+        return Ok(());
+    }
+    let config_path = cli.config_path();
+    let passphrase = cli.resolve_passphrase()?;
+    let passphrase = passphrase.as_deref();
+    match cli.command {
+        None => {
+            Cli::command().print_help()?;
+            Ok(())
+        }
+        Some(CliCommand::ShowConfig) => show_config(&config_path, passphrase),
+        Some(CliCommand::SetAllocation { symbol, pct }) => {
+            set_allocation(&config_path, &symbol, pct, passphrase)
+        }
+        Some(CliCommand::Start) => start(&config_path, passphrase),
+        Some(CliCommand::Stop) => stop(&config_path),
+        Some(CliCommand::Status) => status(&config_path),
+        Some(CliCommand::History) => history(&config_path),
+    }
+}
+
+fn show_config(config_path: &Path, passphrase: Option<&str>) -> Result<(), KinError> {
+    let mut config = Config::load_with_passphrase(config_path, passphrase)?;
+    if !config.api_secret.is_empty() {
+        config.api_secret = "******".to_string().into();
+    }
+    println!("{}", config.to_text(config.format)?);
+    Ok(())
+}
+
+fn set_allocation(
+    config_path: &Path,
+    symbol: &str,
+    pct: f64,
+    passphrase: Option<&str>,
+) -> Result<(), KinError> {
+    let symbol = symbol.trim().to_uppercase();
+    if symbol == USDT {
+        return Err(KinError::invalid_allocation(
+            USDT,
+            "calculated automatically and cannot be set",
+        ));
+    }
+    if !(0.0..=100.0).contains(&pct) {
+        return Err(KinError::invalid_allocation(
+            &symbol,
+            "must be between 0 and 100",
+        ));
+    }
+
+    let mut config = match Config::load_with_passphrase(config_path, passphrase) {
+        Err(KinError::ConfigNotFound(_)) => Config::default(),
+        result => result?,
+    };
+    config.portfolio_allocation.set(&symbol, pct);
+    config.portfolio_allocation.check_total()?;
+    config.save_with_passphrase(config_path, passphrase)?;
+
+    // The GUI mirrors its active profile into the config file, so edits made
+    // there are copied back into the profile to survive the next GUI save.
+    let profiles_path = ProfileManager::path_for(config_path);
+    if profiles_path.exists() {
+        let mut profiles = ProfileManager::load_with_passphrase(&profiles_path, passphrase)?;
+        profiles.active_mut().portfolio_allocation = config.portfolio_allocation.clone();
+        profiles.save_with_passphrase(&profiles_path, passphrase)?;
+    }
+
+    println!(
+        "{} set to {:.1}% (USDT {:.1}%).",
+        symbol,
+        pct,
+        config.portfolio_allocation.usdt()
+    );
+    Ok(())
+}
+
+/// How long `status` waits for the backend to answer; it only reads commands
+/// between checks.
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long `stop` waits for the backend to finish its current cycle and exit.
+const STOP_TIMEOUT: Duration = Duration::from_secs(60);
+
+// The portfolio the GUI lists this config under, by its slot: configs in
+// `PortfolioManager::default_dir()` are the portfolios after the main one, any
+// other config is the main portfolio
+fn portfolio_slot(config_path: &Path) -> (usize, String) {
+    let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let config_path = canonical(config_path);
+    let portfolios = PortfolioManager::load(
+        &Config::get_config_path(None),
+        Config::default(),
+        &PortfolioManager::default_dir(),
+        None,
+    );
+    portfolios
+        .portfolios
+        .iter()
+        .enumerate()
+        .skip(1)
+        .find(|(_, portfolio)| canonical(&portfolio.config_path) == config_path)
+        .map_or((0, MAIN_PORTFOLIO.to_string()), |(slot, portfolio)| {
+            (slot, portfolio.name.clone())
+        })
+}
+
+// The backend of a portfolio listens on the endpoint of its slot, as in the GUI;
+// nothing listening there means none is running
+fn connect_backend(slot: usize) -> Option<BackendSocket> {
+    BackendSocket::connect(&BackendEndpoint::for_slot(slot).ipc_path).ok()
+}
+
+// Receives on a separate thread since `recv` blocks. Some(message) for the first
+// message `done` accepts, Some(None) when the backend closed the connection
+// first, None when `timeout` passed.
+fn recv_until(
+    socket: BackendSocket,
+    timeout: Duration,
+    done: impl Fn(&BackendMessage) -> bool + Send + 'static,
+) -> Option<Option<BackendMessage>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let result = loop {
+            match socket.recv() {
+                Ok(message) if done(&message) => break Some(message),
+                Ok(_) => {}
+                Err(_) => break None,
+            }
+        };
+        let _ = sender.send(result);
+    });
+    receiver.recv_timeout(timeout).ok()
+}
+
+// The GUI's keychain account for this config: the portfolio it belongs to and
+// the active profile
+fn keyring_account(
+    portfolio: &str,
+    config_path: &Path,
+    config: &Config,
+    passphrase: Option<&str>,
+) -> String {
+    let profiles =
+        ProfileManager::load_with_passphrase(&ProfileManager::path_for(config_path), passphrase);
+    let profile = profiles
+        .as_ref()
+        .map_or(DEFAULT_PROFILE, |profiles| profiles.active_name());
+    config::keyring_account(portfolio, profile, config.exchange)
+}
+
+fn start(config_path: &Path, passphrase: Option<&str>) -> Result<(), KinError> {
+    let (slot, portfolio) = portfolio_slot(config_path);
+    if connect_backend(slot).is_some() {
+        return Err(KinError::Backend("already running".to_string()));
+    }
+    let config = Config::load_with_passphrase(config_path, passphrase)?;
+    let account = keyring_account(&portfolio, config_path, &config, passphrase);
+    let (api_key, api_secret) = Config::load_secrets(&account)
+        .unwrap_or_else(|_| (config.api_key.clone(), config.api_secret.clone()));
+    let config_json = match passphrase {
+        Some(_) => Some(config.to_text(ConfigFormat::Json)?),
+        None => None,
+    };
+
+    let mut child = backend::spawn(
+        config_path,
+        &api_key,
+        &api_secret,
+        config_json.as_deref(),
+        &config,
+        // Without the GUI's market caps and price history the targets fall back
+        // to the custom ones for market-cap weighting and risk parity
+        &config.target_allocation(&MarketData::default()),
+        &BackendEndpoint::for_slot(slot),
+    )?;
+    println!("Backend started (pid {}).", child.id());
+
+    // Python logging goes to stderr; pass it through untouched
+    if let Some(stderr) = child.stderr.take() {
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                eprintln!("{}", line);
+            }
+        });
+    }
+    if let Some(stdout) = child.stdout.take() {
+        let db = Database::open_for_config(config_path)
+            .map_err(|e| eprintln!("Failed to open history database: {}", e))
+            .ok();
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if !line.trim().is_empty() {
+                print_message(BackendMessage::from_line(&line), db.as_ref(), &config);
+            }
+        }
+    }
+
+    let exit_status = child.wait()?;
+    println!("Backend exited ({}).", exit_status);
+    Ok(())
+}
+
+// The GUI keeps its lots in memory; here they are read back for every fill
+fn book_tax_lots(
+    db: &Database,
+    asset: &str,
+    side: OrderSide,
+    quantity: f64,
+    price: f64,
+    config: &Config,
+) -> Result<(), KinError> {
+    let mut lots = db.load_tax_lots()?;
+    let gains = tax::record_fill(
+        &mut lots,
+        asset,
+        side,
+        quantity,
+        price,
+        Utc::now(),
+        config.cost_basis_method,
+    );
+    db.save_tax_lots(&lots)?;
+    for gain in &gains {
+        db.insert_realized_gain(gain)?;
+    }
+    Ok(())
+}
+
+fn print_message(message: BackendMessage, db: Option<&Database>, config: &Config) {
+    match message {
+        BackendMessage::Status {
+            status,
+            paper_trading,
+        } => {
+            if paper_trading {
+                println!("Status: {} (paper trading)", status);
+            } else {
+                println!("Status: {}", status);
+            }
+        }
+        BackendMessage::PortfolioSnapshot {
+            allocations,
+            total_value_usdt,
+            ..
+        } => {
+            let mut allocations: Vec<_> = allocations.into_iter().collect();
+            allocations.sort_by(|a, b| a.0.cmp(&b.0));
+            let summary: Vec<String> = allocations
+                .iter()
+                .map(|(symbol, pct)| format!("{} {:.1}%", symbol, pct))
+                .collect();
+            println!(
+                "Portfolio: {:.2} USDT ({})",
+                total_value_usdt,
+                summary.join(", ")
+            );
+        }
+        BackendMessage::RebalanceProgress {
+            total_trades,
+            completed_trades,
+            current_asset,
+        } => match current_asset {
+            Some(asset) => println!(
+                "Placing order for {} ({}/{})",
+                asset,
+                completed_trades + 1,
+                total_trades
+            ),
+            None => println!("Rebalance done: {} trades", total_trades),
+        },
+        BackendMessage::PriceHistory { symbols, prices } => {
+            let days = prices.iter().map(Vec::len).min().unwrap_or(0);
+            println!("Price history: {} days for {}", days, symbols.join(", "));
+        }
+        BackendMessage::TradeExecuted {
+            asset,
+            side,
+            from_pct,
+            to_pct,
+            usdt_traded,
+            order_id,
+            portfolio_value_usdt,
+            quantity,
+            price,
+        } => {
+            println!(
+                "Trade executed: {} {} {:.2} USDT (order {})",
+                side, asset, usdt_traded, order_id
+            );
+            if let (Some(db), true) = (db, quantity > 0.0 && price > 0.0) {
+                let side = if side == "sell" {
+                    OrderSide::Sell
+                } else {
+                    OrderSide::Buy
+                };
+                if let Err(e) = book_tax_lots(db, &asset, side, quantity, price, config) {
+                    eprintln!("Failed to update tax lots: {}", e);
+                }
+            }
+            let event = RebalanceEvent {
+                timestamp: Utc::now(),
+                asset,
+                from_pct,
+                to_pct,
+                usdt_traded,
+                order_id,
+                portfolio_value_usdt,
+                fee_usdt: usdt_traded * config.taker_fee_rate,
+            };
+            if let Some(Err(e)) = db.map(|db| db.insert_event(&event)) {
+                eprintln!("Failed to save history: {}", e);
+            }
+        }
+        BackendMessage::OrderUpdate(order) => println!(
+            "Order {}: {} {} {} @ {} ({})",
+            order.id, order.side, order.qty, order.symbol, order.price, order.status
+        ),
+        BackendMessage::Error { message } => eprintln!("ERROR: {}", message),
+        BackendMessage::Log { line } => println!("{}", line),
+    }
+}
+
+fn stop(config_path: &Path) -> Result<(), KinError> {
+    let Some(socket) = connect_backend(portfolio_slot(config_path).0) else {
+        println!("Backend is not running.");
+        return Ok(());
+    };
+    socket.send(BackendCommand::Stop)?;
+    let stopped = |message: &BackendMessage| matches!(message, BackendMessage::Status { status, .. } if status == "Stopped");
+    match recv_until(socket, STOP_TIMEOUT, stopped) {
+        Some(_) => println!("Backend stopped."),
+        None => println!(
+            "Stop requested; the backend is still finishing its cycle after {}s.",
+            STOP_TIMEOUT.as_secs()
+        ),
+    }
+    Ok(())
+}
+
+fn status(config_path: &Path) -> Result<(), KinError> {
+    let Some(socket) = connect_backend(portfolio_slot(config_path).0) else {
+        println!("Stopped");
+        return Ok(());
+    };
+    socket.send(BackendCommand::Status)?;
+    let is_status = |message: &BackendMessage| matches!(message, BackendMessage::Status { .. });
+    match recv_until(socket, STATUS_TIMEOUT, is_status) {
+        Some(Some(BackendMessage::Status {
+            status,
+            paper_trading,
+        })) => {
+            if paper_trading {
+                println!("{} (paper trading)", status);
+            } else {
+                println!("{}", status);
+            }
+        }
+        Some(_) => println!("Stopped"),
+        None => println!(
+            "Running (busy, no answer within {}s)",
+            STATUS_TIMEOUT.as_secs()
+        ),
+    }
+    Ok(())
+}
+
+fn history(config_path: &Path) -> Result<(), KinError> {
+    let mut events = Database::open_for_config(config_path)?.load_events(usize::MAX)?;
+    if events.is_empty() {
+        println!("No rebalancing trades recorded yet.");
+        return Ok(());
+    }
+    events.sort_by_key(|e| e.timestamp);
+    println!(
+        "{:<19}  {:<12} {:>8} {:>8} {:>12}  Order ID",
+        "Time", "Asset", "From %", "To %", "USDT"
+    );
+    for event in &events {
+        println!(
+            "{:<19}  {:<12} {:>8.2} {:>8.2} {:>12.2}  {}",
+            event.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            event.asset,
+            event.from_pct,
+            event.to_pct,
+            event.usdt_traded,
+            event.order_id
+        );
+    }
+    Ok(())
+}
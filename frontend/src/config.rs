@@ -1,45 +1,1832 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use crate::crypto;
+use crate::currency::DisplayCurrency;
+use crate::error::KinError;
+use crate::exchange::retry::RetryConfig;
+use crate::exchange::ExchangeChoice;
+use crate::keybindings::KeyBindings;
+use crate::locale::Locale;
+use crate::metrics;
+use crate::schedule::RebalanceSchedule;
+use crate::slippage::SlippageModel;
+use crate::tax::CostBasisMethod;
+use crate::theme::Theme;
+use crate::webhook::WebhookEvent;
+
+/// USDT is never stored as an asset entry; its share is always the remainder.
+pub const USDT: &str = "USDT";
+
+/// Service name the API credentials are stored under in the OS keychain.
+pub const KEYRING_SERVICE: &str = "kin-portfolio-rebalancer";
+
+/// Version written by this build. Files without a `version` key are treated as v1.
+pub const CONFIG_VERSION: u32 = 2;
+
+/// Leverage the backend opens every futures position with.
+pub const DEFAULT_LEVERAGE: f64 = 3.0;
+
+/// Total effective exposure (target × leverage, summed) above this multiple of
+/// the portfolio value is flagged in the editor.
+pub const EXPOSURE_RISK_LIMIT: f64 = 2.0;
+
+/// Bounds of `Config::ui_scale`.
+pub const MIN_UI_SCALE: f32 = 0.75;
+pub const MAX_UI_SCALE: f32 = 2.0;
+
+/// Length limit of `Config::notes`, in characters.
+pub const MAX_NOTES_CHARS: usize = 1000;
+
+/// Bound of `Config::display_precision`.
+pub const MAX_DISPLAY_PRECISION: u8 = 6;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct AssetAllocation {
+    /// Gate.io futures contract, e.g. "BTC_USDT"
+    pub symbol: String,
+    /// Target share of the portfolio value, in percent
+    #[schemars(range(min = 0.0, max = 100.0))]
+    pub target_pct: f64,
+    /// Leverage the position is opened with
+    #[serde(default = "default_leverage")]
+    #[schemars(range(min = 1.0))]
+    pub leverage: f64,
+    /// Groups the asset is reported under, e.g. "Layer1" or "DeFi"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl AssetAllocation {
+    pub fn new(symbol: impl Into<String>, target_pct: f64) -> Self {
+        Self {
+            symbol: symbol.into(),
+            target_pct,
+            leverage: DEFAULT_LEVERAGE,
+            tags: Vec::new(),
+        }
+    }
+
+    // Position size as a percentage of the portfolio value
+    pub fn effective_exposure(&self) -> f64 {
+        self.target_pct * self.leverage
+    }
+}
+
+fn default_leverage() -> f64 {
+    DEFAULT_LEVERAGE
+}
+
+/// Group of the assets without tags in `PortfolioAllocation::by_tag`.
+pub const UNTAGGED: &str = "Untagged";
+
+/// Comma-separated tags as typed, trimmed, without empty or repeated ones.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(from = "AllocationLayout", into = "Vec<AssetAllocation>")]
 pub struct PortfolioAllocation {
+    pub assets: Vec<AssetAllocation>,
+}
+
+impl PortfolioAllocation {
+    pub fn get(&self, symbol: &str) -> Option<f64> {
+        self.assets
+            .iter()
+            .find(|a| a.symbol == symbol)
+            .map(|a| a.target_pct)
+    }
+
+    // Sum of all non-USDT targets
+    pub fn crypto_total(&self) -> f64 {
+        self.assets.iter().map(|a| a.target_pct).sum()
+    }
+
+    pub fn effective_exposure(&self) -> f64 {
+        self.assets
+            .iter()
+            .map(AssetAllocation::effective_exposure)
+            .sum()
+    }
+
+    /// Err when the assets add up to more than 100%, leaving USDT nothing.
+    pub fn check_total(&self) -> Result<(), KinError> {
+        let crypto_total = self.crypto_total();
+        if !crypto_total.is_finite() {
+            return Err(KinError::invalid_allocation(
+                "Total",
+                "sum of assets is not a finite number",
+            ));
+        }
+        if crypto_total > 100.0 {
+            return Err(KinError::invalid_allocation(
+                "Total",
+                &format!("sum of assets ({:.1}%) cannot exceed 100%", crypto_total),
+            ));
+        }
+        Ok(())
+    }
+
+    // USDT takes whatever the other assets leave over
+    pub fn usdt(&self) -> f64 {
+        (100.0 - self.crypto_total()).max(0.0)
+    }
+
+    /// Every tag used by the assets, sorted.
+    pub fn tag_names(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.assets.iter().flat_map(|a| a.tags.clone()).collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Symbols of the assets tagged `tag`.
+    pub fn symbols_with_tag(&self, tag: &str) -> Vec<&str> {
+        self.assets
+            .iter()
+            .filter(|a| a.tags.iter().any(|t| t == tag))
+            .map(|a| a.symbol.as_str())
+            .collect()
+    }
+
+    /// Target percentages summed per tag, sorted by tag with `UNTAGGED` last.
+    /// An asset with several tags is split evenly between them so the groups
+    /// still add up to the assets' total; USDT is left out.
+    pub fn by_tag(&self) -> Vec<(String, f64)> {
+        let mut groups: Vec<(String, f64)> = Vec::new();
+        let mut add = |tag: &str, pct: f64| match groups.iter_mut().find(|(t, _)| t == tag) {
+            Some((_, total)) => *total += pct,
+            None => groups.push((tag.to_string(), pct)),
+        };
+        for asset in &self.assets {
+            if asset.tags.is_empty() {
+                add(UNTAGGED, asset.target_pct);
+            }
+            for tag in &asset.tags {
+                add(tag, asset.target_pct / asset.tags.len() as f64);
+            }
+        }
+        groups.sort_by(|(a, _), (b, _)| (a == UNTAGGED, a).cmp(&(b == UNTAGGED, b)));
+        groups
+    }
+
+    // Updates an existing asset in place, otherwise appends it
+    pub fn set(&mut self, symbol: &str, target_pct: f64) {
+        match self.assets.iter_mut().find(|a| a.symbol == symbol) {
+            Some(asset) => asset.target_pct = target_pct,
+            None => self.assets.push(AssetAllocation::new(symbol, target_pct)),
+        }
+    }
+}
+
+/// `symbols` rearranged by a saved display order: the ones `order` lists come
+/// first, in that order, then the rest as they come. Symbols of `order` that are
+/// no longer in `symbols` are dropped.
+pub fn display_order(order: &[String], symbols: &[String]) -> Vec<String> {
+    let mut remaining: Vec<&String> = symbols.iter().collect();
+    let mut ordered = Vec::with_capacity(symbols.len());
+    for symbol in order {
+        if let Some(pos) = remaining.iter().position(|s| *s == symbol) {
+            ordered.push(remaining.remove(pos).clone());
+        }
+    }
+    ordered.extend(remaining.into_iter().cloned());
+    ordered
+}
+
+/// Swaps two symbols of a display order; leaves it unchanged unless both are listed.
+pub fn swap_symbols(order: &mut [String], a: &str, b: &str) {
+    let position = |symbol: &str| order.iter().position(|s| s == symbol);
+    if let (Some(i), Some(j)) = (position(a), position(b)) {
+        order.swap(i, j);
+    }
+}
+
+impl Default for PortfolioAllocation {
+    fn default() -> Self {
+        Self {
+            assets: vec![
+                AssetAllocation::new("BTC_USDT", 25.0),
+                AssetAllocation::new("ETH_USDT", 15.0),
+                AssetAllocation::new("LTC_USDT", 10.0),
+            ],
+        }
+    }
+}
+
+impl From<PortfolioAllocation> for Vec<AssetAllocation> {
+    fn from(allocation: PortfolioAllocation) -> Self {
+        allocation.assets
+    }
+}
+
+// 兼容旧版配置文件：旧版本使用固定的四个字段
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AllocationLayout {
+    Assets(Vec<AssetAllocation>),
+    Legacy(LegacyAllocation),
+}
+
+#[derive(Deserialize)]
+struct LegacyAllocation {
     #[serde(rename = "BTC_USDT")]
-    pub BTC_USDT: f64,
+    btc_usdt: f64,
     #[serde(rename = "ETH_USDT")]
-    pub ETH_USDT: f64,
+    eth_usdt: f64,
     #[serde(rename = "LTC_USDT")]
-    pub LTC_USDT: f64,
-    #[serde(rename = "USDT")]
-    pub USDT: f64,
+    ltc_usdt: f64,
+    // USDT was stored but is always derived, so it is read and dropped
+    #[serde(rename = "USDT", default)]
+    _usdt: f64,
 }
 
-impl Default for PortfolioAllocation {
+impl From<AllocationLayout> for PortfolioAllocation {
+    fn from(layout: AllocationLayout) -> Self {
+        match layout {
+            AllocationLayout::Assets(assets) => Self {
+                assets: assets.into_iter().filter(|a| a.symbol != USDT).collect(),
+            },
+            AllocationLayout::Legacy(legacy) => Self {
+                assets: vec![
+                    AssetAllocation::new("BTC_USDT", legacy.btc_usdt),
+                    AssetAllocation::new("ETH_USDT", legacy.eth_usdt),
+                    AssetAllocation::new("LTC_USDT", legacy.ltc_usdt),
+                ],
+            },
+        }
+    }
+}
+
+/// Where the asset weights come from.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AllocationMode {
+    /// The `target_pct` of each asset
+    #[default]
+    Custom,
+    /// 100% split evenly between the assets
+    EqualWeight,
+    /// Proportional to each coin's market cap on CoinGecko, keeping the USDT
+    /// share the custom targets leave
+    MarketCapWeight,
+    /// Inverse-volatility weights, so every asset adds the same volatility
+    RiskParity(RiskParityConfig),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RiskParityConfig {
+    /// Days of daily closes the volatility of each asset is estimated from
+    #[schemars(range(min = 2))]
+    pub lookback_days: u32,
+    /// Annualised portfolio volatility aimed for, as a fraction, e.g. 0.3 for
+    /// 30%; the assets hold less than 100% when they would exceed it
+    #[schemars(range(min = 0.0))]
+    pub target_volatility: f64,
+}
+
+impl RiskParityConfig {
+    pub const DEFAULT: RiskParityConfig = RiskParityConfig {
+        lookback_days: 30,
+        target_volatility: 0.3,
+    };
+}
+
+impl Default for RiskParityConfig {
     fn default() -> Self {
-        Self {
-            BTC_USDT: 25.0,
-            ETH_USDT: 15.0,
-            LTC_USDT: 10.0,
-            USDT: 50.0,
+        Self::DEFAULT
+    }
+}
+
+/// Data fetched at runtime that some allocation modes weight the assets by.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarketData {
+    // USD market cap per contract, from CoinGecko
+    pub market_caps: HashMap<String, f64>,
+    // Annualised volatility per contract, from the closes in the history database
+    pub volatilities: HashMap<String, f64>,
+}
+
+impl AllocationMode {
+    pub const ALL: [AllocationMode; 4] = [
+        AllocationMode::Custom,
+        AllocationMode::EqualWeight,
+        AllocationMode::MarketCapWeight,
+        AllocationMode::RiskParity(RiskParityConfig::DEFAULT),
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AllocationMode::Custom => "Custom",
+            AllocationMode::EqualWeight => "Equal weight (1/N)",
+            AllocationMode::MarketCapWeight => "Market-cap weight",
+            AllocationMode::RiskParity(_) => "Risk parity",
+        }
+    }
+
+    /// Same variant, whatever the parameters.
+    pub fn same_kind(self, other: AllocationMode) -> bool {
+        std::mem::discriminant(&self) == std::mem::discriminant(&other)
+    }
+
+    /// Asset weights derived from the custom `allocation`. Market-cap and risk
+    /// parity weighting keep the custom targets until `market_data` covers them.
+    pub fn weights(
+        self,
+        allocation: &PortfolioAllocation,
+        market_data: &MarketData,
+    ) -> PortfolioAllocation {
+        let has_volatility = |symbol: &str| market_data.volatilities.contains_key(symbol);
+        match self {
+            AllocationMode::EqualWeight => metrics::equal_weight_targets(allocation),
+            AllocationMode::MarketCapWeight if !market_data.market_caps.is_empty() => {
+                metrics::market_cap_targets(allocation, &market_data.market_caps)
+            }
+            AllocationMode::RiskParity(config)
+                if allocation.assets.iter().all(|a| has_volatility(&a.symbol)) =>
+            {
+                metrics::risk_parity_targets(
+                    allocation,
+                    &market_data.volatilities,
+                    config.target_volatility,
+                )
+            }
+            _ => allocation.clone(),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Kelly fractions above this share of the portfolio are flagged as over-concentrated.
+pub const KELLY_CONCENTRATION_LIMIT_PCT: f64 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PositionSizing {
+    /// Each asset's `target_pct` is its target
+    #[default]
+    FixedPct,
+    /// The Kelly fraction of the portfolio is invested, split between the
+    /// assets in proportion to their `target_pct`
+    KellyCriterion {
+        /// Share of trades that win, 0 to 1
+        #[schemars(range(min = 0.0, max = 1.0))]
+        win_rate: f64,
+        /// Average win divided by average loss
+        #[schemars(range(min = 0.0))]
+        avg_win_loss_ratio: f64,
+        /// Multiple of the full Kelly bet, e.g. 0.5 for half Kelly
+        #[schemars(range(min = 0.0, max = 1.0))]
+        fraction: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfigFormat {
+    #[default]
+    Json,
+    Toml,
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => ConfigFormat::Toml,
+            _ => ConfigFormat::Json,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ConfigFormat::Json => "json",
+            ConfigFormat::Toml => "toml",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct Config {
+    /// Config file format version
+    #[serde(default = "default_version")]
+    #[schemars(range(min = 1))]
+    pub version: u32,
+    // Decided by the file extension at load time, never written to the file
+    #[serde(skip)]
+    pub format: ConfigFormat,
+    /// Gate.io API key; empty when it is kept in the system keychain
+    #[serde(default)]
     pub api_key: String,
-    pub api_secret: String,
+    /// Gate.io API secret; empty when it is kept in the system keychain
+    // Zeroed when the config is dropped
+    #[serde(default)]
+    #[schemars(with = "String")]
+    pub api_secret: Zeroizing<String>,
+    /// Target allocation per asset; USDT holds whatever the assets leave over
+    #[serde(default)]
+    #[schemars(with = "Vec<AssetAllocation>")]
     pub portfolio_allocation: PortfolioAllocation,
+    /// Order of the assets in the allocation editor, by symbol
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub display_order: Vec<String>,
+    /// The user's own notes on why the allocation is set up this way
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+    /// Deviation from the target, in percentage points, that triggers a rebalance
+    #[serde(default = "default_rebalance_threshold")]
+    #[schemars(range(min = 0.0, max = 100.0))]
     pub rebalance_threshold: f64,
+    /// Overrides `rebalance_threshold` for the listed assets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_asset_threshold: Option<HashMap<String, f64>>,
+    /// Smallest USDT deposit that is invested right away
+    #[serde(default = "default_min_usdt_inflow")]
+    #[schemars(range(min = 0.0))]
     pub min_usdt_inflow: f64,
+    /// Planned trades with a smaller notional are skipped; the exchange rejects dust orders
+    #[serde(default = "default_min_trade_usdt")]
+    #[schemars(range(min = 0.0))]
+    pub min_trade_usdt: f64,
+    /// Fee on market orders as a fraction of the notional, e.g. 0.00075 for 0.075%
+    #[serde(default = "default_taker_fee_rate")]
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub taker_fee_rate: f64,
+    /// Fee on limit orders that rest on the book, as a fraction of the notional
+    #[serde(default = "default_maker_fee_rate")]
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub maker_fee_rate: f64,
+    /// Where the asset weights come from
+    #[serde(default)]
+    pub allocation_mode: AllocationMode,
+    /// How the target of each asset is sized
+    #[serde(default)]
+    pub position_sizing: PositionSizing,
+    /// Order in which sells consume tax lots for the capital gains report
+    #[serde(default)]
+    pub cost_basis_method: CostBasisMethod,
+    /// Expected price impact of market orders, for the cost estimates in plans
+    #[serde(default)]
+    pub slippage_model: SlippageModel,
+    /// When to rebalance regardless of drift
+    #[serde(default)]
+    pub schedule: RebalanceSchedule,
+    /// Seconds between the UI's own balance and price queries to the exchange
+    #[serde(default = "default_poll_interval_seconds")]
+    #[schemars(range(min = 1))]
+    pub poll_interval_seconds: u64,
+    /// Pause rebalancing once the portfolio is this many percent below its peak
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 0.0, max = 100.0))]
+    pub max_drawdown_halt_pct: Option<f64>,
+    /// Maintenance margin as a fraction of the position value, for the liquidation prices shown
+    #[serde(default = "default_maintenance_margin_rate")]
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub maintenance_margin_rate: f64,
+    /// Free margin, as a percentage of the total position value, below which the margin ratio is flagged
+    #[serde(default = "default_margin_alert_threshold_pct")]
+    #[schemars(range(min = 0.0))]
+    pub margin_alert_threshold_pct: f64,
+    /// Annual risk-free rate, as a fraction, that the estimated Sharpe ratio is measured against
+    #[serde(default = "default_risk_free_rate_annual")]
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub risk_free_rate_annual: f64,
+    /// Currency monetary values are shown in; amounts in the file stay in USDT
+    #[serde(default, alias = "base_currency")]
+    pub display_currency: DisplayCurrency,
+    /// Decimal and thousands separators of numbers in the UI
+    #[serde(default)]
+    pub locale: Locale,
+    /// Decimal places allocation percentages are shown with in the editor
+    #[serde(default = "default_display_precision")]
+    #[schemars(range(max = 6))]
+    pub display_precision: u8,
+    /// Dark, light, or whatever the desktop prefers
+    #[serde(default)]
+    pub theme: Theme,
+    /// Zoom on top of the OS display scaling
+    #[serde(default = "default_ui_scale")]
+    #[schemars(range(min = 0.75, max = 2.0))]
+    pub ui_scale: f32,
+    /// Closing the window hides it to the system tray instead of quitting
+    #[serde(default)]
+    pub minimize_to_tray: bool,
+    /// Show a desktop notification for every executed trade
+    #[serde(default = "default_notifications_enabled")]
+    pub notifications_enabled: bool,
+    /// Exchange whose futures testnet the API keys belong to
+    #[serde(default)]
+    pub exchange: ExchangeChoice,
+    /// Orders are simulated against a virtual ledger on both the UI and backend side
+    #[serde(default)]
+    pub paper_trading: bool,
+    /// URL that rebalancing events are POSTed to as JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// Events POSTed to `webhook_url`
+    #[serde(default = "default_webhook_events")]
+    pub webhook_events: Vec<WebhookEvent>,
+    /// Look for a newer release on GitHub at startup, at most once a day
+    #[serde(default = "default_check_for_updates")]
+    pub check_for_updates: bool,
+    /// When the last update check succeeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_update_check: Option<DateTime<Utc>>,
+    /// Retries of exchange requests failing with network errors or transient
+    /// statuses; orders are never retried, as that could place them twice
+    #[serde(default)]
+    pub retry: RetryConfig,
+    /// Previous versions of the file kept on save, `.bak.1` being the newest; 0 keeps none
+    #[serde(default = "default_backup_count")]
+    pub backup_count: u8,
+    /// Stamped by `save`, so backups can be told apart
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_at: Option<DateTime<Utc>>,
+    /// Show the setup wizard on the next start. Files written before the wizard
+    /// existed lack the key and belong to users who are set up already.
+    #[serde(default)]
+    pub first_run: bool,
+    /// Keyboard shortcuts, e.g. "Ctrl+R"; Ctrl is Cmd on macOS
+    #[serde(default)]
+    pub bindings: KeyBindings,
+    /// Least severe messages written to the log: off, error, warn, info, debug or trace
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    /// Log file; defaults to a `.log` file beside the config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_file: Option<PathBuf>,
+    /// Port on 127.0.0.1 serving `/health` and `/metrics`; 0 turns the server off.
+    /// Read at startup only
+    #[serde(default = "default_health_port")]
+    pub health_port: u16,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
+            format: ConfigFormat::Json,
             api_key: String::new(),
-            api_secret: String::new(),
+            api_secret: Zeroizing::default(),
             portfolio_allocation: PortfolioAllocation::default(),
-            rebalance_threshold: 5.0,
-            min_usdt_inflow: 5.0,
+            display_order: Vec::new(),
+            notes: String::new(),
+            rebalance_threshold: default_rebalance_threshold(),
+            per_asset_threshold: None,
+            min_usdt_inflow: default_min_usdt_inflow(),
+            min_trade_usdt: default_min_trade_usdt(),
+            taker_fee_rate: default_taker_fee_rate(),
+            maker_fee_rate: default_maker_fee_rate(),
+            allocation_mode: AllocationMode::Custom,
+            position_sizing: PositionSizing::FixedPct,
+            cost_basis_method: CostBasisMethod::Fifo,
+            slippage_model: SlippageModel::None,
+            schedule: RebalanceSchedule::default(),
+            poll_interval_seconds: default_poll_interval_seconds(),
+            max_drawdown_halt_pct: None,
+            maintenance_margin_rate: default_maintenance_margin_rate(),
+            margin_alert_threshold_pct: default_margin_alert_threshold_pct(),
+            risk_free_rate_annual: default_risk_free_rate_annual(),
+            display_currency: DisplayCurrency::Usdt,
+            locale: Locale::EnUs,
+            display_precision: default_display_precision(),
+            theme: Theme::Dark,
+            ui_scale: default_ui_scale(),
+            minimize_to_tray: false,
+            notifications_enabled: default_notifications_enabled(),
+            exchange: ExchangeChoice::default(),
+            paper_trading: false,
+            webhook_url: None,
+            webhook_events: default_webhook_events(),
+            check_for_updates: default_check_for_updates(),
+            last_update_check: None,
+            retry: RetryConfig::default(),
+            backup_count: default_backup_count(),
+            saved_at: None,
+            first_run: true,
+            bindings: KeyBindings::default(),
+            log_level: default_log_level(),
+            log_file: None,
+            health_port: default_health_port(),
+        }
+    }
+}
+
+fn default_version() -> u32 {
+    1
+}
+
+fn default_rebalance_threshold() -> f64 {
+    5.0
+}
+
+fn default_min_usdt_inflow() -> f64 {
+    5.0
+}
+
+fn default_min_trade_usdt() -> f64 {
+    5.0
+}
+
+fn default_poll_interval_seconds() -> u64 {
+    60
+}
+
+fn default_maintenance_margin_rate() -> f64 {
+    0.005
+}
+
+fn default_margin_alert_threshold_pct() -> f64 {
+    20.0
+}
+
+fn default_risk_free_rate_annual() -> f64 {
+    0.05
+}
+
+// Gate.io's base futures fees
+fn default_taker_fee_rate() -> f64 {
+    0.00075
+}
+
+fn default_maker_fee_rate() -> f64 {
+    0.00025
+}
+
+fn default_notifications_enabled() -> bool {
+    true
+}
+
+fn default_check_for_updates() -> bool {
+    true
+}
+
+fn default_backup_count() -> u8 {
+    5
+}
+
+fn default_display_precision() -> u8 {
+    1
+}
+
+fn default_ui_scale() -> f32 {
+    1.0
+}
+
+fn default_webhook_events() -> Vec<WebhookEvent> {
+    WebhookEvent::ALL.to_vec()
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_health_port() -> u16 {
+    8765
+}
+
+impl Config {
+    /// Targets the rebalancer aims for: weights by `allocation_mode`, sized by
+    /// `position_sizing`.
+    pub fn target_allocation(&self, market_data: &MarketData) -> PortfolioAllocation {
+        let weights = self
+            .allocation_mode
+            .weights(&self.portfolio_allocation, market_data);
+        match self.position_sizing {
+            PositionSizing::FixedPct => weights,
+            PositionSizing::KellyCriterion {
+                win_rate,
+                avg_win_loss_ratio,
+                fraction,
+            } => metrics::kelly_targets(
+                &weights,
+                metrics::kelly_size(win_rate, avg_win_loss_ratio, fraction),
+            ),
+        }
+    }
+
+    /// JSON Schema (draft-07) of the config file, for validation in external editors.
+    pub fn json_schema() -> Value {
+        let schema = schemars::gen::SchemaSettings::draft07()
+            .into_generator()
+            .into_root_schema_for::<Config>();
+        serde_json::to_value(schema).unwrap_or_default()
+    }
+}
+
+impl Config {
+    /// Rebalance threshold for `symbol`, falling back to the global one.
+    pub fn threshold_for(&self, symbol: &str) -> f64 {
+        self.per_asset_threshold
+            .as_ref()
+            .and_then(|thresholds| thresholds.get(symbol))
+            .copied()
+            .unwrap_or(self.rebalance_threshold)
+    }
+
+    /// Upgrades a raw config document to `CONFIG_VERSION` one step at a time,
+    /// then deserializes it.
+    pub fn migrate(mut raw: Value) -> Result<Config, KinError> {
+        let mut version = raw
+            .get("version")
+            .and_then(Value::as_u64)
+            .map(|v| v as u32)
+            .unwrap_or_else(default_version);
+
+        if version > CONFIG_VERSION {
+            return Err(KinError::UnsupportedVersion(version));
+        }
+
+        while version < CONFIG_VERSION {
+            raw = match version {
+                1 => migrate_v1_to_v2(raw)?,
+                v => return Err(KinError::UnsupportedVersion(v)),
+            };
+            version += 1;
+        }
+
+        let config: Config = serde_json::from_value(raw)?;
+        config.check_percentages()?;
+        config.check_leverage()?;
+        if config.display_precision > MAX_DISPLAY_PRECISION {
+            return Err(KinError::invalid_setting(
+                "display_precision",
+                &format!("must be at most {}", MAX_DISPLAY_PRECISION),
+            ));
+        }
+        // A zero interval would be due on every check
+        if config.schedule == (RebalanceSchedule::Interval { hours: 0 }) {
+            return Err(KinError::invalid_setting(
+                "schedule",
+                "interval must be at least 1 hour",
+            ));
+        }
+        Ok(config)
+    }
+
+    // Hand-edited files bypass the editor's validation
+    fn check_percentages(&self) -> Result<(), KinError> {
+        let in_range = |pct: f64| (0.0..=100.0).contains(&pct);
+        for asset in &self.portfolio_allocation.assets {
+            if !in_range(asset.target_pct) {
+                return Err(KinError::invalid_allocation(
+                    &asset.symbol,
+                    "must be between 0 and 100",
+                ));
+            }
+        }
+        let thresholds = self.per_asset_threshold.iter().flatten();
+        let settings = [
+            ("rebalance_threshold", Some(self.rebalance_threshold)),
+            ("max_drawdown_halt_pct", self.max_drawdown_halt_pct),
+            (
+                "margin_alert_threshold_pct",
+                Some(self.margin_alert_threshold_pct),
+            ),
+        ];
+        for (field, pct) in settings
+            .into_iter()
+            .chain(thresholds.map(|(_, pct)| ("per_asset_threshold", Some(*pct))))
+        {
+            if pct.is_some_and(|pct| !in_range(pct)) {
+                return Err(KinError::invalid_setting(
+                    field,
+                    "must be between 0 and 100",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Margin is position value / leverage, so zero would divide by zero; the
+    // exchanges only take whole leverage
+    fn check_leverage(&self) -> Result<(), KinError> {
+        let max_leverage = self.exchange.max_leverage();
+        for asset in &self.portfolio_allocation.assets {
+            if !(1.0..=max_leverage).contains(&asset.leverage) || asset.leverage.fract() != 0.0 {
+                return Err(KinError::invalid_allocation(
+                    &asset.symbol,
+                    &format!(
+                        "leverage must be a whole number between 1 and {}x on {}",
+                        max_leverage,
+                        self.exchange.label()
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Both formats are parsed into a JSON value first so they share the migration chain
+    pub fn from_text(text: &str, format: ConfigFormat) -> Result<Config, KinError> {
+        let raw: Value = match format {
+            ConfigFormat::Json => serde_json::from_str(text)?,
+            ConfigFormat::Toml => toml::from_str(text)?,
+        };
+        let mut config = Config::migrate(raw)?;
+        config.format = format;
+        Ok(config)
+    }
+
+    pub fn to_text(&self, format: ConfigFormat) -> Result<String, KinError> {
+        Ok(match format {
+            ConfigFormat::Json => serde_json::to_string_pretty(self)?,
+            ConfigFormat::Toml => toml::to_string_pretty(self)?,
+        })
+    }
+
+    // A TOML config takes precedence when both formats exist
+    pub fn default_path() -> PathBuf {
+        let json_path = dirs::home_dir()
+            .unwrap_or_default()
+            .join(".portfolio_rebalancer.json");
+        let toml_path = json_path.with_extension(ConfigFormat::Toml.extension());
+        if toml_path.exists() {
+            toml_path
+        } else {
+            json_path
+        }
+    }
+
+    /// The config file given with `--config`, or `default_path`.
+    pub fn get_config_path(override_path: Option<PathBuf>) -> PathBuf {
+        override_path.unwrap_or_else(Config::default_path)
+    }
+
+    pub fn log_file_path(&self, config_path: &Path) -> PathBuf {
+        self.log_file
+            .clone()
+            .unwrap_or_else(|| config_path.with_extension("log"))
+    }
+
+    pub fn load(path: &Path) -> Result<Config, KinError> {
+        Config::load_with_passphrase(path, None)
+    }
+
+    /// Loads a plain or encrypted config; encrypted files need `passphrase`.
+    pub fn load_with_passphrase(path: &Path, passphrase: Option<&str>) -> Result<Config, KinError> {
+        Config::load_as(path, ConfigFormat::from_path(path), passphrase)
+    }
+
+    // Backups ("config.toml.bak.1") do not end in the extension of their format
+    fn load_as(
+        path: &Path,
+        format: ConfigFormat,
+        passphrase: Option<&str>,
+    ) -> Result<Config, KinError> {
+        if !path.exists() {
+            return Err(KinError::ConfigNotFound(path.to_path_buf()));
+        }
+        let data = fs::read(path)?;
+        if crypto::is_encrypted(&data) {
+            let passphrase =
+                passphrase.ok_or_else(|| KinError::PassphraseRequired(path.to_path_buf()))?;
+            let mut config = crypto::decrypt_config(&data, passphrase)?;
+            config.format = format;
+            return Ok(config);
         }
+        let text = String::from_utf8(data)
+            .map_err(|e| KinError::ConfigIo(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+        Config::from_text(&text, format)
+    }
+
+    /// Writes the allocation (USDT included) followed by the rebalancing
+    /// parameters and strategy notes, as two header-led sections separated by a blank line.
+    pub fn export_to_csv(&self, path: &Path) -> Result<(), KinError> {
+        let mut file = fs::File::create(path)?;
+
+        let mut writer = csv::Writer::from_writer(&mut file);
+        writer.write_record(["symbol", "target_pct"])?;
+        for asset in &self.portfolio_allocation.assets {
+            writer.write_record([asset.symbol.clone(), asset.target_pct.to_string()])?;
+        }
+        writer.write_record([
+            USDT.to_string(),
+            self.portfolio_allocation.usdt().to_string(),
+        ])?;
+        writer.flush()?;
+        drop(writer);
+
+        // The csv writer quotes empty records, so the separator line is written raw
+        file.write_all(b"\n")?;
+
+        let mut writer = csv::Writer::from_writer(&mut file);
+        writer.write_record(["parameter", "value"])?;
+        writer.write_record([
+            "rebalance_threshold".to_string(),
+            self.rebalance_threshold.to_string(),
+        ])?;
+        writer.write_record([
+            "min_usdt_inflow".to_string(),
+            self.min_usdt_inflow.to_string(),
+        ])?;
+        if !self.notes.is_empty() {
+            writer.write_record(["notes", self.notes.as_str()])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    // The format follows the file extension, not `self.format`
+    pub fn save(&self, path: &Path) -> Result<(), KinError> {
+        self.save_with_passphrase(path, None)
+    }
+
+    /// Writes the config with a fresh `saved_at`, encrypted when a passphrase is
+    /// given. The file is replaced atomically and the old one kept as a backup.
+    pub fn save_with_passphrase(
+        &self,
+        path: &Path,
+        passphrase: Option<&str>,
+    ) -> Result<(), KinError> {
+        let stamped = Config {
+            saved_at: Some(Utc::now()),
+            ..self.clone()
+        };
+        let data = match passphrase {
+            Some(passphrase) => crypto::encrypt_config(&stamped, passphrase)?,
+            None => stamped.to_text(ConfigFormat::from_path(path))?.into_bytes(),
+        };
+        write_with_backups(path, &data, self.backup_count)?;
+        Ok(())
+    }
+
+    /// Backups of the file at `path` that exist, newest first.
+    pub fn list_backups(
+        path: &Path,
+        backup_count: u8,
+        passphrase: Option<&str>,
+    ) -> Vec<ConfigBackup> {
+        (1..=backup_count)
+            .map(|n| (n, backup_path(path, n)))
+            .filter(|(_, backup)| backup.exists())
+            .map(|(n, backup)| ConfigBackup {
+                n,
+                saved_at: Config::load_as(&backup, ConfigFormat::from_path(path), passphrase)
+                    .ok()
+                    .and_then(|config| config.saved_at),
+            })
+            .collect()
+    }
+
+    /// Puts backup `n` back in place of `path`. The replaced file becomes the
+    /// newest backup, so a restore can be undone the same way.
+    pub fn restore_backup(path: &Path, n: u8, backup_count: u8) -> Result<(), KinError> {
+        let data = fs::read(backup_path(path, n))?;
+        write_with_backups(path, &data, backup_count)?;
+        Ok(())
+    }
+}
+
+/// One of the `.bak.N` files beside a config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigBackup {
+    pub n: u8,
+    // None for unreadable backups and ones written before `saved_at` existed
+    pub saved_at: Option<DateTime<Utc>>,
+}
+
+// "config.json" -> "config.json.<suffix>"
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+pub fn backup_path(path: &Path, n: u8) -> PathBuf {
+    with_suffix(path, &format!("bak.{}", n))
+}
+
+/// Replaces `path` with `data` through `<path>.tmp` and a rename, so a failed
+/// write leaves the old file intact. Before the rename the old file is copied to
+/// `.bak.1`, earlier backups moving up to `.bak.<backup_count>`.
+fn write_with_backups(path: &Path, data: &[u8], backup_count: u8) -> io::Result<()> {
+    let tmp = with_suffix(path, "tmp");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+    if backup_count > 0 && path.exists() {
+        for n in (1..backup_count).rev() {
+            let older = backup_path(path, n);
+            if older.exists() {
+                fs::rename(&older, backup_path(path, n + 1))?;
+            }
+        }
+        fs::copy(path, backup_path(path, 1))?;
+    }
+    fs::rename(&tmp, path)
+}
+
+/// Keychain account holding the credentials of `profile` in `portfolio` on
+/// `exchange`, e.g. "Main/Default/gateio"; each has its own key and secret.
+pub fn keyring_account(portfolio: &str, profile: &str, exchange: ExchangeChoice) -> String {
+    format!("{}/{}/{}", portfolio, profile, exchange.id())
+}
+
+// 系统钥匙串中的API凭据（macOS Keychain / Windows Credential Store / Linux Secret Service）
+impl Config {
+    pub fn load_secrets(account: &str) -> Result<(String, Zeroizing<String>), KinError> {
+        let (key, secret) = secret_entries(account)?;
+        let key = key.get_password()?;
+        let secret = secret.get_password()?;
+        Ok((key, Zeroizing::new(secret)))
+    }
+
+    pub fn store_secrets(account: &str, key: &str, secret: &str) -> Result<(), KinError> {
+        let (key_entry, secret_entry) = secret_entries(account)?;
+        key_entry.set_password(key)?;
+        secret_entry.set_password(secret)?;
+        Ok(())
+    }
+
+    /// Moves the single pair of credentials older versions shared between all
+    /// portfolios and profiles to `account`, unless that has its own already.
+    /// Returns whether there was such a pair.
+    pub fn migrate_legacy_secrets(account: &str) -> Result<bool, KinError> {
+        let (legacy_key, legacy_secret) = secret_entries("")?;
+        let (key, secret) = match (legacy_key.get_password(), legacy_secret.get_password()) {
+            (Ok(key), Ok(secret)) => (key, Zeroizing::new(secret)),
+            (Err(keyring::Error::NoEntry), _) | (_, Err(keyring::Error::NoEntry)) => {
+                return Ok(false)
+            }
+            (Err(e), _) | (_, Err(e)) => return Err(e.into()),
+        };
+        match Config::load_secrets(account) {
+            Err(KinError::Keyring(keyring::Error::NoEntry)) => {
+                Config::store_secrets(account, &key, &secret)?
+            }
+            Err(e) => return Err(e),
+            Ok(_) => {}
+        }
+        legacy_key.delete_credential()?;
+        legacy_secret.delete_credential()?;
+        Ok(true)
+    }
+}
+
+// An empty `account` names the entries of older versions
+fn secret_entries(account: &str) -> Result<(keyring::Entry, keyring::Entry), KinError> {
+    let user = |name: &str| match account {
+        "" => name.to_string(),
+        account => format!("{}/{}", account, name),
+    };
+    Ok((
+        keyring::Entry::new(KEYRING_SERVICE, &user("api_key"))?,
+        keyring::Entry::new(KEYRING_SERVICE, &user("api_secret"))?,
+    ))
+}
+
+// Gate.io APIv4 keys are 32 and secrets 64 alphanumeric (hex) characters,
+// Binance keys and secrets both 64
+fn credential_lengths(exchange: ExchangeChoice) -> (usize, usize) {
+    match exchange {
+        ExchangeChoice::Gateio => (32, 64),
+        ExchangeChoice::Binance => (64, 64),
+    }
+}
+
+/// Catches mistyped or truncated keys before the exchange rejects them.
+pub fn validate_api_key(key: &str, exchange: ExchangeChoice) -> Result<(), String> {
+    validate_credential(key, "API key", credential_lengths(exchange).0)
+}
+
+pub fn validate_api_secret(secret: &str, exchange: ExchangeChoice) -> Result<(), String> {
+    validate_credential(secret, "API secret", credential_lengths(exchange).1)
+}
+
+fn validate_credential(value: &str, name: &str, len: usize) -> Result<(), String> {
+    if let Some(c) = value.chars().find(|c| !c.is_ascii_alphanumeric()) {
+        return Err(format!("{} contains an invalid character {:?}.", name, c));
+    }
+    if value.len() != len {
+        return Err(format!(
+            "{} must be {} characters long (got {}).",
+            name,
+            len,
+            value.len()
+        ));
+    }
+    Ok(())
+}
+
+// v1 -> v2: fixed BTC/ETH/LTC/USDT object becomes a list of asset entries
+fn migrate_v1_to_v2(mut raw: Value) -> Result<Value, KinError> {
+    let root = raw
+        .as_object_mut()
+        .ok_or_else(|| KinError::MalformedConfig("root must be a JSON object".to_string()))?;
+
+    if let Some(Value::Object(allocation)) = root.get("portfolio_allocation") {
+        let assets: Vec<Value> = allocation
+            .iter()
+            .filter(|(symbol, _)| symbol.as_str() != USDT)
+            .map(|(symbol, pct)| json!({ "symbol": symbol, "target_pct": pct }))
+            .collect();
+        root.insert("portfolio_allocation".to_string(), Value::Array(assets));
+    }
+    root.insert("version".to_string(), json!(2));
+    Ok(raw)
+}
+
+/// Reads a two-column `symbol,percentage` CSV covering the whole portfolio
+/// (USDT included). A leading header row is skipped.
+pub fn import_from_csv(path: &Path) -> Result<PortfolioAllocation, KinError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_path(path)?;
+
+    let mut assets: Vec<AssetAllocation> = Vec::new();
+    let mut total = 0.0;
+    let mut last_line = 0;
+    for (index, record) in reader.records().enumerate() {
+        let record = record?;
+        let line = record.position().map_or(index as u64 + 1, |p| p.line()) as usize;
+        last_line = line;
+        let csv_error = |reason: String| KinError::CsvImport { line, reason };
+
+        if record.len() != 2 {
+            return Err(csv_error(format!(
+                "expected 2 columns (symbol,percentage), found {}",
+                record.len()
+            )));
+        }
+        let symbol = record[0].to_uppercase();
+        let pct = match record[1].parse::<f64>() {
+            Ok(pct) => pct,
+            Err(_) if index == 0 => continue, // header row
+            Err(_) => return Err(csv_error(format!("\"{}\" is not a number", &record[1]))),
+        };
+        if symbol.is_empty() {
+            return Err(csv_error("symbol is empty".to_string()));
+        }
+        // `parse` accepts "nan" and "inf", which would slip past the total check
+        if !pct.is_finite() {
+            return Err(csv_error(format!(
+                "{} percentage \"{}\" is not a finite number",
+                symbol, &record[1]
+            )));
+        }
+        if pct < 0.0 {
+            return Err(csv_error(format!(
+                "{} percentage cannot be negative",
+                symbol
+            )));
+        }
+        if symbol == USDT {
+            total += pct;
+            continue;
+        }
+        if assets.iter().any(|a| a.symbol == symbol) {
+            return Err(csv_error(format!("{} is listed more than once", symbol)));
+        }
+        total += pct;
+        assets.push(AssetAllocation::new(symbol, pct));
+    }
+
+    if (total - 100.0).abs() > 0.01 {
+        return Err(KinError::CsvImport {
+            line: last_line,
+            reason: format!("percentages sum to {:.2}, expected 100", total),
+        });
+    }
+    Ok(PortfolioAllocation { assets })
+}
+
+pub const DEFAULT_PROFILE: &str = "Default";
+
+/// A set of named configs, one of which is active at a time.
+#[derive(Debug, Serialize, Clone)]
+pub struct ProfileManager {
+    active: String,
+    profiles: Vec<(String, Config)>,
+}
+
+impl ProfileManager {
+    pub fn new(name: String, config: Config) -> Self {
+        Self {
+            active: name.clone(),
+            profiles: vec![(name, config)],
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, KinError> {
+        ProfileManager::load_with_passphrase(path, None)
+    }
+
+    /// The active profile's config, or the plain config file when there are no profiles.
+    pub fn load_active(config_path: &Path, passphrase: Option<&str>) -> Result<Config, KinError> {
+        ProfileManager::load_with_passphrase(&ProfileManager::path_for(config_path), passphrase)
+            .map(|profiles| profiles.active().clone())
+            .or_else(|_| Config::load_with_passphrase(config_path, passphrase))
+    }
+
+    // Every stored config goes through `Config::migrate` so old profiles upgrade too
+    pub fn load_with_passphrase(path: &Path, passphrase: Option<&str>) -> Result<Self, KinError> {
+        let mut data = fs::read(path)?;
+        if crypto::is_encrypted(&data) {
+            let passphrase =
+                passphrase.ok_or_else(|| KinError::PassphraseRequired(path.to_path_buf()))?;
+            data = crypto::decrypt(&data, passphrase)?;
+        }
+        let raw: Value = serde_json::from_slice(&data)?;
+
+        let entries = raw
+            .get("profiles")
+            .and_then(Value::as_array)
+            .ok_or_else(|| {
+                KinError::MalformedConfig("profiles file has no \"profiles\" list".to_string())
+            })?;
+        let mut profiles = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let (name, config) = match entry.as_array().map(Vec::as_slice) {
+                Some([Value::String(name), config]) => (name.clone(), config.clone()),
+                _ => {
+                    return Err(KinError::MalformedConfig(format!(
+                        "bad profile entry: {}",
+                        entry
+                    )))
+                }
+            };
+            profiles.push((name, Config::migrate(config)?));
+        }
+        if profiles.is_empty() {
+            return Err(KinError::MalformedConfig(
+                "profiles file contains no profiles".to_string(),
+            ));
+        }
+
+        let active = raw
+            .get("active")
+            .and_then(Value::as_str)
+            .filter(|name| profiles.iter().any(|(n, _)| n == name))
+            .map(str::to_string)
+            .unwrap_or_else(|| profiles[0].0.clone());
+
+        Ok(Self { active, profiles })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), KinError> {
+        self.save_with_passphrase(path, None)
+    }
+
+    pub fn save_with_passphrase(
+        &self,
+        path: &Path,
+        passphrase: Option<&str>,
+    ) -> Result<(), KinError> {
+        let text = serde_json::to_string_pretty(self)?;
+        let data = match passphrase {
+            Some(passphrase) => crypto::encrypt(text.as_bytes(), passphrase)?,
+            None => text.into_bytes(),
+        };
+        // The active profile is backed up through the config file
+        write_with_backups(path, &data, 0)?;
+        Ok(())
+    }
+
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".portfolio_rebalancer_profiles.json")
+    }
+
+    /// Profiles kept beside `config_path`, e.g. `work_profiles.json` for
+    /// `work.toml`; `default_path` for the default config.
+    pub fn path_for(config_path: &Path) -> PathBuf {
+        let stem = config_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default();
+        config_path.with_file_name(format!("{}_profiles.json", stem))
+    }
+
+    pub fn active(&self) -> &Config {
+        self.get(&self.active)
+            .expect("active profile always exists")
+    }
+
+    pub fn active_mut(&mut self) -> &mut Config {
+        let active = &self.active;
+        self.profiles
+            .iter_mut()
+            .find(|(name, _)| name == active)
+            .map(|(_, config)| config)
+            .expect("active profile always exists")
+    }
+
+    pub fn active_name(&self) -> &str {
+        &self.active
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&str, &mut Config)> {
+        self.profiles
+            .iter_mut()
+            .map(|(name, config)| (name.as_str(), config))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Config> {
+        self.profiles
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, config)| config)
+    }
+
+    pub fn switch(&mut self, name: &str) -> Result<(), KinError> {
+        if self.get(name).is_none() {
+            return Err(KinError::Profile(format!(
+                "Profile \"{}\" does not exist",
+                name
+            )));
+        }
+        self.active = name.to_string();
+        Ok(())
+    }
+
+    // Adding an existing name replaces that profile's config
+    pub fn add(&mut self, name: String, config: Config) {
+        match self.profiles.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, existing)) => *existing = config,
+            None => self.profiles.push((name, config)),
+        }
+    }
+
+    pub fn delete(&mut self, name: &str) -> Result<(), KinError> {
+        if self.profiles.len() <= 1 {
+            return Err(KinError::Profile(
+                "Cannot delete the last remaining profile".to_string(),
+            ));
+        }
+        let index = self
+            .profiles
+            .iter()
+            .position(|(n, _)| n == name)
+            .ok_or_else(|| KinError::Profile(format!("Profile \"{}\" does not exist", name)))?;
+        self.profiles.remove(index);
+        if self.active == name {
+            self.active = self.profiles[0].0.clone();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zeroize::Zeroize;
+
+    #[test]
+    fn migrates_v1_four_field_allocation() {
+        let v1 = json!({
+            "api_key": "key",
+            "api_secret": "secret",
+            "portfolio_allocation": {
+                "BTC_USDT": 30.0,
+                "ETH_USDT": 20.0,
+                "LTC_USDT": 5.0,
+                "USDT": 45.0
+            },
+            "rebalance_threshold": 4.0,
+            "min_usdt_inflow": 10.0
+        });
+
+        let config = Config::migrate(v1).unwrap();
+
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.api_key, "key");
+        assert_eq!(
+            config.portfolio_allocation.assets,
+            vec![
+                AssetAllocation::new("BTC_USDT", 30.0),
+                AssetAllocation::new("ETH_USDT", 20.0),
+                AssetAllocation::new("LTC_USDT", 5.0),
+            ]
+        );
+        assert_eq!(config.portfolio_allocation.get(USDT), None);
+        assert_eq!(config.portfolio_allocation.usdt(), 45.0);
+        assert_eq!(config.rebalance_threshold, 4.0);
+        assert_eq!(config.min_usdt_inflow, 10.0);
+        assert_eq!(config.display_currency, DisplayCurrency::Usdt);
+    }
+
+    #[test]
+    fn reads_the_old_base_currency_key() {
+        let mut old = serde_json::to_value(Config::default()).unwrap();
+        old.as_object_mut().unwrap().remove("display_currency");
+        old["base_currency"] = json!("BTC");
+        let config: Config = serde_json::from_value(old).unwrap();
+        assert_eq!(config.display_currency, DisplayCurrency::Btc);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let config = Config::default();
+        let text = config.to_text(ConfigFormat::Json).unwrap();
+        assert_eq!(
+            Config::from_text(&text, ConfigFormat::Json).unwrap(),
+            config
+        );
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let mut config = Config {
+            api_key: "key".to_string(),
+            per_asset_threshold: Some([("LTC_USDT".to_string(), 8.0)].into()),
+            ..Config::default()
+        };
+        config
+            .portfolio_allocation
+            .assets
+            .push(AssetAllocation::new("SOL_USDT", 2.5));
+
+        let text = config.to_text(ConfigFormat::Toml).unwrap();
+        let parsed = Config::from_text(&text, ConfigFormat::Toml).unwrap();
+
+        assert_eq!(parsed.format, ConfigFormat::Toml);
+        assert_eq!(
+            parsed,
+            Config {
+                format: ConfigFormat::Toml,
+                ..config
+            }
+        );
+    }
+
+    #[test]
+    fn only_new_configs_start_the_wizard() {
+        assert!(Config::default().first_run);
+        let mut old = serde_json::to_value(Config::default()).unwrap();
+        old.as_object_mut().unwrap().remove("first_run");
+        let config: Config = serde_json::from_value(old).unwrap();
+        assert!(!config.first_run);
+    }
+
+    #[test]
+    fn display_order_keeps_every_symbol_once() {
+        let symbols: Vec<String> = ["BTC_USDT", "ETH_USDT", "LTC_USDT", "SOL_USDT"]
+            .map(String::from)
+            .into();
+        let saved = ["LTC_USDT", "DOGE_USDT", "BTC_USDT"].map(String::from);
+        // Removed assets are dropped, new ones are appended
+        let mut order = display_order(&saved, &symbols);
+        assert_eq!(order, ["LTC_USDT", "BTC_USDT", "ETH_USDT", "SOL_USDT"]);
+
+        swap_symbols(&mut order, "SOL_USDT", "LTC_USDT");
+        assert_eq!(order, ["SOL_USDT", "BTC_USDT", "ETH_USDT", "LTC_USDT"]);
+        swap_symbols(&mut order, "SOL_USDT", "DOGE_USDT");
+        assert_eq!(order, ["SOL_USDT", "BTC_USDT", "ETH_USDT", "LTC_USDT"]);
+        assert_eq!(display_order(&[], &symbols), symbols);
+    }
+
+    #[test]
+    fn per_asset_threshold_falls_back_to_global() {
+        let mut config = Config::default();
+        assert_eq!(config.threshold_for("LTC_USDT"), 5.0);
+        config.per_asset_threshold = Some([("LTC_USDT".to_string(), 8.0)].into());
+        assert_eq!(config.threshold_for("LTC_USDT"), 8.0);
+        assert_eq!(config.threshold_for("BTC_USDT"), 5.0);
+    }
+
+    #[test]
+    fn format_follows_extension() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a/b.toml")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a/b.json")),
+            ConfigFormat::Json
+        );
+    }
+
+    #[test]
+    fn config_override_keeps_its_own_profiles() {
+        let custom = PathBuf::from("ci/work.toml");
+        assert_eq!(Config::get_config_path(Some(custom.clone())), custom);
+        assert_eq!(Config::get_config_path(None), Config::default_path());
+        assert_eq!(
+            ProfileManager::path_for(&custom),
+            PathBuf::from("ci/work_profiles.json")
+        );
+        assert_eq!(
+            ProfileManager::path_for(&Config::default_path()),
+            ProfileManager::default_path()
+        );
+    }
+
+    #[test]
+    fn saves_keep_rotating_backups_that_can_be_restored() {
+        let dir = std::env::temp_dir().join(format!("kin-backups-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let mut config = Config {
+            backup_count: 2,
+            ..Config::default()
+        };
+        for threshold in [1.0, 2.0, 3.0, 4.0] {
+            config.rebalance_threshold = threshold;
+            config.save(&path).unwrap();
+        }
+        assert!(!with_suffix(&path, "tmp").exists());
+        assert!(!backup_path(&path, 3).exists());
+        let backups = Config::list_backups(&path, 5, None);
+        assert_eq!(backups.iter().map(|b| b.n).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(backups.iter().all(|b| b.saved_at.is_some()));
+        let threshold = |p: &Path| {
+            Config::load_as(p, ConfigFormat::Toml, None)
+                .unwrap()
+                .rebalance_threshold
+        };
+        assert_eq!(threshold(&backup_path(&path, 1)), 3.0);
+        assert_eq!(threshold(&backup_path(&path, 2)), 2.0);
+
+        // The replaced file becomes the newest backup
+        Config::restore_backup(&path, 2, 2).unwrap();
+        assert_eq!(threshold(&path), 2.0);
+        assert_eq!(threshold(&backup_path(&path, 1)), 4.0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn write_temp_csv(name: &str, text: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("kin-{}-{}.csv", name, std::process::id()));
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn imports_csv_with_header() {
+        let path = write_temp_csv(
+            "import-ok",
+            "symbol,percentage\nbtc_usdt,30\nETH_USDT, 20\nUSDT,50\n",
+        );
+        let allocation = import_from_csv(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(allocation.get("BTC_USDT"), Some(30.0));
+        assert_eq!(allocation.get("ETH_USDT"), Some(20.0));
+        assert_eq!(allocation.usdt(), 50.0);
+    }
+
+    #[test]
+    fn csv_import_reports_line() {
+        let path = write_temp_csv("import-bad", "BTC_USDT,30\nETH_USDT,-5\n");
+        let result = import_from_csv(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(KinError::CsvImport { line: 2, .. })));
+
+        for text in [
+            "BTC_USDT,30\nETH_USDT,nan\nUSDT,70\n",
+            "BTC_USDT,30\nETH_USDT,inf\n",
+        ] {
+            let path = write_temp_csv("import-nan", text);
+            let result = import_from_csv(&path);
+            fs::remove_file(&path).unwrap();
+            assert!(
+                matches!(result, Err(KinError::CsvImport { line: 2, .. })),
+                "{:?} gave {:?}",
+                text,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn exports_config_csv_sections() {
+        let path = write_temp_csv("export", "");
+        Config::default().export_to_csv(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            text,
+            "symbol,target_pct\nBTC_USDT,25\nETH_USDT,15\nLTC_USDT,10\nUSDT,50\n\n\
+             parameter,value\nrebalance_threshold,5\nmin_usdt_inflow,5\n"
+        );
+
+        let config = Config {
+            notes: "Halving cycle, \"BTC heavy\"".to_string(),
+            ..Config::default()
+        };
+        config.export_to_csv(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(text.ends_with("min_usdt_inflow,5\nnotes,\"Halving cycle, \"\"BTC heavy\"\"\"\n"));
+    }
+
+    #[test]
+    fn keyring_accounts_differ_per_portfolio_profile_and_exchange() {
+        let accounts = [
+            keyring_account("Main", "Default", ExchangeChoice::Gateio),
+            keyring_account("Main", "Default", ExchangeChoice::Binance),
+            keyring_account("Main", "Aggressive", ExchangeChoice::Gateio),
+            keyring_account("Hedge", "Default", ExchangeChoice::Gateio),
+        ];
+        assert_eq!(accounts[0], "Main/Default/gateio");
+        let unique: std::collections::HashSet<&String> = accounts.iter().collect();
+        assert_eq!(unique.len(), accounts.len());
+    }
+
+    #[test]
+    fn validates_api_credentials() {
+        let gateio = ExchangeChoice::Gateio;
+        assert!(validate_api_key(&"a1".repeat(16), gateio).is_ok());
+        assert!(validate_api_secret(&"f0".repeat(32), gateio).is_ok());
+        assert!(validate_api_key("abc123", gateio)
+            .unwrap_err()
+            .contains("32 characters"));
+        assert!(validate_api_key(&format!("{} ", "a".repeat(31)), gateio)
+            .unwrap_err()
+            .contains("invalid character"));
+        assert!(validate_api_secret(&"a1".repeat(16), gateio).is_err());
+        // Binance keys are as long as their secrets
+        assert!(validate_api_key(&"aB".repeat(32), ExchangeChoice::Binance).is_ok());
+        assert!(validate_api_key(&"a1".repeat(16), ExchangeChoice::Binance).is_err());
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let future = json!({ "version": CONFIG_VERSION + 1 });
+        assert!(matches!(
+            Config::migrate(future),
+            Err(KinError::UnsupportedVersion(v)) if v == CONFIG_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn rejects_out_of_range_percentages() {
+        let mut raw = serde_json::to_value(Config::default()).unwrap();
+        raw["portfolio_allocation"][0]["target_pct"] = json!(150.0);
+        assert!(matches!(
+            Config::migrate(raw.clone()),
+            Err(KinError::InvalidAllocation { field, .. }) if field == "BTC_USDT"
+        ));
+
+        raw["portfolio_allocation"][0]["target_pct"] = json!(25.0);
+        raw["per_asset_threshold"] = json!({ "LTC_USDT": -1.0 });
+        assert!(matches!(
+            Config::migrate(raw.clone()),
+            Err(KinError::InvalidSetting { field, .. }) if field == "per_asset_threshold"
+        ));
+
+        raw["per_asset_threshold"] = json!(null);
+        raw["display_precision"] = json!(7);
+        assert!(matches!(
+            Config::migrate(raw),
+            Err(KinError::InvalidSetting { field, .. }) if field == "display_precision"
+        ));
+    }
+
+    #[test]
+    fn rejects_zero_hour_interval() {
+        let path = std::env::temp_dir().join(format!("kin-interval-{}.json", std::process::id()));
+        let mut raw = serde_json::to_value(Config::default()).unwrap();
+        raw["schedule"] = json!({ "type": "interval", "hours": 0 });
+        fs::write(&path, raw.to_string()).unwrap();
+        let result = Config::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result,
+            Err(KinError::InvalidSetting { field, .. }) if field == "schedule"
+        ));
+    }
+
+    #[test]
+    fn rejects_leverage_outside_the_exchange_range() {
+        for leverage in [0.0, 0.5, 2.5, 101.0] {
+            let mut raw = serde_json::to_value(Config::default()).unwrap();
+            raw["portfolio_allocation"][0]["leverage"] = json!(leverage);
+            assert!(matches!(
+                Config::migrate(raw),
+                Err(KinError::InvalidAllocation { .. })
+            ));
+        }
+        let mut raw = serde_json::to_value(Config::default()).unwrap();
+        raw["exchange"] = json!("binance");
+        raw["portfolio_allocation"][0]["leverage"] = json!(125.0);
+        assert!(Config::migrate(raw).is_ok());
+    }
+
+    #[test]
+    fn groups_targets_by_tag() {
+        let tagged = |symbol: &str, pct: f64, tags: &str| AssetAllocation {
+            tags: parse_tags(tags),
+            ..AssetAllocation::new(symbol, pct)
+        };
+        let allocation = PortfolioAllocation {
+            assets: vec![
+                tagged("BTC_USDT", 30.0, "Large cap, Layer1"),
+                tagged("ETH_USDT", 20.0, " Layer1 ,Layer1,"),
+                tagged("UNI_USDT", 5.0, "DeFi"),
+                tagged("LTC_USDT", 10.0, ""),
+            ],
+        };
+        assert_eq!(allocation.assets[1].tags, ["Layer1"]);
+        assert_eq!(allocation.tag_names(), ["DeFi", "Large cap", "Layer1"]);
+        assert_eq!(
+            allocation.symbols_with_tag("Layer1"),
+            ["BTC_USDT", "ETH_USDT"]
+        );
+        assert_eq!(
+            allocation.by_tag(),
+            [
+                ("DeFi".to_string(), 5.0),
+                ("Large cap".to_string(), 15.0),
+                ("Layer1".to_string(), 35.0),
+                (UNTAGGED.to_string(), 10.0),
+            ]
+        );
+
+        // Tags are only written when there are some
+        let json = serde_json::to_value(&allocation).unwrap();
+        assert_eq!(json[2]["tags"], json!(["DeFi"]));
+        assert!(json[3].get("tags").is_none());
+        let loaded: PortfolioAllocation = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded, allocation);
+    }
+
+    #[test]
+    fn missing_fields_take_their_defaults() {
+        let config = Config::from_text(
+            r#"{"api_key":"test","api_secret":"test"}"#,
+            ConfigFormat::Json,
+        )
+        .unwrap();
+        let expected = Config {
+            api_key: "test".to_string(),
+            api_secret: "test".to_string().into(),
+            // Files without the key predate the setup wizard
+            first_run: false,
+            ..Config::default()
+        };
+        assert_eq!(config, expected);
+        assert_eq!(config.rebalance_threshold, 5.0);
+        assert_eq!(config.portfolio_allocation, PortfolioAllocation::default());
+
+        let config = Config::from_text(
+            r#"{"allocation_mode":{"risk_parity":{"lookback_days":60}}}"#,
+            ConfigFormat::Json,
+        )
+        .unwrap();
+        assert_eq!(
+            config.allocation_mode,
+            AllocationMode::RiskParity(RiskParityConfig {
+                lookback_days: 60,
+                ..RiskParityConfig::DEFAULT
+            })
+        );
+    }
+
+    #[test]
+    fn api_secret_is_zeroed() {
+        let mut secret = Zeroizing::new(String::from("0123456789abcdef"));
+        let (ptr, capacity) = (secret.as_ptr(), secret.capacity());
+        // What dropping runs before the buffer is freed; reading it after the
+        // actual drop would be a use-after-free
+        secret.zeroize();
+        assert_eq!(secret.capacity(), capacity);
+        let bytes = unsafe { std::slice::from_raw_parts(ptr, capacity) };
+        assert!(bytes.iter().all(|b| *b == 0));
+
+        let config = Config {
+            api_secret: "secret".to_string().into(),
+            ..Config::default()
+        };
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["api_secret"], "secret");
+        let loaded: Config = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.api_secret.as_str(), "secret");
+    }
+
+    #[test]
+    fn default_config_matches_its_schema() {
+        let schema = Config::json_schema();
+        assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+        assert!(schema["properties"]["rebalance_threshold"]["description"].is_string());
+        let validator = jsonschema::draft7::new(&schema).unwrap();
+
+        let mut config = serde_json::to_value(Config::default()).unwrap();
+        assert!(validator.is_valid(&config));
+        config["portfolio_allocation"][0]["target_pct"] = json!(150.0);
+        assert!(!validator.is_valid(&config));
     }
 }
@@ -0,0 +1,173 @@
+use chrono::Local;
+use std::backtrace::Backtrace;
+use std::env::consts;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::thread;
+use zeroize::Zeroize;
+
+use crate::config::{Config, ProfileManager};
+use crate::error::KinError;
+use crate::logging;
+
+const REDACTED: &str = "<redacted>";
+
+/// Installs a panic hook that writes a crash report (panic, backtrace, redacted
+/// config, platform and the latest log lines) to the home directory and, when the
+/// UI thread panicked, offers to copy its path. The default hook still prints to stderr.
+pub fn install(config_path: PathBuf, passphrase: Option<String>) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        // 钩子本身不能再panic，所有I/O都包在catch_unwind里
+        let written = panic::catch_unwind(AssertUnwindSafe(|| {
+            write_report(info, &config_path, passphrase.as_deref())
+        }));
+        match written {
+            Ok(Ok(path)) => {
+                eprintln!("Crash report saved to {:?}", path);
+                // Panics in worker threads do not end the app
+                if thread::current().name() == Some("main") {
+                    let _ = panic::catch_unwind(|| offer_to_copy(&path));
+                }
+            }
+            Ok(Err(e)) => eprintln!("Failed to write crash report: {}", e),
+            Err(_) => eprintln!("Failed to write crash report"),
+        }
+    }));
+}
+
+pub fn report_path() -> PathBuf {
+    dirs::home_dir().unwrap_or_default().join(format!(
+        ".portfolio_rebalancer_crash_{}.txt",
+        Local::now().format("%Y%m%d_%H%M%S")
+    ))
+}
+
+fn write_report(
+    info: &PanicHookInfo<'_>,
+    config_path: &Path,
+    passphrase: Option<&str>,
+) -> Result<PathBuf, KinError> {
+    let location = info
+        .location()
+        .map(|l| format!(" at {}:{}", l.file(), l.line()))
+        .unwrap_or_default();
+    let panic = format!("{}{}", panic_message(info), location);
+    let config = ProfileManager::load_active(config_path, passphrase);
+    let text = report(
+        &panic,
+        &Backtrace::force_capture().to_string(),
+        &config,
+        &logging::recent_lines(),
+    );
+    let path = report_path();
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+fn panic_message(info: &PanicHookInfo<'_>) -> String {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "(no message)".to_string())
+}
+
+// The webhook URL often carries a token too
+fn redacted(mut config: Config) -> Config {
+    for secret in [&mut config.api_key, &mut config.api_secret] {
+        if !secret.is_empty() {
+            secret.zeroize();
+            secret.push_str(REDACTED);
+        }
+    }
+    if config.webhook_url.is_some() {
+        config.webhook_url = Some(REDACTED.to_string());
+    }
+    config
+}
+
+fn report(
+    panic: &str,
+    backtrace: &str,
+    config: &Result<Config, KinError>,
+    log_lines: &[String],
+) -> String {
+    let config = match config {
+        Ok(config) => serde_json::to_string_pretty(&redacted(config.clone()))
+            .unwrap_or_else(|e| format!("(not serializable: {})", e)),
+        Err(e) => format!("(not loaded: {})", e),
+    };
+    format!(
+        "KIN Portfolio Rebalancer {} crashed at {}\n\
+         Platform: {} {} ({})\n\n\
+         == Panic ==\n{}\n\n\
+         == Backtrace ==\n{}\n\n\
+         == Config ==\n{}\n\n\
+         == Last {} log lines ==\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        Local::now().to_rfc3339(),
+        consts::OS,
+        consts::ARCH,
+        consts::FAMILY,
+        panic,
+        backtrace,
+        config,
+        log_lines.len(),
+        log_lines.join("\n"),
+    )
+}
+
+// Native dialog, since the egui window cannot draw after a panic in `update`
+fn offer_to_copy(path: &Path) {
+    let answer = rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Error)
+        .set_title("KIN Portfolio Rebalancer")
+        .set_description(format!(
+            "The application crashed. A report was saved to {}. Would you like to copy the path to the clipboard?",
+            path.display()
+        ))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show();
+    if answer != rfd::MessageDialogResult::Yes {
+        return;
+    }
+    // On Linux the clipboard is only kept after exit if a clipboard manager runs
+    if let Err(e) = arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(path.display().to_string()))
+    {
+        eprintln!("Failed to copy the crash report path: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_redacts_credentials_and_includes_log_tail() {
+        let config = Config {
+            api_key: "k".repeat(32),
+            api_secret: "s".repeat(64).into(),
+            webhook_url: Some("https://hooks.example.com/token".to_string()),
+            notes: "Overweight BTC until the halving".to_string(),
+            ..Config::default()
+        };
+        let lines = vec!["INFO started".to_string(), "WARN slow".to_string()];
+        let text = report("boom at src/app.rs:1", "<backtrace>", &Ok(config), &lines);
+
+        assert!(text.contains("boom at src/app.rs:1"));
+        assert!(text.contains("== Last 2 log lines ==\nINFO started\nWARN slow"));
+        assert!(text.contains(REDACTED));
+        assert!(text.contains("Overweight BTC until the halving"));
+        assert!(!text.contains(&"k".repeat(32)));
+        assert!(!text.contains(&"s".repeat(64)));
+        assert!(!text.contains("token"));
+
+        let missing = report("boom", "", &Err(KinError::ConfigNotFound("x".into())), &[]);
+        assert!(missing.contains("(not loaded: "));
+    }
+}
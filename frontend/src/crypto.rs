@@ -0,0 +1,98 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use sha2::Sha256;
+
+use crate::config::{Config, ConfigFormat};
+use crate::error::KinError;
+
+/// Marks a file written by `encrypt`; followed by the salt, the nonce and the ciphertext.
+pub const MAGIC: &[u8; 8] = b"KINENC\x00\x01";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN + NONCE_LEN;
+const PBKDF2_ROUNDS: u32 = 100_000;
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Key<Aes256Gcm> {
+    let mut key = Key::<Aes256Gcm>::default();
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    key
+}
+
+/// AES-256-GCM with a fresh random salt and nonce per call.
+pub fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>, KinError> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt));
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| KinError::Encryption("encryption failed".to_string()))?;
+
+    let mut data = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+// GCM cannot tell a wrong key from tampered data; both are reported as a wrong passphrase
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, KinError> {
+    if !is_encrypted(data) {
+        return Err(KinError::Encryption("missing KINENC header".to_string()));
+    }
+    if data.len() < HEADER_LEN {
+        return Err(KinError::Encryption("truncated header".to_string()));
+    }
+    let (salt, rest) = data[MAGIC.len()..].split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, salt));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| KinError::WrongPassphrase)
+}
+
+/// The config is encrypted as JSON whatever the file extension.
+pub fn encrypt_config(config: &Config, passphrase: &str) -> Result<Vec<u8>, KinError> {
+    encrypt(config.to_text(ConfigFormat::Json)?.as_bytes(), passphrase)
+}
+
+pub fn decrypt_config(ciphertext: &[u8], passphrase: &str) -> Result<Config, KinError> {
+    let plaintext = decrypt(ciphertext, passphrase)?;
+    let text = String::from_utf8(plaintext)
+        .map_err(|_| KinError::Encryption("decrypted config is not UTF-8".to_string()))?;
+    Config::from_text(&text, ConfigFormat::Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_round_trip() {
+        let config = Config {
+            api_key: "key".to_string(),
+            api_secret: "secret".to_string().into(),
+            ..Config::default()
+        };
+        let data = encrypt_config(&config, "correct horse").unwrap();
+        assert!(is_encrypted(&data));
+        assert!(!String::from_utf8_lossy(&data).contains("secret"));
+        assert_eq!(decrypt_config(&data, "correct horse").unwrap(), config);
+    }
+
+    #[test]
+    fn wrong_passphrase_is_reported() {
+        let data = encrypt_config(&Config::default(), "correct horse").unwrap();
+        assert!(matches!(
+            decrypt_config(&data, "battery staple"),
+            Err(KinError::WrongPassphrase)
+        ));
+    }
+}
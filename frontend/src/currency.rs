@@ -0,0 +1,141 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::locale::{format_number, Locale};
+
+/// Currency monetary values are shown in. Everything is stored and traded in
+/// USDT; BTC and ETH are converted at the price of their `<symbol>_USDT` contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DisplayCurrency {
+    #[default]
+    Usdt,
+    Btc,
+    Eth,
+}
+
+impl DisplayCurrency {
+    pub const ALL: [DisplayCurrency; 3] = [
+        DisplayCurrency::Usdt,
+        DisplayCurrency::Btc,
+        DisplayCurrency::Eth,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            DisplayCurrency::Usdt => "USDT",
+            DisplayCurrency::Btc => "BTC",
+            DisplayCurrency::Eth => "ETH",
+        }
+    }
+
+    /// Contract the currency is priced by; None for USDT itself.
+    pub fn contract(self) -> Option<&'static str> {
+        match self {
+            DisplayCurrency::Usdt => None,
+            DisplayCurrency::Btc => Some("BTC_USDT"),
+            DisplayCurrency::Eth => Some("ETH_USDT"),
+        }
+    }
+
+    // Enough digits to show a few USDT worth of BTC
+    pub fn decimals(self) -> usize {
+        match self {
+            DisplayCurrency::Usdt => 2,
+            DisplayCurrency::Btc | DisplayCurrency::Eth => 6,
+        }
+    }
+
+    /// USDT per unit, or None while the contract price is not in `prices`.
+    pub fn price(self, prices: &HashMap<String, f64>) -> Option<f64> {
+        match self.contract() {
+            None => Some(1.0),
+            Some(contract) => prices.get(contract).copied().filter(|p| *p > 0.0),
+        }
+    }
+}
+
+impl fmt::Display for DisplayCurrency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Converts a USDT amount into `currency`. Without a price for the currency the
+/// amount is returned unchanged, so check `DisplayCurrency::price` before labelling it.
+pub fn convert_from_usdt(
+    value_usdt: f64,
+    currency: DisplayCurrency,
+    prices: &HashMap<String, f64>,
+) -> f64 {
+    match currency.price(prices) {
+        Some(price) => value_usdt / price,
+        None => value_usdt,
+    }
+}
+
+/// "0.001234 BTC", falling back to "12.34 USDT" while the BTC price is unknown.
+pub fn format_amount(
+    value_usdt: f64,
+    currency: DisplayCurrency,
+    prices: &HashMap<String, f64>,
+    locale: Locale,
+) -> String {
+    let currency = if currency.price(prices).is_some() {
+        currency
+    } else {
+        DisplayCurrency::Usdt
+    };
+    format!(
+        "{} {}",
+        format_number(
+            convert_from_usdt(value_usdt, currency, prices),
+            currency.decimals(),
+            locale
+        ),
+        currency
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_at_the_contract_price() {
+        let prices = HashMap::from([("BTC_USDT".to_string(), 50_000.0)]);
+        assert_eq!(
+            convert_from_usdt(100.0, DisplayCurrency::Usdt, &prices),
+            100.0
+        );
+        assert_eq!(
+            convert_from_usdt(100.0, DisplayCurrency::Btc, &prices),
+            0.002
+        );
+        assert_eq!(
+            format_amount(100.0, DisplayCurrency::Btc, &prices, Locale::EnUs),
+            "0.002000 BTC"
+        );
+        // No ETH price yet: stays in USDT
+        assert_eq!(
+            format_amount(100.0, DisplayCurrency::Eth, &prices, Locale::EnUs),
+            "100.00 USDT"
+        );
+        assert_eq!(
+            format_amount(1000.0, DisplayCurrency::Usdt, &prices, Locale::DeDe),
+            "1.000,00 USDT"
+        );
+    }
+
+    #[test]
+    fn reads_the_old_base_currency_strings() {
+        let currency: DisplayCurrency = serde_json::from_str("\"ETH\"").unwrap();
+        assert_eq!(currency, DisplayCurrency::Eth);
+        assert_eq!(
+            serde_json::to_string(&DisplayCurrency::Usdt).unwrap(),
+            "\"USDT\""
+        );
+    }
+}
@@ -0,0 +1,475 @@
+use chrono::NaiveDate;
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Row};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::error::KinError;
+use crate::history::{Order, RebalanceEvent};
+use crate::tax::{RealizedGain, TaxLot};
+
+/// Version of the tables created by this build, stored in `schema_version`.
+pub const SCHEMA_VERSION: u32 = 5;
+
+/// Trade history stored in SQLite next to the config file.
+pub struct Database {
+    conn: Connection,
+}
+
+impl Database {
+    /// Sidecar file next to the config, e.g. `.portfolio_rebalancer.db`.
+    pub fn path_for(config_path: &Path) -> PathBuf {
+        config_path.with_extension("db")
+    }
+
+    pub fn open(path: &Path) -> Result<Database, KinError> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL);
+             CREATE TABLE IF NOT EXISTS events (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 timestamp TEXT NOT NULL,
+                 asset TEXT NOT NULL,
+                 from_pct REAL NOT NULL,
+                 to_pct REAL NOT NULL,
+                 usdt_traded REAL NOT NULL,
+                 order_id TEXT NOT NULL,
+                 portfolio_value_usdt REAL NOT NULL DEFAULT 0,
+                 fee_usdt REAL NOT NULL DEFAULT 0
+             );
+             CREATE TABLE IF NOT EXISTS orders (
+                 id TEXT PRIMARY KEY,
+                 symbol TEXT NOT NULL,
+                 side TEXT NOT NULL,
+                 qty REAL NOT NULL,
+                 price REAL NOT NULL,
+                 status TEXT NOT NULL,
+                 created_at TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS tax_lots (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 asset TEXT NOT NULL,
+                 quantity REAL NOT NULL,
+                 cost_basis_usdt REAL NOT NULL,
+                 acquired_at TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS realized_gains (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 asset TEXT NOT NULL,
+                 date TEXT NOT NULL,
+                 proceeds REAL NOT NULL,
+                 cost_basis REAL NOT NULL,
+                 gain REAL NOT NULL,
+                 holding_period TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS price_history (
+                 symbol TEXT NOT NULL,
+                 date TEXT NOT NULL,
+                 close REAL NOT NULL,
+                 PRIMARY KEY (symbol, date)
+             );",
+        )?;
+        migrate_schema(&conn)?;
+        Ok(Database { conn })
+    }
+
+    /// Opens the database for `config_path`, importing the JSON history written
+    /// by earlier versions when the database is still empty.
+    pub fn open_for_config(config_path: &Path) -> Result<Database, KinError> {
+        let db = Database::open(&Database::path_for(config_path))?;
+        let legacy_path = RebalanceEvent::history_path(config_path);
+        if legacy_path.exists() && db.count_events()? == 0 {
+            for event in RebalanceEvent::load_all(&legacy_path)? {
+                db.insert_event(&event)?;
+            }
+        }
+        Ok(db)
+    }
+
+    pub fn insert_event(&self, event: &RebalanceEvent) -> Result<(), KinError> {
+        self.conn.execute(
+            "INSERT INTO events (timestamp, asset, from_pct, to_pct, usdt_traded, order_id, portfolio_value_usdt, fee_usdt)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                event.timestamp,
+                event.asset,
+                event.from_pct,
+                event.to_pct,
+                event.usdt_traded,
+                event.order_id,
+                event.portfolio_value_usdt,
+                event.fee_usdt,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The most recent `limit` events, oldest first.
+    pub fn load_events(&self, limit: usize) -> Result<Vec<RebalanceEvent>, KinError> {
+        let mut statement = self.conn.prepare(
+            "SELECT * FROM (
+                 SELECT id, timestamp, asset, from_pct, to_pct, usdt_traded, order_id, portfolio_value_usdt, fee_usdt
+                 FROM events ORDER BY id DESC LIMIT ?1
+             ) ORDER BY id ASC",
+        )?;
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let events = statement
+            .query_map([limit], event_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(events)
+    }
+
+    /// Inserts `order`, or updates the one with its ID when the backend reports
+    /// a new status.
+    pub fn upsert_order(&self, order: &Order) -> Result<(), KinError> {
+        self.conn.execute(
+            "INSERT INTO orders (id, symbol, side, qty, price, status, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(id) DO UPDATE SET qty = ?4, price = ?5, status = ?6",
+            params![
+                order.id,
+                order.symbol,
+                order.side.to_string(),
+                order.qty,
+                order.price,
+                order.status.to_string(),
+                order.created_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The most recent `limit` orders, oldest first.
+    pub fn load_orders(&self, limit: usize) -> Result<Vec<Order>, KinError> {
+        let mut statement = self.conn.prepare(
+            "SELECT * FROM (
+                 SELECT id, symbol, side, qty, price, status, created_at
+                 FROM orders ORDER BY created_at DESC LIMIT ?1
+             ) ORDER BY created_at ASC",
+        )?;
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let orders = statement
+            .query_map([limit], order_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(orders)
+    }
+
+    /// Replaces the stored lots with `lots`, which a sell may have shrunk or removed.
+    pub fn save_tax_lots(&self, lots: &[TaxLot]) -> Result<(), KinError> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM tax_lots", [])?;
+        for lot in lots {
+            tx.execute(
+                "INSERT INTO tax_lots (asset, quantity, cost_basis_usdt, acquired_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    lot.asset,
+                    lot.quantity,
+                    lot.cost_basis_usdt,
+                    lot.acquired_at
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn load_tax_lots(&self) -> Result<Vec<TaxLot>, KinError> {
+        let mut statement = self.conn.prepare(
+            "SELECT asset, quantity, cost_basis_usdt, acquired_at FROM tax_lots ORDER BY id",
+        )?;
+        let lots = statement
+            .query_map([], |row| {
+                Ok(TaxLot {
+                    asset: row.get("asset")?,
+                    quantity: row.get("quantity")?,
+                    cost_basis_usdt: row.get("cost_basis_usdt")?,
+                    acquired_at: row.get("acquired_at")?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(lots)
+    }
+
+    pub fn insert_realized_gain(&self, gain: &RealizedGain) -> Result<(), KinError> {
+        self.conn.execute(
+            "INSERT INTO realized_gains (asset, date, proceeds, cost_basis, gain, holding_period)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                gain.asset,
+                gain.date,
+                gain.proceeds,
+                gain.cost_basis,
+                gain.gain,
+                gain.holding_period.to_string(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Every realized gain, oldest first; the tax report needs all of them.
+    pub fn load_realized_gains(&self) -> Result<Vec<RealizedGain>, KinError> {
+        let mut statement = self.conn.prepare(
+            "SELECT asset, date, proceeds, cost_basis, gain, holding_period
+             FROM realized_gains ORDER BY id",
+        )?;
+        let gains = statement
+            .query_map([], |row| {
+                Ok(RealizedGain {
+                    asset: row.get("asset")?,
+                    date: row.get("date")?,
+                    proceeds: row.get("proceeds")?,
+                    cost_basis: row.get("cost_basis")?,
+                    gain: row.get("gain")?,
+                    holding_period: text_enum(row, "holding_period")?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(gains)
+    }
+
+    /// Stores daily closes of `symbol`, replacing the close of a day already stored.
+    pub fn upsert_daily_closes(
+        &self,
+        symbol: &str,
+        closes: &[(NaiveDate, f64)],
+    ) -> Result<(), KinError> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (date, close) in closes {
+            tx.execute(
+                "INSERT INTO price_history (symbol, date, close) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(symbol, date) DO UPDATE SET close = excluded.close",
+                params![symbol, date, close],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Closes of `symbol` from `since` on, oldest first.
+    pub fn load_daily_closes(&self, symbol: &str, since: NaiveDate) -> Result<Vec<f64>, KinError> {
+        let mut statement = self.conn.prepare(
+            "SELECT close FROM price_history WHERE symbol = ?1 AND date >= ?2 ORDER BY date",
+        )?;
+        let closes = statement
+            .query_map(params![symbol, since], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(closes)
+    }
+
+    fn count_events(&self) -> Result<u64, KinError> {
+        Ok(self
+            .conn
+            .query_row("SELECT COUNT(*) FROM events", [], |row| row.get(0))?)
+    }
+}
+
+fn event_from_row(row: &Row<'_>) -> rusqlite::Result<RebalanceEvent> {
+    Ok(RebalanceEvent {
+        timestamp: row.get("timestamp")?,
+        asset: row.get("asset")?,
+        from_pct: row.get("from_pct")?,
+        to_pct: row.get("to_pct")?,
+        usdt_traded: row.get("usdt_traded")?,
+        order_id: row.get("order_id")?,
+        portfolio_value_usdt: row.get("portfolio_value_usdt")?,
+        fee_usdt: row.get("fee_usdt")?,
+    })
+}
+
+fn order_from_row(row: &Row<'_>) -> rusqlite::Result<Order> {
+    Ok(Order {
+        id: row.get("id")?,
+        symbol: row.get("symbol")?,
+        side: text_enum(row, "side")?,
+        qty: row.get("qty")?,
+        price: row.get("price")?,
+        status: text_enum(row, "status")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+// Side and status are stored as their serde names, e.g. "buy" and "filled"
+fn text_enum<T: DeserializeOwned>(row: &Row<'_>, column: &str) -> rusqlite::Result<T> {
+    let text: String = row.get(column)?;
+    serde_json::from_value(Value::String(text)).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(
+            row.as_ref().column_index(column).unwrap_or_default(),
+            Type::Text,
+            Box::new(e),
+        )
+    })
+}
+
+/// Brings an existing database up to `SCHEMA_VERSION` one step at a time.
+/// New columns are added here as `ALTER TABLE events ADD COLUMN ...` steps.
+fn migrate_schema(conn: &Connection) -> Result<(), KinError> {
+    let mut version: u32 = conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |row| row.get(0),
+    )?;
+    if version > SCHEMA_VERSION {
+        return Err(KinError::UnsupportedSchema(version));
+    }
+    if version == SCHEMA_VERSION {
+        return Ok(());
+    }
+    while version < SCHEMA_VERSION {
+        match version {
+            // A new file: `open` has just created the current tables, so skip the steps
+            0 => version = SCHEMA_VERSION - 1,
+            // Versions 2, 4 and 5 added tables, which `open` creates when missing
+            1 | 3 | 4 => {}
+            2 => conn
+                .execute(
+                    "ALTER TABLE events ADD COLUMN fee_usdt REAL NOT NULL DEFAULT 0",
+                    [],
+                )
+                .map(|_| ())?,
+            v => return Err(KinError::UnsupportedSchema(v)),
+        }
+        version += 1;
+    }
+    conn.execute("DELETE FROM schema_version", [])?;
+    conn.execute(
+        "INSERT INTO schema_version (version) VALUES (?1)",
+        [version],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::OrderSide;
+    use crate::history::OrderStatus;
+    use crate::tax::{self, CostBasisMethod};
+    use chrono::{TimeZone, Utc};
+    use std::fs;
+
+    fn temp_db(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("kin-db-{}-{}.db", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn event(minute: u32) -> RebalanceEvent {
+        RebalanceEvent {
+            timestamp: Utc.with_ymd_and_hms(2024, 5, 1, 12, minute, 0).unwrap(),
+            asset: "BTC_USDT".to_string(),
+            from_pct: 30.0,
+            to_pct: 25.0,
+            usdt_traded: 50.0,
+            order_id: format!("order-{}", minute),
+            portfolio_value_usdt: 1000.0,
+            fee_usdt: 0.0375,
+        }
+    }
+
+    #[test]
+    fn loads_the_latest_events_oldest_first() {
+        let path = temp_db("latest");
+        let db = Database::open(&path).unwrap();
+        for minute in 0..5 {
+            db.insert_event(&event(minute)).unwrap();
+        }
+        assert_eq!(db.load_events(2).unwrap(), vec![event(3), event(4)]);
+        assert_eq!(db.load_events(100).unwrap().len(), 5);
+        let _ = fs::remove_file(&path);
+    }
+
+    fn order(id: &str, minute: u32, status: OrderStatus) -> Order {
+        Order {
+            id: id.to_string(),
+            symbol: "ETH_USDT".to_string(),
+            side: OrderSide::Sell,
+            qty: 2.0,
+            price: 3000.0,
+            status,
+            created_at: Utc.with_ymd_and_hms(2024, 5, 1, 12, minute, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn order_updates_replace_the_stored_status() {
+        let path = temp_db("orders");
+        let db = Database::open(&path).unwrap();
+        db.upsert_order(&order("2", 5, OrderStatus::Open)).unwrap();
+        db.upsert_order(&order("1", 0, OrderStatus::Filled))
+            .unwrap();
+        db.upsert_order(&order("2", 5, OrderStatus::Cancelled))
+            .unwrap();
+        assert_eq!(
+            db.load_orders(10).unwrap(),
+            vec![
+                order("1", 0, OrderStatus::Filled),
+                order("2", 5, OrderStatus::Cancelled)
+            ]
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn tax_lots_are_replaced_and_gains_appended() {
+        let path = temp_db("tax");
+        let db = Database::open(&path).unwrap();
+        let at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let mut lots = Vec::new();
+        tax::buy(&mut lots, "BTC_USDT", 2.0, 50_000.0, at);
+        db.save_tax_lots(&lots).unwrap();
+        let gains = tax::sell(
+            &mut lots,
+            "BTC_USDT",
+            0.5,
+            60_000.0,
+            at,
+            CostBasisMethod::Fifo,
+        );
+        db.save_tax_lots(&lots).unwrap();
+        db.insert_realized_gain(&gains[0]).unwrap();
+        assert_eq!(db.load_tax_lots().unwrap(), lots);
+        assert_eq!(db.load_realized_gains().unwrap(), gains);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn daily_closes_are_upserted_by_date() {
+        let path = temp_db("closes");
+        let db = Database::open(&path).unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2024, 5, d).unwrap();
+        db.upsert_daily_closes("BTC_USDT", &[(day(1), 100.0), (day(2), 110.0)])
+            .unwrap();
+        db.upsert_daily_closes("BTC_USDT", &[(day(2), 105.0), (day(3), 120.0)])
+            .unwrap();
+        db.upsert_daily_closes("ETH_USDT", &[(day(2), 3000.0)])
+            .unwrap();
+        assert_eq!(
+            db.load_daily_closes("BTC_USDT", day(1)).unwrap(),
+            vec![100.0, 105.0, 120.0]
+        );
+        assert_eq!(
+            db.load_daily_closes("BTC_USDT", day(2)).unwrap(),
+            vec![105.0, 120.0]
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn reopening_keeps_events_and_schema_version() {
+        let path = temp_db("reopen");
+        Database::open(&path)
+            .unwrap()
+            .insert_event(&event(0))
+            .unwrap();
+        let db = Database::open(&path).unwrap();
+        assert_eq!(db.load_events(10).unwrap(), vec![event(0)]);
+        let version: u32 = db
+            .conn
+            .query_row("SELECT version FROM schema_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        let _ = fs::remove_file(&path);
+    }
+}
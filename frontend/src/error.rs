@@ -0,0 +1,225 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::CONFIG_VERSION;
+use crate::db::SCHEMA_VERSION;
+
+/// An editor field that can fail validation; allocations are identified by row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldId {
+    Allocation(usize),
+    AllocationTotal,
+    RebalanceThreshold,
+    MinUsdtInflow,
+    MinTradeUsdt,
+    // Fee rates, entered in percent
+    TakerFeeRate,
+    MakerFeeRate,
+    // Per-asset threshold, identified by allocation row
+    AssetThreshold(usize),
+    Notes,
+}
+
+#[derive(Debug)]
+pub enum KinError {
+    ConfigIo(io::Error),
+    ConfigParse(serde_json::Error),
+    TomlParse(toml::de::Error),
+    TomlSerialize(toml::ser::Error),
+    ConfigNotFound(PathBuf),
+    MalformedConfig(String),
+    UnsupportedVersion(u32),
+    // `field` is the asset symbol (or "Total") the user needs to fix
+    InvalidAllocation { field: String, reason: String },
+    InvalidSetting { field: String, reason: String },
+    ValidationErrors(Vec<(FieldId, String)>),
+    Profile(String),
+    Csv(csv::Error),
+    // `line` is 1-based, as shown in a spreadsheet
+    CsvImport { line: usize, reason: String },
+    BackendLaunch(io::Error),
+    Backend(String),
+    // Socket / named pipe to a running backend
+    Ipc(io::Error),
+    // Request to the backend's local HTTP API
+    BackendApi(reqwest::Error),
+    ApiAuth(String),
+    Keyring(keyring::Error),
+    // The config file is encrypted and no passphrase was given
+    PassphraseRequired(PathBuf),
+    WrongPassphrase,
+    Encryption(String),
+    Tray(String),
+    Database(rusqlite::Error),
+    // History database written by a newer build
+    UnsupportedSchema(u32),
+    FileWatch(notify::Error),
+    Backtest(String),
+    Webhook(reqwest::Error),
+    // Non-2xx response from the webhook endpoint
+    WebhookStatus(u16),
+    // CoinGecko market cap request
+    MarketData(reqwest::Error),
+    // GitHub Releases version check
+    UpdateCheck(reqwest::Error),
+    Clipboard(arboard::Error),
+    // Local /health and /metrics server
+    HealthServer(String),
+}
+
+impl KinError {
+    pub fn invalid_allocation(field: &str, reason: &str) -> Self {
+        KinError::InvalidAllocation {
+            field: field.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn invalid_setting(field: &str, reason: &str) -> Self {
+        KinError::InvalidSetting {
+            field: field.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for KinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KinError::ConfigIo(e) => write!(f, "Config file I/O error: {}", e),
+            KinError::ConfigParse(e) => write!(f, "Failed to parse config: {}", e),
+            KinError::TomlParse(e) => write!(f, "Failed to parse TOML config: {}", e),
+            KinError::TomlSerialize(e) => write!(f, "Failed to write TOML config: {}", e),
+            KinError::ConfigNotFound(path) => write!(f, "Config file not found at {:?}", path),
+            KinError::MalformedConfig(msg) => write!(f, "Malformed config: {}", msg),
+            KinError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported config version {} (this build reads up to {})",
+                version, CONFIG_VERSION
+            ),
+            KinError::InvalidAllocation { field, reason } => {
+                write!(f, "Invalid allocation for {}: {}", field, reason)
+            }
+            KinError::InvalidSetting { field, reason } => {
+                write!(f, "Invalid {}: {}", field, reason)
+            }
+            KinError::ValidationErrors(errors) => {
+                let messages: Vec<&str> = errors.iter().map(|(_, msg)| msg.as_str()).collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+            KinError::Profile(msg) => write!(f, "{}", msg),
+            KinError::Csv(e) => write!(f, "CSV error: {}", e),
+            KinError::CsvImport { line, reason } => write!(f, "CSV line {}: {}", line, reason),
+            KinError::BackendLaunch(e) => write!(f, "Failed to start backend: {}", e),
+            KinError::Backend(msg) => write!(f, "Backend: {}", msg),
+            KinError::Ipc(e) => write!(f, "Backend connection error: {}", e),
+            KinError::BackendApi(e) => write!(f, "Backend API request failed: {}", e),
+            KinError::ApiAuth(msg) => write!(f, "{}", msg),
+            KinError::Keyring(e) => write!(f, "System keychain error: {}", e),
+            KinError::PassphraseRequired(path) => {
+                write!(f, "{:?} is encrypted; a passphrase is required", path)
+            }
+            KinError::WrongPassphrase => {
+                write!(f, "Wrong passphrase, or the encrypted file is corrupted")
+            }
+            KinError::Encryption(msg) => write!(f, "Encryption error: {}", msg),
+            KinError::Tray(msg) => write!(f, "System tray: {}", msg),
+            KinError::Database(e) => write!(f, "History database error: {}", e),
+            KinError::UnsupportedSchema(version) => write!(
+                f,
+                "Unsupported history database version {} (this build reads up to {})",
+                version, SCHEMA_VERSION
+            ),
+            KinError::FileWatch(e) => write!(f, "Failed to watch config file: {}", e),
+            KinError::Backtest(msg) => write!(f, "Backtest: {}", msg),
+            KinError::Webhook(e) => write!(f, "Webhook request failed: {}", e),
+            KinError::WebhookStatus(status) => write!(f, "Webhook returned HTTP {}", status),
+            KinError::MarketData(e) => write!(f, "Market cap request failed: {}", e),
+            KinError::UpdateCheck(e) => write!(f, "Update check failed: {}", e),
+            KinError::Clipboard(e) => write!(f, "Clipboard error: {}", e),
+            KinError::HealthServer(msg) => write!(f, "Health check server: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for KinError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            KinError::ConfigIo(e) | KinError::BackendLaunch(e) | KinError::Ipc(e) => Some(e),
+            KinError::ConfigParse(e) => Some(e),
+            KinError::TomlParse(e) => Some(e),
+            KinError::TomlSerialize(e) => Some(e),
+            KinError::Keyring(e) => Some(e),
+            KinError::Csv(e) => Some(e),
+            KinError::Database(e) => Some(e),
+            KinError::FileWatch(e) => Some(e),
+            KinError::Clipboard(e) => Some(e),
+            KinError::Webhook(e)
+            | KinError::BackendApi(e)
+            | KinError::MarketData(e)
+            | KinError::UpdateCheck(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for KinError {
+    fn from(e: io::Error) -> Self {
+        KinError::ConfigIo(e)
+    }
+}
+
+impl From<serde_json::Error> for KinError {
+    fn from(e: serde_json::Error) -> Self {
+        KinError::ConfigParse(e)
+    }
+}
+
+impl From<toml::de::Error> for KinError {
+    fn from(e: toml::de::Error) -> Self {
+        KinError::TomlParse(e)
+    }
+}
+
+impl From<toml::ser::Error> for KinError {
+    fn from(e: toml::ser::Error) -> Self {
+        KinError::TomlSerialize(e)
+    }
+}
+
+impl From<csv::Error> for KinError {
+    fn from(e: csv::Error) -> Self {
+        KinError::Csv(e)
+    }
+}
+
+impl From<keyring::Error> for KinError {
+    fn from(e: keyring::Error) -> Self {
+        KinError::Keyring(e)
+    }
+}
+
+impl From<rusqlite::Error> for KinError {
+    fn from(e: rusqlite::Error) -> Self {
+        KinError::Database(e)
+    }
+}
+
+impl From<notify::Error> for KinError {
+    fn from(e: notify::Error) -> Self {
+        KinError::FileWatch(e)
+    }
+}
+
+impl From<arboard::Error> for KinError {
+    fn from(e: arboard::Error) -> Self {
+        KinError::Clipboard(e)
+    }
+}
+
+impl From<reqwest::Error> for KinError {
+    fn from(e: reqwest::Error) -> Self {
+        KinError::Webhook(e)
+    }
+}
@@ -0,0 +1,469 @@
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::{Method, RequestBuilder};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+use super::gateio::{number_field, LEVERAGE};
+use super::rate_limiter::{self, RateLimiter};
+use super::retry::{with_retry, RetryConfig};
+use super::{ApiError, Balances, Exchange, OrderResult, OrderSide, Position, PING_TIMEOUT};
+use crate::config::USDT;
+
+pub const TESTNET_BASE_URL: &str = "https://testnet.binancefuture.com";
+
+// Signed requests older than this when they reach Binance are rejected
+const RECV_WINDOW_MS: u64 = 5000;
+
+// Binance USDⓈ-M quotas: 2400 request weight per minute, shared by all
+// endpoints, and 300 orders per 10 s. At these counts the reads weigh at most
+// 2100 (5 for the account and positions, 10 for the unfiltered premium index)
+const RATE_LIMITS: [(&str, u32, Duration); 5] = [
+    ("/fapi/v2/account", 100, Duration::from_secs(60)),
+    ("/fapi/v2/positionRisk", 100, Duration::from_secs(60)),
+    ("/fapi/v1/premiumIndex", 100, Duration::from_secs(60)),
+    ("/fapi/v1/exchangeInfo", 100, Duration::from_secs(60)),
+    ("/fapi/v1/order", 300, Duration::from_secs(10)),
+];
+
+/// Minimal signed client for the Binance USDⓈ-M futures REST API. Contracts keep
+/// the Gate.io names used everywhere else ("BTC_USDT") and are mapped to Binance
+/// symbols ("BTCUSDT") at the edge; quantities are in the base coin.
+#[derive(Debug, Clone)]
+pub struct BinanceClient {
+    pub api_key: String,
+    // Wiped from memory when the client is dropped
+    pub api_secret: Zeroizing<String>,
+    pub base_url: String,
+    http: reqwest::Client,
+    // Shared by clones, which talk to the same account
+    rate_limiter: Arc<Mutex<RateLimiter>>,
+    retry: RetryConfig,
+    // LOT_SIZE step by contract, fetched once and shared by clones
+    step_sizes: Arc<Mutex<HashMap<String, f64>>>,
+}
+
+impl BinanceClient {
+    pub fn new(api_key: String, api_secret: String, base_url: String) -> Self {
+        Self {
+            api_key,
+            api_secret: Zeroizing::new(api_secret),
+            base_url,
+            http: reqwest::Client::new(),
+            rate_limiter: Arc::new(Mutex::new(RateLimiter::new(&RATE_LIMITS))),
+            retry: RetryConfig::default(),
+            step_sizes: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Retries for balance queries failing with transient errors. Orders are
+    /// never retried.
+    pub fn with_retry_config(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn testnet(api_key: String, api_secret: String) -> Self {
+        Self::new(api_key, api_secret, TESTNET_BASE_URL.to_string())
+    }
+
+    /// Margin held per contract plus the free margin under `USDT`, computed like
+    /// `GateioClient::get_futures_balances` so both exchanges report the same way.
+    pub async fn get_futures_balances(&self) -> Result<Balances, ApiError> {
+        with_retry(&self.retry, || self.fetch_futures_balances()).await
+    }
+
+    async fn fetch_futures_balances(&self) -> Result<Balances, ApiError> {
+        let account = self.signed(Method::GET, "/fapi/v2/account", "").await?;
+        let total = number_field(&account, "totalWalletBalance")?;
+        let mut balances = HashMap::new();
+        let mut used_margin = 0.0;
+        for position in self.position_risk().await? {
+            let size = number_field(&position, "positionAmt")?;
+            let mark_price = number_field(&position, "markPrice")?;
+            if size == 0.0 || mark_price <= 0.0 {
+                continue;
+            }
+            let Some(symbol) = position.get("symbol").and_then(Value::as_str) else {
+                continue;
+            };
+            let margin = size.abs() * mark_price / LEVERAGE;
+            used_margin += margin;
+            *balances.entry(from_binance_symbol(symbol)).or_insert(0.0) += margin;
+        }
+        balances.insert(USDT.to_string(), (total - used_margin).max(0.0));
+        Ok(balances)
+    }
+
+    async fn position_risk(&self) -> Result<Vec<Value>, ApiError> {
+        match self
+            .signed(Method::GET, "/fapi/v2/positionRisk", "")
+            .await?
+        {
+            Value::Array(positions) => Ok(positions),
+            _ => Err(ApiError::UnexpectedResponse(
+                "positionRisk is not a list".to_string(),
+            )),
+        }
+    }
+
+    /// Quantity step of `symbol` from its LOT_SIZE filter, e.g. 0.001 BTC for BTC_USDT.
+    pub async fn step_size(&self, symbol: &str) -> Result<f64, ApiError> {
+        let cached = self
+            .step_sizes
+            .lock()
+            .ok()
+            .and_then(|step_sizes| step_sizes.get(symbol).copied());
+        if let Some(step_size) = cached {
+            return Ok(step_size);
+        }
+        let step_sizes = step_sizes(&self.public("/fapi/v1/exchangeInfo", "").await?);
+        let step_size = step_sizes.get(symbol).copied();
+        if let Ok(mut cached) = self.step_sizes.lock() {
+            *cached = step_sizes;
+        }
+        step_size
+            .ok_or_else(|| ApiError::UnexpectedResponse(format!("unknown contract {}", symbol)))
+    }
+
+    async fn throttle(&self, endpoint: &str) {
+        rate_limiter::throttle(&self.rate_limiter, "Binance", endpoint).await;
+    }
+
+    // Market data needs no signature
+    async fn public(&self, path: &str, query: &str) -> Result<Value, ApiError> {
+        self.throttle(path).await;
+        let mut url = format!("{}{}", self.base_url, path);
+        if !query.is_empty() {
+            url = format!("{}?{}", url, query);
+        }
+        self.send(self.http.get(&url)).await
+    }
+
+    // `params` is an urlencoded query without `timestamp`, which is added here
+    async fn signed(&self, method: Method, path: &str, params: &str) -> Result<Value, ApiError> {
+        self.throttle(path).await;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let mut query = format!("recvWindow={}&timestamp={}", RECV_WINDOW_MS, timestamp);
+        if !params.is_empty() {
+            query = format!("{}&{}", params, query);
+        }
+        let signature = sign(&self.api_secret, &query);
+        let url = format!(
+            "{}{}?{}&signature={}",
+            self.base_url, path, query, signature
+        );
+        self.send(
+            self.http
+                .request(method, &url)
+                .header("X-MBX-APIKEY", &self.api_key),
+        )
+        .await
+    }
+
+    // Errors come back as {"code": -2015, "msg": "..."}
+    async fn send(&self, request: RequestBuilder) -> Result<Value, ApiError> {
+        let response = request.header("Accept", "application/json").send().await?;
+        let status = response.status();
+        let body: Value = response.json().await?;
+        if !status.is_success() {
+            return Err(ApiError::Status {
+                status: status.as_u16(),
+                label: body.get("code").map(Value::to_string).unwrap_or_default(),
+                message: body
+                    .get("msg")
+                    .and_then(Value::as_str)
+                    .unwrap_or("")
+                    .to_string(),
+            });
+        }
+        Ok(body)
+    }
+}
+
+#[async_trait]
+impl Exchange for BinanceClient {
+    async fn get_balances(&self) -> Result<Balances, ApiError> {
+        self.get_futures_balances().await
+    }
+
+    async fn get_prices(&self, symbols: &[&str]) -> Result<HashMap<String, f64>, ApiError> {
+        let mut prices = HashMap::new();
+        for &symbol in symbols {
+            let query = format!("symbol={}", to_binance_symbol(symbol));
+            let index = self.public("/fapi/v1/premiumIndex", &query).await?;
+            prices.insert(symbol.to_string(), number_field(&index, "markPrice")?);
+        }
+        Ok(prices)
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, ApiError> {
+        let mut open = Vec::new();
+        for position in self.position_risk().await? {
+            let size = number_field(&position, "positionAmt")?;
+            let Some(symbol) = position.get("symbol").and_then(Value::as_str) else {
+                continue;
+            };
+            if size == 0.0 {
+                continue;
+            }
+            open.push(Position {
+                symbol: from_binance_symbol(symbol),
+                side: if size > 0.0 {
+                    OrderSide::Buy
+                } else {
+                    OrderSide::Sell
+                },
+                size: size.abs(),
+                entry_price: number_field(&position, "entryPrice")?,
+                mark_price: number_field(&position, "markPrice")?,
+                leverage: number_field(&position, "leverage").unwrap_or(LEVERAGE),
+            });
+        }
+        Ok(open)
+    }
+
+    async fn ping(&self) -> Result<(), ApiError> {
+        let url = format!("{}/fapi/v1/ping", self.base_url);
+        self.http.get(&url).timeout(PING_TIMEOUT).send().await?;
+        Ok(())
+    }
+
+    // The premium index of every symbol carries the rate of the current period
+    async fn get_funding_rates(&self) -> Result<HashMap<String, f64>, ApiError> {
+        match self.public("/fapi/v1/premiumIndex", "").await? {
+            Value::Array(indices) => Ok(funding_rates(&indices)),
+            _ => Err(ApiError::UnexpectedResponse(
+                "premiumIndex is not a list".to_string(),
+            )),
+        }
+    }
+
+    // RESULT makes Binance answer once the market order has filled
+    async fn place_order(
+        &self,
+        symbol: &str,
+        side: OrderSide,
+        qty: f64,
+    ) -> Result<OrderResult, ApiError> {
+        let quantity = format_quantity(qty, self.step_size(symbol).await?, symbol)?;
+        let params = format!(
+            "symbol={}&side={}&type=MARKET&quantity={}&newOrderRespType=RESULT",
+            to_binance_symbol(symbol),
+            match side {
+                OrderSide::Buy => "BUY",
+                OrderSide::Sell => "SELL",
+            },
+            quantity
+        );
+        // Not retried: an order the exchange accepted before the connection
+        // dropped would be placed a second time
+        let order = self.signed(Method::POST, "/fapi/v1/order", &params).await?;
+        let order_id = match order.get("orderId") {
+            Some(Value::String(id)) => id.clone(),
+            Some(id) => id.to_string(),
+            None => {
+                return Err(ApiError::UnexpectedResponse(
+                    "order has no orderId".to_string(),
+                ))
+            }
+        };
+        Ok(OrderResult {
+            order_id,
+            symbol: symbol.to_string(),
+            side,
+            filled_qty: number_field(&order, "executedQty")?,
+            fill_price: number_field(&order, "avgPrice").unwrap_or(0.0),
+        })
+    }
+}
+
+/// Binance signature: hex HMAC-SHA256 of the query string (and form body, which
+/// this client never sends), keyed with the API secret.
+pub fn sign(secret: &str, query: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(query.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// "BTC_USDT" -> "BTCUSDT"
+fn to_binance_symbol(contract: &str) -> String {
+    contract.replace('_', "")
+}
+
+// "BTCUSDT" -> "BTC_USDT"; other quote currencies are left as they are
+fn from_binance_symbol(symbol: &str) -> String {
+    match symbol.strip_suffix(USDT) {
+        Some(base) if !base.is_empty() => format!("{}_{}", base, USDT),
+        _ => symbol.to_string(),
+    }
+}
+
+// Rounded down to the lot step, since a larger order could exceed the margin;
+// never zero, which the exchange would reject
+fn format_quantity(qty: f64, step_size: f64, symbol: &str) -> Result<String, ApiError> {
+    let steps = (qty.abs() / step_size + 1e-9).floor();
+    if steps == 0.0 {
+        return Err(ApiError::InvalidOrder(format!(
+            "{} {} is less than one lot step ({})",
+            qty.abs(),
+            symbol,
+            step_size
+        )));
+    }
+    let decimals = (-step_size.log10()).ceil().max(0.0) as usize;
+    Ok(format!("{:.*}", decimals, steps * step_size))
+}
+
+// LOT_SIZE steps of the USDT-margined symbols in /fapi/v1/exchangeInfo
+fn step_sizes(info: &Value) -> HashMap<String, f64> {
+    info.get("symbols")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|symbol| {
+            let name = symbol.get("symbol")?.as_str()?;
+            let lot_size = symbol.get("filters")?.as_array()?.iter().find(|filter| {
+                filter.get("filterType").and_then(Value::as_str) == Some("LOT_SIZE")
+            })?;
+            let step_size = number_field(lot_size, "stepSize").ok()?;
+            (name.ends_with(USDT) && step_size > 0.0)
+                .then(|| (from_binance_symbol(name), step_size))
+        })
+        .collect()
+}
+
+// USDT-margined symbols only; Binance also lists USDC ones
+fn funding_rates(indices: &[Value]) -> HashMap<String, f64> {
+    indices
+        .iter()
+        .filter_map(|index| {
+            let symbol = index.get("symbol")?.as_str()?;
+            if !symbol.ends_with(USDT) {
+                return None;
+            }
+            Some((
+                from_binance_symbol(symbol),
+                number_field(index, "lastFundingRate").ok()?,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn signs_like_the_binance_docs() {
+        let query = "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1\
+                     &price=0.1&recvWindow=5000&timestamp=1499827319559";
+        assert_eq!(
+            sign(
+                "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j",
+                query
+            ),
+            "c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71"
+        );
+    }
+
+    #[test]
+    fn maps_symbols_and_quantities() {
+        assert_eq!(to_binance_symbol("BTC_USDT"), "BTCUSDT");
+        assert_eq!(from_binance_symbol("BTCUSDT"), "BTC_USDT");
+        assert_eq!(from_binance_symbol("1000PEPEUSDT"), "1000PEPE_USDT");
+        assert_eq!(from_binance_symbol("ETHUSDC"), "ETHUSDC");
+        assert_eq!(
+            format_quantity(0.0123456, 0.001, "BTC_USDT").unwrap(),
+            "0.012"
+        );
+        assert_eq!(format_quantity(-1.5, 0.001, "BTC_USDT").unwrap(), "1.500");
+        assert_eq!(format_quantity(0.3, 0.001, "BTC_USDT").unwrap(), "0.300");
+        assert_eq!(format_quantity(1234.5, 1.0, "DOGE_USDT").unwrap(), "1234");
+        assert_eq!(format_quantity(0.75, 0.1, "SOL_USDT").unwrap(), "0.7");
+        assert!(matches!(
+            format_quantity(0.0004, 0.001, "BTC_USDT"),
+            Err(ApiError::InvalidOrder(_))
+        ));
+    }
+
+    #[test]
+    fn reads_step_sizes_from_the_lot_size_filter() {
+        let info = json!({"symbols": [
+            {"symbol": "BTCUSDT", "filters": [
+                {"filterType": "PRICE_FILTER", "tickSize": "0.10"},
+                {"filterType": "LOT_SIZE", "stepSize": "0.001", "minQty": "0.001"}
+            ]},
+            {"symbol": "DOGEUSDT", "filters": [{"filterType": "LOT_SIZE", "stepSize": "1"}]},
+            {"symbol": "BTCUSDC", "filters": [{"filterType": "LOT_SIZE", "stepSize": "0.001"}]}
+        ]});
+        let steps = step_sizes(&info);
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps["BTC_USDT"], 0.001);
+        assert_eq!(steps["DOGE_USDT"], 1.0);
+    }
+
+    #[test]
+    fn reads_funding_rates_from_the_premium_index() {
+        let indices = [
+            json!({"symbol": "BTCUSDT", "markPrice": "60000.0", "lastFundingRate": "0.00010000"}),
+            json!({"symbol": "ETHUSDT", "lastFundingRate": "-0.00025"}),
+            json!({"symbol": "BTCUSDC", "lastFundingRate": "0.0002"}),
+        ];
+        let rates = funding_rates(&indices);
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates["BTC_USDT"], 0.0001);
+        assert_eq!(rates["ETH_USDT"], -0.00025);
+    }
+
+    // Against the Binance futures testnet with the keys in BINANCE_TESTNET_API_KEY
+    // and BINANCE_TESTNET_API_SECRET: cargo test binance -- --ignored
+    fn testnet_client() -> BinanceClient {
+        let var =
+            |name: &str| std::env::var(name).unwrap_or_else(|_| panic!("{} is not set", name));
+        BinanceClient::testnet(
+            var("BINANCE_TESTNET_API_KEY"),
+            var("BINANCE_TESTNET_API_SECRET"),
+        )
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    #[ignore = "needs network access and Binance testnet keys"]
+    fn testnet_balances_and_prices() {
+        let client = testnet_client();
+        let balances = block_on(client.get_balances()).unwrap();
+        assert!(balances[USDT] >= 0.0);
+        let prices = block_on(client.get_prices(&["BTC_USDT", "ETH_USDT"])).unwrap();
+        assert!(prices["BTC_USDT"] > 0.0 && prices["ETH_USDT"] > 0.0);
+        let rates = block_on(client.get_funding_rates()).unwrap();
+        assert!(rates.contains_key("BTC_USDT"));
+    }
+
+    #[test]
+    #[ignore = "needs network access and Binance testnet keys"]
+    fn testnet_round_trip_order() {
+        let client = testnet_client();
+        let bought = block_on(client.place_order("BTC_USDT", OrderSide::Buy, 0.001)).unwrap();
+        assert_eq!(bought.filled_qty, 0.001);
+        assert!(bought.fill_price > 0.0);
+        let sold = block_on(client.place_order("BTC_USDT", OrderSide::Sell, 0.001)).unwrap();
+        assert_ne!(sold.order_id, bought.order_id);
+    }
+}
@@ -0,0 +1,456 @@
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::Method;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha512};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+use super::rate_limiter::{self, RateLimiter};
+use super::retry::{with_retry, RetryConfig};
+use super::{ApiError, Balances, Exchange, OrderResult, OrderSide, Position, PING_TIMEOUT};
+use crate::config::USDT;
+
+pub const TESTNET_BASE_URL: &str = "https://fx-api-testnet.gateio.ws/api/v4";
+
+// 与后端保持一致：全仓3倍杠杆
+pub const LEVERAGE: f64 = 3.0;
+
+// Gate.io v4 quotas: 200 requests per 10 s for each public or private read
+// endpoint, 100 orders per second
+const RATE_LIMITS: [(&str, u32, Duration); 6] = [
+    ("/futures/usdt/accounts", 200, Duration::from_secs(10)),
+    ("/futures/usdt/positions", 200, Duration::from_secs(10)),
+    ("/futures/usdt/tickers", 200, Duration::from_secs(10)),
+    ("/futures/usdt/contracts", 200, Duration::from_secs(10)),
+    ("/futures/usdt/time", 200, Duration::from_secs(10)),
+    ("/futures/usdt/orders", 100, Duration::from_secs(1)),
+];
+
+/// Minimal signed client for the Gate.io v4 REST API (USDT-settled futures).
+#[derive(Debug, Clone)]
+pub struct GateioClient {
+    pub api_key: String,
+    // Wiped from memory when the client is dropped
+    pub api_secret: Zeroizing<String>,
+    pub base_url: String,
+    http: reqwest::Client,
+    // Shared by clones, which talk to the same account
+    rate_limiter: Arc<Mutex<RateLimiter>>,
+    retry: RetryConfig,
+    // Base coin per contract by contract name, fetched once and shared by clones
+    multipliers: Arc<Mutex<HashMap<String, f64>>>,
+}
+
+impl GateioClient {
+    pub fn new(api_key: String, api_secret: String, base_url: String) -> Self {
+        Self {
+            api_key,
+            api_secret: Zeroizing::new(api_secret),
+            base_url,
+            http: reqwest::Client::new(),
+            rate_limiter: Arc::new(Mutex::new(RateLimiter::new(&RATE_LIMITS))),
+            retry: RetryConfig::default(),
+            multipliers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Retries for balance queries failing with transient errors. Orders are
+    /// never retried.
+    pub fn with_retry_config(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn testnet(api_key: String, api_secret: String) -> Self {
+        Self::new(api_key, api_secret, TESTNET_BASE_URL.to_string())
+    }
+
+    /// Margin held per contract plus the free margin under `USDT`, all in USDT.
+    /// Mirrors `PortfolioManager.get_current_portfolio` in the Python backend.
+    pub async fn get_futures_balances(&self) -> Result<Balances, ApiError> {
+        with_retry(&self.retry, || self.fetch_futures_balances()).await
+    }
+
+    async fn fetch_futures_balances(&self) -> Result<Balances, ApiError> {
+        let account = self
+            .request(Method::GET, "/futures/usdt/accounts", "", "")
+            .await?;
+        let total = number_field(&account, "total")?;
+        let positions = self
+            .request(Method::GET, "/futures/usdt/positions", "", "")
+            .await?;
+        let positions = positions
+            .as_array()
+            .ok_or_else(|| ApiError::UnexpectedResponse("positions is not a list".to_string()))?;
+
+        let mut balances = HashMap::new();
+        let mut used_margin = 0.0;
+        for position in positions {
+            let size = number_field(position, "size")?;
+            let mark_price = number_field(position, "mark_price")?;
+            if size == 0.0 || mark_price <= 0.0 {
+                continue;
+            }
+            let Some(contract) = position.get("contract").and_then(Value::as_str) else {
+                continue;
+            };
+            let multiplier = self.quanto_multiplier(contract).await?;
+            let margin = size.abs() * multiplier * mark_price / LEVERAGE;
+            used_margin += margin;
+            *balances.entry(contract.to_string()).or_insert(0.0) += margin;
+        }
+        balances.insert(USDT.to_string(), (total - used_margin).max(0.0));
+        Ok(balances)
+    }
+
+    /// Names of all USDT-settled futures contracts. The endpoint is public, so
+    /// the request is not signed and works without credentials.
+    pub async fn list_contracts(&self) -> Result<Vec<String>, ApiError> {
+        Ok(self
+            .contracts()
+            .await?
+            .iter()
+            .filter_map(|contract| contract.get("name").and_then(Value::as_str))
+            .map(str::to_string)
+            .collect())
+    }
+
+    async fn throttle(&self, endpoint: &str) {
+        rate_limiter::throttle(&self.rate_limiter, "Gate.io", endpoint).await;
+    }
+
+    async fn contracts(&self) -> Result<Vec<Value>, ApiError> {
+        self.throttle("/futures/usdt/contracts").await;
+        let url = format!("{}/futures/usdt/contracts", self.base_url);
+        let body: Value = self
+            .http
+            .get(&url)
+            .header("Accept", "application/json")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        match body {
+            Value::Array(contracts) => Ok(contracts),
+            _ => Err(ApiError::UnexpectedResponse(
+                "contracts is not a list".to_string(),
+            )),
+        }
+    }
+
+    /// Base coin per contract, e.g. 0.0001 BTC for BTC_USDT.
+    pub async fn quanto_multiplier(&self, symbol: &str) -> Result<f64, ApiError> {
+        let cached = self
+            .multipliers
+            .lock()
+            .ok()
+            .and_then(|multipliers| multipliers.get(symbol).copied());
+        if let Some(multiplier) = cached {
+            return Ok(multiplier);
+        }
+        let multipliers = quanto_multipliers(&self.contracts().await?);
+        let multiplier = multipliers.get(symbol).copied();
+        if let Ok(mut cached) = self.multipliers.lock() {
+            *cached = multipliers;
+        }
+        multiplier
+            .ok_or_else(|| ApiError::UnexpectedResponse(format!("unknown contract {}", symbol)))
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        query: &str,
+        body: &str,
+    ) -> Result<Value, ApiError> {
+        self.throttle(path).await;
+        let mut url = format!("{}{}", self.base_url, path);
+        if !query.is_empty() {
+            url = format!("{}?{}", url, query);
+        }
+        let sign_path = reqwest::Url::parse(&url)
+            .map_err(|e| ApiError::UnexpectedResponse(format!("bad URL {}: {}", url, e)))?
+            .path()
+            .to_string();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            .to_string();
+        let signature = sign(
+            &self.api_secret,
+            method.as_str(),
+            &sign_path,
+            query,
+            body,
+            &timestamp,
+        );
+
+        let response = self
+            .http
+            .request(method, &url)
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .body(body.to_string())
+            .header("KEY", &self.api_key)
+            .header("Timestamp", &timestamp)
+            .header("SIGN", signature)
+            .send()
+            .await?;
+        let status = response.status();
+        let body: Value = response.json().await?;
+        if !status.is_success() {
+            let text = |key: &str| {
+                body.get(key)
+                    .and_then(Value::as_str)
+                    .unwrap_or("")
+                    .to_string()
+            };
+            return Err(ApiError::Status {
+                status: status.as_u16(),
+                label: text("label"),
+                message: text("message"),
+            });
+        }
+        Ok(body)
+    }
+}
+
+#[async_trait]
+impl Exchange for GateioClient {
+    async fn get_balances(&self) -> Result<Balances, ApiError> {
+        self.get_futures_balances().await
+    }
+
+    async fn get_prices(&self, symbols: &[&str]) -> Result<HashMap<String, f64>, ApiError> {
+        let mut prices = HashMap::new();
+        for &symbol in symbols {
+            let query = format!("contract={}", symbol);
+            let tickers = self
+                .request(Method::GET, "/futures/usdt/tickers", &query, "")
+                .await?;
+            let ticker = tickers
+                .as_array()
+                .and_then(|list| list.first())
+                .ok_or_else(|| ApiError::UnexpectedResponse(format!("no ticker for {}", symbol)))?;
+            prices.insert(symbol.to_string(), number_field(ticker, "mark_price")?);
+        }
+        Ok(prices)
+    }
+
+    // Cross margin reports leverage 0; the backend trades those at `LEVERAGE`
+    async fn get_positions(&self) -> Result<Vec<Position>, ApiError> {
+        let positions = self
+            .request(Method::GET, "/futures/usdt/positions", "", "")
+            .await?;
+        let positions = positions
+            .as_array()
+            .ok_or_else(|| ApiError::UnexpectedResponse("positions is not a list".to_string()))?;
+        let mut open = Vec::new();
+        for position in positions {
+            let size = number_field(position, "size")?;
+            let Some(contract) = position.get("contract").and_then(Value::as_str) else {
+                continue;
+            };
+            if size == 0.0 {
+                continue;
+            }
+            let leverage = number_field(position, "leverage").unwrap_or(0.0);
+            let multiplier = self.quanto_multiplier(contract).await?;
+            open.push(Position {
+                symbol: contract.to_string(),
+                side: if size > 0.0 {
+                    OrderSide::Buy
+                } else {
+                    OrderSide::Sell
+                },
+                size: size.abs() * multiplier,
+                entry_price: number_field(position, "entry_price")?,
+                mark_price: number_field(position, "mark_price")?,
+                leverage: if leverage > 0.0 { leverage } else { LEVERAGE },
+            });
+        }
+        Ok(open)
+    }
+
+    async fn ping(&self) -> Result<(), ApiError> {
+        self.throttle("/futures/usdt/time").await;
+        let url = format!("{}/futures/usdt/time", self.base_url);
+        self.http.get(&url).timeout(PING_TIMEOUT).send().await?;
+        Ok(())
+    }
+
+    // Public like the contract list; rates come back as strings, e.g. "0.0001"
+    async fn get_funding_rates(&self) -> Result<HashMap<String, f64>, ApiError> {
+        Ok(funding_rates(&self.contracts().await?))
+    }
+
+    // Gate.io futures sizes are whole contracts, negative for sells; price 0 with IOC is a market order
+    async fn place_order(
+        &self,
+        symbol: &str,
+        side: OrderSide,
+        qty: f64,
+    ) -> Result<OrderResult, ApiError> {
+        let multiplier = self.quanto_multiplier(symbol).await?;
+        let contracts = contract_count(qty, multiplier, symbol)?;
+        let size = match side {
+            OrderSide::Buy => contracts,
+            OrderSide::Sell => -contracts,
+        };
+        let body =
+            json!({ "contract": symbol, "size": size, "price": "0", "tif": "ioc" }).to_string();
+        // Not retried: an order the exchange accepted before the connection
+        // dropped would be placed a second time
+        let order = self
+            .request(Method::POST, "/futures/usdt/orders", "", &body)
+            .await?;
+        let order_id = match order.get("id") {
+            Some(Value::String(id)) => id.clone(),
+            Some(id) => id.to_string(),
+            None => return Err(ApiError::UnexpectedResponse("order has no id".to_string())),
+        };
+        Ok(OrderResult {
+            order_id,
+            symbol: symbol.to_string(),
+            side,
+            filled_qty: number_field(&order, "size")?.abs() * multiplier,
+            fill_price: number_field(&order, "fill_price").unwrap_or(0.0),
+        })
+    }
+}
+
+/// Gate.io v4 signature: hex HMAC-SHA512 over
+/// `method\npath\nquery\nhex(sha512(body))\ntimestamp`.
+pub fn sign(
+    secret: &str,
+    method: &str,
+    path: &str,
+    query: &str,
+    body: &str,
+    timestamp: &str,
+) -> String {
+    let body_hash = hex::encode(Sha512::digest(body.as_bytes()));
+    let payload = format!(
+        "{}\n{}\n{}\n{}\n{}",
+        method, path, query, body_hash, timestamp
+    );
+    let mut mac =
+        Hmac::<Sha512>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(payload.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// Whole contracts closest to `qty` of the base coin; never zero, which the
+// exchange would reject
+fn contract_count(qty: f64, multiplier: f64, symbol: &str) -> Result<i64, ApiError> {
+    let contracts = (qty.abs() / multiplier).round() as i64;
+    if contracts == 0 {
+        return Err(ApiError::InvalidOrder(format!(
+            "{} {} is less than half a contract ({})",
+            qty.abs(),
+            symbol,
+            multiplier
+        )));
+    }
+    Ok(contracts)
+}
+
+// Contracts without a name or multiplier are left out
+fn quanto_multipliers(contracts: &[Value]) -> HashMap<String, f64> {
+    contracts
+        .iter()
+        .filter_map(|contract| {
+            let name = contract.get("name")?.as_str()?;
+            let multiplier = number_field(contract, "quanto_multiplier").ok()?;
+            (multiplier > 0.0).then(|| (name.to_string(), multiplier))
+        })
+        .collect()
+}
+
+// Contracts without a name or rate are left out
+fn funding_rates(contracts: &[Value]) -> HashMap<String, f64> {
+    contracts
+        .iter()
+        .filter_map(|contract| {
+            let name = contract.get("name")?.as_str()?;
+            Some((
+                name.to_string(),
+                number_field(contract, "funding_rate").ok()?,
+            ))
+        })
+        .collect()
+}
+
+// Gate.io and Binance send most numbers as strings
+pub(super) fn number_field(value: &Value, key: &str) -> Result<f64, ApiError> {
+    match value.get(key) {
+        Some(Value::Number(n)) => n.as_f64(),
+        Some(Value::String(s)) => s.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| ApiError::UnexpectedResponse(format!("missing or invalid \"{}\"", key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_funding_rates_from_contracts() {
+        let contracts = [
+            json!({"name": "BTC_USDT", "funding_rate": "0.0001"}),
+            json!({"name": "ETH_USDT", "funding_rate": "-0.00025"}),
+            json!({"name": "LTC_USDT"}),
+        ];
+        let rates = funding_rates(&contracts);
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates["BTC_USDT"], 0.0001);
+        assert_eq!(rates["ETH_USDT"], -0.00025);
+    }
+
+    #[test]
+    fn converts_base_coin_to_contracts() {
+        let contracts = [
+            json!({"name": "BTC_USDT", "quanto_multiplier": "0.0001"}),
+            json!({"name": "ETH_USDT", "quanto_multiplier": "0.01"}),
+            json!({"name": "LTC_USDT"}),
+        ];
+        let multipliers = quanto_multipliers(&contracts);
+        assert_eq!(multipliers.len(), 2);
+        assert_eq!(
+            contract_count(0.0025, multipliers["BTC_USDT"], "BTC_USDT").unwrap(),
+            25
+        );
+        assert_eq!(
+            contract_count(-0.126, multipliers["ETH_USDT"], "ETH_USDT").unwrap(),
+            13
+        );
+        assert!(matches!(
+            contract_count(0.00004, multipliers["BTC_USDT"], "BTC_USDT"),
+            Err(ApiError::InvalidOrder(_))
+        ));
+    }
+
+    #[test]
+    fn signs_like_the_gateio_docs() {
+        let signature = sign(
+            "secret",
+            "GET",
+            "/api/v4/futures/usdt/accounts",
+            "",
+            "",
+            "1700000000",
+        );
+        assert_eq!(
+            signature,
+            "a4eba9837bed721f11eee035c0b432af2db5c5f8b1756ef041af6b22f08b5a1e\
+             9ecba008f43f8957bec13ff530ccdb2a68ad6baf1ec824c70dce16613f79914e"
+        );
+    }
+}
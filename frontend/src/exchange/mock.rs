@@ -0,0 +1,358 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use super::{ApiError, Balances, Exchange, OrderResult, OrderSide, Position};
+use crate::config::{DEFAULT_LEVERAGE, USDT};
+
+/// Virtual account a paper trading session starts with.
+pub const PAPER_INITIAL_USDT: f64 = 10_000.0;
+
+/// Canned account states for running the UI and tests without the testnet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestScenario {
+    /// Holdings match the default allocation exactly
+    AllAssetsOnTarget,
+    /// BTC holds 40% against a 25% target, paid for from USDT
+    BtcOverweight,
+    /// Every request fails as if the exchange were unreachable
+    NetworkError,
+    /// Every request is rejected for a bad API key
+    AuthError,
+}
+
+impl TestScenario {
+    pub const ALL: [TestScenario; 4] = [
+        TestScenario::AllAssetsOnTarget,
+        TestScenario::BtcOverweight,
+        TestScenario::NetworkError,
+        TestScenario::AuthError,
+    ];
+
+    /// Name on the command line, e.g. "btc-overweight".
+    pub fn name(self) -> &'static str {
+        match self {
+            TestScenario::AllAssetsOnTarget => "all-assets-on-target",
+            TestScenario::BtcOverweight => "btc-overweight",
+            TestScenario::NetworkError => "network-error",
+            TestScenario::AuthError => "auth-error",
+        }
+    }
+}
+
+impl fmt::Display for TestScenario {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TestScenario {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TestScenario::ALL
+            .into_iter()
+            .find(|scenario| scenario.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = TestScenario::ALL.iter().map(|s| s.name()).collect();
+                format!(
+                    "unknown scenario {:?}, expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// In-memory exchange with fixed or fed prices; every order it receives is
+/// recorded and moves margin between `USDT` and the contract in its ledger.
+/// Positions are not marked to market, so balances only change through orders.
+#[derive(Default)]
+pub struct MockExchange {
+    balances: Mutex<Balances>,
+    prices: HashMap<String, f64>,
+    // Quotes prices instead of `prices` when set
+    price_feed: Option<Arc<dyn Exchange>>,
+    // Fail every call with this status and label instead of answering
+    failure: Option<(u16, String)>,
+    orders: Mutex<Vec<OrderResult>>,
+}
+
+impl MockExchange {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A small account holding the default assets, used by the `mock-exchange` feature.
+    pub fn sample() -> Self {
+        Self::new()
+            .with_balance("BTC_USDT", 250.0)
+            .with_balance("ETH_USDT", 150.0)
+            .with_balance("LTC_USDT", 50.0)
+            .with_balance(USDT, 550.0)
+            .with_price("BTC_USDT", 60000.0)
+            .with_price("ETH_USDT", 3000.0)
+            .with_price("LTC_USDT", 80.0)
+    }
+
+    /// A 1200 USDT account in the state `scenario` describes, priced like `sample`.
+    /// Balances are margin, so at 3x a 25% target holds 100 USDT of it.
+    pub fn with_scenario(scenario: TestScenario) -> Self {
+        let priced = Self::new()
+            .with_price("BTC_USDT", 60000.0)
+            .with_price("ETH_USDT", 3000.0)
+            .with_price("LTC_USDT", 80.0);
+        let holdings = |btc, eth, ltc, usdt| {
+            Self::new()
+                .with_balance("BTC_USDT", btc)
+                .with_balance("ETH_USDT", eth)
+                .with_balance("LTC_USDT", ltc)
+                .with_balance(USDT, usdt)
+        };
+        let account = match scenario {
+            TestScenario::AllAssetsOnTarget => holdings(100.0, 60.0, 40.0, 1000.0),
+            TestScenario::BtcOverweight => holdings(160.0, 60.0, 40.0, 940.0),
+            TestScenario::NetworkError => {
+                holdings(100.0, 60.0, 40.0, 1000.0).failing_with(503, "SERVICE_UNAVAILABLE")
+            }
+            TestScenario::AuthError => {
+                holdings(100.0, 60.0, 40.0, 1000.0).failing_with(401, "INVALID_KEY")
+            }
+        };
+        Self {
+            prices: priced.prices,
+            ..account
+        }
+    }
+
+    /// A fresh paper trading account priced by `price_feed`.
+    pub fn paper(price_feed: Arc<dyn Exchange>) -> Self {
+        Self {
+            price_feed: Some(price_feed),
+            ..Self::new().with_balance(USDT, PAPER_INITIAL_USDT)
+        }
+    }
+
+    pub fn with_balance(mut self, symbol: &str, usdt: f64) -> Self {
+        if let Ok(balances) = self.balances.get_mut() {
+            balances.insert(symbol.to_string(), usdt);
+        }
+        self
+    }
+
+    pub fn with_price(mut self, symbol: &str, price: f64) -> Self {
+        self.prices.insert(symbol.to_string(), price);
+        self
+    }
+
+    pub fn failing(self, label: &str) -> Self {
+        self.failing_with(400, label)
+    }
+
+    fn failing_with(mut self, status: u16, label: &str) -> Self {
+        self.failure = Some((status, label.to_string()));
+        self
+    }
+
+    /// Orders placed so far, oldest first.
+    pub fn orders(&self) -> Vec<OrderResult> {
+        self.orders.lock().map(|o| o.clone()).unwrap_or_default()
+    }
+
+    fn check_failure(&self) -> Result<(), ApiError> {
+        match &self.failure {
+            Some((status, label)) => Err(ApiError::Status {
+                status: *status,
+                label: label.clone(),
+                message: "mock failure".to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl Exchange for MockExchange {
+    async fn get_balances(&self) -> Result<Balances, ApiError> {
+        self.check_failure()?;
+        self.balances
+            .lock()
+            .map(|b| b.clone())
+            .map_err(|_| ApiError::UnexpectedResponse("ledger poisoned".to_string()))
+    }
+
+    async fn get_prices(&self, symbols: &[&str]) -> Result<HashMap<String, f64>, ApiError> {
+        self.check_failure()?;
+        if let Some(feed) = &self.price_feed {
+            return feed.get_prices(symbols).await;
+        }
+        symbols
+            .iter()
+            .map(|&symbol| {
+                self.prices
+                    .get(symbol)
+                    .map(|&price| (symbol.to_string(), price))
+                    .ok_or_else(|| {
+                        ApiError::UnexpectedResponse(format!("no ticker for {}", symbol))
+                    })
+            })
+            .collect()
+    }
+
+    // Positions are not marked to market, so each is entered at the current price
+    async fn get_positions(&self) -> Result<Vec<Position>, ApiError> {
+        let balances = self.get_balances().await?;
+        let mut positions = Vec::new();
+        for (symbol, margin) in balances {
+            if symbol == USDT || margin <= 0.0 {
+                continue;
+            }
+            let price = self.get_prices(&[&symbol]).await?[&symbol];
+            positions.push(Position {
+                size: margin * DEFAULT_LEVERAGE / price,
+                symbol,
+                side: OrderSide::Buy,
+                entry_price: price,
+                mark_price: price,
+                leverage: DEFAULT_LEVERAGE,
+            });
+        }
+        positions.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        Ok(positions)
+    }
+
+    async fn ping(&self) -> Result<(), ApiError> {
+        self.check_failure()?;
+        match &self.price_feed {
+            Some(feed) => feed.ping().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn get_funding_rates(&self) -> Result<HashMap<String, f64>, ApiError> {
+        self.check_failure()?;
+        match &self.price_feed {
+            Some(feed) => feed.get_funding_rates().await,
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    async fn place_order(
+        &self,
+        symbol: &str,
+        side: OrderSide,
+        qty: f64,
+    ) -> Result<OrderResult, ApiError> {
+        self.check_failure()?;
+        let fill_price = self.get_prices(&[symbol]).await?[symbol];
+        let mut orders = self
+            .orders
+            .lock()
+            .map_err(|_| ApiError::UnexpectedResponse("order book poisoned".to_string()))?;
+        let result = OrderResult {
+            order_id: format!("mock-{}", orders.len() + 1),
+            symbol: symbol.to_string(),
+            side,
+            filled_qty: qty.abs(),
+            fill_price,
+        };
+
+        let margin = qty.abs() * fill_price / DEFAULT_LEVERAGE;
+        let signed = match side {
+            OrderSide::Buy => margin,
+            OrderSide::Sell => -margin,
+        };
+        let mut balances = self
+            .balances
+            .lock()
+            .map_err(|_| ApiError::UnexpectedResponse("ledger poisoned".to_string()))?;
+        *balances.entry(symbol.to_string()).or_insert(0.0) += signed;
+        *balances.entry(USDT.to_string()).or_insert(0.0) -= signed;
+
+        orders.push(result.clone());
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn records_orders_at_the_mock_price() {
+        let exchange = MockExchange::new().with_price("BTC_USDT", 50000.0);
+        let order = block_on(exchange.place_order("BTC_USDT", OrderSide::Sell, 2.0)).unwrap();
+        assert_eq!(order.order_id, "mock-1");
+        assert_eq!(order.fill_price, 50000.0);
+        assert_eq!(exchange.orders(), vec![order]);
+        assert!(block_on(exchange.place_order("XRP_USDT", OrderSide::Buy, 1.0)).is_err());
+    }
+
+    #[test]
+    fn orders_move_margin_in_the_ledger() {
+        let feed = Arc::new(MockExchange::new().with_price("BTC_USDT", 60000.0));
+        let exchange = MockExchange::paper(feed);
+        block_on(exchange.place_order("BTC_USDT", OrderSide::Buy, 0.1)).unwrap();
+        block_on(exchange.place_order("BTC_USDT", OrderSide::Sell, 0.05)).unwrap();
+
+        let balances = block_on(exchange.get_balances()).unwrap();
+        // 0.05 BTC at 60000 and 3x leverage holds 1000 USDT of margin
+        assert!((balances["BTC_USDT"] - 1000.0).abs() < 1e-9);
+        assert!((balances[USDT] - (PAPER_INITIAL_USDT - 1000.0)).abs() < 1e-9);
+        assert_eq!(exchange.orders()[1].fill_price, 60000.0);
+    }
+
+    #[test]
+    fn failing_exchange_reports_the_label() {
+        let exchange: Box<dyn Exchange> = Box::new(MockExchange::sample().failing("INVALID_KEY"));
+        match block_on(exchange.get_balances()) {
+            Err(ApiError::Status { label, .. }) => assert_eq!(label, "INVALID_KEY"),
+            other => panic!("expected a status error, got {:?}", other),
+        }
+        // A failing exchange is unreachable, a working mock always answers
+        assert!(block_on(exchange.ping()).is_err());
+        assert!(block_on(MockExchange::sample().ping()).is_ok());
+    }
+
+    #[test]
+    fn scenarios_plan_as_described() {
+        let config = crate::config::Config::default();
+        let plan = |scenario: TestScenario| {
+            let exchange = MockExchange::with_scenario(scenario);
+            let balances = block_on(exchange.get_balances()).unwrap();
+            let symbols: Vec<&str> = balances
+                .keys()
+                .map(String::as_str)
+                .filter(|s| *s != USDT)
+                .collect();
+            let prices = block_on(exchange.get_prices(&symbols)).unwrap();
+            crate::planner::compute_plan(&balances, &config, &prices, &Default::default())
+        };
+        assert!(plan(TestScenario::AllAssetsOnTarget).is_empty());
+        let overweight = plan(TestScenario::BtcOverweight);
+        assert_eq!(overweight.trades.len(), 1);
+        assert_eq!(overweight.trades[0].symbol, "BTC_USDT");
+        assert_eq!(overweight.trades[0].side, OrderSide::Sell);
+
+        for (scenario, expected) in [
+            (TestScenario::NetworkError, 503),
+            (TestScenario::AuthError, 401),
+        ] {
+            let exchange = MockExchange::with_scenario(scenario);
+            assert!(
+                matches!(block_on(exchange.get_balances()), Err(ApiError::Status { status, .. }) if status == expected)
+            );
+        }
+        assert_eq!("btc-overweight".parse(), Ok(TestScenario::BtcOverweight));
+        assert!("btc".parse::<TestScenario>().is_err());
+    }
+}
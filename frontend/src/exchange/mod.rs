@@ -0,0 +1,218 @@
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub mod binance;
+pub mod gateio;
+pub mod mock;
+pub mod rate_limiter;
+pub mod retry;
+
+/// A ping that takes longer than this counts as the exchange being unreachable.
+pub const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Margin held per symbol in USDT; free margin is listed under `USDT`.
+pub type Balances = HashMap<String, f64>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+impl fmt::Display for OrderSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderSide::Buy => write!(f, "buy"),
+            OrderSide::Sell => write!(f, "sell"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderResult {
+    pub order_id: String,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub filled_qty: f64,
+    pub fill_price: f64,
+}
+
+/// An open futures position; `side` is `Buy` for longs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub symbol: String,
+    pub side: OrderSide,
+    pub size: f64,
+    pub entry_price: f64,
+    pub mark_price: f64,
+    pub leverage: f64,
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    Http(reqwest::Error),
+    // Non-2xx response; `label` is the exchange's error code, e.g. INVALID_KEY
+    Status {
+        status: u16,
+        label: String,
+        message: String,
+    },
+    UnexpectedResponse(String),
+    // Rejected before sending, e.g. a quantity below one contract
+    InvalidOrder(String),
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Http(e) => write!(f, "Exchange request failed: {}", e),
+            ApiError::Status {
+                status,
+                label,
+                message,
+            } => write!(f, "Exchange returned {} {}: {}", status, label, message),
+            ApiError::UnexpectedResponse(msg) => write!(f, "Unexpected exchange response: {}", msg),
+            ApiError::InvalidOrder(msg) => write!(f, "Order not placed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        ApiError::Http(e)
+    }
+}
+
+/// The account operations the rebalancer needs from an exchange.
+#[async_trait]
+pub trait Exchange: Send + Sync {
+    async fn get_balances(&self) -> Result<Balances, ApiError>;
+
+    /// Mark prices in USDT for the given contract symbols.
+    async fn get_prices(&self, symbols: &[&str]) -> Result<HashMap<String, f64>, ApiError>;
+
+    async fn get_positions(&self) -> Result<Vec<Position>, ApiError>;
+
+    /// Round trip to a lightweight public endpoint; any HTTP answer means the
+    /// exchange is reachable. Exchanges that are not remote answer right away.
+    async fn ping(&self) -> Result<(), ApiError> {
+        Ok(())
+    }
+
+    /// Current 8-hour funding rate per contract, e.g. 0.0001 for 0.01%. Longs
+    /// pay shorts when it is positive. Exchanges without funding report none.
+    async fn get_funding_rates(&self) -> Result<HashMap<String, f64>, ApiError> {
+        Ok(HashMap::new())
+    }
+
+    /// Market order for `qty` of the base coin, e.g. 0.0025 for BTC_USDT.
+    /// Exchanges trading whole contracts convert it, and `OrderResult::filled_qty`
+    /// is in the base coin as well.
+    async fn place_order(
+        &self,
+        symbol: &str,
+        side: OrderSide,
+        qty: f64,
+    ) -> Result<OrderResult, ApiError>;
+}
+
+/// Which exchange's futures testnet the app and the backend trade on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExchangeChoice {
+    #[default]
+    Gateio,
+    Binance,
+}
+
+impl ExchangeChoice {
+    pub const ALL: [ExchangeChoice; 2] = [ExchangeChoice::Gateio, ExchangeChoice::Binance];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExchangeChoice::Gateio => "Gate.io",
+            ExchangeChoice::Binance => "Binance",
+        }
+    }
+
+    /// Name in the config file, also passed to the backend as `KIN_EXCHANGE`.
+    pub fn id(self) -> &'static str {
+        match self {
+            ExchangeChoice::Gateio => "gateio",
+            ExchangeChoice::Binance => "binance",
+        }
+    }
+
+    /// Highest leverage the exchange offers on its USDT perpetuals.
+    pub fn max_leverage(self) -> f64 {
+        match self {
+            ExchangeChoice::Gateio => 100.0,
+            ExchangeChoice::Binance => 125.0,
+        }
+    }
+
+    /// Trading page of the testnet, to inspect the positions the backend opened.
+    pub fn testnet_url(self) -> &'static str {
+        match self {
+            ExchangeChoice::Gateio => "https://www.gate.io/en/testnet/futures_trade/USDT/BTC_USDT",
+            ExchangeChoice::Binance => binance::TESTNET_BASE_URL,
+        }
+    }
+}
+
+/// The exchange the app trades on; build with `--features mock-exchange` to
+/// run the UI against canned data instead of the testnet of `choice`.
+///
+/// With `paper_trading` orders only move a virtual ledger, priced by the testnet
+/// tickers (public, so no credentials are needed). `retry` applies to balance
+/// queries on either exchange; orders are never retried.
+#[cfg(not(feature = "mock-exchange"))]
+pub fn connect(
+    choice: ExchangeChoice,
+    api_key: &str,
+    api_secret: &str,
+    paper_trading: bool,
+    retry: &retry::RetryConfig,
+) -> Arc<dyn Exchange> {
+    let client: Arc<dyn Exchange> = match choice {
+        ExchangeChoice::Gateio => Arc::new(
+            gateio::GateioClient::testnet(api_key.to_string(), api_secret.to_string())
+                .with_retry_config(retry.clone()),
+        ),
+        ExchangeChoice::Binance => Arc::new(
+            binance::BinanceClient::testnet(api_key.to_string(), api_secret.to_string())
+                .with_retry_config(retry.clone()),
+        ),
+    };
+    if paper_trading {
+        Arc::new(mock::MockExchange::paper(client))
+    } else {
+        client
+    }
+}
+
+#[cfg(feature = "mock-exchange")]
+pub fn connect(
+    _choice: ExchangeChoice,
+    _api_key: &str,
+    _api_secret: &str,
+    _paper_trading: bool,
+    _retry: &retry::RetryConfig,
+) -> Arc<dyn Exchange> {
+    Arc::new(mock::MockExchange::sample())
+}
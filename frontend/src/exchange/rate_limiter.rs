@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Fixed-window request quotas per endpoint path. Endpoints without a limit are
+/// never held back.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    // endpoint -> (requests allowed, per window)
+    limits: HashMap<String, (u32, Duration)>,
+    // endpoint -> (requests made, start of the current window)
+    buckets: HashMap<String, (u32, Instant)>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: &[(&str, u32, Duration)]) -> Self {
+        Self {
+            limits: limits
+                .iter()
+                .map(|&(endpoint, requests, window)| (endpoint.to_string(), (requests, window)))
+                .collect(),
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Counts a request to `endpoint`. None when it may go ahead, otherwise how
+    /// long until the window resets; the request is not counted then.
+    pub fn acquire(&mut self, endpoint: &str) -> Option<Duration> {
+        self.acquire_at(endpoint, Instant::now())
+    }
+
+    fn acquire_at(&mut self, endpoint: &str, now: Instant) -> Option<Duration> {
+        let &(limit, window) = self.limits.get(endpoint)?;
+        let (count, start) = self.buckets.entry(endpoint.to_string()).or_insert((0, now));
+        let elapsed = now.saturating_duration_since(*start);
+        if elapsed >= window {
+            *count = 0;
+            *start = now;
+        } else if *count >= limit {
+            return Some(window - elapsed);
+        }
+        *count += 1;
+        None
+    }
+}
+
+/// Waits out an exhausted quota of `endpoint` instead of letting `exchange`
+/// reject the request.
+pub async fn throttle(limiter: &Mutex<RateLimiter>, exchange: &str, endpoint: &str) {
+    loop {
+        let wait = match limiter.lock() {
+            Ok(mut limiter) => limiter.acquire(endpoint),
+            Err(_) => None,
+        };
+        let Some(wait) = wait else {
+            return;
+        };
+        log::warn!(
+            "{} rate limit reached for {}; waiting {} ms",
+            exchange,
+            endpoint,
+            wait.as_millis()
+        );
+        tokio::time::sleep(wait).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_until_the_window_resets() {
+        let mut limiter = RateLimiter::new(&[("/orders", 2, Duration::from_secs(1))]);
+        let start = Instant::now();
+        assert_eq!(limiter.acquire_at("/orders", start), None);
+        assert_eq!(limiter.acquire_at("/orders", start), None);
+        assert_eq!(
+            limiter.acquire_at("/orders", start + Duration::from_millis(300)),
+            Some(Duration::from_millis(700))
+        );
+        assert_eq!(
+            limiter.acquire_at("/orders", start + Duration::from_secs(1)),
+            None
+        );
+        // Unlimited endpoint
+        for _ in 0..10 {
+            assert_eq!(limiter.acquire_at("/tickers", start), None);
+        }
+    }
+}
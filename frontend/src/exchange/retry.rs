@@ -0,0 +1,151 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+use super::ApiError;
+
+/// How often and how patiently a failed exchange request is tried again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Tries in total, the first one included; 1 disables retries
+    #[schemars(range(min = 1))]
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every further one
+    pub base_delay_ms: u64,
+    /// Upper bound of the doubled delay
+    pub max_delay_ms: u64,
+    /// HTTP statuses worth retrying; network errors always are
+    pub retryable_status_codes: Vec<u16>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 200,
+            max_delay_ms: 5_000,
+            retryable_status_codes: vec![429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryConfig {
+    // Delay after failed attempt number `attempt` (1-based)
+    fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u64
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u64::MAX);
+        Duration::from_millis(
+            self.base_delay_ms
+                .saturating_mul(factor)
+                .min(self.max_delay_ms),
+        )
+    }
+
+    fn is_retryable(&self, error: &ApiError) -> bool {
+        let status = match error {
+            ApiError::Status { status, .. } => Some(*status),
+            ApiError::Http(e) => match e.status() {
+                Some(status) => Some(status.as_u16()),
+                // Timeouts and dropped connections
+                None => return e.is_timeout() || e.is_connect(),
+            },
+            ApiError::UnexpectedResponse(_) | ApiError::InvalidOrder(_) => None,
+        };
+        status.is_some_and(|status| self.retryable_status_codes.contains(&status))
+    }
+}
+
+/// Runs `f` until it succeeds, fails with an error that is not transient, or
+/// `config.max_attempts` is used up, sleeping with exponential backoff in between.
+pub async fn with_retry<F, Fut, T>(config: &RetryConfig, mut f: F) -> Result<T, ApiError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ApiError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Err(e) if attempt < config.max_attempts && config.is_retryable(&e) => {
+                let delay = config.delay(attempt);
+                log::warn!(
+                    "{}; retrying in {} ms (attempt {} of {})",
+                    e,
+                    delay.as_millis(),
+                    attempt + 1,
+                    config.max_attempts
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn status(status: u16) -> ApiError {
+        ApiError::Status {
+            status,
+            label: String::new(),
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_maximum() {
+        let config = RetryConfig::default();
+        let delays: Vec<u64> = (1..=7)
+            .map(|attempt| config.delay(attempt).as_millis() as u64)
+            .collect();
+        assert_eq!(delays, [200, 400, 800, 1600, 3200, 5000, 5000]);
+        assert_eq!(config.delay(100), Duration::from_millis(5000));
+    }
+
+    #[test]
+    fn retries_only_transient_errors() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let config = RetryConfig {
+            base_delay_ms: 1,
+            ..RetryConfig::default()
+        };
+
+        let calls = Cell::new(0);
+        let result = runtime.block_on(with_retry(&config, || {
+            calls.set(calls.get() + 1);
+            let outcome = if calls.get() < 3 {
+                Err(status(503))
+            } else {
+                Ok(42)
+            };
+            async move { outcome }
+        }));
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(calls.get(), 3);
+
+        calls.set(0);
+        let result: Result<(), ApiError> = runtime.block_on(with_retry(&config, || {
+            calls.set(calls.get() + 1);
+            async { Err(status(502)) }
+        }));
+        assert!(matches!(result, Err(ApiError::Status { status: 502, .. })));
+        assert_eq!(calls.get(), 3);
+
+        calls.set(0);
+        let result: Result<(), ApiError> = runtime.block_on(with_retry(&config, || {
+            calls.set(calls.get() + 1);
+            async { Err(status(401)) }
+        }));
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+}
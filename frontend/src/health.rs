@@ -0,0 +1,167 @@
+use std::fmt::Write;
+use std::sync::{Arc, RwLock};
+use std::thread::{self, JoinHandle};
+
+use serde_json::json;
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::error::KinError;
+use crate::update::APP_VERSION;
+
+/// What the health server reports, written by the UI thread.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AppState {
+    pub is_running: bool,
+    // None until balances have been fetched
+    pub health_score: Option<u8>,
+    // Live allocation minus target in percentage points, by symbol
+    pub drift: Vec<(String, f64)>,
+}
+
+pub type SharedState = Arc<RwLock<AppState>>;
+
+/// Serves `GET /health` and `GET /metrics` on 127.0.0.1 from a background
+/// thread, for liveness probes and Prometheus scrapes. Stops when dropped.
+pub struct HealthServer {
+    server: Arc<Server>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HealthServer {
+    pub fn start(port: u16, state: SharedState) -> Result<Self, KinError> {
+        let server = Server::http(("127.0.0.1", port)).map_err(|e| {
+            KinError::HealthServer(format!("cannot listen on port {}: {}", port, e))
+        })?;
+        let server = Arc::new(server);
+        let listener = Arc::clone(&server);
+        let thread = thread::Builder::new()
+            .name("health-server".to_string())
+            .spawn(move || {
+                for request in listener.incoming_requests() {
+                    let snapshot = state.read().map(|s| s.clone()).unwrap_or_default();
+                    let (status, content_type, body) =
+                        respond(request.method(), request.url(), &snapshot);
+                    let header = Header::from_bytes("Content-Type", content_type)
+                        .expect("static header is valid");
+                    let response = Response::from_string(body)
+                        .with_status_code(status)
+                        .with_header(header);
+                    if let Err(e) = request.respond(response) {
+                        log::debug!("Health check response failed: {}", e);
+                    }
+                }
+            })
+            .map_err(|e| KinError::HealthServer(e.to_string()))?;
+        log::info!("Health check server listening on http://127.0.0.1:{}", port);
+        Ok(Self {
+            server,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for HealthServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Status, content type and body for a request; the query string is ignored
+fn respond(method: &Method, url: &str, state: &AppState) -> (u16, &'static str, String) {
+    let path = url.split('?').next().unwrap_or(url);
+    match (method, path) {
+        (Method::Get, "/health") => (
+            200,
+            "application/json",
+            json!({
+                "status": "ok",
+                "is_running": state.is_running,
+                "version": APP_VERSION,
+            })
+            .to_string(),
+        ),
+        (Method::Get, "/metrics") => (200, "text/plain; version=0.0.4", metrics_text(state)),
+        (_, "/health" | "/metrics") => (405, "text/plain", "Method Not Allowed\n".to_string()),
+        _ => (404, "text/plain", "Not Found\n".to_string()),
+    }
+}
+
+// Prometheus text exposition format; gauges without a value yet are left without samples
+fn metrics_text(state: &AppState) -> String {
+    let mut text = String::new();
+    let _ = writeln!(
+        text,
+        "# HELP kin_backend_running Whether the rebalancer backend is running."
+    );
+    let _ = writeln!(text, "# TYPE kin_backend_running gauge");
+    let _ = writeln!(text, "kin_backend_running {}", u8::from(state.is_running));
+    let _ = writeln!(
+        text,
+        "# HELP kin_portfolio_health_score 100 minus the percentage points assets drift beyond the rebalance threshold."
+    );
+    let _ = writeln!(text, "# TYPE kin_portfolio_health_score gauge");
+    if let Some(score) = state.health_score {
+        let _ = writeln!(text, "kin_portfolio_health_score {}", score);
+    }
+    let _ = writeln!(
+        text,
+        "# HELP kin_allocation_drift_percent Live allocation minus target, in percentage points."
+    );
+    let _ = writeln!(text, "# TYPE kin_allocation_drift_percent gauge");
+    for (symbol, drift) in &state.drift {
+        let _ = writeln!(
+            text,
+            "kin_allocation_drift_percent{{symbol=\"{}\"}} {}",
+            escape_label(symbol),
+            drift
+        );
+    }
+    text
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_and_metrics_responses() {
+        let state = AppState {
+            is_running: true,
+            health_score: Some(87),
+            drift: vec![
+                ("BTC_USDT".to_string(), 2.5),
+                ("ETH_USDT".to_string(), -1.25),
+            ],
+        };
+        let (status, content_type, body) = respond(&Method::Get, "/health", &state);
+        assert_eq!((status, content_type), (200, "application/json"));
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({"status": "ok", "is_running": true, "version": APP_VERSION})
+        );
+
+        let (status, _, body) = respond(&Method::Get, "/metrics?x=1", &state);
+        assert_eq!(status, 200);
+        assert!(body.contains("\nkin_backend_running 1\n"));
+        assert!(body.contains("\nkin_portfolio_health_score 87\n"));
+        assert!(body.contains("\nkin_allocation_drift_percent{symbol=\"BTC_USDT\"} 2.5\n"));
+        assert!(body.contains("\nkin_allocation_drift_percent{symbol=\"ETH_USDT\"} -1.25\n"));
+
+        let (_, _, body) = respond(&Method::Get, "/metrics", &AppState::default());
+        assert!(!body.contains("\nkin_portfolio_health_score "));
+
+        assert_eq!(respond(&Method::Post, "/health", &state).0, 405);
+        assert_eq!(respond(&Method::Get, "/", &state).0, 404);
+    }
+}
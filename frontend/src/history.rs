@@ -0,0 +1,262 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::error::KinError;
+use crate::exchange::OrderSide;
+
+/// Rows per page of the Orders tab.
+pub const ORDERS_PER_PAGE: usize = 50;
+// Snapshots arrive every few seconds; one sample a minute is plenty for the chart
+const VALUE_SAMPLE_INTERVAL_SECS: i64 = 60;
+// A little over a month at one sample a minute
+const MAX_VALUE_SAMPLES: usize = 50_000;
+
+/// A single trade the backend executed while rebalancing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RebalanceEvent {
+    pub timestamp: DateTime<Utc>,
+    pub asset: String,
+    pub from_pct: f64,
+    pub to_pct: f64,
+    pub usdt_traded: f64,
+    pub order_id: String,
+    // 0.0 for events recorded before the backend reported it
+    #[serde(default)]
+    pub portfolio_value_usdt: f64,
+    // Estimated at the taker fee rate when the trade was recorded; 0.0 before that
+    #[serde(default)]
+    pub fee_usdt: f64,
+}
+
+impl RebalanceEvent {
+    /// JSON history written before the SQLite database, e.g.
+    /// `.portfolio_rebalancer.history.json`; imported by `Database::open_for_config`.
+    pub fn history_path(config_path: &Path) -> PathBuf {
+        config_path.with_extension("history.json")
+    }
+
+    pub fn load_all(path: &Path) -> Result<Vec<RebalanceEvent>, KinError> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let text = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    // One row per trade, with a header taken from the field names
+    pub fn export_all_to_csv(events: &[RebalanceEvent], path: &Path) -> Result<(), KinError> {
+        let mut writer = csv::Writer::from_path(path)?;
+        for event in events {
+            writer.serialize(event)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    Open,
+    Filled,
+    Cancelled,
+    Failed,
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderStatus::Open => write!(f, "open"),
+            OrderStatus::Filled => write!(f, "filled"),
+            OrderStatus::Cancelled => write!(f, "cancelled"),
+            OrderStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// An order placed on the exchange, by the backend or a dry run, as last reported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Order {
+    // Exchange-assigned order ID
+    pub id: String,
+    pub symbol: String,
+    pub side: OrderSide,
+    // Contracts, always positive
+    pub qty: f64,
+    pub price: f64,
+    pub status: OrderStatus,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Order {
+    /// Whether the symbol or order ID contains `query`, ignoring case.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        self.symbol.to_lowercase().contains(&query) || self.id.to_lowercase().contains(&query)
+    }
+}
+
+/// Pages needed for `rows` rows; an empty list still has one page.
+pub fn page_count(rows: usize) -> usize {
+    rows.div_ceil(ORDERS_PER_PAGE).max(1)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryColumn {
+    Timestamp,
+    Asset,
+    FromPct,
+    ToPct,
+    UsdtTraded,
+    OrderId,
+}
+
+impl HistoryColumn {
+    pub const ALL: [HistoryColumn; 6] = [
+        HistoryColumn::Timestamp,
+        HistoryColumn::Asset,
+        HistoryColumn::FromPct,
+        HistoryColumn::ToPct,
+        HistoryColumn::UsdtTraded,
+        HistoryColumn::OrderId,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HistoryColumn::Timestamp => "Time (UTC)",
+            HistoryColumn::Asset => "Asset",
+            HistoryColumn::FromPct => "From %",
+            HistoryColumn::ToPct => "To %",
+            HistoryColumn::UsdtTraded => "USDT",
+            HistoryColumn::OrderId => "Order ID",
+        }
+    }
+
+    pub fn compare(self, a: &RebalanceEvent, b: &RebalanceEvent) -> Ordering {
+        match self {
+            HistoryColumn::Timestamp => a.timestamp.cmp(&b.timestamp),
+            HistoryColumn::Asset => a.asset.cmp(&b.asset),
+            HistoryColumn::FromPct => a.from_pct.total_cmp(&b.from_pct),
+            HistoryColumn::ToPct => a.to_pct.total_cmp(&b.to_pct),
+            HistoryColumn::UsdtTraded => a.usdt_traded.total_cmp(&b.usdt_traded),
+            HistoryColumn::OrderId => a.order_id.cmp(&b.order_id),
+        }
+    }
+}
+
+/// Portfolio value over time, sampled from the backend's snapshots. Kept in
+/// memory only; the chart starts empty on every launch.
+#[derive(Debug, Clone, Default)]
+pub struct ValueHistory {
+    pub timestamps: Vec<DateTime<Utc>>,
+    pub values_usdt: Vec<f64>,
+}
+
+impl ValueHistory {
+    /// Records `value_usdt` unless the last sample is under a minute old.
+    pub fn push(&mut self, timestamp: DateTime<Utc>, value_usdt: f64) {
+        if self
+            .timestamps
+            .last()
+            .is_some_and(|last| timestamp - *last < Duration::seconds(VALUE_SAMPLE_INTERVAL_SECS))
+        {
+            return;
+        }
+        self.timestamps.push(timestamp);
+        self.values_usdt.push(value_usdt);
+        if self.timestamps.len() > MAX_VALUE_SAMPLES {
+            let excess = self.timestamps.len() - MAX_VALUE_SAMPLES;
+            self.timestamps.drain(..excess);
+            self.values_usdt.drain(..excess);
+        }
+    }
+
+    /// Indices of the samples within `range` before `now`.
+    pub fn indices(&self, range: TimeRange, now: DateTime<Utc>) -> Range<usize> {
+        let start = match range.duration() {
+            Some(duration) => self.timestamps.partition_point(|t| *t < now - duration),
+            None => 0,
+        };
+        start..self.timestamps.len()
+    }
+
+    pub fn export_to_csv(&self, indices: Range<usize>, path: &Path) -> Result<(), KinError> {
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(["timestamp", "value_usdt"])?;
+        for i in indices {
+            writer.write_record([
+                self.timestamps[i].to_rfc3339(),
+                self.values_usdt[i].to_string(),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// How far back the portfolio value chart goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeRange {
+    Day,
+    Week,
+    Month,
+    #[default]
+    All,
+}
+
+impl TimeRange {
+    pub const ALL: [TimeRange; 4] = [
+        TimeRange::Day,
+        TimeRange::Week,
+        TimeRange::Month,
+        TimeRange::All,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TimeRange::Day => "1D",
+            TimeRange::Week => "1W",
+            TimeRange::Month => "1M",
+            TimeRange::All => "All",
+        }
+    }
+
+    fn duration(self) -> Option<Duration> {
+        match self {
+            TimeRange::Day => Some(Duration::days(1)),
+            TimeRange::Week => Some(Duration::weeks(1)),
+            TimeRange::Month => Some(Duration::days(30)),
+            TimeRange::All => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_history_samples_once_a_minute_and_slices_by_range() {
+        let now = Utc::now();
+        let mut history = ValueHistory::default();
+        for (minutes_ago, value) in [(60 * 24 * 10, 90.0), (120, 95.0), (119, 96.0), (30, 100.0)] {
+            history.push(now - Duration::minutes(minutes_ago), value);
+        }
+        // Within a minute of the previous sample
+        history.push(now - Duration::seconds(30 * 60 - 20), 101.0);
+        assert_eq!(history.values_usdt, [90.0, 95.0, 96.0, 100.0]);
+
+        assert_eq!(history.indices(TimeRange::All, now), 0..4);
+        assert_eq!(history.indices(TimeRange::Week, now), 1..4);
+        assert_eq!(history.indices(TimeRange::Day, now), 1..4);
+        assert_eq!(
+            history.indices(TimeRange::Day, now + Duration::hours(23)),
+            3..4
+        );
+    }
+}
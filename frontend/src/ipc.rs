@@ -0,0 +1,242 @@
+use eframe::egui;
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::error::KinError;
+use crate::protocol::{BackendCommand, BackendMessage};
+
+/// How long `connect_with_timeout` waits for a freshly started backend to listen.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+const CONNECT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[cfg(unix)]
+type Stream = std::os::unix::net::UnixStream;
+// 命名管道的客户端可以直接当作文件打开读写
+#[cfg(windows)]
+type Stream = std::fs::File;
+
+/// Where the backend listens; handed to it as `KIN_IPC_PATH`.
+pub fn default_path() -> String {
+    path_for_slot(0)
+}
+
+/// Path of the backend in `slot`, so that several can run side by side; slot 0
+/// is `default_path`.
+#[cfg(unix)]
+pub fn path_for_slot(slot: usize) -> String {
+    let name = match slot {
+        0 => "kin_rebalancer.sock".to_string(),
+        n => format!("kin_rebalancer_{}.sock", n),
+    };
+    std::env::temp_dir()
+        .join(name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(windows)]
+pub fn path_for_slot(slot: usize) -> String {
+    match slot {
+        0 => r"\\.\pipe\kin_rebalancer".to_string(),
+        n => format!(r"\\.\pipe\kin_rebalancer_{}", n),
+    }
+}
+
+#[cfg(unix)]
+fn open_stream(path: &str) -> io::Result<Stream> {
+    Stream::connect(path)
+}
+
+#[cfg(windows)]
+fn open_stream(path: &str) -> io::Result<Stream> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+}
+
+/// Connection to a running backend over a Unix domain socket (a named pipe on
+/// Windows), speaking the same newline-delimited JSON as its stdout.
+pub struct BackendSocket {
+    #[cfg(unix)]
+    stream: Stream,
+    #[cfg(windows)]
+    path: String,
+    reader: Mutex<BufReader<Stream>>,
+    connected: AtomicBool,
+}
+
+impl BackendSocket {
+    pub fn connect(path: &str) -> Result<BackendSocket, KinError> {
+        let stream = open_stream(path).map_err(KinError::Ipc)?;
+        let reader = BufReader::new(stream.try_clone().map_err(KinError::Ipc)?);
+        Ok(BackendSocket {
+            #[cfg(unix)]
+            stream,
+            #[cfg(windows)]
+            path: path.to_string(),
+            reader: Mutex::new(reader),
+            connected: AtomicBool::new(true),
+        })
+    }
+
+    /// Retries `connect` until the backend has created `path` or `timeout` passes.
+    pub fn connect_with_timeout(path: &str, timeout: Duration) -> Result<BackendSocket, KinError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match BackendSocket::connect(path) {
+                Ok(socket) => return Ok(socket),
+                Err(_) if Instant::now() < deadline => thread::sleep(CONNECT_POLL_INTERVAL),
+                Err(KinError::Ipc(e)) => {
+                    return Err(KinError::Ipc(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!(
+                            "no backend at {} after {}s ({})",
+                            path,
+                            timeout.as_secs(),
+                            e
+                        ),
+                    )))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn send(&self, cmd: BackendCommand) -> Result<(), KinError> {
+        let mut line = serde_json::to_string(&cmd)?;
+        line.push('\n');
+        self.write_line(line.as_bytes())
+            .map_err(|e| self.disconnected(e))
+    }
+
+    #[cfg(unix)]
+    fn write_line(&self, line: &[u8]) -> io::Result<()> {
+        (&self.stream).write_all(line)?;
+        (&self.stream).flush()
+    }
+
+    // 同步打开的管道句柄上，读线程阻塞时写入也会阻塞，所以每条命令另开一个连接发送
+    #[cfg(windows)]
+    fn write_line(&self, line: &[u8]) -> io::Result<()> {
+        let mut pipe = open_stream(&self.path)?;
+        pipe.write_all(line)?;
+        pipe.flush()
+    }
+
+    /// Blocks until the next message; lines that are not protocol messages come
+    /// back as `BackendMessage::Log`.
+    pub fn recv(&self) -> Result<BackendMessage, KinError> {
+        let mut reader = self.reader.lock().unwrap_or_else(|e| e.into_inner());
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) => {
+                    return Err(self.disconnected(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "backend closed the connection",
+                    )))
+                }
+                Ok(_) if line.trim().is_empty() => continue,
+                Ok(_) => return Ok(BackendMessage::from_line(line.trim_end())),
+                Err(e) => return Err(self.disconnected(e)),
+            }
+        }
+    }
+
+    /// False once a send or receive has failed; the caller should reconnect.
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    fn disconnected(&self, e: io::Error) -> KinError {
+        self.connected.store(false, Ordering::SeqCst);
+        KinError::Ipc(e)
+    }
+}
+
+/// Forwards everything received on `socket` to `sender` until the connection
+/// drops, waking the UI like `protocol::spawn_reader`.
+pub fn spawn_reader(
+    socket: Arc<BackendSocket>,
+    sender: Sender<BackendMessage>,
+    ctx: egui::Context,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        while let Ok(message) = socket.recv() {
+            if sender.send(message).is_err() {
+                break;
+            }
+            ctx.request_repaint();
+        }
+        ctx.request_repaint();
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+
+    #[test]
+    fn exchanges_json_lines_with_the_backend() {
+        let path = std::env::temp_dir()
+            .join(format!("kin-ipc-{}.sock", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let _ = std::fs::remove_file(&path);
+
+        let backend_path = path.clone();
+        let backend = thread::spawn(move || {
+            // Listen only after the client has started polling
+            thread::sleep(Duration::from_millis(300));
+            let listener = UnixListener::bind(&backend_path).unwrap();
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .write_all(b"{\"type\": \"status\", \"status\": \"Running\"}\nplain print\n")
+                .unwrap();
+            let mut command = String::new();
+            BufReader::new(&stream).read_line(&mut command).unwrap();
+            command
+        });
+
+        let socket = BackendSocket::connect_with_timeout(&path, CONNECT_TIMEOUT).unwrap();
+        assert_eq!(
+            socket.recv().unwrap(),
+            BackendMessage::Status {
+                status: "Running".to_string(),
+                paper_trading: false
+            }
+        );
+        assert_eq!(
+            socket.recv().unwrap(),
+            BackendMessage::Log {
+                line: "plain print".to_string()
+            }
+        );
+        socket.send(BackendCommand::Stop).unwrap();
+        assert_eq!(backend.join().unwrap(), "{\"type\":\"stop\"}\n");
+
+        assert!(matches!(socket.recv(), Err(KinError::Ipc(_))));
+        assert!(!socket.is_connected());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn gives_up_when_nothing_listens() {
+        let path = std::env::temp_dir()
+            .join("kin-ipc-missing.sock")
+            .to_string_lossy()
+            .into_owned();
+        match BackendSocket::connect_with_timeout(&path, Duration::from_millis(200)) {
+            Err(KinError::Ipc(e)) => assert_eq!(e.kind(), io::ErrorKind::TimedOut),
+            other => panic!("expected a timeout, got {:?}", other.map(|_| ())),
+        }
+    }
+}
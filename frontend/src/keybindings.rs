@@ -0,0 +1,196 @@
+use eframe::egui::{Key, KeyboardShortcut, Modifiers};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Something a keyboard shortcut can trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    StartStop,
+    SaveConfig,
+    DryRun,
+    SwitchToPortfolio,
+    SwitchToLog,
+    SwitchToHistory,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 6] = [
+        KeyAction::StartStop,
+        KeyAction::SaveConfig,
+        KeyAction::DryRun,
+        KeyAction::SwitchToPortfolio,
+        KeyAction::SwitchToLog,
+        KeyAction::SwitchToHistory,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyAction::StartStop => "Start / stop rebalancer",
+            KeyAction::SaveConfig => "Save portfolio config",
+            KeyAction::DryRun => "Dry run",
+            KeyAction::SwitchToPortfolio => "Portfolio Config tab",
+            KeyAction::SwitchToLog => "Backend Log tab",
+            KeyAction::SwitchToHistory => "History tab",
+        }
+    }
+}
+
+/// Stored as "Ctrl+R"-style strings. Ctrl means Cmd on macOS, like egui's `Modifiers::COMMAND`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct KeyBindings {
+    /// Start the rebalancer, or stop it while it runs
+    #[serde(with = "shortcut")]
+    #[schemars(with = "String")]
+    pub start_stop: KeyboardShortcut,
+    /// Save the portfolio config
+    #[serde(with = "shortcut")]
+    #[schemars(with = "String")]
+    pub save_config: KeyboardShortcut,
+    /// Preview the trades a rebalance would place now
+    #[serde(with = "shortcut")]
+    #[schemars(with = "String")]
+    pub dry_run: KeyboardShortcut,
+    /// Show the Portfolio Config tab
+    #[serde(with = "shortcut")]
+    #[schemars(with = "String")]
+    pub switch_to_portfolio: KeyboardShortcut,
+    /// Show the Backend Log tab
+    #[serde(with = "shortcut")]
+    #[schemars(with = "String")]
+    pub switch_to_log: KeyboardShortcut,
+    /// Show the History tab
+    #[serde(with = "shortcut")]
+    #[schemars(with = "String")]
+    pub switch_to_history: KeyboardShortcut,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let ctrl = |key| KeyboardShortcut::new(Modifiers::COMMAND, key);
+        Self {
+            start_stop: ctrl(Key::R),
+            save_config: ctrl(Key::S),
+            dry_run: ctrl(Key::D),
+            switch_to_portfolio: ctrl(Key::Num1),
+            switch_to_log: ctrl(Key::L),
+            switch_to_history: ctrl(Key::H),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn get(&self, action: KeyAction) -> KeyboardShortcut {
+        match action {
+            KeyAction::StartStop => self.start_stop,
+            KeyAction::SaveConfig => self.save_config,
+            KeyAction::DryRun => self.dry_run,
+            KeyAction::SwitchToPortfolio => self.switch_to_portfolio,
+            KeyAction::SwitchToLog => self.switch_to_log,
+            KeyAction::SwitchToHistory => self.switch_to_history,
+        }
+    }
+
+    pub fn set(&mut self, action: KeyAction, shortcut: KeyboardShortcut) {
+        let slot = match action {
+            KeyAction::StartStop => &mut self.start_stop,
+            KeyAction::SaveConfig => &mut self.save_config,
+            KeyAction::DryRun => &mut self.dry_run,
+            KeyAction::SwitchToPortfolio => &mut self.switch_to_portfolio,
+            KeyAction::SwitchToLog => &mut self.switch_to_log,
+            KeyAction::SwitchToHistory => &mut self.switch_to_history,
+        };
+        *slot = shortcut;
+    }
+
+    /// Another action already bound to `shortcut`, if any.
+    pub fn conflict(&self, action: KeyAction, shortcut: KeyboardShortcut) -> Option<KeyAction> {
+        KeyAction::ALL
+            .into_iter()
+            .find(|other| *other != action && self.get(*other) == shortcut)
+    }
+}
+
+// Modifier names in the order they are written
+const MODIFIER_NAMES: [(&str, Modifiers); 3] = [
+    ("Ctrl", Modifiers::COMMAND),
+    ("Alt", Modifiers::ALT),
+    ("Shift", Modifiers::SHIFT),
+];
+
+pub fn format_shortcut(shortcut: &KeyboardShortcut) -> String {
+    let mut parts: Vec<&str> = MODIFIER_NAMES
+        .iter()
+        .filter(|(_, modifier)| shortcut.modifiers.contains(*modifier))
+        .map(|(name, _)| *name)
+        .collect();
+    parts.push(shortcut.logical_key.name());
+    parts.join("+")
+}
+
+pub fn parse_shortcut(text: &str) -> Option<KeyboardShortcut> {
+    let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+    let key = Key::from_name(parts.pop()?)?;
+    let mut modifiers = Modifiers::NONE;
+    for part in parts {
+        let (_, modifier) = MODIFIER_NAMES
+            .iter()
+            .chain([("Cmd", Modifiers::COMMAND)].iter())
+            .find(|(name, _)| name.eq_ignore_ascii_case(part))?;
+        modifiers |= *modifier;
+    }
+    Some(KeyboardShortcut::new(modifiers, key))
+}
+
+mod shortcut {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        shortcut: &KeyboardShortcut,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_shortcut(shortcut))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<KeyboardShortcut, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        parse_shortcut(&text).ok_or_else(|| {
+            serde::de::Error::custom(format!("\"{}\" is not a key combination", text))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcuts_round_trip_through_text() {
+        let shortcut = KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::F5);
+        assert_eq!(format_shortcut(&shortcut), "Ctrl+Shift+F5");
+        assert_eq!(parse_shortcut("Ctrl+Shift+F5"), Some(shortcut));
+        assert_eq!(
+            parse_shortcut("cmd + R"),
+            Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::R))
+        );
+        assert_eq!(parse_shortcut("Ctrl+Nope"), None);
+        assert_eq!(parse_shortcut("Hyper+R"), None);
+    }
+
+    #[test]
+    fn missing_bindings_keep_their_defaults() {
+        let bindings: KeyBindings = serde_json::from_str(r#"{"start_stop": "Alt+S"}"#).unwrap();
+        assert_eq!(
+            bindings.start_stop,
+            KeyboardShortcut::new(Modifiers::ALT, Key::S)
+        );
+        assert_eq!(bindings.save_config, KeyBindings::default().save_config);
+        assert_eq!(
+            bindings.conflict(KeyAction::StartStop, bindings.save_config),
+            Some(KeyAction::SaveConfig)
+        );
+        assert!(serde_json::from_str::<KeyBindings>(r#"{"dry_run": "Ctrl+"}"#).is_err());
+    }
+}
@@ -1,2 +1,37 @@
 pub mod app;
+pub mod audit;
+pub mod backend;
+pub mod backend_client;
+pub mod backtest;
+pub mod cli;
 pub mod config;
+pub mod crash;
+pub mod crypto;
+pub mod currency;
+pub mod db;
+pub mod error;
+pub mod exchange;
+pub mod health;
+pub mod history;
+pub mod ipc;
+pub mod keybindings;
+pub mod locale;
+pub mod logging;
+pub mod market_cap;
+pub mod metrics;
+pub mod planner;
+pub mod portfolio;
+pub mod presets;
+pub mod protocol;
+pub mod risk;
+pub mod schedule;
+pub mod slippage;
+pub mod symbols;
+pub mod tax;
+pub mod theme;
+pub mod tray;
+pub mod update;
+pub mod watcher;
+pub mod webhook;
+pub mod widgets;
+pub mod window_state;
@@ -0,0 +1,84 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// How numbers are written in the UI; stored values are unaffected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Locale {
+    #[default]
+    EnUs,
+    DeDe,
+    ZhCn,
+    JaJp,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 4] = [Locale::EnUs, Locale::DeDe, Locale::ZhCn, Locale::JaJp];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Locale::EnUs => "English (US)",
+            Locale::DeDe => "Deutsch",
+            Locale::ZhCn => "简体中文",
+            Locale::JaJp => "日本語",
+        }
+    }
+
+    // (decimal separator, thousands separator)
+    fn separators(self) -> (char, char) {
+        match self {
+            Locale::EnUs | Locale::ZhCn | Locale::JaJp => ('.', ','),
+            Locale::DeDe => (',', '.'),
+        }
+    }
+}
+
+/// `value` rounded to `decimals` places with the locale's decimal separator and
+/// thousands grouped in threes, e.g. "1.234.567,89" for German.
+pub fn format_number(value: f64, decimals: usize, locale: Locale) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    let (decimal_separator, thousands_separator) = locale.separators();
+    let plain = format!("{:.*}", decimals, value.abs());
+    let (integer, fraction) = plain.split_once('.').unwrap_or((&plain, ""));
+
+    let mut text = String::with_capacity(plain.len() + integer.len() / 3 + 1);
+    // "-0.0" is not worth a sign
+    if value < 0.0 && plain.bytes().any(|b| matches!(b, b'1'..=b'9')) {
+        text.push('-');
+    }
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            text.push(thousands_separator);
+        }
+        text.push(digit);
+    }
+    if !fraction.is_empty() {
+        text.push(decimal_separator);
+        text.push_str(fraction);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_separators_per_locale() {
+        let expected = [
+            (Locale::EnUs, "1,234,567.89"),
+            (Locale::DeDe, "1.234.567,89"),
+            (Locale::ZhCn, "1,234,567.89"),
+            (Locale::JaJp, "1,234,567.89"),
+        ];
+        for (locale, text) in expected {
+            assert_eq!(format_number(1234567.89, 2, locale), text, "{:?}", locale);
+        }
+        assert_eq!(format_number(-1234.5, 1, Locale::DeDe), "-1.234,5");
+        assert_eq!(format_number(999.96, 1, Locale::EnUs), "1,000.0");
+        assert_eq!(format_number(42.0, 0, Locale::DeDe), "42");
+        assert_eq!(format_number(-0.01, 1, Locale::EnUs), "0.0");
+    }
+}
@@ -0,0 +1,234 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use crate::error::KinError;
+
+/// The log file is moved to `<name>.old` once it would grow past this size.
+pub const MAX_LOG_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// How many of the latest log lines `recent_lines` keeps for crash reports.
+pub const RECENT_LINES: usize = 100;
+
+/// Warnings waiting for `take_warnings`; older ones are dropped past this.
+const MAX_PENDING_WARNINGS: usize = 100;
+
+pub const LOG_LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+
+pub fn parse_level(level: &str) -> Result<LevelFilter, KinError> {
+    LevelFilter::from_str(level.trim()).map_err(|_| {
+        KinError::invalid_setting(
+            "log_level",
+            &format!("\"{}\" is not one of {}", level, LOG_LEVELS.join(", ")),
+        )
+    })
+}
+
+/// Appends to a file, moving it to `<path>.old` (replacing the previous one)
+/// before a write would take it past `max_bytes`.
+pub struct RollingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    file: File,
+    len: u64,
+}
+
+impl RollingFile {
+    pub fn open(path: &Path, max_bytes: u64) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = open_append(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            file,
+            len,
+        })
+    }
+
+    pub fn old_path(path: &Path) -> PathBuf {
+        let mut name = OsString::from(path.as_os_str());
+        name.push(".old");
+        PathBuf::from(name)
+    }
+
+    fn roll(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        fs::rename(&self.path, RollingFile::old_path(&self.path))?;
+        self.file = open_append(&self.path)?;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.len > 0 && self.len + buf.len() as u64 > self.max_bytes {
+            self.roll()?;
+        }
+        let written = self.file.write(buf)?;
+        self.len += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+fn remember(buf: &[u8]) {
+    // try_lock: a panic while logging must not deadlock the crash reporter
+    let Ok(mut recent) = RECENT.try_lock() else {
+        return;
+    };
+    for line in String::from_utf8_lossy(buf).lines() {
+        if recent.len() == RECENT_LINES {
+            recent.pop_front();
+        }
+        recent.push_back(line.to_string());
+    }
+}
+
+/// The last `RECENT_LINES` lines logged since `init`, oldest first.
+pub fn recent_lines() -> Vec<String> {
+    RECENT
+        .try_lock()
+        .map(|recent| recent.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+static WARNINGS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Warnings and errors logged since the last call, e.g. "WARN: ...", for the
+/// UI's log panel.
+pub fn take_warnings() -> Vec<String> {
+    WARNINGS
+        .try_lock()
+        .map(|mut warnings| warnings.drain(..).collect())
+        .unwrap_or_default()
+}
+
+// Queues warnings and errors for the log panel on the way to env_logger
+struct PanelTap(env_logger::Logger);
+
+impl Log for PanelTap {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if record.level() <= Level::Warn && self.0.matches(record) {
+            if let Ok(mut warnings) = WARNINGS.try_lock() {
+                if warnings.len() == MAX_PENDING_WARNINGS {
+                    warnings.pop_front();
+                }
+                warnings.push_back(format!("{}: {}", record.level(), record.args()));
+            }
+        }
+        self.0.log(record);
+    }
+
+    fn flush(&self) {
+        self.0.flush();
+    }
+}
+
+// Records still reach the console when started from a terminal, and the
+// latest lines stay in memory for crash reports
+struct ConsoleAndFile(Option<RollingFile>);
+
+impl Write for ConsoleAndFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let _ = io::stderr().write_all(buf);
+        remember(buf);
+        match &mut self.0 {
+            Some(file) => file.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.0 {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Installs the global logger at `level`, writing to stderr and appending to
+/// `log_file`. `RUST_LOG` overrides the level. An unknown level falls back to
+/// info and an unwritable file to stderr only; both are logged as warnings.
+pub fn init(level: &str, log_file: &Path) {
+    let filter = parse_level(level);
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(filter.as_ref().copied().unwrap_or(LevelFilter::Info));
+    builder.parse_default_env();
+    let (file, file_error) = match RollingFile::open(log_file, MAX_LOG_FILE_BYTES) {
+        Ok(file) => (Some(file), None),
+        Err(e) => (None, Some(e)),
+    };
+    builder.target(env_logger::Target::Pipe(Box::new(ConsoleAndFile(file))));
+    let logger = builder.build();
+    let max_level = logger.filter();
+    if log::set_boxed_logger(Box::new(PanelTap(logger))).is_err() {
+        return;
+    }
+    log::set_max_level(max_level);
+    if let Err(e) = filter {
+        log::warn!("{}; logging at info level.", e);
+    }
+    if let Some(e) = file_error {
+        log::warn!("Failed to open log file ({:?}): {}", log_file, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_level_names_in_any_case() {
+        assert_eq!(parse_level("info").unwrap(), LevelFilter::Info);
+        assert_eq!(parse_level(" DEBUG ").unwrap(), LevelFilter::Debug);
+        assert!(matches!(
+            parse_level("verbose"),
+            Err(KinError::InvalidSetting { .. })
+        ));
+    }
+
+    #[test]
+    fn rolls_over_to_the_old_file_when_full() {
+        let path = std::env::temp_dir().join(format!("kin-log-{}.log", std::process::id()));
+        let old = RollingFile::old_path(&path);
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&old);
+
+        let mut file = RollingFile::open(&path, 10).unwrap();
+        file.write_all(b"first\n").unwrap();
+        file.write_all(b"second\n").unwrap();
+        file.flush().unwrap();
+        assert_eq!(fs::read_to_string(&old).unwrap(), "first\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+
+        // Reopening continues from the existing size
+        let mut file = RollingFile::open(&path, 10).unwrap();
+        file.write_all(b"third\n").unwrap();
+        assert_eq!(fs::read_to_string(&old).unwrap(), "second\n");
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&old).unwrap();
+    }
+}
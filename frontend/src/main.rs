@@ -1,18 +1,102 @@
+use clap::Parser;
 use eframe::egui::{self, FontDefinitions, FontFamily, ViewportBuilder};
 use std::env;
 use std::fs;
-use std::path::Path;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
 
-mod app;
-use app::RebalancerApp;
-mod config;
+use kin_portfolio_rebalancer_gui::app::RebalancerApp;
+use kin_portfolio_rebalancer_gui::cli::{self, Cli};
+use kin_portfolio_rebalancer_gui::config::{ProfileManager, MAX_UI_SCALE, MIN_UI_SCALE};
+use kin_portfolio_rebalancer_gui::crash;
+use kin_portfolio_rebalancer_gui::exchange::mock::TestScenario;
+use kin_portfolio_rebalancer_gui::logging;
+use kin_portfolio_rebalancer_gui::tray::Tray;
+use kin_portfolio_rebalancer_gui::window_state::WindowState;
 
 fn main() -> Result<(), eframe::Error> {
+    // 带子命令运行时进入命令行模式（无GUI）
+    let cli = Cli::parse();
+    if !cli.wants_gui() {
+        if let Err(e) = cli::run(cli) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        return Ok(());
+    }
+    // 加密的配置文件需要在打开窗口前于终端输入口令
+    let passphrase = cli.resolve_passphrase().unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    });
+    run_gui(cli.config_path(), passphrase, cli.mock_exchange)
+}
+
+// Lets Notification Center group the trade notifications under the app
+#[cfg(target_os = "macos")]
+const BUNDLE_IDENTIFIER: &str = "com.btxlithium.kin-portfolio-rebalancer";
+
+// Ctrl+C (and SIGTERM on Unix) set the returned flag and wake the UI, which
+// stops the backend and closes the window instead of leaving the backend orphaned.
+fn install_signal_handlers(ctx: egui::Context) -> Arc<AtomicBool> {
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let flag = shutdown.clone();
+    let repaint = ctx.clone();
+    if let Err(e) = ctrlc::set_handler(move || {
+        flag.store(true, Ordering::SeqCst);
+        repaint.request_repaint();
+    }) {
+        log::warn!("Failed to install Ctrl+C handler: {}", e);
+    }
+
+    #[cfg(unix)]
+    match signal_hook::iterator::Signals::new([signal_hook::consts::SIGTERM]) {
+        Ok(mut signals) => {
+            let flag = shutdown.clone();
+            std::thread::spawn(move || {
+                for _ in signals.forever() {
+                    flag.store(true, Ordering::SeqCst);
+                    ctx.request_repaint();
+                }
+            });
+        }
+        Err(e) => log::warn!("Failed to install SIGTERM handler: {}", e),
+    }
+    #[cfg(not(unix))]
+    drop(ctx);
+
+    shutdown
+}
+
+fn run_gui(
+    config_path: PathBuf,
+    passphrase: Option<String>,
+    mock_exchange: Option<TestScenario>,
+) -> Result<(), eframe::Error> {
+    // 日志级别、文件和界面缩放取自活动配置；读取失败时用默认值，具体错误由应用启动时记录
+    let config =
+        ProfileManager::load_active(&config_path, passphrase.as_deref()).unwrap_or_default();
+    logging::init(&config.log_level, &config.log_file_path(&config_path));
+    crash::install(config_path.clone(), passphrase.clone());
+
+    #[cfg(target_os = "macos")]
+    if let Err(e) = notify_rust::set_application(BUNDLE_IDENTIFIER) {
+        log::warn!("Failed to set notification bundle identifier: {}", e);
+    }
+
+    let window_state = WindowState::load(&WindowState::default_path());
+    let mut viewport = ViewportBuilder::default()
+        .with_inner_size(egui::vec2(555.0, 600.0))
+        .with_min_inner_size(egui::vec2(300.0, 200.0));
+    if let Some(state) = window_state {
+        viewport = viewport
+            .with_position(egui::pos2(state.x, state.y))
+            .with_inner_size(egui::vec2(state.width, state.height));
+    }
     let options = eframe::NativeOptions {
-        viewport: ViewportBuilder::default()
-            .with_inner_size(egui::vec2(555.0, 600.0))
-            .with_min_inner_size(egui::vec2(300.0, 200.0)),
+        viewport,
         ..Default::default()
     };
 
@@ -20,20 +104,24 @@ fn main() -> Result<(), eframe::Error> {
         "KIN Portfolio Rebalancer (TestNet Version)",
         options,
         Box::new(|cc| {
+            // On top of the OS scaling, so high-DPI screens without it stay usable
+            cc.egui_ctx
+                .set_zoom_factor(config.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE));
+
             // 加载自定义字体
             let mut fonts = FontDefinitions::default();
 
             // 尝试将字体从build.rs复制到release目录
-            println!("尝试加载字体文件...");
+            log::debug!("尝试加载字体文件...");
 
             // 打印当前工作目录
             if let Ok(cwd) = env::current_dir() {
-                println!("当前工作目录: {:?}", cwd);
+                log::debug!("当前工作目录: {:?}", cwd);
             }
 
             // 打印可执行文件路径
             if let Ok(exe_path) = env::current_exe() {
-                println!("可执行文件路径: {:?}", exe_path);
+                log::debug!("可执行文件路径: {:?}", exe_path);
             }
 
             // 定义多个可能的字体路径
@@ -46,7 +134,7 @@ fn main() -> Result<(), eframe::Error> {
                 "target/debug/fonts/OPlusSans3.ttf",
             ];
 
-            println!("字体搜索路径: {:?}", font_paths);
+            log::debug!("字体搜索路径: {:?}", font_paths);
 
             let mut font_loaded = false;
 
@@ -54,7 +142,7 @@ fn main() -> Result<(), eframe::Error> {
                 if Path::new(path).exists() {
                     match fs::read(path) {
                         Ok(font_data) => {
-                            println!("成功加载字体: {}", path);
+                            log::info!("成功加载字体: {}", path);
                             // 将字体添加到字体集合中
                             fonts.font_data.insert(
                                 "oplusfont".to_owned(),
@@ -74,19 +162,33 @@ fn main() -> Result<(), eframe::Error> {
                             break;
                         }
                         Err(e) => {
-                            eprintln!("尝试路径 {} 失败: {}", path, e);
+                            log::warn!("尝试路径 {} 失败: {}", path, e);
                         }
                     }
                 } else {
-                    eprintln!("路径不存在: {}", path);
+                    log::debug!("路径不存在: {}", path);
                 }
             }
 
             if !font_loaded {
-                eprintln!("未能加载自定义字体，将使用默认字体");
+                log::warn!("未能加载自定义字体，将使用默认字体");
             }
 
-            Ok(Box::new(RebalancerApp::new(cc)))
+            let mut app = RebalancerApp::new(cc, config_path, window_state, passphrase)
+                .with_shutdown_signal(install_signal_handlers(cc.egui_ctx.clone()));
+            if let Some(scenario) = mock_exchange {
+                app = app.with_mock_exchange(scenario);
+            }
+            // 托盘图标在事件循环启动后创建（macOS 的要求）
+            let (tray_sender, tray_receiver) = mpsc::channel();
+            let app = match Tray::new(tray_sender, cc.egui_ctx.clone()) {
+                Ok(tray) => app.with_tray(tray, tray_receiver),
+                Err(e) => {
+                    log::warn!("{}", e);
+                    app
+                }
+            };
+            Ok(Box::new(app))
         }),
     )
 }
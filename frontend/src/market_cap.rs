@@ -0,0 +1,85 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::error::KinError;
+
+// CoinGecko's public API, no key needed; rate limited to a few calls a minute
+const COINGECKO_MARKETS_URL: &str = "https://api.coingecko.com/api/v3/coins/markets";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Deserialize)]
+struct CoinMarket {
+    symbol: String,
+    #[serde(default)]
+    market_cap: Option<f64>,
+}
+
+// "BTC_USDT" -> "btc"
+fn coin_symbol(contract: &str) -> String {
+    contract
+        .split('_')
+        .next()
+        .unwrap_or(contract)
+        .to_lowercase()
+}
+
+/// Market capitalisation in USD per contract, e.g. "BTC_USDT", fetched from
+/// CoinGecko. Contracts CoinGecko does not list are left out.
+pub async fn fetch_market_caps(contracts: &[String]) -> Result<HashMap<String, f64>, KinError> {
+    let symbols: Vec<String> = contracts.iter().map(|c| coin_symbol(c)).collect();
+    let markets: Vec<CoinMarket> = reqwest::Client::new()
+        .get(COINGECKO_MARKETS_URL)
+        .query(&[("vs_currency", "usd"), ("symbols", &symbols.join(","))])
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(KinError::MarketData)?
+        .json()
+        .await
+        .map_err(KinError::MarketData)?;
+    Ok(market_caps_by_contract(&markets, contracts))
+}
+
+// Several coins can share a ticker; the largest one is the one traded
+fn market_caps_by_contract(markets: &[CoinMarket], contracts: &[String]) -> HashMap<String, f64> {
+    contracts
+        .iter()
+        .filter_map(|contract| {
+            let symbol = coin_symbol(contract);
+            let cap = markets
+                .iter()
+                .filter(|m| m.symbol.eq_ignore_ascii_case(&symbol))
+                .filter_map(|m| m.market_cap)
+                .fold(0.0, f64::max);
+            (cap > 0.0).then(|| (contract.clone(), cap))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_largest_coin_per_ticker() {
+        let markets: Vec<CoinMarket> = serde_json::from_str(
+            r#"[
+                {"id": "bitcoin", "symbol": "btc", "market_cap": 1200000000000},
+                {"id": "ethereum", "symbol": "eth", "market_cap": 400000000000},
+                {"id": "bridged-eth", "symbol": "eth", "market_cap": 5000000},
+                {"id": "unlisted", "symbol": "ltc", "market_cap": null}
+            ]"#,
+        )
+        .unwrap();
+        let contracts = ["BTC_USDT", "ETH_USDT", "LTC_USDT"].map(String::from);
+        assert_eq!(
+            market_caps_by_contract(&markets, &contracts),
+            HashMap::from([
+                ("BTC_USDT".to_string(), 1.2e12),
+                ("ETH_USDT".to_string(), 4e11),
+            ])
+        );
+    }
+}
@@ -0,0 +1,767 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use crate::config::{PortfolioAllocation, DEFAULT_LEVERAGE, USDT};
+use crate::exchange::Balances;
+use crate::history::RebalanceEvent;
+
+const SECONDS_PER_YEAR: f64 = 365.25 * 24.0 * 60.0 * 60.0;
+const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+/// `estimate_sharpe` needs at least this many daily returns.
+pub const MIN_SHARPE_DAYS: usize = 30;
+
+/// Health scores from here up are shown green, from `HEALTH_FAIR` up yellow, below red.
+pub const HEALTH_GOOD: u8 = 80;
+pub const HEALTH_FAIR: u8 = 50;
+
+/// Diversification scores up to `DIVERSIFIED_SCORE` count as well diversified,
+/// up to `MODERATE_SCORE` as moderate and above it as concentrated.
+pub const DIVERSIFIED_SCORE: f64 = 0.4;
+pub const MODERATE_SCORE: f64 = 0.7;
+
+/// Strategy performance estimated from the portfolio value recorded with each
+/// rebalancing trade. Positions are not marked to market between events.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PerformanceMetrics {
+    pub total_return_pct: f64,
+    pub annualised_return_pct: f64,
+    // Risk-free rate taken as zero, annualised by the average spacing of events
+    pub sharpe_ratio: f64,
+    pub max_drawdown_pct: f64,
+    // Share of event-to-event periods in which the portfolio value rose
+    pub win_rate_pct: f64,
+    // Sum of the fees recorded with every event, whatever their portfolio value
+    pub total_fees_paid_usdt: f64,
+}
+
+/// How far `value` is below `peak`, in percent; zero at or above the peak.
+pub fn drawdown_pct(peak: f64, value: f64) -> f64 {
+    if peak > 0.0 && value < peak {
+        (peak - value) / peak * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// All fields but the fees are zero until at least two events with a portfolio
+/// value exist.
+pub fn compute(events: &[RebalanceEvent]) -> PerformanceMetrics {
+    let total_fees_paid_usdt = events.iter().map(|e| e.fee_usdt).sum();
+    let mut points: Vec<(DateTime<Utc>, f64)> = events
+        .iter()
+        .filter(|e| e.portfolio_value_usdt > 0.0)
+        .map(|e| (e.timestamp, e.portfolio_value_usdt))
+        .collect();
+    points.sort_by_key(|&(timestamp, _)| timestamp);
+    // Trades of one rebalancing run all carry the same snapshot value
+    points.dedup_by(|b, a| b.1 == a.1);
+    if points.len() < 2 {
+        return PerformanceMetrics {
+            total_fees_paid_usdt,
+            ..PerformanceMetrics::default()
+        };
+    }
+
+    let (first_time, first_value) = points[0];
+    let (last_time, last_value) = points[points.len() - 1];
+    let total_return = last_value / first_value - 1.0;
+    let years = (last_time - first_time).num_seconds() as f64 / SECONDS_PER_YEAR;
+    let annualised_return = if years > 0.0 {
+        (1.0 + total_return).powf(1.0 / years) - 1.0
+    } else {
+        0.0
+    };
+
+    let returns: Vec<f64> = points.windows(2).map(|w| w[1].1 / w[0].1 - 1.0).collect();
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let sharpe_ratio = if returns.len() > 1 && years > 0.0 {
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        let std_dev = variance.sqrt();
+        if std_dev > 0.0 {
+            mean / std_dev * (n / years).sqrt()
+        } else {
+            0.0
+        }
+    } else {
+        0.0
+    };
+
+    let mut peak = first_value;
+    let mut max_drawdown: f64 = 0.0;
+    for &(_, value) in &points {
+        peak = peak.max(value);
+        max_drawdown = max_drawdown.max(drawdown_pct(peak, value));
+    }
+
+    let wins = returns.iter().filter(|&&r| r > 0.0).count() as f64;
+
+    PerformanceMetrics {
+        total_return_pct: total_return * 100.0,
+        annualised_return_pct: annualised_return * 100.0,
+        sharpe_ratio,
+        max_drawdown_pct: max_drawdown,
+        win_rate_pct: wins / n * 100.0,
+        total_fees_paid_usdt,
+    }
+}
+
+/// Annualised Sharpe ratio of the daily returns of the portfolio value, which is
+/// interpolated linearly between events. Events recorded without a portfolio
+/// value continue from the previous one by the USDT they traded, buys adding and
+/// sells subtracting. None with fewer than `MIN_SHARPE_DAYS` daily returns or
+/// when the returns do not vary.
+pub fn estimate_sharpe(events: &[RebalanceEvent], risk_free_rate_annual: f64) -> Option<f64> {
+    let mut sorted: Vec<&RebalanceEvent> = events.iter().collect();
+    sorted.sort_by_key(|e| e.timestamp);
+    let mut points: Vec<(DateTime<Utc>, f64)> = Vec::with_capacity(sorted.len());
+    for event in sorted {
+        let value = if event.portfolio_value_usdt > 0.0 {
+            event.portfolio_value_usdt
+        } else {
+            // Nothing to continue from before the first recorded value
+            let &(_, previous) = points.last()?;
+            let sign = if event.to_pct >= event.from_pct {
+                1.0
+            } else {
+                -1.0
+            };
+            previous + sign * event.usdt_traded
+        };
+        points.push((event.timestamp, value));
+    }
+    let (first, _) = *points.first()?;
+    let (last, _) = *points.last()?;
+
+    let mut daily_values = Vec::new();
+    let mut segment = 0;
+    let mut day = first;
+    while day <= last {
+        while points[segment + 1..].first().is_some_and(|&(t, _)| t < day) {
+            segment += 1;
+        }
+        let (t0, v0) = points[segment];
+        let value = match points.get(segment + 1) {
+            Some(&(t1, v1)) if t1 > t0 => {
+                let fraction = (day - t0).num_seconds() as f64 / (t1 - t0).num_seconds() as f64;
+                v0 + (v1 - v0) * fraction
+            }
+            _ => v0,
+        };
+        daily_values.push(value);
+        day += chrono::Duration::days(1);
+    }
+
+    let daily_risk_free = risk_free_rate_annual / TRADING_DAYS_PER_YEAR;
+    let excess: Vec<f64> = daily_values
+        .windows(2)
+        .filter(|w| w[0] > 0.0)
+        .map(|w| w[1] / w[0] - 1.0 - daily_risk_free)
+        .collect();
+    if excess.len() < MIN_SHARPE_DAYS {
+        return None;
+    }
+    let n = excess.len() as f64;
+    let mean = excess.iter().sum::<f64>() / n;
+    let std_dev = (excess.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+    (std_dev > 0.0).then(|| mean / std_dev * TRADING_DAYS_PER_YEAR.sqrt())
+}
+
+/// Pearson correlation of the period-to-period returns of each pair of price
+/// series. Longer series are cut to the most recent values of the shortest one;
+/// a series with constant returns has zero correlation with the others.
+pub fn compute_correlation(prices: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let len = prices.iter().map(Vec::len).min().unwrap_or(0);
+    let returns: Vec<Vec<f64>> = prices
+        .iter()
+        .map(|series| {
+            series[series.len() - len..]
+                .windows(2)
+                .map(|w| if w[0] != 0.0 { w[1] / w[0] - 1.0 } else { 0.0 })
+                .collect()
+        })
+        .collect();
+
+    let n = returns.len();
+    let mut matrix = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        matrix[i][i] = 1.0;
+        for j in 0..i {
+            let r = pearson(&returns[i], &returns[j]);
+            matrix[i][j] = r;
+            matrix[j][i] = r;
+        }
+    }
+    matrix
+}
+
+/// Portfolio variance over the average variance of its assets, taking every asset
+/// at unit variance so only the correlations matter: `wᵀCw` with the weights
+/// normalized to sum to 1. 1.0 means the assets move as one; uncorrelated equal
+/// weights give 1/N. Negative weights count by size; no weights give 1.0.
+pub fn diversification_score(weights: &[f64], corr_matrix: &[Vec<f64>]) -> f64 {
+    let total: f64 = weights.iter().map(|w| w.abs()).sum();
+    if total <= 0.0 {
+        return 1.0;
+    }
+    let w: Vec<f64> = weights.iter().map(|w| w.abs() / total).collect();
+    let mut variance = 0.0;
+    for (i, row) in corr_matrix.iter().enumerate().take(w.len()) {
+        for (j, corr) in row.iter().enumerate().take(w.len()) {
+            variance += w[i] * w[j] * corr;
+        }
+    }
+    variance.clamp(0.0, 1.0)
+}
+
+/// Daily returns of a portfolio holding `target`, from the closes of `symbols`
+/// (oldest first, as in `BackendMessage::PriceHistory`): each asset's return
+/// times its target share and leverage, summed. USDT returns nothing.
+pub fn portfolio_returns(
+    symbols: &[String],
+    prices: &[Vec<f64>],
+    target: &PortfolioAllocation,
+) -> Vec<f64> {
+    let len = prices.iter().map(Vec::len).min().unwrap_or(0);
+    let mut returns = vec![0.0; len.saturating_sub(1)];
+    for (symbol, series) in symbols.iter().zip(prices) {
+        let Some(asset) = target.assets.iter().find(|a| a.symbol == *symbol) else {
+            continue;
+        };
+        let weight = asset.target_pct / 100.0 * asset.leverage;
+        for (total, w) in returns
+            .iter_mut()
+            .zip(series[series.len() - len..].windows(2))
+        {
+            if w[0] != 0.0 {
+                *total += weight * (w[1] / w[0] - 1.0);
+            }
+        }
+    }
+    returns
+}
+
+/// 100 minus the percentage points by which each asset drifts beyond its
+/// `threshold_for`, clamped to 0..=100. Current shares are computed like `planner::compute_plan`
+/// (margin × leverage over the total margin); held contracts missing from the
+/// target count as 0% targets, and USDT is left out since it is the remainder.
+pub fn portfolio_health_score(
+    current: &Balances,
+    target: &PortfolioAllocation,
+    threshold_for: impl Fn(&str) -> f64,
+) -> u8 {
+    let total: f64 = current.values().sum();
+    if total <= 0.0 {
+        return 100;
+    }
+    let current_pct = |symbol: &str, leverage: f64| {
+        current.get(symbol).copied().unwrap_or(0.0) * leverage / total * 100.0
+    };
+    let tracked = target.assets.iter().map(|a| {
+        (
+            a.symbol.as_str(),
+            current_pct(&a.symbol, a.leverage),
+            a.target_pct,
+        )
+    });
+    let untracked = current
+        .keys()
+        .filter(|s| *s != USDT && target.get(s).is_none())
+        .map(|s| (s.as_str(), current_pct(s, DEFAULT_LEVERAGE), 0.0));
+    let penalty: f64 = tracked
+        .chain(untracked)
+        .map(|(symbol, actual, wanted)| ((actual - wanted).abs() - threshold_for(symbol)).max(0.0))
+        .sum();
+    (100.0 - penalty).clamp(0.0, 100.0).round() as u8
+}
+
+/// Funding paid per day over three 8-hour periods: position size (margin ×
+/// leverage) times the absolute rate, summed over the held contracts.
+pub fn funding_cost_daily_usdt(
+    current: &Balances,
+    target: &PortfolioAllocation,
+    funding_rates: &HashMap<String, f64>,
+) -> f64 {
+    current
+        .iter()
+        .filter(|(symbol, _)| *symbol != USDT)
+        .filter_map(|(symbol, margin)| {
+            let rate = funding_rates.get(symbol)?;
+            Some(margin * leverage_of(target, symbol) * rate.abs() * 3.0)
+        })
+        .sum()
+}
+
+/// Free margin as a percentage of the total position value (margin × leverage),
+/// from a snapshot's allocation percentages; `None` without open positions.
+pub fn margin_ratio_pct(
+    allocations: &HashMap<String, f64>,
+    target: &PortfolioAllocation,
+) -> Option<f64> {
+    let position_value: f64 = allocations
+        .iter()
+        .filter(|(symbol, _)| *symbol != USDT)
+        .map(|(symbol, pct)| pct * leverage_of(target, symbol))
+        .sum();
+    if position_value <= 0.0 {
+        return None;
+    }
+    let available = allocations.get(USDT).copied().unwrap_or(0.0);
+    Some(available / position_value * 100.0)
+}
+
+/// Equal-weight targets: 100% split evenly between the assets.
+pub fn equal_weight_targets(allocation: &PortfolioAllocation) -> PortfolioAllocation {
+    let share = 100.0 / allocation.assets.len().max(1) as f64;
+    let mut targets = allocation.clone();
+    for asset in &mut targets.assets {
+        asset.target_pct = share;
+    }
+    targets
+}
+
+/// Market-cap targets: the USDT reserve of `allocation` is kept and the rest is
+/// split in proportion to `market_caps`. Assets without a known cap get nothing.
+pub fn market_cap_targets(
+    allocation: &PortfolioAllocation,
+    market_caps: &HashMap<String, f64>,
+) -> PortfolioAllocation {
+    let invested_pct = 100.0 - allocation.usdt();
+    let cap_of = |symbol: &str| market_caps.get(symbol).copied().unwrap_or(0.0).max(0.0);
+    let total_cap: f64 = allocation.assets.iter().map(|a| cap_of(&a.symbol)).sum();
+    let mut targets = allocation.clone();
+    for asset in &mut targets.assets {
+        asset.target_pct = if total_cap > 0.0 {
+            invested_pct * cap_of(&asset.symbol) / total_cap
+        } else {
+            0.0
+        };
+    }
+    targets
+}
+
+/// Inverse-volatility weights `(1/σᵢ) / Σ(1/σⱼ)`, summing to 1, so that each
+/// asset contributes the same volatility. Assets without a positive volatility
+/// are left out.
+pub fn risk_parity_weights(vol_estimates: &HashMap<String, f64>) -> HashMap<String, f64> {
+    let inverse_total: f64 = vol_estimates
+        .values()
+        .filter(|&&vol| vol > 0.0)
+        .map(|vol| 1.0 / vol)
+        .sum();
+    vol_estimates
+        .iter()
+        .filter(|(_, &vol)| vol > 0.0)
+        .map(|(symbol, vol)| (symbol.clone(), 1.0 / vol / inverse_total))
+        .collect()
+}
+
+/// Annualised volatility of daily closes (oldest first): the sample standard
+/// deviation of the daily returns times √365, crypto trading every day.
+pub fn annualised_volatility(closes: &[f64]) -> Option<f64> {
+    let returns: Vec<f64> = closes
+        .windows(2)
+        .filter(|w| w[0] != 0.0)
+        .map(|w| w[1] / w[0] - 1.0)
+        .collect();
+    if returns.len() < 2 {
+        return None;
+    }
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Some(variance.sqrt() * 365f64.sqrt())
+}
+
+/// Risk parity targets: the assets of `allocation` weighted by
+/// `risk_parity_weights`, scaled down so the portfolio volatility stays under
+/// `target_volatility`. Correlations are taken as 1, which makes the volatility
+/// of the exposure `Σ wᵢ × leverageᵢ × σᵢ` an upper bound. USDT keeps the rest.
+pub fn risk_parity_targets(
+    allocation: &PortfolioAllocation,
+    volatilities: &HashMap<String, f64>,
+    target_volatility: f64,
+) -> PortfolioAllocation {
+    let vols: HashMap<String, f64> = allocation
+        .assets
+        .iter()
+        .filter_map(|a| Some((a.symbol.clone(), *volatilities.get(&a.symbol)?)))
+        .collect();
+    let weights = risk_parity_weights(&vols);
+    let weight_of = |symbol: &str| weights.get(symbol).copied().unwrap_or(0.0);
+    let full_volatility: f64 = allocation
+        .assets
+        .iter()
+        .map(|a| weight_of(&a.symbol) * a.leverage * vols.get(&a.symbol).unwrap_or(&0.0))
+        .sum();
+    let invested = if full_volatility > 0.0 {
+        (target_volatility.max(0.0) / full_volatility).min(1.0)
+    } else {
+        1.0
+    };
+    let mut targets = allocation.clone();
+    for asset in &mut targets.assets {
+        asset.target_pct = 100.0 * invested * weight_of(&asset.symbol);
+    }
+    targets
+}
+
+/// Kelly criterion bet `p − (1 − p) / b` as a fraction of the bankroll, scaled
+/// by `fraction` (1.0 = full Kelly, 0.5 = half Kelly). Zero without an edge.
+pub fn kelly_size(win_rate: f64, win_loss_ratio: f64, fraction: f64) -> f64 {
+    if win_loss_ratio <= 0.0 {
+        return 0.0;
+    }
+    let full_kelly = win_rate - (1.0 - win_rate) / win_loss_ratio;
+    (full_kelly * fraction).max(0.0)
+}
+
+/// Targets under Kelly sizing: the Kelly share of the portfolio, at most all of
+/// it, split between the assets in proportion to their fixed targets, which are
+/// normalized to sum to 100%. USDT keeps the rest.
+pub fn kelly_targets(allocation: &PortfolioAllocation, kelly: f64) -> PortfolioAllocation {
+    let weight_total = allocation.crypto_total();
+    let invested_pct = kelly.clamp(0.0, 1.0) * 100.0;
+    let mut targets = allocation.clone();
+    for asset in &mut targets.assets {
+        asset.target_pct = if weight_total > 0.0 {
+            invested_pct * asset.target_pct / weight_total
+        } else {
+            0.0
+        };
+    }
+    targets
+}
+
+// Leverage the backend trades `symbol` at; untracked contracts use the default
+fn leverage_of(target: &PortfolioAllocation, symbol: &str) -> f64 {
+    target
+        .assets
+        .iter()
+        .find(|a| a.symbol == symbol)
+        .map_or(DEFAULT_LEVERAGE, |a| a.leverage)
+}
+
+fn pearson(a: &[f64], b: &[f64]) -> f64 {
+    if a.len() < 2 {
+        return 0.0;
+    }
+    let n = a.len() as f64;
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+    if var_a > 0.0 && var_b > 0.0 {
+        cov / (var_a * var_b).sqrt()
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AssetAllocation;
+    use chrono::{Duration, TimeZone};
+
+    fn event(seconds: i64, value: f64) -> RebalanceEvent {
+        RebalanceEvent {
+            timestamp: Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+                + Duration::seconds(seconds),
+            asset: "BTC_USDT".to_string(),
+            from_pct: 30.0,
+            to_pct: 25.0,
+            usdt_traded: 10.0,
+            order_id: seconds.to_string(),
+            portfolio_value_usdt: value,
+            fee_usdt: 0.0,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn known_sequence_over_one_year() {
+        let year = SECONDS_PER_YEAR as i64;
+        // 100 -> 110 -> 99 -> 121, the last value exactly one year after the first
+        let mut events = vec![
+            event(0, 100.0),
+            event(60, 100.0), // second trade of the same run
+            event(year / 3, 110.0),
+            event(2 * year / 3, 99.0),
+            event(year, 121.0),
+        ];
+        events[0].fee_usdt = 0.25;
+        events[3].fee_usdt = 0.5;
+        let metrics = compute(&events);
+        assert_close(metrics.total_fees_paid_usdt, 0.75);
+        assert_close(metrics.total_return_pct, 21.0);
+        assert_close(metrics.annualised_return_pct, 21.0);
+        assert_close(metrics.max_drawdown_pct, 10.0);
+        assert_close(metrics.win_rate_pct, 200.0 / 3.0);
+        // returns 0.1, -0.1, 0.2222: mean / sample std * sqrt(3 periods per year)
+        assert_close(metrics.sharpe_ratio, 0.788_723_006_7);
+    }
+
+    #[test]
+    fn sharpe_of_alternating_daily_returns() {
+        // +3% and -1% on alternate days: mean 1%, sample deviation 2% × √(n / (n - 1))
+        let day = 24 * 60 * 60;
+        let mut value = 1000.0;
+        let mut events = vec![event(0, value)];
+        for i in 1..=60 {
+            value *= if i % 2 == 1 { 1.03 } else { 0.99 };
+            events.push(event(i * day, value));
+        }
+        let risk_free = 0.0252;
+        let std_dev = 0.02 * (60.0f64 / 59.0).sqrt();
+        let expected = (0.01 - risk_free / 252.0) / std_dev * 252f64.sqrt();
+        assert_close(estimate_sharpe(&events, risk_free).unwrap(), expected);
+
+        // 29 daily returns are not enough
+        assert_eq!(estimate_sharpe(&events[..30], risk_free), None);
+        assert_eq!(estimate_sharpe(&[], risk_free), None);
+    }
+
+    #[test]
+    fn too_few_events_give_zeroes() {
+        assert_eq!(compute(&[]), PerformanceMetrics::default());
+        assert_eq!(
+            compute(&[event(0, 100.0), event(10, 0.0)]),
+            PerformanceMetrics::default()
+        );
+    }
+
+    #[test]
+    fn drawdown_from_peak() {
+        assert_close(drawdown_pct(200.0, 150.0), 25.0);
+        assert_eq!(drawdown_pct(200.0, 250.0), 0.0);
+        assert_eq!(drawdown_pct(0.0, 10.0), 0.0);
+    }
+
+    fn allocation(targets: &[(&str, f64)]) -> PortfolioAllocation {
+        PortfolioAllocation {
+            assets: targets
+                .iter()
+                .map(|(symbol, pct)| AssetAllocation::new(*symbol, *pct))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn health_score_of_balanced_and_drifted_portfolios() {
+        let target = allocation(&[("BTC_USDT", 30.0), ("ETH_USDT", 15.0)]);
+        // Margin × 3 leverage: BTC 30%, ETH 15%, USDT the rest
+        let on_target = Balances::from([
+            ("BTC_USDT".to_string(), 10.0),
+            ("ETH_USDT".to_string(), 5.0),
+            (USDT.to_string(), 85.0),
+        ]);
+        assert_eq!(portfolio_health_score(&on_target, &target, |_| 5.0), 100);
+        assert_eq!(
+            portfolio_health_score(&Balances::new(), &target, |_| 5.0),
+            100
+        );
+
+        // BTC at 60%: 30 points off, 25 beyond the threshold
+        let drifted = Balances::from([
+            ("BTC_USDT".to_string(), 20.0),
+            ("ETH_USDT".to_string(), 5.0),
+            (USDT.to_string(), 75.0),
+        ]);
+        assert_eq!(portfolio_health_score(&drifted, &target, |_| 5.0), 75);
+        // A wider threshold for BTC alone: 10 points beyond it
+        let btc_wide = |symbol: &str| if symbol == "BTC_USDT" { 20.0 } else { 5.0 };
+        assert_eq!(portfolio_health_score(&drifted, &target, btc_wide), 90);
+
+        // Everything in an untracked contract: far beyond any threshold
+        let wild = Balances::from([("DOGE_USDT".to_string(), 100.0)]);
+        assert_eq!(portfolio_health_score(&wild, &target, |_| 5.0), 0);
+    }
+
+    #[test]
+    fn funding_cost_over_three_periods() {
+        let target = allocation(&[("BTC_USDT", 30.0)]);
+        let balances = Balances::from([
+            ("BTC_USDT".to_string(), 100.0),
+            ("ETH_USDT".to_string(), 50.0),
+            (USDT.to_string(), 850.0),
+        ]);
+        let rates = HashMap::from([
+            ("BTC_USDT".to_string(), 0.0001),
+            ("ETH_USDT".to_string(), -0.0002),
+        ]);
+        // BTC 300 × 0.0001 × 3 + ETH 150 (default leverage) × 0.0002 × 3
+        let expected = 300.0 * 0.0001 * 3.0 + 50.0 * DEFAULT_LEVERAGE * 0.0002 * 3.0;
+        assert_close(
+            funding_cost_daily_usdt(&balances, &target, &rates),
+            expected,
+        );
+        assert_eq!(
+            funding_cost_daily_usdt(&balances, &target, &HashMap::new()),
+            0.0
+        );
+    }
+
+    #[test]
+    fn margin_ratio_of_snapshot_allocations() {
+        let target = allocation(&[("BTC_USDT", 30.0)]);
+        // 10% margin at 3x and 5% untracked at the default: 45% in positions, 85% free
+        let allocations = HashMap::from([
+            ("BTC_USDT".to_string(), 10.0),
+            ("ETH_USDT".to_string(), 5.0),
+            (USDT.to_string(), 85.0),
+        ]);
+        let expected = 85.0 / (10.0 * 3.0 + 5.0 * DEFAULT_LEVERAGE) * 100.0;
+        assert_close(margin_ratio_pct(&allocations, &target).unwrap(), expected);
+        let cash_only = HashMap::from([(USDT.to_string(), 100.0)]);
+        assert_eq!(margin_ratio_pct(&cash_only, &target), None);
+    }
+
+    #[test]
+    fn equal_and_market_cap_weights() {
+        // 70% in assets, 30% USDT reserve
+        let custom = allocation(&[("BTC_USDT", 40.0), ("ETH_USDT", 20.0), ("LTC_USDT", 10.0)]);
+        let equal = equal_weight_targets(&custom);
+        assert!(equal
+            .assets
+            .iter()
+            .all(|a| (a.target_pct - 100.0 / 3.0).abs() < 1e-9));
+
+        let caps = HashMap::from([
+            ("BTC_USDT".to_string(), 1200.0),
+            ("ETH_USDT".to_string(), 400.0),
+        ]);
+        let by_cap = market_cap_targets(&custom, &caps);
+        assert_close(by_cap.get("BTC_USDT").unwrap(), 52.5);
+        assert_close(by_cap.get("ETH_USDT").unwrap(), 17.5);
+        assert_close(by_cap.get("LTC_USDT").unwrap(), 0.0);
+        assert_close(by_cap.usdt(), 30.0);
+    }
+
+    #[test]
+    fn risk_parity_weights_are_inverse_volatility() {
+        let vols = |pairs: &[(&str, f64)]| -> HashMap<String, f64> {
+            pairs.iter().map(|&(s, v)| (s.to_string(), v)).collect()
+        };
+        // 1/0.2 : 1/0.4 = 2 : 1
+        let weights = risk_parity_weights(&vols(&[("BTC_USDT", 0.2), ("ETH_USDT", 0.4)]));
+        assert_close(weights["BTC_USDT"], 2.0 / 3.0);
+        assert_close(weights["ETH_USDT"], 1.0 / 3.0);
+        // 10 : 5 : 2.5 = 4 : 2 : 1
+        let weights = risk_parity_weights(&vols(&[
+            ("BTC_USDT", 0.1),
+            ("ETH_USDT", 0.2),
+            ("LTC_USDT", 0.4),
+            ("DOGE_USDT", 0.0),
+        ]));
+        assert_close(weights["BTC_USDT"], 4.0 / 7.0);
+        assert_close(weights["ETH_USDT"], 2.0 / 7.0);
+        assert_close(weights["LTC_USDT"], 1.0 / 7.0);
+        assert!(!weights.contains_key("DOGE_USDT"));
+
+        // Weights of 2/3 and 1/3 at 3x carry 3 × (2/3 × 0.2 + 1/3 × 0.4) = 0.8
+        // of volatility; a 0.4 target halves the exposure
+        let custom = allocation(&[("BTC_USDT", 50.0), ("ETH_USDT", 20.0)]);
+        let vols = vols(&[("BTC_USDT", 0.2), ("ETH_USDT", 0.4)]);
+        let targets = risk_parity_targets(&custom, &vols, 0.4);
+        assert_close(targets.get("BTC_USDT").unwrap(), 100.0 / 3.0);
+        assert_close(targets.get("ETH_USDT").unwrap(), 50.0 / 3.0);
+        assert_close(targets.usdt(), 50.0);
+        let uncapped = risk_parity_targets(&custom, &vols, 2.0);
+        assert_close(uncapped.crypto_total(), 100.0);
+    }
+
+    #[test]
+    fn volatility_of_alternating_returns() {
+        // +10%, −10%, +10% around a mean of 1/30: squared deviations sum to
+        // 0.24/9, over n − 1 = 2 gives a variance of 0.04/3
+        let closes = [100.0, 110.0, 99.0, 108.9];
+        let expected = (0.04f64 / 3.0).sqrt();
+        assert_close(
+            annualised_volatility(&closes).unwrap(),
+            expected * 365f64.sqrt(),
+        );
+        assert_eq!(annualised_volatility(&[100.0, 110.0]), None);
+    }
+
+    #[test]
+    fn kelly_size_and_targets() {
+        // 60% wins at even odds: 0.6 − 0.4 / 1
+        assert_close(kelly_size(0.6, 1.0, 1.0), 0.2);
+        assert_close(kelly_size(0.6, 1.0, 0.5), 0.1);
+        // Wins twice the size of losses: 0.55 − 0.45 / 2
+        assert_close(kelly_size(0.55, 2.0, 1.0), 0.325);
+        assert_eq!(kelly_size(0.4, 1.0, 1.0), 0.0);
+        assert_eq!(kelly_size(0.9, 0.0, 1.0), 0.0);
+
+        // 80% invested, split 3:1 like the fixed targets
+        let targets = kelly_targets(&allocation(&[("BTC_USDT", 30.0), ("ETH_USDT", 10.0)]), 0.8);
+        assert_close(targets.get("BTC_USDT").unwrap(), 60.0);
+        assert_close(targets.get("ETH_USDT").unwrap(), 20.0);
+        assert_close(targets.usdt(), 20.0);
+        let capped = kelly_targets(&allocation(&[("BTC_USDT", 30.0)]), 1.5);
+        assert_close(capped.get("BTC_USDT").unwrap(), 100.0);
+    }
+
+    #[test]
+    fn diversification_of_correlated_and_uncorrelated_assets() {
+        let correlated = vec![vec![1.0, 1.0], vec![1.0, 1.0]];
+        assert_close(diversification_score(&[0.7, 0.3], &correlated), 1.0);
+        let uncorrelated = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+        assert_close(diversification_score(&[30.0, 30.0], &uncorrelated), 0.5);
+        // 0.75² + 0.25²
+        assert_close(diversification_score(&[3.0, 1.0], &uncorrelated), 0.625);
+        assert_close(diversification_score(&[0.0, 0.0], &uncorrelated), 1.0);
+    }
+
+    #[test]
+    fn portfolio_returns_weigh_assets_by_exposure() {
+        let symbols = ["BTC_USDT", "ETH_USDT"].map(String::from);
+        // ETH has one more close than BTC, so its oldest one is left out
+        let prices = vec![vec![100.0, 110.0, 99.0], vec![50.0, 10.0, 10.0, 12.0]];
+        let target = allocation(&[("BTC_USDT", 20.0), ("ETH_USDT", 10.0)]);
+        // 20% at 3x of +10% and 10% at 3x of +0%, then −10% and +20%
+        let returns = portfolio_returns(&symbols, &prices, &target);
+        assert_eq!(returns.len(), 2);
+        assert_close(returns[0], 0.06);
+        assert_close(returns[1], -0.06 + 0.06);
+    }
+
+    #[test]
+    fn correlation_of_synthetic_series() {
+        let base = vec![100.0, 110.0, 99.0, 120.0, 114.0];
+        // Same returns at a different price level, and the base mirrored around 200
+        let scaled: Vec<f64> = base.iter().map(|p| p * 3.0).collect();
+        let mirrored: Vec<f64> = base.iter().map(|p| 200.0 - p).collect();
+        let flat = vec![50.0; 5];
+        let matrix = compute_correlation(&[base, scaled, mirrored, flat]);
+
+        assert_close(matrix[0][0], 1.0);
+        assert_close(matrix[0][1], 1.0);
+        assert!(matrix[0][2] < -0.9, "{}", matrix[0][2]);
+        assert_close(matrix[2][0], matrix[0][2]);
+        assert_close(matrix[0][3], 0.0);
+        assert_close(matrix[3][3], 1.0);
+    }
+
+    #[test]
+    fn correlation_uses_the_common_tail() {
+        let long = vec![1.0, 1000.0, 100.0, 110.0, 121.0, 100.0];
+        let short = vec![10.0, 11.0, 12.1, 10.0];
+        let matrix = compute_correlation(&[long, short]);
+        assert_close(matrix[0][1], 1.0);
+        assert!(compute_correlation(&[]).is_empty());
+    }
+}
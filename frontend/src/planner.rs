@@ -0,0 +1,285 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::config::{AssetAllocation, Config, MarketData, USDT};
+use crate::exchange::{Balances, OrderSide};
+
+// 与后端 _calculate_rebalance_amounts 一致：保证金调整低于10 USDT的资产跳过
+const MIN_ADJUSTMENT_USDT: f64 = 10.0;
+
+/// `ProposedTrade::reason` of trades skipped for being under `Config::min_trade_usdt`.
+pub const BELOW_MIN_TRADE: &str = "below_min_trade";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ProposedTrade {
+    pub symbol: String,
+    pub side: OrderSide,
+    // Position value to trade (margin change × leverage)
+    pub notional_usdt: f64,
+    // Taker fee of the market order; the backend's plans leave it out
+    #[serde(default)]
+    pub estimated_fee_usdt: f64,
+    // Fill away from the mark price under `Config::slippage_model`
+    #[serde(default)]
+    pub estimated_slippage_usdt: f64,
+    pub reason: String,
+}
+
+impl ProposedTrade {
+    /// Base-coin quantity at `price`, as passed to `Exchange::place_order`.
+    pub fn quantity(&self, price: f64) -> f64 {
+        self.notional_usdt / price
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct RebalancePlan {
+    pub trades: Vec<ProposedTrade>,
+    // Too small to place; shown in the preview but never executed
+    #[serde(default)]
+    pub skipped: Vec<ProposedTrade>,
+}
+
+impl RebalancePlan {
+    pub fn is_empty(&self) -> bool {
+        self.trades.is_empty()
+    }
+
+    /// Fees of the trades that would be placed, skipped ones excluded.
+    pub fn total_fees_usdt(&self) -> f64 {
+        self.trades.iter().map(|t| t.estimated_fee_usdt).sum()
+    }
+
+    /// Slippage of the trades that would be placed, skipped ones excluded.
+    pub fn total_slippage_usdt(&self) -> f64 {
+        self.trades.iter().map(|t| t.estimated_slippage_usdt).sum()
+    }
+}
+
+/// The trades a threshold rebalance would place right now, without placing them.
+///
+/// `current` holds the margin per contract plus free `USDT`, as returned by
+/// `Exchange::get_balances`. A target percentage is the position's share of the
+/// portfolio value, so it needs `value × target_pct / 100 / leverage` of margin.
+/// Like the Python backend, nothing is traded unless at least one asset drifts more
+/// than `config.threshold_for` it percentage points from its target; then every
+/// asset whose margin is off by at least `MIN_ADJUSTMENT_USDT` is adjusted. Assets
+/// without a positive price are left out because no order size can be computed,
+/// and trades under `config.min_trade_usdt` go to `RebalancePlan::skipped`.
+/// Orders are placed at market, so fees are estimated at `config.taker_fee_rate`.
+/// Targets come from `Config::target_allocation` with the given `market_data`.
+pub fn compute_plan(
+    current: &Balances,
+    config: &Config,
+    prices: &HashMap<String, f64>,
+    market_data: &MarketData,
+) -> RebalancePlan {
+    let target = &config.target_allocation(market_data);
+    let total: f64 = current.values().sum();
+    if total <= 0.0 {
+        return RebalancePlan::default();
+    }
+
+    // Held contracts missing from the target are sold off (target 0%)
+    let mut assets: Vec<AssetAllocation> = target.assets.clone();
+    let mut held: Vec<&str> = current
+        .keys()
+        .map(String::as_str)
+        .filter(|s| *s != USDT && target.get(s).is_none())
+        .collect();
+    held.sort_unstable();
+    assets.extend(
+        held.into_iter()
+            .map(|symbol| AssetAllocation::new(symbol, 0.0)),
+    );
+
+    let rows: Vec<(AssetAllocation, f64, f64)> = assets
+        .into_iter()
+        .map(|asset| {
+            let margin = current.get(&asset.symbol).copied().unwrap_or(0.0);
+            let current_pct = margin * asset.leverage / total * 100.0;
+            (asset, margin, current_pct)
+        })
+        .collect();
+    if rows.iter().all(|(asset, _, current_pct)| {
+        (current_pct - asset.target_pct).abs() <= config.threshold_for(&asset.symbol)
+    }) {
+        return RebalancePlan::default();
+    }
+
+    let (trades, mut skipped): (Vec<_>, Vec<_>) = rows
+        .into_iter()
+        .filter_map(|(asset, margin, current_pct)| {
+            let required_margin = (total * asset.target_pct / 100.0) / asset.leverage;
+            let margin_diff = required_margin - margin;
+            let price = prices.get(&asset.symbol).copied().unwrap_or(0.0);
+            if margin_diff.abs() < MIN_ADJUSTMENT_USDT || price <= 0.0 {
+                return None;
+            }
+            let side = if margin_diff > 0.0 {
+                OrderSide::Buy
+            } else {
+                OrderSide::Sell
+            };
+            let notional_usdt = margin_diff.abs() * asset.leverage;
+            Some(ProposedTrade {
+                side,
+                notional_usdt,
+                estimated_fee_usdt: notional_usdt * config.taker_fee_rate,
+                estimated_slippage_usdt: config.slippage_model.estimate(notional_usdt),
+                reason: format!(
+                    "{:.1}% vs target {:.1}% ({:+.1}%), margin {:.2} -> {:.2} USDT at {}x",
+                    current_pct,
+                    asset.target_pct,
+                    current_pct - asset.target_pct,
+                    margin,
+                    required_margin,
+                    asset.leverage
+                ),
+                symbol: asset.symbol,
+            })
+        })
+        .partition(|trade| trade.notional_usdt >= config.min_trade_usdt);
+    for trade in &mut skipped {
+        trade.reason = BELOW_MIN_TRADE.to_string();
+    }
+    RebalancePlan { trades, skipped }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::PortfolioAllocation;
+
+    // 5% global threshold
+    fn config() -> Config {
+        Config {
+            portfolio_allocation: PortfolioAllocation {
+                assets: vec![
+                    AssetAllocation::new("BTC_USDT", 30.0),
+                    AssetAllocation::new("ETH_USDT", 20.0),
+                ],
+            },
+            ..Config::default()
+        }
+    }
+
+    fn balances(entries: &[(&str, f64)]) -> Balances {
+        entries.iter().map(|(s, v)| (s.to_string(), *v)).collect()
+    }
+
+    fn prices() -> HashMap<String, f64> {
+        balances(&[("BTC_USDT", 60000.0), ("ETH_USDT", 3000.0)])
+    }
+
+    #[test]
+    fn no_trades_within_threshold() {
+        // 3x: 105 margin is 31.5% of 1000, 65 is 19.5%
+        let current = balances(&[("BTC_USDT", 105.0), ("ETH_USDT", 65.0), (USDT, 830.0)]);
+        assert!(compute_plan(&current, &config(), &prices(), &MarketData::default()).is_empty());
+    }
+
+    #[test]
+    fn no_trades_for_an_empty_account() {
+        assert!(compute_plan(
+            &Balances::new(),
+            &config(),
+            &prices(),
+            &MarketData::default()
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn trades_every_asset_once_one_breaches_the_threshold() {
+        let current = balances(&[("BTC_USDT", 150.0), ("ETH_USDT", 50.0), (USDT, 800.0)]);
+        let plan = compute_plan(&current, &config(), &prices(), &MarketData::default());
+        assert_eq!(plan.trades.len(), 2);
+        // 45% held vs 30% target: margin 150 -> 100
+        let btc = &plan.trades[0];
+        assert_eq!(
+            (btc.symbol.as_str(), btc.side),
+            ("BTC_USDT", OrderSide::Sell)
+        );
+        assert!((btc.notional_usdt - 150.0).abs() < 1e-9);
+        assert!((btc.quantity(60000.0) - 0.0025).abs() < 1e-12);
+        assert!(btc.reason.contains("at 3x"));
+        // 15% held vs 20% target is within the threshold but still topped up
+        let eth = &plan.trades[1];
+        assert_eq!(
+            (eth.symbol.as_str(), eth.side),
+            ("ETH_USDT", OrderSide::Buy)
+        );
+        assert!((eth.notional_usdt - 50.0).abs() < 1e-9);
+        // Gate.io's 0.075% taker fee on 200 USDT traded
+        assert!((plan.total_fees_usdt() - 0.15).abs() < 1e-9);
+    }
+
+    #[test]
+    fn per_asset_threshold_overrides_the_global_one() {
+        // BTC drifts 15%, ETH 5%
+        let current = balances(&[("BTC_USDT", 150.0), ("ETH_USDT", 50.0), (USDT, 800.0)]);
+        let mut config = config();
+        config.per_asset_threshold = Some([("BTC_USDT".to_string(), 20.0)].into());
+        assert!(compute_plan(&current, &config, &prices(), &MarketData::default()).is_empty());
+
+        config.per_asset_threshold = Some(
+            [
+                ("BTC_USDT".to_string(), 20.0),
+                ("ETH_USDT".to_string(), 1.0),
+            ]
+            .into(),
+        );
+        let plan = compute_plan(&current, &config, &prices(), &MarketData::default());
+        assert_eq!(plan.trades.len(), 2);
+    }
+
+    #[test]
+    fn skips_trades_below_the_minimum_size() {
+        let current = balances(&[("BTC_USDT", 150.0), ("ETH_USDT", 50.0), (USDT, 800.0)]);
+        let mut config = config();
+        config.min_trade_usdt = 100.0;
+        let plan = compute_plan(&current, &config, &prices(), &MarketData::default());
+        assert_eq!(plan.trades.len(), 1);
+        assert_eq!(plan.trades[0].symbol, "BTC_USDT");
+        assert_eq!(plan.skipped.len(), 1);
+        assert_eq!(plan.skipped[0].symbol, "ETH_USDT");
+        assert_eq!(plan.skipped[0].reason, BELOW_MIN_TRADE);
+    }
+
+    #[test]
+    fn margin_scales_with_leverage() {
+        let mut config = config();
+        config.portfolio_allocation.assets[0].leverage = 1.0;
+        let current = balances(&[
+            ("BTC_USDT", 100.0),
+            ("ETH_USDT", 200.0 / 3.0),
+            (USDT, 2500.0 / 3.0),
+        ]);
+        let plan = compute_plan(&current, &config, &prices(), &MarketData::default());
+        // Unlevered BTC needs 300 margin for its 30%
+        assert_eq!(plan.trades.len(), 1);
+        assert_eq!(plan.trades[0].side, OrderSide::Buy);
+        assert!((plan.trades[0].notional_usdt - 200.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sells_untargeted_positions_and_skips_unpriced_ones() {
+        let current = balances(&[
+            ("BTC_USDT", 100.0),
+            ("ETH_USDT", 200.0 / 3.0),
+            ("LTC_USDT", 100.0),
+            (USDT, 2200.0 / 3.0),
+        ]);
+        let plan = compute_plan(&current, &config(), &prices(), &MarketData::default());
+        assert!(plan.is_empty());
+
+        let mut with_ltc = prices();
+        with_ltc.insert("LTC_USDT".to_string(), 80.0);
+        let plan = compute_plan(&current, &config(), &with_ltc, &MarketData::default());
+        assert_eq!(plan.trades.len(), 1);
+        assert_eq!(plan.trades[0].symbol, "LTC_USDT");
+        assert_eq!(plan.trades[0].side, OrderSide::Sell);
+    }
+}
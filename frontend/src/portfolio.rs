@@ -0,0 +1,321 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+
+use crate::backend::{self, BackendEndpoint};
+use crate::config::{Config, ConfigFormat};
+use crate::error::KinError;
+use crate::protocol::BackendMessage;
+
+/// Name of the portfolio kept in the config file the app was started with.
+pub const MAIN_PORTFOLIO: &str = "Main";
+
+/// What the backend of a portfolio is doing.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum BackendStatus {
+    #[default]
+    Stopped,
+    Running,
+    // Exit status or error, as shown in the sidebar
+    Exited(String),
+}
+
+impl BackendStatus {
+    pub fn label(&self) -> String {
+        match self {
+            BackendStatus::Stopped => "Stopped".to_string(),
+            BackendStatus::Running => "Running".to_string(),
+            BackendStatus::Exited(status) => format!("Exited ({})", status),
+        }
+    }
+}
+
+/// A portfolio with its own config file and backend process.
+pub struct Portfolio {
+    pub name: String,
+    pub config: Config,
+    pub config_path: PathBuf,
+    // Encrypted with a passphrase other than the app's: listed so the slots
+    // after it keep their endpoints, but it cannot be opened
+    pub locked: bool,
+    // Only held here while another portfolio is on screen; the app owns the
+    // process of the selected one
+    pub process: Option<Child>,
+    pub status: BackendStatus,
+    // Channel the process's output readers send to, kept alive with `process`
+    // so the backend never blocks on a full pipe
+    pub output: Option<(Sender<BackendMessage>, Receiver<BackendMessage>)>,
+}
+
+impl Portfolio {
+    fn new(name: String, config: Config, config_path: PathBuf) -> Self {
+        Self {
+            name,
+            config,
+            config_path,
+            locked: false,
+            process: None,
+            status: BackendStatus::Stopped,
+            output: None,
+        }
+    }
+
+    /// Drains the output of a detached backend, returning its log lines, and
+    /// notices when it exited.
+    pub fn poll_detached(&mut self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some((_, receiver)) = &self.output {
+            loop {
+                match receiver.try_recv() {
+                    Ok(BackendMessage::Log { line }) => lines.push(line),
+                    Ok(_) => {}
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.output = None;
+                        break;
+                    }
+                }
+            }
+        }
+        if let Some(child) = &mut self.process {
+            match child.try_wait() {
+                Ok(None) => {}
+                Ok(Some(status)) => {
+                    self.status = BackendStatus::Exited(status.to_string());
+                    self.process = None;
+                    self.output = None;
+                }
+                Err(e) => {
+                    self.status = BackendStatus::Exited(e.to_string());
+                    self.process = None;
+                    self.output = None;
+                }
+            }
+        }
+        lines
+    }
+
+    /// Shuts the detached backend down, waiting for it to exit.
+    pub fn stop(&mut self) {
+        if let Some(mut child) = self.process.take() {
+            if let Err(e) = backend::shutdown(&mut child) {
+                log::error!("Failed to stop the backend of \"{}\": {}", self.name, e);
+            }
+        }
+        self.output = None;
+        self.status = BackendStatus::Stopped;
+    }
+}
+
+/// Portfolios that can run side by side, each in its own backend process. The
+/// first one is the config the app was started with; the others live in
+/// `dir` as `<name>.json`.
+pub struct PortfolioManager {
+    pub portfolios: Vec<Portfolio>,
+    dir: PathBuf,
+    selected: usize,
+}
+
+impl PortfolioManager {
+    pub fn default_dir() -> PathBuf {
+        dirs::home_dir().unwrap_or_default().join(".kin_portfolios")
+    }
+
+    /// The main portfolio followed by the ones in `dir` by name, decrypted with
+    /// `passphrase` where needed. Files it does not open are listed as locked,
+    /// other files that fail to load are skipped with a warning; a missing `dir`
+    /// just means none.
+    pub fn load(
+        main_config_path: &Path,
+        main_config: Config,
+        dir: &Path,
+        passphrase: Option<&str>,
+    ) -> Self {
+        let mut portfolios = vec![Portfolio::new(
+            MAIN_PORTFOLIO.to_string(),
+            main_config,
+            main_config_path.to_path_buf(),
+        )];
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| portfolio_name(path).is_some())
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+        for path in paths {
+            let Some(name) = portfolio_name(&path) else {
+                continue;
+            };
+            match Config::load_with_passphrase(&path, passphrase) {
+                Ok(config) => portfolios.push(Portfolio::new(name, config, path)),
+                Err(KinError::PassphraseRequired(_) | KinError::WrongPassphrase) => {
+                    log::warn!("Portfolio {:?} is encrypted with another passphrase.", path);
+                    portfolios.push(Portfolio {
+                        locked: true,
+                        ..Portfolio::new(name, Config::default(), path)
+                    });
+                }
+                Err(e) => log::warn!("Skipping portfolio {:?}: {}", path, e),
+            }
+        }
+        Self {
+            portfolios,
+            dir: dir.to_path_buf(),
+            selected: 0,
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected_portfolio(&self) -> &Portfolio {
+        &self.portfolios[self.selected]
+    }
+
+    pub fn selected_portfolio_mut(&mut self) -> &mut Portfolio {
+        &mut self.portfolios[self.selected]
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.portfolios.len() {
+            self.selected = index;
+        }
+    }
+
+    /// Each portfolio's backend listens on its own socket and port; portfolios
+    /// are only ever appended, so an index keeps its endpoint.
+    pub fn endpoint(&self, index: usize) -> BackendEndpoint {
+        BackendEndpoint::for_slot(index)
+    }
+
+    /// Writes `config` to `<dir>/<name>.json` and appends the portfolio; returns
+    /// its index.
+    pub fn add(&mut self, name: &str, config: Config) -> Result<usize, KinError> {
+        let name = name.trim();
+        validate_name(name)?;
+        if self
+            .portfolios
+            .iter()
+            .any(|p| p.name.eq_ignore_ascii_case(name))
+        {
+            return Err(KinError::Profile(format!(
+                "Portfolio \"{}\" already exists.",
+                name
+            )));
+        }
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!("{}.json", name));
+        let config = Config {
+            format: ConfigFormat::Json,
+            ..config
+        };
+        config.save(&path)?;
+        self.portfolios
+            .push(Portfolio::new(name.to_string(), config, path));
+        Ok(self.portfolios.len() - 1)
+    }
+}
+
+// Also used as a file name; `_` is left out so names cannot collide with the
+// `<name>_profiles.json` files stored beside them
+fn validate_name(name: &str) -> Result<(), KinError> {
+    if name.is_empty() {
+        return Err(KinError::Profile(
+            "Portfolio name cannot be empty.".to_string(),
+        ));
+    }
+    if name.eq_ignore_ascii_case(MAIN_PORTFOLIO) {
+        return Err(KinError::Profile(format!(
+            "\"{}\" is the portfolio of the main config file.",
+            MAIN_PORTFOLIO
+        )));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == ' ')
+    {
+        return Err(KinError::Profile(
+            "Portfolio names may only contain letters, digits, spaces and '-'.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// `<name>.json` files in the portfolio directory; profiles files and backups are not portfolios
+fn portfolio_name(path: &Path) -> Option<String> {
+    if path.extension()? != "json" {
+        return None;
+    }
+    let name = path.file_stem()?.to_str()?;
+    validate_name(name).ok().map(|()| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_and_reloads_portfolios() {
+        let dir = std::env::temp_dir().join(format!("kin-portfolios-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let main_path = dir.join("main").join("config.json");
+        let mut manager = PortfolioManager::load(&main_path, Config::default(), &dir, None);
+        assert_eq!(manager.portfolios.len(), 1);
+
+        let aggressive = Config {
+            rebalance_threshold: 2.0,
+            ..Config::default()
+        };
+        assert_eq!(manager.add("Aggressive", aggressive).unwrap(), 1);
+        assert_eq!(manager.add("Conservative", Config::default()).unwrap(), 2);
+        assert!(manager.add("aggressive", Config::default()).is_err());
+        assert!(manager.add("main", Config::default()).is_err());
+        assert!(manager.add("../escape", Config::default()).is_err());
+        assert!(manager.add("a_profiles", Config::default()).is_err());
+        // Files beside the portfolios that are not portfolios themselves
+        fs::write(dir.join("Aggressive_profiles.json"), "{}").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let manager = PortfolioManager::load(&main_path, Config::default(), &dir, None);
+        let names: Vec<&str> = manager.portfolios.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, [MAIN_PORTFOLIO, "Aggressive", "Conservative"]);
+        assert_eq!(manager.portfolios[1].config.rebalance_threshold, 2.0);
+        assert_ne!(manager.endpoint(0), manager.endpoint(1));
+        assert_eq!(manager.endpoint(0), BackendEndpoint::default());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn lists_portfolios_it_cannot_decrypt_as_locked() {
+        let dir = std::env::temp_dir().join(format!("kin-locked-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let main_path = dir.join("main").join("config.json");
+        Config::default()
+            .save_with_passphrase(&dir.join("Secret.json"), Some("hunter2"))
+            .unwrap();
+        Config::default().save(&dir.join("Plain.json")).unwrap();
+
+        let manager = PortfolioManager::load(&main_path, Config::default(), &dir, None);
+        let locked: Vec<(&str, bool)> = manager
+            .portfolios
+            .iter()
+            .map(|p| (p.name.as_str(), p.locked))
+            .collect();
+        assert_eq!(
+            locked,
+            [(MAIN_PORTFOLIO, false), ("Plain", false), ("Secret", true)]
+        );
+
+        let manager = PortfolioManager::load(&main_path, Config::default(), &dir, Some("hunter2"));
+        assert!(manager.portfolios.iter().all(|p| !p.locked));
+        let manager = PortfolioManager::load(&main_path, Config::default(), &dir, Some("wrong"));
+        assert!(manager.portfolios[2].locked);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
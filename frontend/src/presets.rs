@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::config::{AssetAllocation, PortfolioAllocation, USDT};
+use crate::error::KinError;
+
+/// A ready-made starting point for the portfolio editor. USDT takes whatever
+/// the listed assets leave over, as in a saved config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllocationPreset {
+    pub name: String,
+    pub allocation: PortfolioAllocation,
+    pub rebalance_threshold: f64,
+    pub min_usdt_inflow: f64,
+}
+
+impl AllocationPreset {
+    fn new(
+        name: &str,
+        assets: &[(&str, f64)],
+        rebalance_threshold: f64,
+        min_usdt_inflow: f64,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            allocation: PortfolioAllocation {
+                assets: assets
+                    .iter()
+                    .map(|&(symbol, pct)| AssetAllocation::new(symbol, pct))
+                    .collect(),
+            },
+            rebalance_threshold,
+            min_usdt_inflow,
+        }
+    }
+}
+
+/// An allocation as copied to the clipboard for sharing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedAllocation {
+    pub portfolio_allocation: PortfolioAllocation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rebalance_threshold: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_usdt_inflow: Option<f64>,
+}
+
+impl SharedAllocation {
+    pub fn to_json(&self) -> Result<String, KinError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Changes pasted onto the portfolio editor; only the `Some` fields are applied.
+/// Besides copied allocations, the old flat layout is read with every field
+/// optional, e.g. `{"BTC_USDT": 30, "ETH_USDT": null}`, so a few weights can be
+/// shared without the rest.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(from = "PastedAllocation")]
+pub struct PortfolioAllocationPatch {
+    // (symbol, target %), rows not listed keep their weight
+    pub assets: Vec<(String, f64)>,
+    pub rebalance_threshold: Option<f64>,
+    pub min_usdt_inflow: Option<f64>,
+}
+
+// 扁平格式放在数组前面：旧版布局只认三个固定字段，会丢掉其他币种
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PastedAllocation {
+    Shared(SharedAllocation),
+    Flat(BTreeMap<String, Option<f64>>),
+    Allocation(PortfolioAllocation),
+}
+
+impl From<PastedAllocation> for PortfolioAllocationPatch {
+    fn from(pasted: PastedAllocation) -> Self {
+        let targets = |allocation: PortfolioAllocation| {
+            allocation
+                .assets
+                .into_iter()
+                .map(|a| (a.symbol, a.target_pct))
+                .collect()
+        };
+        match pasted {
+            PastedAllocation::Shared(shared) => Self {
+                assets: targets(shared.portfolio_allocation),
+                rebalance_threshold: shared.rebalance_threshold,
+                min_usdt_inflow: shared.min_usdt_inflow,
+            },
+            PastedAllocation::Allocation(allocation) => Self {
+                assets: targets(allocation),
+                ..Self::default()
+            },
+            PastedAllocation::Flat(mut fields) => {
+                let rebalance_threshold = fields.remove("rebalance_threshold").flatten();
+                let min_usdt_inflow = fields.remove("min_usdt_inflow").flatten();
+                Self {
+                    // USDT is always derived from the rest
+                    assets: fields
+                        .into_iter()
+                        .filter(|(symbol, _)| symbol != USDT)
+                        .filter_map(|(symbol, pct)| Some((symbol, pct?)))
+                        .collect(),
+                    rebalance_threshold,
+                    min_usdt_inflow,
+                }
+            }
+        }
+    }
+}
+
+impl PortfolioAllocationPatch {
+    pub fn from_json(text: &str) -> Result<Self, KinError> {
+        let patch: Self = serde_json::from_str(text.trim()).map_err(|_| {
+            KinError::invalid_allocation("Clipboard", "not a portfolio allocation in JSON")
+        })?;
+        if patch.is_empty() {
+            return Err(KinError::invalid_allocation(
+                "Clipboard",
+                "the JSON does not set any allocation",
+            ));
+        }
+        Ok(patch)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+            && self.rebalance_threshold.is_none()
+            && self.min_usdt_inflow.is_none()
+    }
+}
+
+// 杠杆代币波动较大，越激进的组合再平衡阈值越宽
+pub fn all() -> Vec<AllocationPreset> {
+    vec![
+        AllocationPreset::new(
+            "Conservative",
+            &[("BTC_USDT", 15.0), ("ETH_USDT", 10.0)],
+            3.0,
+            10.0,
+        ),
+        AllocationPreset::new(
+            "Balanced",
+            &[("BTC_USDT", 25.0), ("ETH_USDT", 15.0), ("LTC_USDT", 10.0)],
+            5.0,
+            5.0,
+        ),
+        AllocationPreset::new(
+            "Aggressive",
+            &[("BTC_USDT", 40.0), ("ETH_USDT", 25.0), ("LTC_USDT", 10.0)],
+            8.0,
+            5.0,
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pasted_json_becomes_a_patch() {
+        let preset = &all()[1];
+        let json = SharedAllocation {
+            portfolio_allocation: preset.allocation.clone(),
+            rebalance_threshold: Some(preset.rebalance_threshold),
+            min_usdt_inflow: Some(preset.min_usdt_inflow),
+        }
+        .to_json()
+        .unwrap();
+        let copied = PortfolioAllocationPatch::from_json(&json).unwrap();
+        assert_eq!(copied.assets.len(), preset.allocation.assets.len());
+        assert_eq!(copied.assets[0], ("BTC_USDT".to_string(), 25.0));
+        assert_eq!(copied.rebalance_threshold, Some(preset.rebalance_threshold));
+
+        let bare =
+            PortfolioAllocationPatch::from_json(r#"[{"symbol": "BTC_USDT", "target_pct": 60}]"#)
+                .unwrap();
+        assert_eq!(bare.assets, [("BTC_USDT".to_string(), 60.0)]);
+        assert_eq!(bare.rebalance_threshold, None);
+
+        let partial = PortfolioAllocationPatch::from_json(
+            r#"{"BTC_USDT": 30, "ETH_USDT": null, "SOL_USDT": 5, "USDT": 65, "min_usdt_inflow": 8}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            partial.assets,
+            [
+                ("BTC_USDT".to_string(), 30.0),
+                ("SOL_USDT".to_string(), 5.0)
+            ]
+        );
+        assert_eq!(partial.rebalance_threshold, None);
+        assert_eq!(partial.min_usdt_inflow, Some(8.0));
+
+        assert!(PortfolioAllocationPatch::from_json("BTC 60%").is_err());
+        assert!(PortfolioAllocationPatch::from_json(r#"{"ETH_USDT": null}"#).is_err());
+    }
+
+    #[test]
+    fn presets_sum_to_one_hundred() {
+        for preset in all() {
+            let allocation = &preset.allocation;
+            assert!(allocation.crypto_total() <= 100.0, "{}", preset.name);
+            let total = allocation.crypto_total() + allocation.usdt();
+            assert!(
+                (total - 100.0).abs() < 1e-9,
+                "{} sums to {}",
+                preset.name,
+                total
+            );
+        }
+    }
+}
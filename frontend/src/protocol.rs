@@ -0,0 +1,115 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+
+use crate::config::PortfolioAllocation;
+use crate::history::Order;
+
+/// One line of the newline-delimited JSON protocol the Python backend writes to stdout.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackendMessage {
+    Status {
+        status: String,
+        // Echoed by the startup status so the UI can confirm the execution mode
+        #[serde(default)]
+        paper_trading: bool,
+    },
+    PortfolioSnapshot {
+        // Actual allocation in percent, keyed by symbol (USDT included)
+        allocations: HashMap<String, f64>,
+        total_value_usdt: f64,
+        // 8-hour funding rate per held contract; missing from older backends
+        #[serde(default)]
+        funding_rates: HashMap<String, f64>,
+    },
+    TradeExecuted {
+        asset: String,
+        side: String,
+        from_pct: f64,
+        to_pct: f64,
+        usdt_traded: f64,
+        order_id: String,
+        // Total portfolio value just before the trade; 0.0 from older backends
+        #[serde(default)]
+        portfolio_value_usdt: f64,
+        // Contracts filled and their average price, for the tax lots; 0.0 from older backends
+        #[serde(default)]
+        quantity: f64,
+        #[serde(default)]
+        price: f64,
+    },
+    // Placed order and each later change of its status; the fields sit beside "type"
+    OrderUpdate(Order),
+    // Sent before each order of a rebalance and once more when all are placed
+    RebalanceProgress {
+        total_trades: usize,
+        completed_trades: usize,
+        // Contract the next order is for; None once the rebalance is done
+        #[serde(default)]
+        current_asset: Option<String>,
+    },
+    PriceHistory {
+        symbols: Vec<String>,
+        // Daily closes per symbol, oldest first
+        prices: Vec<Vec<f64>>,
+    },
+    Error {
+        message: String,
+    },
+    // Any output line that is not a protocol message (prints, tracebacks, logging)
+    Log {
+        line: String,
+    },
+}
+
+/// One line of the newline-delimited JSON the frontend writes to the backend socket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackendCommand {
+    // Asks the backend to answer with a `BackendMessage::Status`
+    Status,
+    // Finish the current cycle and exit
+    Stop,
+    // Keep reporting snapshots but place no rebalancing trades until resumed
+    PauseRebalancing,
+    ResumeRebalancing,
+    // Targets resolved by `allocation_mode` and `position_sizing`; the backend
+    // rebalances toward these instead of the custom `portfolio_allocation`
+    SetTargets { targets: PortfolioAllocation },
+}
+
+impl BackendMessage {
+    pub fn from_line(line: &str) -> BackendMessage {
+        serde_json::from_str(line).unwrap_or_else(|_| BackendMessage::Log {
+            line: line.to_string(),
+        })
+    }
+}
+
+/// Reads `reader` line by line on a background thread until EOF, forwarding each
+/// line as a `BackendMessage` and waking the UI so it is handled promptly.
+pub fn spawn_reader<R>(
+    reader: R,
+    sender: Sender<BackendMessage>,
+    ctx: egui::Context,
+) -> JoinHandle<()>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            if sender.send(BackendMessage::from_line(&line)).is_err() {
+                break; // UI side dropped the receiver
+            }
+            ctx.request_repaint();
+        }
+    })
+}
@@ -0,0 +1,93 @@
+use crate::exchange::OrderSide;
+
+/// Positions whose mark price is closer than this to the liquidation price,
+/// in percent of the mark price, are flagged red.
+pub const LIQUIDATION_WARNING_PCT: f64 = 10.0;
+
+/// Price at which an isolated-margin position is liquidated, using Gate.io's
+/// simplified formula: the initial margin (1 / leverage) minus the maintenance
+/// margin is what the price may move against the position. Fees and funding
+/// are ignored, so the real price is slightly closer.
+pub fn liquidation_price(
+    entry_price: f64,
+    leverage: f64,
+    side: OrderSide,
+    maintenance_margin_rate: f64,
+) -> f64 {
+    let buffer = 1.0 / leverage - maintenance_margin_rate;
+    match side {
+        OrderSide::Buy => entry_price * (1.0 - buffer),
+        OrderSide::Sell => entry_price * (1.0 + buffer),
+    }
+}
+
+/// How far the price may still move before liquidation, in percent of
+/// `mark_price`; zero once it has been crossed.
+pub fn distance_to_liquidation_pct(
+    mark_price: f64,
+    liquidation_price: f64,
+    side: OrderSide,
+) -> f64 {
+    if mark_price <= 0.0 {
+        return 0.0;
+    }
+    let distance = match side {
+        OrderSide::Buy => mark_price - liquidation_price,
+        OrderSide::Sell => liquidation_price - mark_price,
+    };
+    (distance / mark_price * 100.0).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn liquidation_price_of_longs_and_shorts() {
+        // 10x long from 10,000 with 0.5% maintenance margin: 10000 × (1 − 0.1 + 0.005)
+        assert_close(
+            liquidation_price(10_000.0, 10.0, OrderSide::Buy, 0.005),
+            9_050.0,
+        );
+        // Same position short: 10000 × (1 + 0.1 − 0.005)
+        assert_close(
+            liquidation_price(10_000.0, 10.0, OrderSide::Sell, 0.005),
+            10_950.0,
+        );
+        // 100x leaves half a percent of room: 50000 × (1 − 0.01 + 0.005)
+        assert_close(
+            liquidation_price(50_000.0, 100.0, OrderSide::Buy, 0.005),
+            49_750.0,
+        );
+        // The default 3x long survives a drop of about a third
+        assert_close(
+            liquidation_price(3_000.0, 3.0, OrderSide::Buy, 0.005),
+            2_015.0,
+        );
+    }
+
+    #[test]
+    fn distance_shrinks_to_zero_at_liquidation() {
+        assert_close(
+            distance_to_liquidation_pct(10_000.0, 9_050.0, OrderSide::Buy),
+            9.5,
+        );
+        assert_close(
+            distance_to_liquidation_pct(10_000.0, 10_950.0, OrderSide::Sell),
+            9.5,
+        );
+        assert_eq!(
+            distance_to_liquidation_pct(9_000.0, 9_050.0, OrderSide::Buy),
+            0.0
+        );
+    }
+}
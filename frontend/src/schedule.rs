@@ -0,0 +1,122 @@
+use chrono::{NaiveTime, Weekday};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// When the backend rebalances regardless of drift. The deviation threshold
+/// keeps applying between scheduled runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RebalanceSchedule {
+    /// Only rebalance when an asset drifts past the threshold
+    #[default]
+    ThresholdOnly,
+    /// Every day at `time` (local time, HH:MM:SS)
+    Daily { time: NaiveTime },
+    /// Every `weekday` ("Mon" to "Sun") at `time`
+    Weekly {
+        #[schemars(with = "String")]
+        weekday: Weekday,
+        time: NaiveTime,
+    },
+    /// Every `hours` hours
+    Interval {
+        #[schemars(range(min = 1))]
+        hours: u64,
+    },
+}
+
+/// The variant of a `RebalanceSchedule` without its parameters, for the editor combo-box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleKind {
+    ThresholdOnly,
+    Daily,
+    Weekly,
+    Interval,
+}
+
+impl ScheduleKind {
+    pub const ALL: [ScheduleKind; 4] = [
+        ScheduleKind::ThresholdOnly,
+        ScheduleKind::Daily,
+        ScheduleKind::Weekly,
+        ScheduleKind::Interval,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ScheduleKind::ThresholdOnly => "Threshold only",
+            ScheduleKind::Daily => "Daily",
+            ScheduleKind::Weekly => "Weekly",
+            ScheduleKind::Interval => "Every N hours",
+        }
+    }
+}
+
+impl RebalanceSchedule {
+    // 09:00 when switching from a schedule without a time of day
+    pub fn with_kind(self, kind: ScheduleKind) -> RebalanceSchedule {
+        let time = self
+            .time()
+            .unwrap_or_else(|| NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+        match kind {
+            ScheduleKind::ThresholdOnly => RebalanceSchedule::ThresholdOnly,
+            ScheduleKind::Daily => RebalanceSchedule::Daily { time },
+            ScheduleKind::Weekly => RebalanceSchedule::Weekly {
+                weekday: Weekday::Mon,
+                time,
+            },
+            ScheduleKind::Interval => RebalanceSchedule::Interval { hours: 24 },
+        }
+    }
+
+    pub fn time(&self) -> Option<NaiveTime> {
+        match self {
+            RebalanceSchedule::Daily { time } | RebalanceSchedule::Weekly { time, .. } => {
+                Some(*time)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn time_mut(&mut self) -> Option<&mut NaiveTime> {
+        match self {
+            RebalanceSchedule::Daily { time } | RebalanceSchedule::Weekly { time, .. } => {
+                Some(time)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> ScheduleKind {
+        match self {
+            RebalanceSchedule::ThresholdOnly => ScheduleKind::ThresholdOnly,
+            RebalanceSchedule::Daily { .. } => ScheduleKind::Daily,
+            RebalanceSchedule::Weekly { .. } => ScheduleKind::Weekly,
+            RebalanceSchedule::Interval { .. } => ScheduleKind::Interval,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_variant_round_trips_through_json() {
+        let nine = NaiveTime::from_hms_opt(9, 30, 0).unwrap();
+        let schedules = [
+            RebalanceSchedule::ThresholdOnly,
+            RebalanceSchedule::Daily { time: nine },
+            RebalanceSchedule::Weekly {
+                weekday: Weekday::Fri,
+                time: nine,
+            },
+            RebalanceSchedule::Interval { hours: 12 },
+        ];
+        for schedule in schedules {
+            let json = serde_json::to_string(&schedule).unwrap();
+            let parsed: RebalanceSchedule = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, schedule, "{}", json);
+        }
+    }
+}
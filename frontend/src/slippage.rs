@@ -0,0 +1,139 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Order sizes are expressed relative to this, so the parameters stay readable
+const MILLION_USDT: f64 = 1_000_000.0;
+
+/// How much worse than the mark price a market order of a given size is
+/// expected to fill, for the cost estimates in rebalance plans.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SlippageModel {
+    /// Orders fill at the mark price
+    #[default]
+    None,
+    /// Price impact grows by `bps_per_million_usdt` basis points per million USDT traded
+    LinearBps {
+        #[schemars(range(min = 0.0))]
+        bps_per_million_usdt: f64,
+    },
+    /// Square-root impact: a one million USDT order moves the price by
+    /// `coeff` (a fraction), smaller orders by `coeff × √(notional / 1M)`
+    Sqrt {
+        #[schemars(range(min = 0.0))]
+        coeff: f64,
+    },
+}
+
+/// The variant of a `SlippageModel` without its parameters, for the editor combo-box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlippageKind {
+    None,
+    LinearBps,
+    Sqrt,
+}
+
+impl SlippageKind {
+    pub const ALL: [SlippageKind; 3] = [
+        SlippageKind::None,
+        SlippageKind::LinearBps,
+        SlippageKind::Sqrt,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SlippageKind::None => "None",
+            SlippageKind::LinearBps => "Linear (bps per 1M USDT)",
+            SlippageKind::Sqrt => "Square root",
+        }
+    }
+}
+
+impl SlippageModel {
+    /// Expected cost in USDT of filling an order of `notional_usdt` away from the mark price.
+    pub fn estimate(&self, notional_usdt: f64) -> f64 {
+        let notional = notional_usdt.abs();
+        let impact = match *self {
+            SlippageModel::None => 0.0,
+            SlippageModel::LinearBps {
+                bps_per_million_usdt,
+            } => bps_per_million_usdt / 10_000.0 * notional / MILLION_USDT,
+            SlippageModel::Sqrt { coeff } => coeff * (notional / MILLION_USDT).sqrt(),
+        };
+        notional * impact
+    }
+
+    // Defaults that are in the right range for liquid Gate.io perpetuals
+    pub fn with_kind(self, kind: SlippageKind) -> SlippageModel {
+        if kind == self.kind() {
+            return self;
+        }
+        match kind {
+            SlippageKind::None => SlippageModel::None,
+            SlippageKind::LinearBps => SlippageModel::LinearBps {
+                bps_per_million_usdt: 10.0,
+            },
+            SlippageKind::Sqrt => SlippageModel::Sqrt { coeff: 0.001 },
+        }
+    }
+
+    pub fn kind(&self) -> SlippageKind {
+        match self {
+            SlippageModel::None => SlippageKind::None,
+            SlippageModel::LinearBps { .. } => SlippageKind::LinearBps,
+            SlippageModel::Sqrt { .. } => SlippageKind::Sqrt,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn no_model_means_no_slippage() {
+        assert_eq!(SlippageModel::None.estimate(1_000_000.0), 0.0);
+    }
+
+    #[test]
+    fn linear_impact_grows_with_the_square_of_the_size() {
+        let model = SlippageModel::LinearBps {
+            bps_per_million_usdt: 10.0,
+        };
+        // 1M USDT moves the price 10 bps: 0.1% of 1M
+        assert_close(model.estimate(1_000_000.0), 1_000.0);
+        // Half the size, half the impact: 5 bps of 500k
+        assert_close(model.estimate(500_000.0), 250.0);
+        // Sells cost the same as buys
+        assert_close(model.estimate(-100_000.0), 10.0);
+    }
+
+    #[test]
+    fn sqrt_impact_of_a_quarter_million_is_half_that_of_a_million() {
+        let model = SlippageModel::Sqrt { coeff: 0.001 };
+        assert_close(model.estimate(1_000_000.0), 1_000.0);
+        // 0.1% × √0.25 = 0.05% of 250k
+        assert_close(model.estimate(250_000.0), 125.0);
+        assert_close(model.estimate(0.0), 0.0);
+    }
+
+    #[test]
+    fn every_variant_round_trips_through_json() {
+        for kind in SlippageKind::ALL {
+            let model = SlippageModel::None.with_kind(kind);
+            let json = serde_json::to_string(&model).unwrap();
+            let parsed: SlippageModel = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, model, "{}", json);
+            assert_eq!(parsed.kind(), kind);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::KinError;
+
+// Common USDT-settled contracts, so suggestions work before the first fetch and offline
+const BUNDLED_SYMBOLS: &str = include_str!("../symbols.json");
+
+/// Most suggestions shown below the "Add Asset" field at once.
+pub const MAX_SUGGESTIONS: usize = 8;
+
+/// Futures contracts suggested while typing a new asset symbol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SymbolSuggestions {
+    pub symbols: Vec<String>,
+}
+
+impl SymbolSuggestions {
+    pub fn bundled() -> Self {
+        serde_json::from_str(BUNDLED_SYMBOLS).expect("bundled symbols.json is a list of strings")
+    }
+
+    // Contract list fetched from Gate.io on the first launch
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".portfolio_rebalancer_symbols.json")
+    }
+
+    /// The cached contract list, or the bundled one while there is none.
+    pub fn load(path: &Path) -> Self {
+        fs::read(path)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<SymbolSuggestions>(&data).ok())
+            .filter(|suggestions| !suggestions.symbols.is_empty())
+            .unwrap_or_else(SymbolSuggestions::bundled)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), KinError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Symbols containing `query` (case-insensitive), those starting with it
+    /// first, leaving out `exclude` and an exact match of what was typed.
+    pub fn matching(&self, query: &str, exclude: &[&str]) -> Vec<&str> {
+        let query = query.trim().to_uppercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let candidates = || {
+            self.symbols
+                .iter()
+                .map(String::as_str)
+                .filter(|s| *s != query && !exclude.contains(s))
+        };
+        candidates()
+            .filter(|s| s.starts_with(&query))
+            .chain(candidates().filter(|s| !s.starts_with(&query) && s.contains(&query)))
+            .take(MAX_SUGGESTIONS)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundles_enough_symbols_for_offline_use() {
+        let bundled = SymbolSuggestions::bundled();
+        assert!(bundled.symbols.len() >= 50);
+        assert!(bundled.symbols.iter().all(|s| s.ends_with("_USDT")));
+    }
+
+    #[test]
+    fn prefix_matches_come_first() {
+        let suggestions = SymbolSuggestions {
+            symbols: ["ETC_USDT", "BTC_USDT", "SOL_USDT", "ETH_USDT", "TON_USDT"]
+                .map(String::from)
+                .to_vec(),
+        };
+        assert_eq!(
+            suggestions.matching("et", &[]),
+            vec!["ETC_USDT", "ETH_USDT"]
+        );
+        assert_eq!(
+            suggestions.matching("t", &["ETH_USDT"]),
+            vec!["TON_USDT", "ETC_USDT", "BTC_USDT", "SOL_USDT"]
+        );
+        assert_eq!(suggestions.matching("btc_usdt", &[]), Vec::<&str>::new());
+        assert!(suggestions.matching(" ", &[]).is_empty());
+    }
+}
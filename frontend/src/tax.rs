@@ -0,0 +1,233 @@
+use chrono::{DateTime, Datelike, Duration, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::error::KinError;
+use crate::exchange::OrderSide;
+
+/// Lots held longer than this are long-term gains when sold.
+pub const LONG_TERM_HOLDING_DAYS: i64 = 365;
+
+/// Which lots a sell consumes first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CostBasisMethod {
+    /// Oldest lots first
+    #[default]
+    Fifo,
+    /// Newest lots first
+    Lifo,
+}
+
+/// Contracts bought in one trade that have not been sold yet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxLot {
+    pub asset: String,
+    pub quantity: f64,
+    // What the remaining quantity cost, in USDT
+    pub cost_basis_usdt: f64,
+    pub acquired_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HoldingPeriod {
+    Short,
+    Long,
+}
+
+impl fmt::Display for CostBasisMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CostBasisMethod::Fifo => write!(f, "FIFO"),
+            CostBasisMethod::Lifo => write!(f, "LIFO"),
+        }
+    }
+}
+
+impl fmt::Display for HoldingPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HoldingPeriod::Short => write!(f, "short"),
+            HoldingPeriod::Long => write!(f, "long"),
+        }
+    }
+}
+
+/// Gain or loss of selling (part of) one lot; a row of the tax report.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RealizedGain {
+    pub asset: String,
+    // When the sell happened
+    pub date: DateTime<Utc>,
+    pub proceeds: f64,
+    pub cost_basis: f64,
+    pub gain: f64,
+    pub holding_period: HoldingPeriod,
+}
+
+/// Adds a lot for `quantity` contracts bought at `price`.
+pub fn buy(lots: &mut Vec<TaxLot>, asset: &str, quantity: f64, price: f64, at: DateTime<Utc>) {
+    lots.push(TaxLot {
+        asset: asset.to_string(),
+        quantity,
+        cost_basis_usdt: quantity * price,
+        acquired_at: at,
+    });
+}
+
+/// Consumes `quantity` contracts of `asset` from `lots` in `method` order and
+/// returns one gain per lot touched. Emptied lots are removed, partly sold ones
+/// keep the cost of what is left. Quantity beyond the held lots opens a short,
+/// which is not tracked, so it realizes nothing.
+pub fn sell(
+    lots: &mut Vec<TaxLot>,
+    asset: &str,
+    quantity: f64,
+    price: f64,
+    at: DateTime<Utc>,
+    method: CostBasisMethod,
+) -> Vec<RealizedGain> {
+    let mut indices: Vec<usize> = (0..lots.len())
+        .filter(|&i| lots[i].asset == asset)
+        .collect();
+    indices.sort_by_key(|&i| lots[i].acquired_at);
+    if method == CostBasisMethod::Lifo {
+        indices.reverse();
+    }
+
+    let mut remaining = quantity;
+    let mut gains = Vec::new();
+    for i in indices {
+        if remaining <= 0.0 {
+            break;
+        }
+        let lot = &mut lots[i];
+        let sold = remaining.min(lot.quantity);
+        let cost_basis = lot.cost_basis_usdt * sold / lot.quantity;
+        let proceeds = sold * price;
+        let holding_period = if at - lot.acquired_at > Duration::days(LONG_TERM_HOLDING_DAYS) {
+            HoldingPeriod::Long
+        } else {
+            HoldingPeriod::Short
+        };
+        gains.push(RealizedGain {
+            asset: asset.to_string(),
+            date: at,
+            proceeds,
+            cost_basis,
+            gain: proceeds - cost_basis,
+            holding_period,
+        });
+        lot.quantity -= sold;
+        lot.cost_basis_usdt -= cost_basis;
+        remaining -= sold;
+    }
+    lots.retain(|lot| lot.quantity > 1e-12);
+    gains
+}
+
+/// Books a filled order: buys open a lot, sells consume lots and return the gains.
+pub fn record_fill(
+    lots: &mut Vec<TaxLot>,
+    asset: &str,
+    side: OrderSide,
+    quantity: f64,
+    price: f64,
+    at: DateTime<Utc>,
+    method: CostBasisMethod,
+) -> Vec<RealizedGain> {
+    match side {
+        OrderSide::Buy => {
+            buy(lots, asset, quantity, price, at);
+            Vec::new()
+        }
+        OrderSide::Sell => sell(lots, asset, quantity, price, at, method),
+    }
+}
+
+/// Realized gain per calendar year (UTC), oldest year first.
+pub fn annual_summary(gains: &[RealizedGain]) -> BTreeMap<i32, f64> {
+    let mut years = BTreeMap::new();
+    for gain in gains {
+        *years.entry(gain.date.year()).or_insert(0.0) += gain.gain;
+    }
+    years
+}
+
+// asset,date,proceeds,cost_basis,gain,holding_period
+pub fn export_gains_to_csv(gains: &[RealizedGain], path: &Path) -> Result<(), KinError> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for gain in gains {
+        writer.serialize(gain)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn day(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap()
+    }
+
+    fn two_lots() -> Vec<TaxLot> {
+        let mut lots = Vec::new();
+        buy(&mut lots, "BTC_USDT", 1.0, 20_000.0, day(2023, 1, 1));
+        buy(&mut lots, "BTC_USDT", 1.0, 40_000.0, day(2024, 6, 1));
+        buy(&mut lots, "ETH_USDT", 5.0, 2_000.0, day(2023, 1, 1));
+        lots
+    }
+
+    #[test]
+    fn fifo_sells_the_oldest_lot_first() {
+        let mut lots = two_lots();
+        let gains = sell(
+            &mut lots,
+            "BTC_USDT",
+            1.5,
+            50_000.0,
+            day(2024, 7, 1),
+            CostBasisMethod::Fifo,
+        );
+        assert_eq!(gains.len(), 2);
+        // The whole 2023 lot, held over a year
+        assert_eq!(gains[0].gain, 30_000.0);
+        assert_eq!(gains[0].holding_period, HoldingPeriod::Long);
+        // Half of the 2024 lot
+        assert_eq!(gains[1].proceeds, 25_000.0);
+        assert_eq!(gains[1].cost_basis, 20_000.0);
+        assert_eq!(gains[1].holding_period, HoldingPeriod::Short);
+
+        let btc: Vec<_> = lots.iter().filter(|l| l.asset == "BTC_USDT").collect();
+        assert_eq!(btc.len(), 1);
+        assert_eq!((btc[0].quantity, btc[0].cost_basis_usdt), (0.5, 20_000.0));
+    }
+
+    #[test]
+    fn lifo_sells_the_newest_lot_first_and_ignores_shorts() {
+        let mut lots = two_lots();
+        let gains = sell(
+            &mut lots,
+            "BTC_USDT",
+            3.0,
+            30_000.0,
+            day(2024, 7, 1),
+            CostBasisMethod::Lifo,
+        );
+        assert_eq!(
+            gains.iter().map(|g| g.gain).collect::<Vec<_>>(),
+            vec![-10_000.0, 10_000.0]
+        );
+        assert!(lots.iter().all(|l| l.asset == "ETH_USDT"));
+
+        let summary = annual_summary(&gains);
+        assert_eq!(summary, BTreeMap::from([(2024, 0.0)]));
+    }
+}
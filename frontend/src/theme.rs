@@ -0,0 +1,66 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::process::Command;
+
+/// Colour scheme of the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    /// Follow the desktop's light/dark preference
+    System,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Dark, Theme::Light, Theme::System];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+            Theme::System => "System",
+        }
+    }
+}
+
+/// Whether the Linux desktop prefers a dark theme, for when the windowing
+/// system reports none (macOS and Windows always do). KDE is read from its
+/// colour scheme name, other desktops are asked through `gsettings`.
+pub fn desktop_prefers_dark() -> Option<bool> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let desktop = env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+    if desktop.to_uppercase().contains("KDE") {
+        let kdeglobals = dirs::config_dir()?.join("kdeglobals");
+        return fs::read_to_string(kdeglobals)
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("ColorScheme="))
+            .map(|scheme| scheme.to_lowercase().contains("dark"));
+    }
+    // GNOME 42+ has color-scheme; older versions only name a "-dark" GTK theme
+    let scheme = gsettings("color-scheme")?;
+    if scheme.contains("prefer-dark") {
+        return Some(true);
+    }
+    if scheme.contains("prefer-light") {
+        return Some(false);
+    }
+    gsettings("gtk-theme").map(|theme| theme.to_lowercase().contains("dark"))
+}
+
+fn gsettings(key: &str) -> Option<String> {
+    let output = Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", key])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
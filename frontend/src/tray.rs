@@ -0,0 +1,169 @@
+use eframe::egui;
+use std::sync::mpsc::Sender;
+
+use crate::error::KinError;
+
+/// Tray menu actions forwarded to `RebalancerApp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayCommand {
+    ShowWindow,
+    StartRebalancer,
+    StopRebalancer,
+    Quit,
+}
+
+impl TrayCommand {
+    pub const ALL: [TrayCommand; 4] = [
+        TrayCommand::ShowWindow,
+        TrayCommand::StartRebalancer,
+        TrayCommand::StopRebalancer,
+        TrayCommand::Quit,
+    ];
+
+    // Used as the menu item id
+    pub fn id(self) -> &'static str {
+        match self {
+            TrayCommand::ShowWindow => "show_window",
+            TrayCommand::StartRebalancer => "start_rebalancer",
+            TrayCommand::StopRebalancer => "stop_rebalancer",
+            TrayCommand::Quit => "quit",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TrayCommand::ShowWindow => "Show Window",
+            TrayCommand::StartRebalancer => "Start Rebalancer",
+            TrayCommand::StopRebalancer => "Stop Rebalancer",
+            TrayCommand::Quit => "Quit",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|command| command.id() == id)
+    }
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+mod platform {
+    use super::*;
+    use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+    use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
+
+    const ICON_SIZE: u32 = 32;
+    const RUNNING_COLOR: [u8; 3] = [46, 204, 113];
+    const STOPPED_COLOR: [u8; 3] = [128, 128, 128];
+
+    pub struct Tray {
+        icon: TrayIcon,
+        status_item: MenuItem,
+        running: bool,
+        status: String,
+    }
+
+    impl Tray {
+        /// Registers the icon and forwards menu clicks to `sender`, waking `ctx`.
+        /// "Show Window" is applied directly since a hidden window may not run `update`.
+        pub fn new(sender: Sender<TrayCommand>, ctx: egui::Context) -> Result<Self, KinError> {
+            let menu = Menu::new();
+            let items: Vec<MenuItem> = TrayCommand::ALL
+                .into_iter()
+                .map(|command| MenuItem::with_id(command.id(), command.label(), true, None))
+                .collect();
+            let status_item = MenuItem::new("Status: Stopped", false, None);
+            let separator = PredefinedMenuItem::separator();
+            menu.append_items(&[
+                &items[0],
+                &items[1],
+                &items[2],
+                &separator,
+                &status_item,
+                &separator,
+                &items[3],
+            ])
+            .map_err(|e| KinError::Tray(e.to_string()))?;
+
+            let icon = TrayIconBuilder::new()
+                .with_menu(Box::new(menu))
+                .with_tooltip("KIN Portfolio Rebalancer")
+                .with_icon(status_icon(false)?)
+                .build()
+                .map_err(|e| KinError::Tray(e.to_string()))?;
+
+            MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+                let Some(command) = TrayCommand::from_id(&event.id.0) else {
+                    return;
+                };
+                if command == TrayCommand::ShowWindow {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                }
+                let _ = sender.send(command);
+                ctx.request_repaint();
+            }));
+
+            Ok(Self {
+                icon,
+                status_item,
+                running: false,
+                status: "Stopped".to_string(),
+            })
+        }
+
+        pub fn set_status(&mut self, running: bool, status: &str) {
+            if self.status != status {
+                self.status_item.set_text(format!("Status: {}", status));
+                self.status = status.to_string();
+            }
+            if self.running != running {
+                match status_icon(running) {
+                    Ok(icon) => {
+                        if let Err(e) = self.icon.set_icon(Some(icon)) {
+                            log::warn!("Failed to update tray icon: {}", e);
+                        }
+                    }
+                    Err(e) => log::warn!("{}", e),
+                }
+                self.running = running;
+            }
+        }
+    }
+
+    // 程序没有图标文件，用纯色圆点区分运行(绿)/停止(灰)
+    fn status_icon(running: bool) -> Result<Icon, KinError> {
+        let [r, g, b] = if running {
+            RUNNING_COLOR
+        } else {
+            STOPPED_COLOR
+        };
+        let center = (ICON_SIZE as f32 - 1.0) / 2.0;
+        let radius = ICON_SIZE as f32 / 2.0 - 1.0;
+        let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+        for y in 0..ICON_SIZE {
+            for x in 0..ICON_SIZE {
+                let distance = (x as f32 - center).hypot(y as f32 - center);
+                let alpha = if distance <= radius { 255 } else { 0 };
+                rgba.extend_from_slice(&[r, g, b, alpha]);
+            }
+        }
+        Icon::from_rgba(rgba, ICON_SIZE, ICON_SIZE).map_err(|e| KinError::Tray(e.to_string()))
+    }
+}
+
+// tray-icon needs a GTK event loop on Linux, which eframe does not run
+#[cfg(not(any(windows, target_os = "macos")))]
+mod platform {
+    use super::*;
+
+    pub struct Tray;
+
+    impl Tray {
+        pub fn new(_sender: Sender<TrayCommand>, _ctx: egui::Context) -> Result<Self, KinError> {
+            Err(KinError::Tray("not supported on this platform".to_string()))
+        }
+
+        pub fn set_status(&mut self, _running: bool, _status: &str) {}
+    }
+}
+
+pub use platform::Tray;
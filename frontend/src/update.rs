@@ -0,0 +1,126 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+use crate::error::KinError;
+
+/// Version of this build, from Cargo.toml.
+pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/btxLithium/Kin-Portfolio-Rebalancer/releases/latest";
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Startup checks are skipped until this long after the last one.
+pub const CHECK_INTERVAL_HOURS: i64 = 24;
+
+#[derive(Debug, Deserialize)]
+struct LatestRelease {
+    tag_name: String,
+    html_url: String,
+}
+
+/// A release newer than this build.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    // Without the leading "v"
+    pub version: String,
+    // Release page on GitHub
+    pub url: String,
+}
+
+/// Asks GitHub Releases whether a newer version than `APP_VERSION` is out.
+pub struct UpdateChecker {
+    client: reqwest::Client,
+    current_version: String,
+}
+
+impl Default for UpdateChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UpdateChecker {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            current_version: APP_VERSION.to_string(),
+        }
+    }
+
+    /// The latest release if it is newer than this build, None when up to date.
+    pub async fn check(&self) -> Result<Option<Release>, KinError> {
+        let release: LatestRelease = self
+            .client
+            .get(LATEST_RELEASE_URL)
+            // GitHub rejects API requests without a User-Agent
+            .header(
+                reqwest::header::USER_AGENT,
+                format!("kin-portfolio-rebalancer/{}", APP_VERSION),
+            )
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(KinError::UpdateCheck)?
+            .json()
+            .await
+            .map_err(KinError::UpdateCheck)?;
+        Ok(
+            is_newer(&release.tag_name, &self.current_version).then(|| Release {
+                version: release.tag_name.trim_start_matches('v').to_string(),
+                url: release.html_url,
+            }),
+        )
+    }
+}
+
+/// Whether a startup check should run, given when the last one happened.
+pub fn is_due(last_check: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    last_check.is_none_or(|last| now - last >= Duration::hours(CHECK_INTERVAL_HOURS))
+}
+
+// "v1.2.3" -> (1, 2, 3); missing parts are 0 and pre-release/build suffixes are ignored
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version
+        .trim()
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()?;
+    let mut parts = core.split('.').map(str::parse::<u64>);
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+/// Whether release tag `tag` is a higher version than `current`; unparseable tags never are.
+pub fn is_newer(tag: &str, current: &str) -> bool {
+    match (parse_version(tag), parse_version(current)) {
+        (Some(tag), Some(current)) => tag > current,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_release_tags_numerically() {
+        assert!(is_newer("v0.2.0", "0.1.9"));
+        assert!(is_newer("v0.10.0", "0.9.0"));
+        assert!(is_newer("1.2", "1.1.5"));
+        assert!(!is_newer("v0.1.0", "0.1.0"));
+        assert!(!is_newer("v0.1.0-beta", "0.1.0"));
+        assert!(!is_newer("v0.0.9", "0.1.0"));
+        assert!(!is_newer("nightly", "0.1.0"));
+    }
+
+    #[test]
+    fn checks_at_most_once_a_day() {
+        let now = Utc::now();
+        assert!(is_due(None, now));
+        assert!(!is_due(Some(now - Duration::hours(23)), now));
+        assert!(is_due(Some(now - Duration::hours(24)), now));
+    }
+}
@@ -0,0 +1,79 @@
+use eframe::egui;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+use crate::error::KinError;
+
+/// Reports changes made to the config file from outside the app.
+pub struct ConfigWatcher {
+    // Watching stops when this is dropped
+    _watcher: RecommendedWatcher,
+    receiver: Receiver<()>,
+}
+
+impl ConfigWatcher {
+    // The parent directory is watched because editors often save by replacing the file
+    pub fn new(path: &Path, ctx: egui::Context) -> Result<Self, KinError> {
+        let file_name = path.file_name().map(PathBuf::from).unwrap_or_default();
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else {
+                    return;
+                };
+                if !matches!(
+                    event.kind,
+                    EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                ) {
+                    return;
+                }
+                if event.paths.iter().any(|p| p.ends_with(&file_name)) {
+                    let _ = sender.send(());
+                    ctx.request_repaint();
+                }
+            })?;
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            _watcher: watcher,
+            receiver,
+        })
+    }
+
+    /// Drains pending events; true if the file was touched since the last call.
+    pub fn changed(&self) -> bool {
+        self.receiver.try_iter().count() > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn reports_writes_to_the_watched_file_only() {
+        let dir = std::env::temp_dir().join(format!("kin_watcher_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        std::fs::write(&path, "{}").unwrap();
+
+        let watcher = ConfigWatcher::new(&path, egui::Context::default()).unwrap();
+        std::fs::write(dir.join("other.json"), "{}").unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!watcher.changed());
+
+        std::fs::write(&path, "{\"edited\": true}").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !watcher.changed() {
+            assert!(Instant::now() < deadline, "no change reported");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
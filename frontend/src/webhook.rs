@@ -0,0 +1,161 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::thread;
+use std::time::Duration;
+
+use crate::error::KinError;
+
+// Attempts after the first one fails with a network error
+const MAX_RETRIES: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Events that can be forwarded to `Config::webhook_url`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    TradeExecuted,
+    BackendError,
+    BackendStopped,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 3] = [
+        WebhookEvent::TradeExecuted,
+        WebhookEvent::BackendError,
+        WebhookEvent::BackendStopped,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WebhookEvent::TradeExecuted => "Trade executed",
+            WebhookEvent::BackendError => "Backend error",
+            WebhookEvent::BackendStopped => "Backend stopped",
+        }
+    }
+}
+
+/// JSON body POSTed to the webhook.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WebhookPayload {
+    // "test" for the payload sent from the API settings
+    pub event_type: String,
+    pub timestamp: DateTime<Utc>,
+    pub asset: Option<String>,
+    pub details: Value,
+}
+
+impl WebhookPayload {
+    pub fn new(event: WebhookEvent, asset: Option<String>, details: Value) -> Self {
+        let event_type = serde_json::to_value(event)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+        Self {
+            event_type,
+            timestamp: Utc::now(),
+            asset,
+            details,
+        }
+    }
+
+    pub fn test() -> Self {
+        Self {
+            event_type: "test".to_string(),
+            timestamp: Utc::now(),
+            asset: None,
+            details: Value::String("Test message from KIN Portfolio Rebalancer".to_string()),
+        }
+    }
+}
+
+/// POSTs `payload` to `url` and returns the HTTP status code. Network errors
+/// are retried `MAX_RETRIES` times; an error status is not retried.
+/// Blocks for up to a minute, so call it off the UI thread.
+pub fn send_webhook(url: &str, payload: &WebhookPayload) -> Result<u16, KinError> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    let mut attempt = 0;
+    loop {
+        match client.post(url).json(payload).send() {
+            Ok(response) => {
+                let status = response.status();
+                return if status.is_success() {
+                    Ok(status.as_u16())
+                } else {
+                    Err(KinError::WebhookStatus(status.as_u16()))
+                };
+            }
+            Err(e) if attempt < MAX_RETRIES && !e.is_builder() => {
+                attempt += 1;
+                log::warn!(
+                    "Webhook failed ({}), retry {}/{} in {}s",
+                    e,
+                    attempt,
+                    MAX_RETRIES,
+                    RETRY_DELAY.as_secs()
+                );
+                thread::sleep(RETRY_DELAY);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    // Answers one request with `status_line` and returns the request it received
+    fn serve_once(status_line: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            // Headers and the small JSON body arrive well within a few reads
+            while !String::from_utf8_lossy(&request).contains("}") {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            write!(stream, "{}\r\nContent-Length: 0\r\n\r\n", status_line).unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn posts_the_payload_as_json() {
+        let (url, server) = serve_once("HTTP/1.1 204 No Content");
+        let payload = WebhookPayload::new(
+            WebhookEvent::TradeExecuted,
+            Some("BTC_USDT".to_string()),
+            serde_json::json!({"side": "buy"}),
+        );
+        assert_eq!(send_webhook(&url, &payload).unwrap(), 204);
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /hook"));
+        assert!(request.contains("\"event_type\":\"trade_executed\""));
+        assert!(request.contains("\"asset\":\"BTC_USDT\""));
+    }
+
+    #[test]
+    fn reports_error_statuses() {
+        let (url, server) = serve_once("HTTP/1.1 500 Internal Server Error");
+        match send_webhook(&url, &WebhookPayload::test()) {
+            Err(KinError::WebhookStatus(500)) => {}
+            other => panic!("expected status 500, got {:?}", other),
+        }
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use eframe::egui::{
+    Align2, Color32, FontId, Pos2, Response, Sense, Stroke, StrokeKind, Ui, Vec2, WidgetInfo,
+    WidgetType,
+};
+
+/// Horizontal bar filled to `value_pct` of 0–100%, red below `alert_pct` and
+/// green above it, with a tick at the alert level. Larger values fill the bar.
+pub struct MarginGauge {
+    value_pct: f64,
+    alert_pct: f64,
+    width: f32,
+}
+
+impl MarginGauge {
+    pub fn new(value_pct: f64, alert_pct: f64) -> Self {
+        Self {
+            value_pct,
+            alert_pct,
+            width: 240.0,
+        }
+    }
+
+    pub fn width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn show(&self, ui: &mut Ui) -> Response {
+        let (rect, response) = ui.allocate_exact_size(Vec2::new(self.width, 18.0), Sense::hover());
+        let painter = ui.painter();
+        let fraction = |pct: f64| (pct / 100.0).clamp(0.0, 1.0) as f32;
+
+        painter.rect_filled(rect, 3.0, ui.visuals().extreme_bg_color);
+        let color = if self.value_pct < self.alert_pct {
+            Color32::RED
+        } else {
+            Color32::GREEN
+        };
+        let mut filled = rect;
+        filled.set_width(rect.width() * fraction(self.value_pct));
+        painter.rect_filled(filled, 3.0, color.gamma_multiply(0.7));
+
+        let tick_x = rect.left() + rect.width() * fraction(self.alert_pct);
+        painter.line_segment(
+            [
+                Pos2::new(tick_x, rect.top()),
+                Pos2::new(tick_x, rect.bottom()),
+            ],
+            Stroke::new(2.0, Color32::YELLOW),
+        );
+        painter.rect_stroke(
+            rect,
+            3.0,
+            Stroke::new(1.0, ui.visuals().weak_text_color()),
+            StrokeKind::Inside,
+        );
+        painter.text(
+            rect.center(),
+            Align2::CENTER_CENTER,
+            format!("{:.1}%", self.value_pct),
+            FontId::proportional(12.0),
+            ui.visuals().strong_text_color(),
+        );
+
+        response.on_hover_text(format!("Alert below {:.1}%", self.alert_pct))
+    }
+}
+
+/// Horizontal bar of the assets' total target against 100%, with `text` (e.g.
+/// "Total: 60% / 100%") on it. Green from 95 to 100%, yellow within five points
+/// either side of that, red otherwise. The bar spans 0–110% so an excess shows.
+pub struct AllocationGauge {
+    total_pct: f64,
+    text: String,
+    width: f32,
+}
+
+const ALLOCATION_GAUGE_MAX_PCT: f64 = 110.0;
+
+impl AllocationGauge {
+    pub fn new(total_pct: f64, text: String) -> Self {
+        Self {
+            total_pct,
+            text,
+            width: 240.0,
+        }
+    }
+
+    pub fn width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn color(total_pct: f64) -> Color32 {
+        if (95.0..=100.0).contains(&total_pct) {
+            Color32::GREEN
+        } else if (90.0..=105.0).contains(&total_pct) {
+            Color32::YELLOW
+        } else {
+            Color32::RED
+        }
+    }
+
+    pub fn show(&self, ui: &mut Ui) -> Response {
+        let (rect, response) = ui.allocate_exact_size(Vec2::new(self.width, 18.0), Sense::hover());
+        let painter = ui.painter();
+        let x = |pct: f64| {
+            rect.left() + rect.width() * (pct / ALLOCATION_GAUGE_MAX_PCT).clamp(0.0, 1.0) as f32
+        };
+
+        painter.rect_filled(rect, 3.0, ui.visuals().extreme_bg_color);
+        let mut filled = rect;
+        filled.set_right(x(self.total_pct));
+        painter.rect_filled(filled, 3.0, Self::color(self.total_pct).gamma_multiply(0.7));
+
+        // 100% mark
+        painter.line_segment(
+            [
+                Pos2::new(x(100.0), rect.top()),
+                Pos2::new(x(100.0), rect.bottom()),
+            ],
+            Stroke::new(2.0, ui.visuals().strong_text_color()),
+        );
+        painter.rect_stroke(
+            rect,
+            3.0,
+            Stroke::new(1.0, ui.visuals().weak_text_color()),
+            StrokeKind::Inside,
+        );
+        painter.text(
+            rect.center(),
+            Align2::CENTER_CENTER,
+            &self.text,
+            FontId::proportional(12.0),
+            ui.visuals().strong_text_color(),
+        );
+        response.widget_info(|| {
+            WidgetInfo::labeled(WidgetType::ProgressIndicator, ui.is_enabled(), &self.text)
+        });
+        response
+    }
+}
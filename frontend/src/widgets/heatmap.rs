@@ -0,0 +1,134 @@
+use eframe::egui::{
+    Align2, Color32, FontId, Pos2, Rect, Response, Sense, Stroke, StrokeKind, Ui, Vec2,
+};
+
+const DARK_RED: Color32 = Color32::from_rgb(139, 0, 0);
+const DARK_BLUE: Color32 = Color32::from_rgb(0, 0, 139);
+const LABEL_WIDTH: f32 = 40.0;
+
+/// Colour for a correlation: dark red at 1.0, white at 0.0, dark blue at -1.0.
+pub fn correlation_color(value: f64) -> Color32 {
+    let t = value.clamp(-1.0, 1.0) as f32;
+    let target = if t >= 0.0 { DARK_RED } else { DARK_BLUE };
+    let mix = |c: u8| (255.0 + (c as f32 - 255.0) * t.abs()).round() as u8;
+    Color32::from_rgb(mix(target.r()), mix(target.g()), mix(target.b()))
+}
+
+// Contract names such as "BTC_USDT" are shown as their base currency
+fn short_label(label: &str) -> &str {
+    label.split('_').next().unwrap_or(label)
+}
+
+/// Square grid of the pairwise correlations in `matrix`, labelled by `labels`
+/// on both axes, with a "BTC vs ETH: 0.87" tooltip per cell.
+pub struct CorrelationHeatmap<'a> {
+    labels: &'a [String],
+    matrix: &'a [Vec<f64>],
+    cell_size: f32,
+}
+
+impl<'a> CorrelationHeatmap<'a> {
+    pub fn new(labels: &'a [String], matrix: &'a [Vec<f64>]) -> Self {
+        Self {
+            labels,
+            matrix,
+            cell_size: 36.0,
+        }
+    }
+
+    pub fn cell_size(mut self, cell_size: f32) -> Self {
+        self.cell_size = cell_size;
+        self
+    }
+
+    pub fn show(&self, ui: &mut Ui) -> Response {
+        let n = self.matrix.len();
+        let grid_size = self.cell_size * n as f32;
+        let (rect, response) = ui.allocate_exact_size(
+            Vec2::new(LABEL_WIDTH + grid_size, self.cell_size * 0.6 + grid_size),
+            Sense::hover(),
+        );
+        let origin = Pos2::new(rect.left() + LABEL_WIDTH, rect.top() + self.cell_size * 0.6);
+        let painter = ui.painter();
+        let font = FontId::proportional(11.0);
+        let text_color = ui.visuals().text_color();
+
+        for i in 0..n {
+            let label = short_label(self.label(i));
+            painter.text(
+                Pos2::new(rect.left(), origin.y + self.cell_size * (i as f32 + 0.5)),
+                Align2::LEFT_CENTER,
+                label,
+                font.clone(),
+                text_color,
+            );
+            painter.text(
+                Pos2::new(origin.x + self.cell_size * (i as f32 + 0.5), rect.top()),
+                Align2::CENTER_TOP,
+                label,
+                font.clone(),
+                text_color,
+            );
+            for j in 0..n {
+                let value = self.matrix[i].get(j).copied().unwrap_or(0.0);
+                let cell = self.cell_rect(origin, i, j);
+                painter.rect_filled(cell, 0.0, correlation_color(value));
+                let ink = if value.abs() > 0.5 {
+                    Color32::WHITE
+                } else {
+                    Color32::BLACK
+                };
+                painter.text(
+                    cell.center(),
+                    Align2::CENTER_CENTER,
+                    format!("{:.2}", value),
+                    font.clone(),
+                    ink,
+                );
+            }
+        }
+        painter.rect_stroke(
+            Rect::from_min_size(origin, Vec2::splat(grid_size)),
+            0.0,
+            Stroke::new(1.0, Color32::GRAY),
+            StrokeKind::Outside,
+        );
+
+        match response
+            .hover_pos()
+            .and_then(|pos| self.cell_at(origin, pos))
+        {
+            Some((i, j)) => {
+                let value = self.matrix[i].get(j).copied().unwrap_or(0.0);
+                let text = format!(
+                    "{} vs {}: {:.2}",
+                    short_label(self.label(i)),
+                    short_label(self.label(j)),
+                    value
+                );
+                response.on_hover_text_at_pointer(text)
+            }
+            None => response,
+        }
+    }
+
+    fn label(&self, index: usize) -> &str {
+        self.labels.get(index).map(String::as_str).unwrap_or("?")
+    }
+
+    fn cell_rect(&self, origin: Pos2, row: usize, column: usize) -> Rect {
+        Rect::from_min_size(
+            origin + Vec2::new(column as f32, row as f32) * self.cell_size,
+            Vec2::splat(self.cell_size),
+        )
+    }
+
+    fn cell_at(&self, origin: Pos2, pos: Pos2) -> Option<(usize, usize)> {
+        let offset = (pos - origin) / self.cell_size;
+        let n = self.matrix.len() as f32;
+        if offset.x < 0.0 || offset.y < 0.0 || offset.x >= n || offset.y >= n {
+            return None;
+        }
+        Some((offset.y as usize, offset.x as usize))
+    }
+}
@@ -0,0 +1,3 @@
+pub mod gauge;
+pub mod heatmap;
+pub mod pie_chart;
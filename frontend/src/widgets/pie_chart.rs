@@ -0,0 +1,135 @@
+use eframe::egui::{self, Color32, Mesh, Painter, Pos2, Rect, Response, Sense, Stroke, Ui, Vec2};
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+// Colours handed out to assets in display order; USDT always uses `USDT_COLOR`
+const PALETTE: [Color32; 8] = [
+    Color32::from_rgb(247, 147, 26),
+    Color32::from_rgb(98, 126, 234),
+    Color32::from_rgb(191, 187, 187),
+    Color32::from_rgb(0, 163, 136),
+    Color32::from_rgb(220, 80, 120),
+    Color32::from_rgb(140, 90, 200),
+    Color32::from_rgb(230, 200, 60),
+    Color32::from_rgb(70, 170, 220),
+];
+pub const USDT_COLOR: Color32 = Color32::from_rgb(38, 161, 123);
+
+pub fn slice_color(index: usize) -> Color32 {
+    PALETTE[index % PALETTE.len()]
+}
+
+/// Pie chart of `(label, fraction, colour)` slices. Fractions are normalised,
+/// so percentages work just as well as values in `0.0..=1.0`.
+pub struct AllocationPieChart<'a> {
+    slices: &'a [(String, f64, Color32)],
+    radius: f32,
+}
+
+impl<'a> AllocationPieChart<'a> {
+    pub fn new(slices: &'a [(String, f64, Color32)]) -> Self {
+        Self {
+            slices,
+            radius: 60.0,
+        }
+    }
+
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Draws the chart with a hover tooltip per slice, followed by a legend.
+    pub fn show(&self, ui: &mut Ui) -> Response {
+        let (rect, response) =
+            ui.allocate_exact_size(Vec2::splat(self.radius * 2.0), Sense::hover());
+        self.paint(ui.painter(), rect);
+
+        let response = match response
+            .hover_pos()
+            .and_then(|pos| self.slice_at(rect, pos))
+        {
+            Some(index) => {
+                let (label, _, _) = &self.slices[index];
+                let pct = self.fraction(index) * 100.0;
+                response.on_hover_text_at_pointer(format!("{}: {:.1}%", label, pct))
+            }
+            None => response,
+        };
+
+        for (index, (label, _, color)) in self.slices.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let (swatch, _) = ui.allocate_exact_size(Vec2::splat(10.0), Sense::hover());
+                ui.painter().rect_filled(swatch, 2.0, *color);
+                ui.small(format!("{} {:.1}%", label, self.fraction(index) * 100.0));
+            });
+        }
+
+        response
+    }
+
+    /// Fills the slices as arcs inside `rect`, starting at 12 o'clock and going clockwise.
+    pub fn paint(&self, painter: &Painter, rect: Rect) {
+        let center = rect.center();
+        let radius = rect.width().min(rect.height()) / 2.0;
+
+        if self.total() <= 0.0 {
+            painter.circle_stroke(center, radius, Stroke::new(1.0, Color32::GRAY));
+            return;
+        }
+
+        let mut mesh = Mesh::default();
+        let mut start = -FRAC_PI_2;
+        for (index, (_, _, color)) in self.slices.iter().enumerate() {
+            let sweep = self.fraction(index) as f32 * TAU;
+            if sweep <= 0.0 {
+                continue;
+            }
+            let steps = ((sweep / TAU) * 64.0).ceil().max(2.0) as u32;
+            let base = mesh.vertices.len() as u32;
+            mesh.colored_vertex(center, *color);
+            for step in 0..=steps {
+                let angle = start + sweep * step as f32 / steps as f32;
+                mesh.colored_vertex(point_on_circle(center, radius, angle), *color);
+            }
+            for step in 0..steps {
+                mesh.add_triangle(base, base + 1 + step, base + 2 + step);
+            }
+            start += sweep;
+        }
+        painter.add(egui::Shape::mesh(mesh));
+    }
+
+    fn total(&self) -> f64 {
+        self.slices.iter().map(|(_, value, _)| value.max(0.0)).sum()
+    }
+
+    fn fraction(&self, index: usize) -> f64 {
+        let total = self.total();
+        if total <= 0.0 {
+            0.0
+        } else {
+            self.slices[index].1.max(0.0) / total
+        }
+    }
+
+    fn slice_at(&self, rect: Rect, pos: Pos2) -> Option<usize> {
+        let offset = pos - rect.center();
+        if offset.length() > rect.width().min(rect.height()) / 2.0 {
+            return None;
+        }
+        // Angle measured clockwise from 12 o'clock, in 0..TAU
+        let angle = (offset.y.atan2(offset.x) + FRAC_PI_2).rem_euclid(TAU);
+        let mut end = 0.0;
+        for index in 0..self.slices.len() {
+            end += self.fraction(index) as f32 * TAU;
+            if angle < end {
+                return Some(index);
+            }
+        }
+        None
+    }
+}
+
+fn point_on_circle(center: Pos2, radius: f32, angle: f32) -> Pos2 {
+    center + Vec2::new(angle.cos(), angle.sin()) * radius
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::app::Tab;
+use crate::error::KinError;
+
+/// Share of the window width the navigation sidebar starts at.
+pub const DEFAULT_SIDEBAR_RATIO: f32 = 0.2;
+
+/// Window geometry (in points), the open tab and the sidebar split, restored on
+/// the next launch.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowState {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub active_tab: Tab,
+    // Sidebar width over window width; missing from files of older builds
+    #[serde(default = "default_sidebar_ratio")]
+    pub sidebar_ratio: f32,
+}
+
+fn default_sidebar_ratio() -> f32 {
+    DEFAULT_SIDEBAR_RATIO
+}
+
+impl WindowState {
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".portfolio_rebalancer_window.json")
+    }
+
+    // A missing or unreadable file just means the defaults are used
+    pub fn load(path: &Path) -> Option<WindowState> {
+        let text = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&text) {
+            Ok(state) => Some(state),
+            Err(e) => {
+                log::warn!("Ignoring malformed window state ({:?}): {}", path, e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), KinError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
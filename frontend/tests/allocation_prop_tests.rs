@@ -0,0 +1,90 @@
+use proptest::prelude::*;
+use std::collections::HashMap;
+
+use kin_portfolio_rebalancer_gui::config::{
+    AssetAllocation, Config, MarketData, PortfolioAllocation, USDT,
+};
+use kin_portfolio_rebalancer_gui::exchange::Balances;
+use kin_portfolio_rebalancer_gui::planner::compute_plan;
+
+const SYMBOLS: [&str; 3] = ["BTC", "ETH", "LTC"];
+
+fn allocation(btc: f64, eth: f64, ltc: f64) -> PortfolioAllocation {
+    PortfolioAllocation {
+        assets: SYMBOLS
+            .iter()
+            .zip([btc, eth, ltc])
+            .map(|(symbol, pct)| AssetAllocation::new(*symbol, pct))
+            .collect(),
+    }
+}
+
+prop_compose! {
+    // Any triple of percentages, over 100% in total included
+    fn any_triple()(btc in 0.0..=100.0f64, eth in 0.0..=100.0f64, ltc in 0.0..=100.0f64)
+        -> (f64, f64, f64) {
+        (btc, eth, ltc)
+    }
+}
+
+prop_compose! {
+    // Triples that leave USDT a non-negative share
+    fn valid_triple()(btc in 0.0..=100.0f64)
+        (eth in 0.0..=100.0 - btc, ltc_share in 0.0..=1.0f64, btc in Just(btc))
+        -> (f64, f64, f64) {
+        (btc, eth, (100.0 - btc - eth).max(0.0) * ltc_share)
+    }
+}
+
+prop_compose! {
+    fn any_balances()(margins in prop::collection::vec(0.0..1_000_000.0f64, 4)) -> Balances {
+        SYMBOLS
+            .iter()
+            .chain([&USDT])
+            .map(|symbol| symbol.to_string())
+            .zip(margins)
+            .collect()
+    }
+}
+
+proptest! {
+    #[test]
+    fn usdt_is_never_negative((btc, eth, ltc) in any_triple()) {
+        prop_assert!(allocation(btc, eth, ltc).usdt() >= 0.0);
+    }
+
+    #[test]
+    fn usdt_fills_up_to_100((btc, eth, ltc) in valid_triple()) {
+        let allocation = allocation(btc, eth, ltc);
+        prop_assert!(allocation.check_total().is_ok());
+        let total = btc + eth + ltc + allocation.usdt();
+        prop_assert!((total - 100.0).abs() < 1e-10, "total {}", total);
+    }
+
+    #[test]
+    fn totals_over_100_are_rejected((btc, eth, ltc) in any_triple()) {
+        let result = allocation(btc, eth, ltc).check_total();
+        prop_assert_eq!(result.is_err(), btc + eth + ltc > 100.0);
+    }
+
+    #[test]
+    fn planned_trades_are_never_negative(
+        (btc, eth, ltc) in valid_triple(),
+        current in any_balances(),
+        prices in prop::collection::vec(0.0..100_000.0f64, 3),
+    ) {
+        let config = Config {
+            portfolio_allocation: allocation(btc, eth, ltc),
+            ..Config::default()
+        };
+        let prices: HashMap<String, f64> = SYMBOLS
+            .iter()
+            .map(|symbol| symbol.to_string())
+            .zip(prices)
+            .collect();
+        let plan = compute_plan(&current, &config, &prices, &MarketData::default());
+        for trade in plan.trades.iter().chain(&plan.skipped) {
+            prop_assert!(trade.notional_usdt >= 0.0, "{:?}", trade);
+        }
+    }
+}
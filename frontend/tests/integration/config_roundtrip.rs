@@ -0,0 +1,246 @@
+use chrono::{NaiveTime, TimeZone, Utc, Weekday};
+use eframe::egui::{Key, KeyboardShortcut, Modifiers};
+use std::path::{Path, PathBuf};
+
+use kin_portfolio_rebalancer_gui::app::PortfolioAllocationEditor;
+use kin_portfolio_rebalancer_gui::config::{
+    AllocationMode, AssetAllocation, Config, ConfigFormat, PortfolioAllocation, PositionSizing,
+    RiskParityConfig, CONFIG_VERSION,
+};
+use kin_portfolio_rebalancer_gui::currency::DisplayCurrency;
+use kin_portfolio_rebalancer_gui::error::{FieldId, KinError};
+use kin_portfolio_rebalancer_gui::exchange::retry::RetryConfig;
+use kin_portfolio_rebalancer_gui::exchange::ExchangeChoice;
+use kin_portfolio_rebalancer_gui::keybindings::KeyBindings;
+use kin_portfolio_rebalancer_gui::locale::Locale;
+use kin_portfolio_rebalancer_gui::schedule::RebalanceSchedule;
+use kin_portfolio_rebalancer_gui::slippage::SlippageModel;
+use kin_portfolio_rebalancer_gui::tax::CostBasisMethod;
+use kin_portfolio_rebalancer_gui::theme::Theme;
+use kin_portfolio_rebalancer_gui::webhook::WebhookEvent;
+
+// A fresh directory per test, so tests running in parallel do not share files
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("kin-roundtrip-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Every field set, none of them to its default; listed without `..Default::default()`
+// so a new field fails to compile here until it is covered
+fn non_default_config(path: &Path) -> Config {
+    let shortcut = |key| KeyboardShortcut::new(Modifiers::ALT | Modifiers::SHIFT, key);
+    Config {
+        version: CONFIG_VERSION,
+        format: ConfigFormat::from_path(path),
+        api_key: "key".to_string(),
+        api_secret: "secret".to_string().into(),
+        portfolio_allocation: PortfolioAllocation {
+            assets: vec![
+                AssetAllocation {
+                    leverage: 5.0,
+                    tags: vec!["Large cap".to_string(), "Layer1".to_string()],
+                    ..AssetAllocation::new("BTC_USDT", 40.5)
+                },
+                AssetAllocation::new("SOL_USDT", 12.25),
+            ],
+        },
+        display_order: vec!["SOL_USDT".to_string(), "BTC_USDT".to_string()],
+        notes: "Barbell: \"BTC\" core,\nSOL satellite".to_string(),
+        rebalance_threshold: 7.5,
+        per_asset_threshold: Some([("SOL_USDT".to_string(), 12.0)].into()),
+        min_usdt_inflow: 25.0,
+        min_trade_usdt: 12.5,
+        taker_fee_rate: 0.0006,
+        maker_fee_rate: 0.0001,
+        allocation_mode: AllocationMode::RiskParity(RiskParityConfig {
+            lookback_days: 60,
+            target_volatility: 0.25,
+        }),
+        position_sizing: PositionSizing::KellyCriterion {
+            win_rate: 0.55,
+            avg_win_loss_ratio: 1.5,
+            fraction: 0.5,
+        },
+        cost_basis_method: CostBasisMethod::Lifo,
+        slippage_model: SlippageModel::Sqrt { coeff: 0.002 },
+        schedule: RebalanceSchedule::Weekly {
+            weekday: Weekday::Fri,
+            time: NaiveTime::from_hms_opt(16, 30, 0).unwrap(),
+        },
+        poll_interval_seconds: 45,
+        max_drawdown_halt_pct: Some(30.0),
+        maintenance_margin_rate: 0.01,
+        margin_alert_threshold_pct: 15.0,
+        risk_free_rate_annual: 0.03,
+        display_currency: DisplayCurrency::Btc,
+        locale: Locale::DeDe,
+        display_precision: 3,
+        theme: Theme::System,
+        ui_scale: 1.25,
+        minimize_to_tray: true,
+        notifications_enabled: false,
+        exchange: ExchangeChoice::Binance,
+        paper_trading: true,
+        webhook_url: Some("http://localhost:9000/hook".to_string()),
+        webhook_events: vec![WebhookEvent::BackendStopped],
+        check_for_updates: false,
+        last_update_check: Some(Utc.with_ymd_and_hms(2025, 3, 14, 9, 26, 53).unwrap()),
+        retry: RetryConfig {
+            max_attempts: 5,
+            base_delay_ms: 100,
+            max_delay_ms: 2_000,
+            retryable_status_codes: vec![429, 503],
+        },
+        backup_count: 2,
+        saved_at: None,
+        first_run: false,
+        bindings: KeyBindings {
+            start_stop: shortcut(Key::F5),
+            save_config: shortcut(Key::W),
+            dry_run: shortcut(Key::F6),
+            switch_to_portfolio: shortcut(Key::Num2),
+            switch_to_log: shortcut(Key::G),
+            switch_to_history: shortcut(Key::Y),
+        },
+        log_level: "debug".to_string(),
+        log_file: Some(PathBuf::from("/var/log/kin/rebalancer.log")),
+        health_port: 9876,
+    }
+}
+
+#[test]
+fn every_field_survives_save_and_load() {
+    let dir = temp_dir("fields");
+    for name in ["config.json", "config.toml"] {
+        let path = dir.join(name);
+        let config = non_default_config(&path);
+        assert_ne!(
+            Config {
+                saved_at: None,
+                ..config.clone()
+            },
+            Config::default()
+        );
+
+        config.save(&path).unwrap();
+        let loaded = Config::load(&path).unwrap();
+
+        // `save` stamps the time of writing
+        assert!(loaded.saved_at.is_some(), "{}", name);
+        assert_eq!(
+            Config {
+                saved_at: None,
+                ..loaded
+            },
+            config,
+            "{}",
+            name
+        );
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn editor_values_are_saved_and_reloaded() {
+    let dir = temp_dir("editor");
+    let path = dir.join("config.json");
+    let mut config = Config::default();
+    config.save(&path).unwrap();
+
+    let mut editor = PortfolioAllocationEditor::from_config(&config);
+    editor.set_allocation("BTC_USDT", " 30.5 ");
+    editor.set_allocation("ETH_USDT", "20");
+    editor.set_allocation("SOL_USDT", "4.5");
+    editor.set_rebalance_threshold("6");
+    editor.set_min_usdt_inflow("50");
+    editor.update_config(&mut config).unwrap();
+    config.save(&path).unwrap();
+
+    let loaded = Config::load(&path).unwrap();
+    let allocation = &loaded.portfolio_allocation;
+    assert_eq!(allocation.get("BTC_USDT"), Some(30.5));
+    assert_eq!(allocation.get("ETH_USDT"), Some(20.0));
+    assert_eq!(allocation.get("LTC_USDT"), Some(10.0));
+    assert_eq!(allocation.get("SOL_USDT"), Some(4.5));
+    assert!((allocation.usdt() - 35.0).abs() < 1e-9);
+    assert_eq!(loaded.rebalance_threshold, 6.0);
+    assert_eq!(loaded.min_usdt_inflow, 50.0);
+    assert_eq!(
+        loaded.display_order,
+        ["BTC_USDT", "ETH_USDT", "LTC_USDT", "SOL_USDT"]
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn usdt_remainder_is_rounded_to_display_precision() {
+    for (precision, expected) in [(1, "0.1"), (3, "0.100")] {
+        let config = Config {
+            display_precision: precision,
+            ..Config::default()
+        };
+        // Default rows: BTC_USDT, ETH_USDT, LTC_USDT
+        let mut editor = PortfolioAllocationEditor::from_config(&config);
+        for symbol in ["BTC_USDT", "ETH_USDT", "LTC_USDT"] {
+            editor.set_allocation(symbol, "33.3");
+        }
+        assert_eq!(editor.get_usdt_display(), expected);
+    }
+}
+
+#[test]
+fn invalid_editor_values_are_rejected() {
+    let original = Config::default();
+    // Default rows: BTC_USDT, ETH_USDT, LTC_USDT
+    let cases: [(&str, &str, FieldId); 3] = [
+        ("ETH_USDT", "-5", FieldId::Allocation(1)),
+        ("BTC_USDT", "95", FieldId::AllocationTotal),
+        ("LTC_USDT", "ten", FieldId::Allocation(2)),
+    ];
+    for (symbol, text, field) in cases {
+        let mut editor = PortfolioAllocationEditor::from_config(&original);
+        editor.set_allocation(symbol, text);
+        let mut config = original.clone();
+        match editor.update_config(&mut config) {
+            Err(KinError::ValidationErrors(errors)) => {
+                let fields: Vec<FieldId> = errors.iter().map(|(field, _)| *field).collect();
+                assert_eq!(fields, [field], "{} = {:?}", symbol, text);
+            }
+            other => panic!("{} = {:?} gave {:?}", symbol, text, other),
+        }
+        // Nothing is written on failure
+        assert_eq!(config, original);
+    }
+
+    let mut editor = PortfolioAllocationEditor::from_config(&original);
+    editor.set_rebalance_threshold("");
+    editor.set_allocation("BTC_USDT", "abc");
+    let Err(KinError::ValidationErrors(errors)) = editor.update_config(&mut original.clone())
+    else {
+        panic!("empty threshold and non-numeric allocation were accepted");
+    };
+    assert_eq!(errors.len(), 2);
+}
+
+#[test]
+fn non_finite_percentages_are_rejected() {
+    let original = Config::default();
+    for text in ["nan", "inf", "-infinity"] {
+        let mut editor = PortfolioAllocationEditor::from_config(&original);
+        editor.set_allocation("BTC_USDT", text);
+        let mut config = original.clone();
+        let Err(KinError::ValidationErrors(errors)) = editor.update_config(&mut config) else {
+            panic!("BTC_USDT = {:?} was accepted", text);
+        };
+        let fields: Vec<FieldId> = errors.iter().map(|(field, _)| *field).collect();
+        assert_eq!(fields, [FieldId::Allocation(0)], "BTC_USDT = {:?}", text);
+        assert_eq!(config, original);
+    }
+
+    let allocation = PortfolioAllocation {
+        assets: vec![AssetAllocation::new("BTC_USDT", f64::NAN)],
+    };
+    assert!(allocation.check_total().is_err());
+}
@@ -0,0 +1,152 @@
+//! Layout snapshots of the main window, taken from the AccessKit tree egui builds
+//! every frame. A widget that moves, resizes, changes its text or disappears
+//! fails the test; review and accept intended changes with `cargo insta review`.
+
+use eframe::egui::accesskit::Role;
+use egui_kittest::kittest::{by, Key, Node, Queryable};
+use egui_kittest::Harness;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+use kin_portfolio_rebalancer_gui::app::RebalancerApp;
+use kin_portfolio_rebalancer_gui::config::{Config, ProfileManager};
+use kin_portfolio_rebalancer_gui::exchange::mock::TestScenario;
+
+// Role, label, value and bounds in whole points of `node` and its widgets.
+// Text runs only repeat their parent's value, so they are left out.
+fn layout(node: &Node<'_>) -> Value {
+    let mut entry = json!({ "role": format!("{:?}", node.role()) });
+    if let Some(label) = node.label() {
+        entry["label"] = json!(label);
+    }
+    if let Some(value) = node.value() {
+        entry["value"] = json!(value);
+    }
+    if let Some(rect) = node.bounding_box() {
+        entry["rect"] = json!(format!(
+            "{} {} {} {}",
+            rect.x0.round(),
+            rect.y0.round(),
+            rect.x1.round(),
+            rect.y1.round()
+        ));
+    }
+    let children: Vec<Value> = node
+        .query_all(by().recursive(false))
+        .filter(|child| child.role() != Role::TextRun)
+        .map(|child| layout(&child))
+        .collect();
+    if !children.is_empty() {
+        entry["children"] = json!(children);
+    }
+    entry
+}
+
+// The temporary config directory shows in the footer and differs per machine
+fn snapshot(harness: &Harness<'_, RebalancerApp>) -> String {
+    let json = serde_json::to_string_pretty(&layout(&harness.kittest_state().root())).unwrap();
+    json.replace(&config_dir().display().to_string(), "[config dir]")
+}
+
+fn config_dir() -> PathBuf {
+    std::env::temp_dir().join("kin_ui_snapshots")
+}
+
+// The default config in a fresh directory, past the setup wizard and without
+// update checks, shown against the mock exchange
+fn harness() -> Harness<'static, RebalancerApp> {
+    harness_in(&config_dir(), &[])
+}
+
+// Like `harness`, in `dir` and with `profiles` each holding that config
+fn harness_in(dir: &Path, profiles: &[&str]) -> Harness<'static, RebalancerApp> {
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir_all(dir).unwrap();
+    let config_path: PathBuf = dir.join("config.json");
+    let config = Config {
+        check_for_updates: false,
+        first_run: false,
+        health_port: 0,
+        ..Config::default()
+    };
+    config.save(&config_path).unwrap();
+    if let Some((first, rest)) = profiles.split_first() {
+        let mut manager = ProfileManager::new(first.to_string(), config.clone());
+        for name in rest {
+            manager.add(name.to_string(), config.clone());
+        }
+        manager
+            .save(&ProfileManager::path_for(&config_path))
+            .unwrap();
+    }
+
+    let dir = dir.to_path_buf();
+    let mut harness = Harness::builder()
+        .with_size([1024.0, 768.0])
+        .build_eframe(move |cc| {
+            RebalancerApp::new(cc, config_path, None, None)
+                .with_portfolio_dir(&dir.join("portfolios"))
+                .with_audit_log(&dir.join("audit.log"))
+                .with_mock_exchange(TestScenario::AllAssetsOnTarget)
+        });
+    harness.run_steps(3);
+    harness
+}
+
+#[test]
+fn portfolio_editor_and_api_settings() {
+    let mut harness = harness();
+    harness.get_by_label("Portfolio Config").click();
+    harness.run_steps(2);
+    insta::assert_snapshot!("portfolio_editor", snapshot(&harness));
+
+    let btc = harness.get(by().role(Role::TextInput).value("25"));
+    btc.focus();
+    btc.key_combination(&[Key::Command, Key::A]);
+    btc.type_text("30");
+    harness.run_steps(2);
+    harness.get_by_label("Save Portfolio Config").click();
+    harness.run_steps(2);
+    insta::assert_snapshot!("portfolio_editor_saved", snapshot(&harness));
+
+    harness.get_by_label("API Settings").click();
+    harness.run_steps(2);
+    insta::assert_snapshot!("api_settings", snapshot(&harness));
+}
+
+#[test]
+fn switching_profiles_saves_unsaved_edits() {
+    let dir = std::env::temp_dir().join("kin_ui_profile_switch");
+    let mut harness = harness_in(&dir, &["Default", "Other"]);
+    harness.get_by_label("Portfolio Config").click();
+    harness.run_steps(2);
+    let btc = harness.get(by().role(Role::TextInput).value("25"));
+    btc.focus();
+    btc.key_combination(&[Key::Command, Key::A]);
+    btc.type_text("30");
+    harness.run_steps(2);
+
+    // The profile selector is the first combo box in the side panel
+    harness
+        .query_all(by().role(Role::ComboBox))
+        .next()
+        .unwrap()
+        .click();
+    harness.run_steps(2);
+    harness.get_by_label("Other").click();
+    harness.run_steps(2);
+
+    let profiles =
+        ProfileManager::load(&ProfileManager::path_for(&dir.join("config.json"))).unwrap();
+    assert_eq!(profiles.active_name(), "Other");
+    let allocation = |name: &str| {
+        profiles
+            .get(name)
+            .unwrap()
+            .portfolio_allocation
+            .get("BTC_USDT")
+    };
+    assert_eq!(allocation("Default"), Some(30.0));
+    assert_eq!(allocation("Other"), Some(25.0));
+    let _ = std::fs::remove_dir_all(&dir);
+}